@@ -110,7 +110,7 @@ pub trait SchemeSigner<A: Address<Network = <Self::Scheme as Scheme>::Network>>
     fn sign(
         &self,
         payment: &PaymentSelection<A>,
-    ) -> impl Future<Output = Result<<Self::Scheme as Scheme>::Payload, Self::Error>>;
+    ) -> impl Future<Output = Result<<Self::Scheme as Scheme>::Payload, Self::Error>> + Send;
 }
 
 /// Resource definition.
@@ -127,3 +127,85 @@ pub struct Resource {
     /// Optional output schema for the payment payload.
     pub output_schema: Option<OutputSchema>,
 }
+
+/// A misconfigured [`Resource`].
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+#[non_exhaustive]
+pub enum ResourceError {
+    /// The resource URL does not use `https`, e.g. a leftover `http://localhost` URL.
+    #[error("Resource URL {0} must use https")]
+    NotHttps(Url),
+}
+
+impl Resource {
+    /// The path of the resource URL, e.g. `/api/weather`.
+    pub fn path(&self) -> &str {
+        self.url.path()
+    }
+
+    /// Check that the resource URL uses `https`, as required for production deployments.
+    pub fn require_https(&self) -> Result<(), ResourceError> {
+        match self.url.scheme() {
+            "https" => Ok(()),
+            _ => Err(ResourceError::NotHttps(self.url.clone())),
+        }
+    }
+}
+
+impl<S: resource_builder::IsComplete> ResourceBuilder<S> {
+    /// Build the resource, rejecting URLs that do not use `https`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use x402_core::core::{Resource, ResourceError};
+    ///
+    /// let err = Resource::builder()
+    ///     .url("http://localhost:3000/api/weather".parse().unwrap())
+    ///     .description("Weather")
+    ///     .mime_type("application/json")
+    ///     .try_build_https()
+    ///     .unwrap_err();
+    ///
+    /// assert!(matches!(err, ResourceError::NotHttps(_)));
+    /// ```
+    pub fn try_build_https(self) -> Result<Resource, ResourceError> {
+        let resource = self.build();
+        resource.require_https()?;
+        Ok(resource)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn resource(url: &str) -> ResourceBuilder<impl resource_builder::IsComplete> {
+        Resource::builder()
+            .url(url.parse().unwrap())
+            .description("Weather")
+            .mime_type("application/json")
+    }
+
+    #[test]
+    fn strict_build_rejects_http() {
+        let err = resource("http://localhost:3000/api/weather")
+            .try_build_https()
+            .unwrap_err();
+
+        assert_eq!(
+            err.to_string(),
+            "Resource URL http://localhost:3000/api/weather must use https"
+        );
+    }
+
+    #[test]
+    fn strict_build_accepts_https() {
+        let resource = resource("https://example.com/api/weather?units=metric")
+            .try_build_https()
+            .unwrap();
+
+        assert_eq!(resource.require_https(), Ok(()));
+        assert_eq!(resource.path(), "/api/weather");
+    }
+}
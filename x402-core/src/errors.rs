@@ -12,6 +12,14 @@ pub enum Error {
     /// UTF-8 decoding errors.
     #[error("UTF-8 decode error: {0}")]
     Utf8DecodeError(#[from] std::string::FromUtf8Error),
+
+    /// Invalid payment requirements.
+    #[error("Invalid payment requirements: {0}")]
+    InvalidPaymentRequirements(String),
+
+    /// Invalid supported payment kind.
+    #[error("Invalid supported kind: {0}")]
+    InvalidSupportedKinds(String),
 }
 
 /// A specialized `Result` type for X402 core operations.
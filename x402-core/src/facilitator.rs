@@ -1,16 +1,90 @@
 //! X402 payment facilitator interface and types.
 
+use std::{
+    collections::BTreeMap,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use bon::Builder;
 use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
 
 use crate::{
     transport::{PaymentPayload, PaymentRequirements, SettlementResponse},
-    types::{AnyJson, ExtensionIdentifier, Record, X402Version},
+    types::{AmountValue, AnyJson, Caip2, ExtensionIdentifier, Record, SchemeName, X402Version},
 };
 
+#[cfg(feature = "tokio")]
+mod rate_limit;
+mod split;
+
+#[cfg(feature = "tokio")]
+pub use rate_limit::*;
+pub use split::*;
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct PaymentRequest {
     pub payment_payload: PaymentPayload,
     pub payment_requirements: PaymentRequirements,
+    /// The attestation returned by a previous verification of this payment, echoed on settle.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub attestation: Option<AnyJson>,
+    /// The idempotency key of settle calls, so that a retried settlement is not settled twice.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub idempotency_key: Option<SettlementKey>,
+}
+
+/// A key identifying the settlement of a payment, derived deterministically from its payload.
+///
+/// Retries of the same payment yield the same key, while different payments yield different
+/// keys, so that it can be used as the idempotency key of settle calls.
+///
+/// # Example
+///
+/// ```
+/// use x402_core::{facilitator::SettlementKey, transport::PaymentPayload};
+///
+/// # fn retry(payload: &PaymentPayload) {
+/// assert_eq!(SettlementKey::derive(payload), SettlementKey::derive(&payload.clone()));
+/// # }
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct SettlementKey(pub String);
+
+impl SettlementKey {
+    /// Derive the key of a payment: the hex-encoded SHA-256 of its network and its identity.
+    ///
+    /// The identity is the `from` and `nonce` of EIP-3009 authorizations, the signed
+    /// `transaction` of SVM payloads, and the whole scheme payload otherwise.
+    pub fn derive(payload: &PaymentPayload) -> Self {
+        let scheme_payload = &payload.payload;
+        let authorization = &scheme_payload["authorization"];
+        let identity = match (
+            authorization["from"].as_str(),
+            authorization["nonce"].as_str(),
+            scheme_payload["transaction"].as_str(),
+        ) {
+            (Some(from), Some(nonce), _) => {
+                format!("eip3009:{}:{}", from.to_lowercase(), nonce.to_lowercase())
+            }
+            (_, _, Some(transaction)) => format!("transaction:{transaction}"),
+            _ => format!("payload:{scheme_payload}"),
+        };
+
+        let digest = Sha256::new()
+            .chain_update(payload.accepted.network.as_bytes())
+            .chain_update(b"\n")
+            .chain_update(identity.as_bytes())
+            .finalize();
+        SettlementKey(format!("{digest:x}"))
+    }
+}
+
+impl std::fmt::Display for SettlementKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.0)
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -50,6 +124,10 @@ impl VerifyResult {
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct VerifyValid {
     pub payer: String,
+    /// An opaque attestation of the verification, for facilitators that settle verified payments
+    /// more cheaply, e.g. without re-verifying them.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub attestation: Option<AnyJson>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -59,9 +137,13 @@ pub struct VerifyInvalid {
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[non_exhaustive]
 pub enum SettleResult {
     Success(SettleSuccess),
     Failed(SettleFailed),
+    /// The facilitator accepted the settlement but has not executed it yet, e.g. while the
+    /// transaction confirms.
+    Pending(SettlePending),
 }
 
 impl SettleResult {
@@ -90,6 +172,32 @@ impl SettleResult {
             _ => None,
         }
     }
+
+    pub fn pending(pending: SettlePending) -> Self {
+        SettleResult::Pending(pending)
+    }
+
+    pub fn as_pending(&self) -> Option<&SettlePending> {
+        match self {
+            SettleResult::Pending(v) => Some(v),
+            _ => None,
+        }
+    }
+}
+
+/// A settlement the facilitator has accepted but not executed yet.
+///
+/// Its status can be polled with [`Facilitator::settle_status`] for a [`SettleSuccess`] whose
+/// `transaction` is the [`reference`](Self::reference).
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SettlePending {
+    /// How long to wait before polling the settlement status, if the facilitator said.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub poll_after: Option<Duration>,
+    /// The facilitator's reference of the settlement, e.g. the transaction hash, if known.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reference: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -97,6 +205,114 @@ pub struct SettleSuccess {
     pub payer: String,
     pub transaction: String,
     pub network: String,
+    /// Whether the facilitator signed the settlement with one of the
+    /// [`signers`](SupportedResponse::signers) it advertises for the network.
+    ///
+    /// The signature is over the [`SignedSettlement`] of the fields of the `PAYMENT-RESPONSE`
+    /// header, so that the header can be trusted without trusting the server relaying it.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub verified_signature: bool,
+    /// The advertised signer that signed the settlement, if its signature was verified.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub signer: Option<String>,
+    /// How far the transaction had progressed towards finality when the facilitator reported the
+    /// settlement, if known.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub confirmation: Option<ConfirmationStatus>,
+}
+
+/// The message a facilitator signs over a successful settlement.
+///
+/// The signature is over the JSON of exactly these fields, in this order, so that facilitators can
+/// report more about the settlement, e.g. its confirmation status, without invalidating it.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SignedSettlement {
+    pub success: bool,
+    pub transaction: String,
+    pub network: String,
+    pub payer: String,
+}
+
+impl SignedSettlement {
+    /// The signed bytes, i.e. the JSON of the settlement.
+    pub fn message(&self) -> Vec<u8> {
+        serde_json::to_vec(self).expect("settlement serializes to JSON")
+    }
+}
+
+impl From<&SettleSuccess> for SignedSettlement {
+    fn from(success: &SettleSuccess) -> Self {
+        SignedSettlement {
+            success: true,
+            transaction: success.transaction.clone(),
+            network: success.network.clone(),
+            payer: success.payer.clone(),
+        }
+    }
+}
+
+/// How far a settlement transaction has progressed towards finality.
+///
+/// Ordered from the weakest to the strongest guarantee, so that a minimum status can be required
+/// with a comparison, e.g. `status >= ConfirmationStatus::Confirmed(1)`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ConfirmationStatus {
+    /// The transaction was broadcast but is not included in a block yet.
+    Broadcast,
+    /// The transaction is included, with the given number of confirmations.
+    Confirmed(u64),
+    /// The transaction is final, e.g. in a finalized block.
+    Finalized,
+}
+
+/// The on-chain status of a settlement, for facilitators confirming settlements asynchronously.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "status", rename_all = "camelCase")]
+#[non_exhaustive]
+pub enum SettleStatus {
+    /// The settlement transaction was submitted but is not confirmed yet.
+    Pending,
+    /// The settlement transaction is confirmed, with the given number of confirmations, one if
+    /// not reported.
+    Confirmed {
+        #[serde(default = "one_confirmation")]
+        confirmations: u64,
+    },
+    /// The settlement transaction is final, e.g. in a finalized block.
+    Finalized,
+    /// The settlement transaction failed.
+    Failed {
+        #[serde(rename = "errorReason")]
+        error_reason: String,
+    },
+}
+
+fn one_confirmation() -> u64 {
+    1
+}
+
+impl SettleStatus {
+    /// A confirmed settlement with a single confirmation, e.g. for facilitators not counting
+    /// confirmations.
+    pub fn confirmed() -> Self {
+        SettleStatus::Confirmed { confirmations: 1 }
+    }
+
+    /// How far the settlement has progressed towards finality, or `None` if it failed.
+    ///
+    /// Pending settlements are [`Broadcast`](ConfirmationStatus::Broadcast), and confirmed ones
+    /// have at least one confirmation.
+    pub fn confirmation(&self) -> Option<ConfirmationStatus> {
+        match self {
+            SettleStatus::Pending => Some(ConfirmationStatus::Broadcast),
+            SettleStatus::Confirmed { confirmations } => {
+                Some(ConfirmationStatus::Confirmed((*confirmations).max(1)))
+            }
+            SettleStatus::Finalized => Some(ConfirmationStatus::Finalized),
+            SettleStatus::Failed { .. } => None,
+        }
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -105,15 +321,205 @@ pub struct SettleFailed {
     pub payer: Option<String>,
 }
 
+/// The result of refunding a settled payment with [`Refundable::refund`].
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum RefundResult {
+    Success(RefundSuccess),
+    Failed(RefundFailed),
+}
+
+impl RefundResult {
+    pub fn is_success(&self) -> bool {
+        matches!(self, RefundResult::Success(_))
+    }
+
+    pub fn success(success: RefundSuccess) -> Self {
+        RefundResult::Success(success)
+    }
+
+    pub fn failed(failed: RefundFailed) -> Self {
+        RefundResult::Failed(failed)
+    }
+
+    pub fn as_success(&self) -> Option<&RefundSuccess> {
+        match self {
+            RefundResult::Success(v) => Some(v),
+            _ => None,
+        }
+    }
+
+    pub fn as_failed(&self) -> Option<&RefundFailed> {
+        match self {
+            RefundResult::Failed(v) => Some(v),
+            _ => None,
+        }
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RefundSuccess {
+    /// The refund transaction, or the voided settlement transaction if it was not confirmed yet.
+    pub transaction: String,
+    pub network: String,
+    /// The refunded amount, in smallest units.
+    pub amount: AmountValue,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RefundFailed {
+    pub error_reason: String,
+}
+
+/// A record of a settled payment, combining the settlement result with the settled requirements.
+///
+/// Suitable for persisting to a ledger after a successful payment flow.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PaymentReceipt {
+    /// The address of the payer.
+    pub payer: String,
+    /// The settlement transaction, empty for [`unverified`](PaymentReceipt::unverified) payments.
+    pub transaction: String,
+    /// The network the payment was settled on.
+    pub network: String,
+    /// The payment scheme.
+    pub scheme: SchemeName,
+    /// The settled amount, in smallest units.
+    pub amount: AmountValue,
+    /// The address that received the payment.
+    pub pay_to: String,
+    /// The address of the asset.
+    pub asset: String,
+    /// Unix timestamp in seconds when the receipt was created.
+    pub timestamp: u64,
+    /// The URL of the paid resource, if known.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub resource: Option<String>,
+    /// The seller's order id for the payment, if any.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub order_id: Option<String>,
+    /// Whether the payment was served without being verified or settled, e.g. as the facilitator
+    /// was unreachable. The amount is then owed by the payer rather than received.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub unverified: bool,
+}
+
+impl PaymentReceipt {
+    /// Create a receipt for a settlement of the given payment requirements, timestamped now.
+    pub fn new(settled: &SettleSuccess, requirements: &PaymentRequirements) -> Self {
+        PaymentReceipt {
+            payer: settled.payer.clone(),
+            transaction: settled.transaction.clone(),
+            network: settled.network.clone(),
+            scheme: requirements.scheme.clone(),
+            amount: requirements.amount,
+            pay_to: requirements.pay_to.clone(),
+            asset: requirements.asset.clone(),
+            timestamp: unix_timestamp(),
+            resource: None,
+            order_id: None,
+            unverified: false,
+        }
+    }
+
+    /// Create a receipt for a payment of the given requirements served without verification or
+    /// settlement, timestamped now.
+    ///
+    /// The payer is the one the payload claims, i.e. the `authorization.from` of EVM payloads,
+    /// and empty for other payloads.
+    pub fn liability(payload: &PaymentPayload, requirements: &PaymentRequirements) -> Self {
+        let payer = payload
+            .payload
+            .pointer("/authorization/from")
+            .and_then(|payer| payer.as_str())
+            .unwrap_or_default();
+
+        PaymentReceipt {
+            payer: payer.to_string(),
+            transaction: String::new(),
+            network: requirements.network.clone(),
+            scheme: requirements.scheme.clone(),
+            amount: requirements.amount,
+            pay_to: requirements.pay_to.clone(),
+            asset: requirements.asset.clone(),
+            timestamp: unix_timestamp(),
+            resource: None,
+            order_id: None,
+            unverified: true,
+        }
+    }
+}
+
+fn unix_timestamp() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default()
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct SupportedKinds {
     pub x402_version: X402Version,
-    pub scheme: String,
+    pub scheme: SchemeName,
     pub network: String,
     pub extra: Option<AnyJson>,
 }
 
+#[bon::bon]
+impl SupportedKinds {
+    /// Build a supported payment kind, e.g. for a facilitator's `supported` response.
+    ///
+    /// The `network` of v2 kinds must be a [`Caip2`] identifier, while v1 kinds keep their
+    /// network names, e.g. `base-sepolia`, which must be non-empty like the `scheme`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use x402_core::{facilitator::SupportedKinds, types::{X402V2, X402Version}};
+    ///
+    /// let kind = SupportedKinds::builder()
+    ///     .x402_version(X402Version::V2(X402V2))
+    ///     .scheme("exact")
+    ///     .network("eip155:8453")
+    ///     .build()
+    ///     .unwrap();
+    ///
+    /// assert_eq!(kind.network, "eip155:8453");
+    /// ```
+    #[builder]
+    pub fn new(
+        x402_version: X402Version,
+        #[builder(into)] scheme: SchemeName,
+        #[builder(into)] network: String,
+        extra: Option<AnyJson>,
+    ) -> crate::errors::Result<Self> {
+        let invalid = |reason: String| Err(crate::errors::Error::InvalidSupportedKinds(reason));
+
+        if scheme.is_empty() {
+            return invalid("scheme must not be empty".to_string());
+        }
+        match x402_version {
+            X402Version::V2(_) => {
+                if let Err(err) = network.parse::<Caip2>() {
+                    return invalid(err.to_string());
+                }
+            }
+            X402Version::V1(_) if network.is_empty() => {
+                return invalid("network must not be empty".to_string());
+            }
+            X402Version::V1(_) => {}
+        }
+
+        Ok(SupportedKinds {
+            x402_version,
+            scheme,
+            network,
+            extra,
+        })
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct SupportedResponse {
@@ -126,6 +532,193 @@ pub struct SupportedResponse {
     pub signers: Record<Vec<String>>,
 }
 
+impl SupportedResponse {
+    /// The supported kinds grouped by protocol version, oldest version first.
+    pub fn kinds_by_version(&self) -> BTreeMap<X402Version, Vec<&SupportedKinds>> {
+        let mut grouped: BTreeMap<X402Version, Vec<&SupportedKinds>> = BTreeMap::new();
+        for kind in &self.kinds {
+            grouped
+                .entry(kind.x402_version.clone())
+                .or_default()
+                .push(kind);
+        }
+        grouped
+    }
+
+    /// Sort the supported kinds by protocol version, newest version first.
+    ///
+    /// The sort is stable, so kinds of the same version keep their order.
+    pub fn sort_kinds_by_version(&mut self) {
+        self.kinds
+            .sort_by(|a, b| b.x402_version.cmp(&a.x402_version));
+    }
+
+    /// Keep only the kinds of the given protocol version, if the facilitator supports any.
+    ///
+    /// Facilitators supporting none of the kinds of the version are left unchanged, so that a
+    /// client can fall back to another version.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use x402_core::{facilitator::SupportedResponse, types::{X402V2, X402Version}};
+    ///
+    /// # fn negotiate(supported: SupportedResponse) {
+    /// let supported = supported.prefer_version(X402Version::V2(X402V2));
+    /// # }
+    /// ```
+    pub fn prefer_version(mut self, version: X402Version) -> Self {
+        if self.kinds.iter().any(|kind| kind.x402_version == version) {
+            self.kinds.retain(|kind| kind.x402_version == version);
+        }
+        self
+    }
+
+    /// The signers advertised for the CAIP-2 network, see [`match_signers`].
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use x402_core::facilitator::SupportedResponse;
+    ///
+    /// let supported: SupportedResponse = serde_json::from_value(serde_json::json!({
+    ///     "kinds": [],
+    ///     "extensions": [],
+    ///     "signers": {
+    ///         "eip155:*": ["0xAll"],
+    ///         "eip155:84532": ["0xSepolia"],
+    ///     },
+    /// }))
+    /// .unwrap();
+    ///
+    /// assert_eq!(supported.signers_for("eip155:84532"), ["0xSepolia"]);
+    /// assert_eq!(supported.signers_for("eip155:8453"), ["0xAll"]);
+    /// ```
+    pub fn signers_for(&self, network: &str) -> Vec<&str> {
+        match_signers(&self.signers, network)
+    }
+}
+
+/// The signers of the most specific pattern of `signers` matching the CAIP-2 network, or none if
+/// no pattern matches.
+///
+/// Patterns are matched with the following precedence:
+///
+/// 1. The exact network, e.g. `eip155:84532`.
+/// 2. Patterns ending with `*` whose prefix the network starts with, e.g. the namespace wildcard
+///    `eip155:*`, the longest prefix first.
+/// 3. The global `*`, the empty prefix.
+///
+/// Signers of less specific patterns are not included, so that a facilitator can override its
+/// namespace-wide signers for a single network.
+pub fn match_signers<'a>(signers: &'a Record<Vec<String>>, network: &str) -> Vec<&'a str> {
+    if let Some(exact) = signers.get(network) {
+        return exact.iter().map(String::as_str).collect();
+    }
+
+    signers
+        .iter()
+        .filter_map(|(pattern, addresses)| {
+            let prefix = pattern.strip_suffix('*')?;
+            network
+                .starts_with(prefix)
+                .then_some((prefix.len(), addresses))
+        })
+        .max_by_key(|(specificity, _)| *specificity)
+        .map(|(_, addresses)| addresses.iter().map(String::as_str).collect())
+        .unwrap_or_default()
+}
+
+/// The changes between two consecutive `supported` responses of a facilitator, e.g. to alert
+/// operators when a network is removed.
+///
+/// Kinds are identified by their protocol version, scheme and network.
+///
+/// # Example
+///
+/// ```
+/// use x402_core::facilitator::{SupportedDiff, SupportedResponse};
+///
+/// # fn alert(old: &SupportedResponse, new: &SupportedResponse) {
+/// let diff = SupportedDiff::compute(old, new);
+/// for kind in &diff.removed {
+///     eprintln!("Facilitator dropped {} on {}", kind.scheme, kind.network);
+/// }
+/// # }
+/// ```
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SupportedDiff {
+    /// Kinds supported by the new response only.
+    pub added: Vec<SupportedKinds>,
+    /// Kinds supported by the old response only.
+    pub removed: Vec<SupportedKinds>,
+    /// Kinds supported by both responses with different `extra`, as `(old, new)` pairs.
+    pub changed: Vec<(SupportedKinds, SupportedKinds)>,
+    /// Extensions implemented in the new response only.
+    pub added_extensions: Vec<ExtensionIdentifier>,
+    /// Extensions implemented in the old response only.
+    pub removed_extensions: Vec<ExtensionIdentifier>,
+    /// The CAIP-2 patterns whose signers were added, removed or changed, sorted.
+    pub changed_signers: Vec<String>,
+}
+
+impl SupportedDiff {
+    /// Compute the changes from the `old` to the `new` response.
+    pub fn compute(old: &SupportedResponse, new: &SupportedResponse) -> Self {
+        fn same_kind(a: &SupportedKinds, b: &SupportedKinds) -> bool {
+            a.x402_version == b.x402_version && a.scheme == b.scheme && a.network == b.network
+        }
+        fn missing_from<'a, T: Clone>(
+            from: &'a [T],
+            other: &'a [T],
+            same: impl Fn(&T, &T) -> bool,
+        ) -> Vec<T> {
+            from.iter()
+                .filter(|a| !other.iter().any(|b| same(a, b)))
+                .cloned()
+                .collect()
+        }
+
+        let changed = old
+            .kinds
+            .iter()
+            .filter_map(|a| {
+                let b = new.kinds.iter().find(|b| same_kind(a, b))?;
+                (a.extra != b.extra).then(|| (a.clone(), b.clone()))
+            })
+            .collect();
+
+        let mut changed_signers: Vec<String> = old
+            .signers
+            .keys()
+            .chain(new.signers.keys())
+            .filter(|pattern| old.signers.get(*pattern) != new.signers.get(*pattern))
+            .cloned()
+            .collect();
+        changed_signers.sort();
+        changed_signers.dedup();
+
+        SupportedDiff {
+            added: missing_from(&new.kinds, &old.kinds, same_kind),
+            removed: missing_from(&old.kinds, &new.kinds, same_kind),
+            changed,
+            added_extensions: missing_from(&new.extensions, &old.extensions, |a, b| a.0 == b.0),
+            removed_extensions: missing_from(&old.extensions, &new.extensions, |a, b| a.0 == b.0),
+            changed_signers,
+        }
+    }
+
+    /// Whether the responses are equivalent.
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty()
+            && self.removed.is_empty()
+            && self.changed.is_empty()
+            && self.added_extensions.is_empty()
+            && self.removed_extensions.is_empty()
+            && self.changed_signers.is_empty()
+    }
+}
+
 impl From<SettleSuccess> for SettlementResponse {
     fn from(success: SettleSuccess) -> Self {
         SettlementResponse {
@@ -133,10 +726,43 @@ impl From<SettleSuccess> for SettlementResponse {
             transaction: success.transaction,
             network: success.network,
             payer: success.payer,
+            order_id: None,
+            confirmation: success.confirmation,
         }
     }
 }
 
+/// Optional features of a facilitator beyond the scheme and network kinds it supports.
+///
+/// Lets composed facilitators and paywalls gate optional flows, e.g. only polling the settlement
+/// status of facilitators that confirm settlements asynchronously. Every feature is unsupported
+/// by default.
+///
+/// # Example
+///
+/// ```
+/// use x402_core::facilitator::FacilitatorCapabilities;
+///
+/// let capabilities = FacilitatorCapabilities::builder()
+///     .settlement_status(true)
+///     .build();
+///
+/// assert!(!capabilities.refunds);
+/// ```
+#[derive(Builder, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct FacilitatorCapabilities {
+    /// Settling less than the verified amount.
+    #[builder(default)]
+    pub partial_settlement: bool,
+    /// Reporting the status of settlements with [`Facilitator::settle_status`].
+    #[builder(default)]
+    pub settlement_status: bool,
+    /// Refunding settled payments.
+    #[builder(default)]
+    pub refunds: bool,
+}
+
 /// X402 facilitator interface.
 pub trait Facilitator {
     type Error: std::error::Error;
@@ -152,4 +778,374 @@ pub trait Facilitator {
         &self,
         request: PaymentRequest,
     ) -> impl Future<Output = Result<SettleResult, Self::Error>>;
+
+    /// Query the status of a successful settlement.
+    ///
+    /// Facilitators confirming settlements asynchronously report [`SettleStatus::Pending`] until the
+    /// transaction is confirmed, then the number of confirmations or its finality, see
+    /// [`SettleStatus::confirmation`]. By default every settlement is reported as confirmed once.
+    fn settle_status(
+        &self,
+        _settled: &SettleSuccess,
+    ) -> impl Future<Output = Result<SettleStatus, Self::Error>> {
+        async { Ok(SettleStatus::confirmed()) }
+    }
+
+    /// The optional features of the facilitator, none by default.
+    fn capabilities(&self) -> FacilitatorCapabilities {
+        FacilitatorCapabilities::default()
+    }
+}
+
+/// A [`Facilitator`] that can refund, or void, settled payments.
+///
+/// Refunds are not standardized by the x402 protocol yet, so only facilitators declaring
+/// [`FacilitatorCapabilities::refunds`] should be expected to implement them.
+pub trait Refundable: Facilitator {
+    /// Refund the given amount of a successful settlement to its payer.
+    ///
+    /// The amount may be less than the settled amount for a partial refund.
+    fn refund(
+        &self,
+        original: &SettleSuccess,
+        amount: AmountValue,
+    ) -> impl Future<Output = Result<RefundResult, Self::Error>>;
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use crate::{
+        errors::Error,
+        types::{X402V1, X402V2},
+    };
+
+    use super::*;
+
+    #[test]
+    fn build_supported_kinds() {
+        let evm = SupportedKinds::builder()
+            .x402_version(X402Version::V2(X402V2))
+            .scheme("exact")
+            .network("eip155:84532")
+            .build()
+            .unwrap();
+        assert_eq!(evm.network, "eip155:84532");
+        assert_eq!(evm.extra, None);
+
+        let svm = SupportedKinds::builder()
+            .x402_version(X402Version::V2(X402V2))
+            .scheme("exact")
+            .network("solana:EtWTRABZaYq6iMfeYKouRu166VU2xqa1")
+            .extra(json!({ "feePayer": "2wKupLR9q6wXYppw8Gr2NvWxKBUqm4PPJKkQfoxHDBg4" }))
+            .build()
+            .unwrap();
+        assert_eq!(svm.network.parse::<Caip2>().unwrap().namespace(), "solana");
+
+        // v1 kinds keep their network names
+        let v1 = SupportedKinds::builder()
+            .x402_version(X402Version::V1(X402V1))
+            .scheme("exact")
+            .network("base-sepolia")
+            .build()
+            .unwrap();
+        assert_eq!(v1.network, "base-sepolia");
+    }
+
+    fn mixed_versions() -> SupportedResponse {
+        let kind = |version: X402Version, network: &str| {
+            SupportedKinds::builder()
+                .x402_version(version)
+                .scheme("exact")
+                .network(network)
+                .build()
+                .unwrap()
+        };
+        SupportedResponse {
+            kinds: vec![
+                kind(X402Version::V1(X402V1), "base-sepolia"),
+                kind(X402Version::V2(X402V2), "eip155:84532"),
+                kind(X402Version::V1(X402V1), "base"),
+                kind(X402Version::V2(X402V2), "eip155:8453"),
+            ],
+            extensions: vec![],
+            signers: Record::new(),
+        }
+    }
+
+    fn networks(supported: &SupportedResponse) -> Vec<&str> {
+        supported.kinds.iter().map(|k| k.network.as_str()).collect()
+    }
+
+    #[test]
+    fn signers_for_most_specific_pattern() {
+        let signers = |patterns: &[(&str, &str)]| SupportedResponse {
+            signers: patterns
+                .iter()
+                .map(|(pattern, signer)| (pattern.to_string(), vec![signer.to_string()]))
+                .collect(),
+            ..mixed_versions()
+        };
+        let solana = "solana:EtWTRABZaYq6iMfeYKouRu166VU2xqa1";
+
+        for (patterns, network, expected) in [
+            // Exact beats wildcards
+            (
+                &[("eip155:*", "0xAll"), ("eip155:84532", "0xSepolia")][..],
+                "eip155:84532",
+                &["0xSepolia"][..],
+            ),
+            (
+                &[("eip155:*", "0xAll"), ("eip155:84532", "0xSepolia")],
+                "eip155:8453",
+                &["0xAll"],
+            ),
+            // Namespace wildcards only match their namespace
+            (
+                &[("eip155:*", "0xEvm"), ("solana:*", "SolFeePayer")],
+                solana,
+                &["SolFeePayer"],
+            ),
+            (
+                &[("eip155:*", "0xEvm"), ("solana:*", "SolFeePayer")],
+                "eip155:1",
+                &["0xEvm"],
+            ),
+            // Namespace wildcards beat the global wildcard
+            (
+                &[("*", "Global"), ("solana:*", "SolFeePayer")],
+                solana,
+                &["SolFeePayer"],
+            ),
+            (
+                &[("*", "Global"), ("solana:*", "SolFeePayer")],
+                "eip155:1",
+                &["Global"],
+            ),
+            // No match
+            (&[("solana:*", "SolFeePayer")], "eip155:1", &[]),
+            (&[], "eip155:1", &[]),
+            // A namespace is not a prefix of another
+            (&[("eip155:1", "0xMainnet")], "eip155:10", &[]),
+        ] {
+            assert_eq!(
+                signers(patterns).signers_for(network),
+                expected,
+                "{patterns:?} for {network}"
+            );
+        }
+    }
+
+    #[test]
+    fn supported_diff() {
+        let old = mixed_versions();
+        let mut new = mixed_versions();
+        // Base mainnet v2 removed, Solana added, fee payer added to Base Sepolia
+        new.kinds.remove(3);
+        new.kinds.push(
+            SupportedKinds::builder()
+                .x402_version(X402Version::V2(X402V2))
+                .scheme("exact")
+                .network("solana:EtWTRABZaYq6iMfeYKouRu166VU2xqa1")
+                .build()
+                .unwrap(),
+        );
+        new.kinds[1].extra = Some(json!({ "feePayer": "0xabc" }));
+        new.extensions
+            .push(ExtensionIdentifier("bazaar".to_string()));
+        new.signers
+            .insert("eip155:*".to_string(), vec!["0xabc".to_string()]);
+
+        let diff = SupportedDiff::compute(&old, &new);
+        assert_eq!(
+            networks_of(&diff.added),
+            ["solana:EtWTRABZaYq6iMfeYKouRu166VU2xqa1"]
+        );
+        assert_eq!(networks_of(&diff.removed), ["eip155:8453"]);
+        assert_eq!(diff.changed.len(), 1);
+        assert_eq!(diff.changed[0].0.network, "eip155:84532");
+        assert_eq!(
+            diff.changed[0].1.extra,
+            Some(json!({ "feePayer": "0xabc" }))
+        );
+        assert_eq!(
+            diff.added_extensions,
+            [ExtensionIdentifier("bazaar".to_string())]
+        );
+        assert!(diff.removed_extensions.is_empty());
+        assert_eq!(diff.changed_signers, ["eip155:*"]);
+
+        // The reverse diff swaps additions and removals
+        let reverse = SupportedDiff::compute(&new, &old);
+        assert_eq!(reverse.added, diff.removed);
+        assert_eq!(reverse.removed, diff.added);
+        assert_eq!(reverse.removed_extensions, diff.added_extensions);
+        assert_eq!(reverse.changed_signers, ["eip155:*"]);
+
+        // Rotated signers of a pattern are a change, regardless of the other patterns
+        let mut rotated = new.clone();
+        rotated
+            .signers
+            .insert("eip155:*".to_string(), vec!["0xdef".to_string()]);
+        rotated
+            .signers
+            .insert("solana:*".to_string(), vec!["Fee111".to_string()]);
+        assert_eq!(
+            SupportedDiff::compute(&new, &rotated).changed_signers,
+            ["eip155:*", "solana:*"]
+        );
+
+        assert!(SupportedDiff::compute(&new, &new).is_empty());
+        assert!(!diff.is_empty());
+    }
+
+    fn networks_of(kinds: &[SupportedKinds]) -> Vec<&str> {
+        kinds.iter().map(|k| k.network.as_str()).collect()
+    }
+
+    #[test]
+    fn prefer_supported_version() {
+        let supported = mixed_versions();
+        let grouped = supported.kinds_by_version();
+        assert_eq!(
+            grouped.keys().collect::<Vec<_>>(),
+            [&X402Version::V1(X402V1), &X402Version::V2(X402V2)]
+        );
+        assert_eq!(grouped[&X402Version::V1(X402V1)].len(), 2);
+
+        let mut sorted = mixed_versions();
+        sorted.sort_kinds_by_version();
+        assert_eq!(
+            networks(&sorted),
+            ["eip155:84532", "eip155:8453", "base-sepolia", "base"]
+        );
+
+        let v2 = mixed_versions().prefer_version(X402Version::V2(X402V2));
+        assert_eq!(networks(&v2), ["eip155:84532", "eip155:8453"]);
+        let v1 = mixed_versions().prefer_version(X402Version::V1(X402V1));
+        assert_eq!(networks(&v1), ["base-sepolia", "base"]);
+
+        // Facilitators without kinds of the version are left unchanged
+        let v1_only = v1.prefer_version(X402Version::V2(X402V2));
+        assert_eq!(networks(&v1_only), ["base-sepolia", "base"]);
+    }
+
+    fn payload(network: &str, payload: AnyJson) -> PaymentPayload {
+        serde_json::from_value(json!({
+            "x402Version": 2,
+            "resource": {
+                "url": "https://example.com/resource",
+                "description": "Protected resource",
+                "mimeType": "application/json"
+            },
+            "accepted": {
+                "scheme": "exact",
+                "network": network,
+                "amount": "1000",
+                "asset": "0x833589fCD6eDb6E08f4c7C32D4f71b54bdA02913",
+                "payTo": "0x3CB9B3bBfde8501f411bB69Ad3DC07908ED0dE20",
+                "maxTimeoutSeconds": 60,
+                "extra": null
+            },
+            "payload": payload,
+            "extensions": {}
+        }))
+        .unwrap()
+    }
+
+    fn authorization(from: &str, nonce: &str, signature: &str) -> AnyJson {
+        json!({
+            "signature": signature,
+            "authorization": { "from": from, "nonce": nonce, "value": "1000" }
+        })
+    }
+
+    #[test]
+    fn settlement_key_is_stable_across_retries() {
+        const FROM: &str = "0x857b06519E91e3A54538791bDbb0E22373e36b66";
+        const NONCE: &str = "0x01";
+
+        let evm = payload("eip155:8453", authorization(FROM, NONCE, "0xaa"));
+        let key = SettlementKey::derive(&evm);
+        assert_eq!(key, SettlementKey::derive(&evm.clone()));
+        assert_eq!(key.0.len(), 64);
+
+        // The authorization identifies the payment, whatever the case of its hex
+        let retry = payload(
+            "eip155:8453",
+            authorization(&FROM.to_lowercase(), NONCE, "0xaa"),
+        );
+        assert_eq!(SettlementKey::derive(&retry), key);
+
+        let svm = payload("solana:devnet", json!({ "transaction": "AQID" }));
+        assert_eq!(SettlementKey::derive(&svm), SettlementKey::derive(&svm));
+    }
+
+    #[test]
+    fn settlement_key_differs_between_payments() {
+        const FROM: &str = "0x857b06519E91e3A54538791bDbb0E22373e36b66";
+
+        let keys = [
+            payload("eip155:8453", authorization(FROM, "0x01", "0xaa")),
+            payload("eip155:8453", authorization(FROM, "0x02", "0xaa")),
+            payload("eip155:1", authorization(FROM, "0x01", "0xaa")),
+            payload("solana:devnet", json!({ "transaction": "AQID" })),
+            payload("solana:devnet", json!({ "transaction": "AQIE" })),
+            payload("eip155:8453", json!({ "signature": "0xaa" })),
+            payload("eip155:8453", json!({ "signature": "0xbb" })),
+        ]
+        .map(|payload| SettlementKey::derive(&payload));
+
+        for (i, key) in keys.iter().enumerate() {
+            assert!(!keys[i + 1..].contains(key), "duplicate key {key}");
+        }
+    }
+
+    #[test]
+    fn reject_malformed_network() {
+        for (network, reason) in [
+            ("base-sepolia", "'base-sepolia' is not a CAIP-2 identifier"),
+            ("EIP155:1", "Invalid CAIP-2 namespace 'EIP155'"),
+            ("eip155:", "Invalid CAIP-2 reference ''"),
+        ] {
+            let err = SupportedKinds::builder()
+                .x402_version(X402Version::V2(X402V2))
+                .scheme("exact")
+                .network(network)
+                .build()
+                .unwrap_err();
+
+            assert!(
+                matches!(&err, Error::InvalidSupportedKinds(message) if message.starts_with(reason)),
+                "unexpected error for {network}: {err}"
+            );
+        }
+    }
+
+    #[test]
+    fn settle_status_confirmation() {
+        let status = |status: AnyJson| serde_json::from_value::<SettleStatus>(status).unwrap();
+
+        assert_eq!(
+            status(json!({ "status": "pending" })).confirmation(),
+            Some(ConfirmationStatus::Broadcast)
+        );
+        assert_eq!(
+            status(json!({ "status": "confirmed" })),
+            SettleStatus::confirmed()
+        );
+        assert_eq!(
+            status(json!({ "status": "confirmed", "confirmations": 3 })).confirmation(),
+            Some(ConfirmationStatus::Confirmed(3))
+        );
+        assert_eq!(
+            status(json!({ "status": "finalized" })).confirmation(),
+            Some(ConfirmationStatus::Finalized)
+        );
+        assert_eq!(
+            status(json!({ "status": "failed", "errorReason": "reverted" })).confirmation(),
+            None
+        );
+    }
 }
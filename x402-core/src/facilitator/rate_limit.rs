@@ -0,0 +1,202 @@
+//! A facilitator limiting the rate of calls to another facilitator.
+
+use std::{
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use tokio::time::Instant;
+
+use super::{
+    Facilitator, FacilitatorCapabilities, PaymentRequest, SettleResult, SettleStatus,
+    SettleSuccess, SupportedResponse, VerifyResult,
+};
+
+/// A [`Facilitator`] limiting the calls to the inner facilitator with a token bucket, e.g. to stay
+/// under the rate limits of a hosted facilitator during bursts of traffic.
+///
+/// Up to `burst` calls are made at once, then `per_second` calls per second as the bucket refills.
+/// Calls over the limit wait for a token before being passed to the inner facilitator, in the
+/// order they arrived. Every call reaching the facilitator takes a token, i.e. `supported`,
+/// `verify`, `settle` and `settle_status`. Clones share the same bucket.
+///
+/// Available with the `tokio` feature.
+///
+/// # Example
+///
+/// ```
+/// use x402_core::facilitator::{Facilitator, RateLimitedFacilitator};
+///
+/// # fn rate_limit<F: Facilitator>(remote: F) {
+/// // At most 10 calls per second, with bursts of up to 20 calls
+/// let facilitator = RateLimitedFacilitator::new(remote, 10, 20);
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct RateLimitedFacilitator<F> {
+    pub facilitator: F,
+    bucket: Arc<TokenBucket>,
+}
+
+impl<F> RateLimitedFacilitator<F> {
+    /// Limit the calls to `facilitator` to `per_second` calls per second, with bursts of up to
+    /// `burst` calls.
+    ///
+    /// Both are raised to at least one.
+    pub fn new(facilitator: F, per_second: u32, burst: u32) -> Self {
+        RateLimitedFacilitator {
+            facilitator,
+            bucket: Arc::new(TokenBucket::new(per_second.max(1), burst.max(1))),
+        }
+    }
+}
+
+impl<F: Facilitator> Facilitator for RateLimitedFacilitator<F> {
+    type Error = F::Error;
+
+    async fn supported(&self) -> Result<SupportedResponse, Self::Error> {
+        self.bucket.acquire().await;
+        self.facilitator.supported().await
+    }
+
+    async fn verify(&self, request: PaymentRequest) -> Result<VerifyResult, Self::Error> {
+        self.bucket.acquire().await;
+        self.facilitator.verify(request).await
+    }
+
+    async fn settle(&self, request: PaymentRequest) -> Result<SettleResult, Self::Error> {
+        self.bucket.acquire().await;
+        self.facilitator.settle(request).await
+    }
+
+    async fn settle_status(&self, settled: &SettleSuccess) -> Result<SettleStatus, Self::Error> {
+        self.bucket.acquire().await;
+        self.facilitator.settle_status(settled).await
+    }
+
+    fn capabilities(&self) -> FacilitatorCapabilities {
+        self.facilitator.capabilities()
+    }
+}
+
+/// A token bucket refilling continuously at `per_second` tokens per second, up to `burst` tokens.
+#[derive(Debug)]
+struct TokenBucket {
+    per_second: f64,
+    burst: f64,
+    state: Mutex<BucketState>,
+}
+
+#[derive(Debug)]
+struct BucketState {
+    /// The tokens left, negative when tokens are reserved by waiting calls.
+    tokens: f64,
+    updated: Instant,
+}
+
+impl TokenBucket {
+    fn new(per_second: u32, burst: u32) -> Self {
+        TokenBucket {
+            per_second: per_second.into(),
+            burst: burst.into(),
+            state: Mutex::new(BucketState {
+                tokens: burst.into(),
+                updated: Instant::now(),
+            }),
+        }
+    }
+
+    /// Take a token, waiting until it is refilled if none is left.
+    ///
+    /// The token is reserved before waiting, so that waiting calls are served in order. It is not
+    /// given back if the call is cancelled while waiting.
+    async fn acquire(&self) {
+        let wait = {
+            let mut state = self
+                .state
+                .lock()
+                .unwrap_or_else(|poisoned| poisoned.into_inner());
+            let now = Instant::now();
+            let refilled = now.duration_since(state.updated).as_secs_f64() * self.per_second;
+            state.tokens = (state.tokens + refilled).min(self.burst) - 1.0;
+            state.updated = now;
+
+            if state.tokens >= 0.0 {
+                return;
+            }
+            Duration::from_secs_f64(-state.tokens / self.per_second)
+        };
+
+        tokio::time::sleep(wait).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::convert::Infallible;
+
+    use crate::types::Record;
+
+    use super::*;
+
+    /// A facilitator answering `supported` immediately.
+    struct Empty;
+
+    impl Facilitator for Empty {
+        type Error = Infallible;
+
+        async fn supported(&self) -> Result<SupportedResponse, Self::Error> {
+            Ok(SupportedResponse {
+                kinds: vec![],
+                extensions: vec![],
+                signers: Record::new(),
+            })
+        }
+
+        async fn verify(&self, _request: PaymentRequest) -> Result<VerifyResult, Self::Error> {
+            unreachable!()
+        }
+
+        async fn settle(&self, _request: PaymentRequest) -> Result<SettleResult, Self::Error> {
+            unreachable!()
+        }
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn call_over_the_burst_waits_for_a_token() {
+        let facilitator = RateLimitedFacilitator::new(Empty, 2, 3);
+        let start = Instant::now();
+        let call = || async {
+            facilitator.supported().await.unwrap();
+            start.elapsed()
+        };
+
+        let elapsed = tokio::join!(call(), call(), call(), call());
+
+        assert_eq!(
+            elapsed,
+            (
+                Duration::ZERO,
+                Duration::ZERO,
+                Duration::ZERO,
+                Duration::from_millis(500)
+            )
+        );
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn bucket_refills_up_to_the_burst() {
+        let facilitator = RateLimitedFacilitator::new(Empty, 2, 3);
+        for _ in 0..3 {
+            facilitator.supported().await.unwrap();
+        }
+
+        tokio::time::sleep(Duration::from_secs(10)).await;
+        let start = Instant::now();
+        for _ in 0..4 {
+            facilitator.supported().await.unwrap();
+        }
+
+        assert_eq!(start.elapsed(), Duration::from_millis(500));
+    }
+}
@@ -0,0 +1,290 @@
+//! A facilitator verifying payments with one facilitator and settling them with another.
+
+use super::{
+    Facilitator, FacilitatorCapabilities, PaymentRequest, SettleResult, SettleStatus,
+    SettleSuccess, SupportedResponse, VerifyResult,
+};
+
+/// Errors of a [`SplitFacilitator`].
+#[derive(Debug, thiserror::Error)]
+pub enum SplitFacilitatorError<V: std::error::Error, S: std::error::Error> {
+    /// The verifying facilitator failed.
+    #[error("Verifier error: {0}")]
+    Verifier(V),
+    /// The settling facilitator failed.
+    #[error("Settler error: {0}")]
+    Settler(S),
+}
+
+/// A [`Facilitator`] verifying payments with `verifier` and settling them with `settler`, e.g. a
+/// cheap, trusted local verifier and a remote facilitator executing settlements on chain.
+///
+/// `supported` returns the kinds of the settler that the verifier supports too, with the
+/// settler's `extra`, extensions and signers, as the settler executes the payments. Paywalls
+/// updating their accepted payment requirements thus only advertise payments both can handle.
+///
+/// `settle_status` and `capabilities` are those of the settler. The attestation of a verification
+/// only means something to the facilitator that issued it, so it is not sent to the settler.
+///
+/// # Example
+///
+/// ```
+/// use x402_core::facilitator::{Facilitator, SplitFacilitator};
+///
+/// # fn split<V: Facilitator, S: Facilitator>(local: V, remote: S) {
+/// let facilitator = SplitFacilitator::new(local, remote);
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct SplitFacilitator<V, S> {
+    pub verifier: V,
+    pub settler: S,
+}
+
+impl<V, S> SplitFacilitator<V, S> {
+    pub fn new(verifier: V, settler: S) -> Self {
+        SplitFacilitator { verifier, settler }
+    }
+}
+
+impl<V: Facilitator, S: Facilitator> Facilitator for SplitFacilitator<V, S> {
+    type Error = SplitFacilitatorError<V::Error, S::Error>;
+
+    async fn supported(&self) -> Result<SupportedResponse, Self::Error> {
+        let verifiable = self
+            .verifier
+            .supported()
+            .await
+            .map_err(SplitFacilitatorError::Verifier)?;
+        let mut supported = self
+            .settler
+            .supported()
+            .await
+            .map_err(SplitFacilitatorError::Settler)?;
+
+        supported.kinds.retain(|kind| {
+            verifiable.kinds.iter().any(|verified| {
+                verified.x402_version == kind.x402_version
+                    && verified.scheme == kind.scheme
+                    && verified.network == kind.network
+            })
+        });
+
+        Ok(supported)
+    }
+
+    async fn verify(&self, request: PaymentRequest) -> Result<VerifyResult, Self::Error> {
+        self.verifier
+            .verify(request)
+            .await
+            .map_err(SplitFacilitatorError::Verifier)
+    }
+
+    async fn settle(&self, request: PaymentRequest) -> Result<SettleResult, Self::Error> {
+        self.settler
+            .settle(PaymentRequest {
+                attestation: None,
+                ..request
+            })
+            .await
+            .map_err(SplitFacilitatorError::Settler)
+    }
+
+    async fn settle_status(&self, settled: &SettleSuccess) -> Result<SettleStatus, Self::Error> {
+        self.settler
+            .settle_status(settled)
+            .await
+            .map_err(SplitFacilitatorError::Settler)
+    }
+
+    fn capabilities(&self) -> FacilitatorCapabilities {
+        self.settler.capabilities()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        convert::Infallible,
+        sync::{
+            Mutex,
+            atomic::{AtomicUsize, Ordering},
+        },
+    };
+
+    use serde_json::json;
+
+    use crate::{
+        facilitator::{SupportedKinds, VerifyValid},
+        types::{Record, X402V2, X402Version},
+    };
+
+    use super::*;
+
+    /// A facilitator supporting the given networks, counting its calls.
+    #[derive(Debug, Default)]
+    struct Counting {
+        networks: Vec<&'static str>,
+        supported: AtomicUsize,
+        verify: AtomicUsize,
+        settle: AtomicUsize,
+        settle_status: AtomicUsize,
+        attestations: Mutex<Vec<Option<serde_json::Value>>>,
+    }
+
+    impl Counting {
+        fn new(networks: &[&'static str]) -> Self {
+            Counting {
+                networks: networks.to_vec(),
+                ..Default::default()
+            }
+        }
+
+        fn calls(&self) -> [usize; 4] {
+            [
+                &self.supported,
+                &self.verify,
+                &self.settle,
+                &self.settle_status,
+            ]
+            .map(|calls| calls.load(Ordering::SeqCst))
+        }
+    }
+
+    impl Facilitator for Counting {
+        type Error = Infallible;
+
+        async fn supported(&self) -> Result<SupportedResponse, Self::Error> {
+            self.supported.fetch_add(1, Ordering::SeqCst);
+            Ok(SupportedResponse {
+                kinds: self
+                    .networks
+                    .iter()
+                    .map(|network| {
+                        SupportedKinds::builder()
+                            .x402_version(X402Version::V2(X402V2))
+                            .scheme("exact")
+                            .network(*network)
+                            .extra(json!({ "facilitator": self.networks.len() }))
+                            .build()
+                            .unwrap()
+                    })
+                    .collect(),
+                extensions: vec![],
+                signers: Record::new(),
+            })
+        }
+
+        async fn verify(&self, _request: PaymentRequest) -> Result<VerifyResult, Self::Error> {
+            self.verify.fetch_add(1, Ordering::SeqCst);
+            Ok(VerifyResult::valid(VerifyValid {
+                payer: "0x857b06519E91e3A54538791bDbb0E22373e36b66".to_string(),
+                attestation: Some(json!({ "verificationId": "v-123" })),
+            }))
+        }
+
+        async fn settle(&self, request: PaymentRequest) -> Result<SettleResult, Self::Error> {
+            self.settle.fetch_add(1, Ordering::SeqCst);
+            self.attestations.lock().unwrap().push(request.attestation);
+            Ok(SettleResult::success(SettleSuccess {
+                payer: "0x857b06519E91e3A54538791bDbb0E22373e36b66".to_string(),
+                transaction: "0xdeadbeef".to_string(),
+                network: "eip155:8453".to_string(),
+                verified_signature: false,
+                signer: None,
+                confirmation: None,
+            }))
+        }
+
+        async fn settle_status(
+            &self,
+            _settled: &SettleSuccess,
+        ) -> Result<SettleStatus, Self::Error> {
+            self.settle_status.fetch_add(1, Ordering::SeqCst);
+            Ok(SettleStatus::Pending)
+        }
+
+        fn capabilities(&self) -> FacilitatorCapabilities {
+            FacilitatorCapabilities::builder()
+                .settlement_status(true)
+                .build()
+        }
+    }
+
+    fn request() -> PaymentRequest {
+        let requirements = json!({
+            "scheme": "exact",
+            "network": "eip155:8453",
+            "amount": "1000",
+            "asset": "0x833589fCD6eDb6E08f4c7C32D4f71b54bdA02913",
+            "payTo": "0x3CB9B3bBfde8501f411bB69Ad3DC07908ED0dE20",
+            "maxTimeoutSeconds": 60
+        });
+
+        PaymentRequest {
+            payment_payload: serde_json::from_value(json!({
+                "x402Version": 2,
+                "resource": {
+                    "url": "https://example.com/resource",
+                    "description": "Protected resource",
+                    "mimeType": "application/json"
+                },
+                "accepted": requirements,
+                "payload": {},
+                "extensions": {}
+            }))
+            .unwrap(),
+            payment_requirements: serde_json::from_value(requirements).unwrap(),
+            attestation: None,
+            idempotency_key: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn delegates_verify_and_settle() {
+        let facilitator = SplitFacilitator::new(
+            Counting::new(&["eip155:8453"]),
+            Counting::new(&["eip155:8453"]),
+        );
+
+        let verified = facilitator.verify(request()).await.unwrap();
+        let attestation = verified.as_valid().unwrap().attestation.clone();
+        assert_eq!(facilitator.verifier.calls(), [0, 1, 0, 0]);
+        assert_eq!(facilitator.settler.calls(), [0, 0, 0, 0]);
+
+        let settled = facilitator
+            .settle(PaymentRequest {
+                attestation,
+                ..request()
+            })
+            .await
+            .unwrap();
+        facilitator
+            .settle_status(settled.as_success().unwrap())
+            .await
+            .unwrap();
+        assert_eq!(facilitator.verifier.calls(), [0, 1, 0, 0]);
+        assert_eq!(facilitator.settler.calls(), [0, 0, 1, 1]);
+
+        // The verifier's attestation is not sent to the settler
+        assert_eq!(*facilitator.settler.attestations.lock().unwrap(), [None]);
+        assert!(facilitator.capabilities().settlement_status);
+    }
+
+    #[tokio::test]
+    async fn supported_is_the_intersection_with_settler_kinds() {
+        let facilitator = SplitFacilitator::new(
+            Counting::new(&["eip155:8453", "eip155:84532", "eip155:1"]),
+            Counting::new(&["eip155:84532", "eip155:8453"]),
+        );
+
+        let supported = facilitator.supported().await.unwrap();
+
+        let networks: Vec<_> = supported.kinds.iter().map(|k| k.network.as_str()).collect();
+        assert_eq!(networks, ["eip155:84532", "eip155:8453"]);
+        // The settler's extra is kept
+        assert_eq!(supported.kinds[0].extra, Some(json!({ "facilitator": 2 })));
+        assert_eq!(facilitator.verifier.calls(), [1, 0, 0, 0]);
+        assert_eq!(facilitator.settler.calls(), [1, 0, 0, 0]);
+    }
+}
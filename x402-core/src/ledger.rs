@@ -0,0 +1,326 @@
+//! Payment ledgers for recording settled payments.
+//!
+//! A [`LedgerFacilitator`] wraps any [`Facilitator`] and records a [`PaymentReceipt`]
+//! to a [`Ledger`] for every successful settlement, e.g. for auditing.
+
+use std::{
+    convert::Infallible,
+    sync::{Arc, Mutex},
+};
+
+use crate::facilitator::{
+    Facilitator, FacilitatorCapabilities, PaymentReceipt, PaymentRequest, SettleResult,
+    SettleStatus, SettleSuccess, SupportedResponse, VerifyResult,
+};
+
+/// A store of payment receipts.
+pub trait Ledger {
+    type Error: std::error::Error;
+
+    /// Record the receipt of a successful settlement.
+    fn record(
+        &self,
+        receipt: PaymentReceipt,
+    ) -> impl Future<Output = Result<(), Self::Error>> + Send;
+}
+
+/// Shared ledgers, e.g. to read the receipts of a [`VecLedger`] recorded by a paywall.
+impl<L: Ledger> Ledger for Arc<L> {
+    type Error = L::Error;
+
+    fn record(
+        &self,
+        receipt: PaymentReceipt,
+    ) -> impl Future<Output = Result<(), Self::Error>> + Send {
+        (**self).record(receipt)
+    }
+}
+
+/// An in-memory [`Ledger`], mostly useful for tests.
+#[derive(Debug, Default)]
+pub struct VecLedger {
+    receipts: Mutex<Vec<PaymentReceipt>>,
+}
+
+impl VecLedger {
+    /// Create a ledger with no receipts.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// All receipts recorded so far, in order.
+    pub fn receipts(&self) -> Vec<PaymentReceipt> {
+        self.receipts
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .clone()
+    }
+}
+
+impl Ledger for VecLedger {
+    type Error = Infallible;
+
+    async fn record(&self, receipt: PaymentReceipt) -> Result<(), Self::Error> {
+        self.receipts
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .push(receipt);
+        Ok(())
+    }
+}
+
+/// A callback notified of the receipts a [`LedgerFacilitator`] failed to record, with the error of
+/// the ledger.
+pub type LedgerErrorHook = Arc<dyn Fn(&dyn std::error::Error, &PaymentReceipt) + Send + Sync>;
+
+/// A [`Facilitator`] recording a [`PaymentReceipt`] to a [`Ledger`] on every successful settlement.
+///
+/// `supported`, `verify` and `settle_status` are passed through to the inner facilitator unchanged.
+///
+/// A settlement is never failed because its receipt could not be recorded, as the payer was
+/// already charged. Ledger errors are reported to the [`on_ledger_error`](Self::on_ledger_error)
+/// hook instead, e.g. to log the receipt or record it later.
+#[derive(Clone)]
+pub struct LedgerFacilitator<F, L> {
+    pub facilitator: F,
+    pub ledger: L,
+    pub ledger_error_hook: Option<LedgerErrorHook>,
+}
+
+impl<F, L> LedgerFacilitator<F, L> {
+    /// Wrap the facilitator, recording its settlements to the ledger.
+    pub fn new(facilitator: F, ledger: L) -> Self {
+        LedgerFacilitator {
+            facilitator,
+            ledger,
+            ledger_error_hook: None,
+        }
+    }
+
+    /// Call `hook` with the ledger error and the receipt whenever a successful settlement could not
+    /// be recorded, replacing any previous hook.
+    pub fn on_ledger_error(
+        mut self,
+        hook: impl Fn(&dyn std::error::Error, &PaymentReceipt) + Send + Sync + 'static,
+    ) -> Self {
+        self.ledger_error_hook = Some(Arc::new(hook));
+        self
+    }
+}
+
+impl<F: std::fmt::Debug, L: std::fmt::Debug> std::fmt::Debug for LedgerFacilitator<F, L> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("LedgerFacilitator")
+            .field("facilitator", &self.facilitator)
+            .field("ledger", &self.ledger)
+            .field("ledger_error_hook", &self.ledger_error_hook.is_some())
+            .finish()
+    }
+}
+
+impl<F: Facilitator, L: Ledger> Facilitator for LedgerFacilitator<F, L> {
+    type Error = F::Error;
+
+    async fn supported(&self) -> Result<SupportedResponse, Self::Error> {
+        self.facilitator.supported().await
+    }
+
+    async fn verify(&self, request: PaymentRequest) -> Result<VerifyResult, Self::Error> {
+        self.facilitator.verify(request).await
+    }
+
+    /// Settle with the inner facilitator, then record the receipt of a successful settlement.
+    ///
+    /// The settlement result is returned even if recording fails.
+    async fn settle(&self, request: PaymentRequest) -> Result<SettleResult, Self::Error> {
+        let requirements = request.payment_requirements.clone();
+        let result = self.facilitator.settle(request).await?;
+
+        if let SettleResult::Success(settled) = &result {
+            let receipt = PaymentReceipt::new(settled, &requirements);
+            if let Err(err) = self.ledger.record(receipt.clone()).await
+                && let Some(hook) = &self.ledger_error_hook
+            {
+                hook(&err, &receipt);
+            }
+        }
+
+        Ok(result)
+    }
+
+    async fn settle_status(&self, settled: &SettleSuccess) -> Result<SettleStatus, Self::Error> {
+        self.facilitator.settle_status(settled).await
+    }
+
+    fn capabilities(&self) -> FacilitatorCapabilities {
+        self.facilitator.capabilities()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use crate::{
+        facilitator::{SettleFailed, SettleSuccess},
+        transport::{PaymentPayload, PaymentRequirements},
+        types::{AmountValue, Record, SchemeName, X402V2},
+    };
+
+    use super::*;
+
+    #[derive(Debug)]
+    struct StaticFacilitator(SettleResult);
+
+    impl Facilitator for StaticFacilitator {
+        type Error = Infallible;
+
+        async fn supported(&self) -> Result<SupportedResponse, Self::Error> {
+            unimplemented!()
+        }
+
+        async fn verify(&self, _request: PaymentRequest) -> Result<VerifyResult, Self::Error> {
+            unimplemented!()
+        }
+
+        async fn settle(&self, _request: PaymentRequest) -> Result<SettleResult, Self::Error> {
+            Ok(self.0.clone())
+        }
+    }
+
+    fn request() -> PaymentRequest {
+        let requirements = PaymentRequirements {
+            scheme: SchemeName::EXACT,
+            network: "eip155:8453".to_string(),
+            amount: AmountValue(1000),
+            asset: "0x833589fCD6eDb6E08f4c7C32D4f71b54bdA02913".to_string(),
+            pay_to: "0x3CB9B3bBfde8501f411bB69Ad3DC07908ED0dE20".to_string(),
+            max_timeout_seconds: 60,
+            extra: None,
+        };
+
+        PaymentRequest {
+            payment_payload: PaymentPayload {
+                x402_version: X402V2,
+                resource: serde_json::from_value(json!({
+                    "url": "https://example.com/resource",
+                    "description": "Protected resource",
+                    "mimeType": "application/json"
+                }))
+                .unwrap(),
+                accepted: requirements.clone(),
+                payload: json!({}),
+                extensions: Record::new(),
+            },
+            payment_requirements: requirements,
+            attestation: None,
+            idempotency_key: None,
+        }
+    }
+
+    #[test]
+    fn capabilities_default_to_none_and_are_forwarded() {
+        let facilitator = StaticFacilitator(SettleResult::failed(SettleFailed {
+            error_reason: "insufficient_funds".to_string(),
+            payer: None,
+        }));
+
+        let capabilities = facilitator.capabilities();
+        assert_eq!(capabilities, FacilitatorCapabilities::default());
+        assert!(!capabilities.partial_settlement);
+        assert!(!capabilities.settlement_status);
+        assert!(!capabilities.refunds);
+
+        let ledger = LedgerFacilitator::new(facilitator, VecLedger::new());
+        assert_eq!(ledger.capabilities(), capabilities);
+    }
+
+    #[tokio::test]
+    async fn settle_records_one_receipt() {
+        let facilitator = LedgerFacilitator::new(
+            StaticFacilitator(SettleResult::success(SettleSuccess {
+                payer: "0x857b06519E91e3A54538791bDbb0E22373e36b66".to_string(),
+                transaction: "0xdeadbeef".to_string(),
+                network: "eip155:8453".to_string(),
+                verified_signature: false,
+                signer: None,
+                confirmation: None,
+            })),
+            VecLedger::new(),
+        );
+
+        let result = facilitator.settle(request()).await.unwrap();
+
+        assert!(matches!(result, SettleResult::Success(_)));
+        let receipts = facilitator.ledger.receipts();
+        assert_eq!(receipts.len(), 1);
+        assert_eq!(receipts[0].transaction, "0xdeadbeef");
+        assert_eq!(receipts[0].scheme, "exact");
+        assert_eq!(receipts[0].amount, AmountValue(1000));
+    }
+
+    #[tokio::test]
+    async fn failed_settle_records_nothing() {
+        let facilitator = LedgerFacilitator::new(
+            StaticFacilitator(SettleResult::failed(SettleFailed {
+                error_reason: "insufficient_funds".to_string(),
+                payer: None,
+            })),
+            VecLedger::new(),
+        );
+
+        let result = facilitator.settle(request()).await.unwrap();
+
+        assert!(matches!(result, SettleResult::Failed(_)));
+        assert!(facilitator.ledger.receipts().is_empty());
+    }
+
+    /// A ledger whose writes always fail.
+    #[derive(Debug)]
+    struct BrokenLedger;
+
+    #[derive(Debug, thiserror::Error)]
+    #[error("ledger unavailable")]
+    struct Unavailable;
+
+    impl Ledger for BrokenLedger {
+        type Error = Unavailable;
+
+        async fn record(&self, _receipt: PaymentReceipt) -> Result<(), Self::Error> {
+            Err(Unavailable)
+        }
+    }
+
+    #[tokio::test]
+    async fn ledger_error_keeps_settlement() {
+        let failures = Arc::new(Mutex::new(Vec::new()));
+        let facilitator = LedgerFacilitator::new(
+            StaticFacilitator(SettleResult::success(SettleSuccess {
+                payer: "0x857b06519E91e3A54538791bDbb0E22373e36b66".to_string(),
+                transaction: "0xdeadbeef".to_string(),
+                network: "eip155:8453".to_string(),
+                verified_signature: false,
+                signer: None,
+                confirmation: None,
+            })),
+            BrokenLedger,
+        )
+        .on_ledger_error({
+            let failures = failures.clone();
+            move |err, receipt| {
+                failures
+                    .lock()
+                    .unwrap()
+                    .push((err.to_string(), receipt.transaction.clone()));
+            }
+        });
+
+        let result = facilitator.settle(request()).await.unwrap();
+
+        assert_eq!(result.as_success().unwrap().transaction, "0xdeadbeef");
+        assert_eq!(
+            *failures.lock().unwrap(),
+            vec![("ledger unavailable".to_string(), "0xdeadbeef".to_string())]
+        );
+    }
+}
@@ -5,5 +5,6 @@
 pub mod core;
 pub mod errors;
 pub mod facilitator;
+pub mod ledger;
 pub mod transport;
 pub mod types;
@@ -1,20 +1,27 @@
 //! X402 transport types and serialization.
 
-use std::fmt::Debug;
+pub mod v1;
+
+use std::{fmt::Debug, sync::Arc};
 
 use base64::{Engine, prelude::BASE64_STANDARD};
+use bon::Builder;
 use serde::{Deserialize, Serialize};
 use url::Url;
 
 use crate::{
     core::{Address, NetworkFamily, Payment, Resource, Scheme},
-    types::{AmountValue, AnyJson, Base64EncodedHeader, Extension, Record, X402V2},
+    facilitator::ConfirmationStatus,
+    types::{
+        AmountValue, AnyJson, Base64EncodedHeader, Extension, KnownAssetRegistry, Record,
+        SchemeName, X402V2,
+    },
 };
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
 #[serde(rename_all = "camelCase")]
 pub struct PaymentRequirements {
-    pub scheme: String,
+    pub scheme: SchemeName,
     pub network: String,
     pub amount: AmountValue,
     pub asset: String,
@@ -23,7 +30,89 @@ pub struct PaymentRequirements {
     pub extra: Option<AnyJson>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+impl PaymentRequirements {
+    /// The amount in whole tokens of the asset, if known to the registry, e.g. `0.001000 USDC`.
+    ///
+    /// Falls back to the amount in the asset's smallest units for unknown assets.
+    pub fn display_amount(&self, registry: &KnownAssetRegistry) -> String {
+        match registry.get(&self.network, &self.asset) {
+            Some(asset) => self.amount.display_with(asset.decimals, &asset.symbol),
+            None => self.amount.to_string(),
+        }
+    }
+}
+
+#[bon::bon]
+impl PaymentRequirements {
+    /// Build payment requirements for any scheme and network, without a typed scheme.
+    ///
+    /// Prefer the typed schemes where available. This builder validates that `amount` and
+    /// `max_timeout_seconds` are positive and that `scheme`, `network`, `asset` and `pay_to`
+    /// are non-empty.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use x402_core::transport::PaymentRequirements;
+    ///
+    /// let requirements = PaymentRequirements::builder()
+    ///     .scheme("exact")
+    ///     .network("eip155:8453")
+    ///     .amount(1000u64)
+    ///     .asset("0x833589fCD6eDb6E08f4c7C32D4f71b54bdA02913")
+    ///     .pay_to("0x3CB9B3bBfde8501f411bB69Ad3DC07908ED0dE20")
+    ///     .max_timeout_seconds(60)
+    ///     .build()
+    ///     .unwrap();
+    ///
+    /// assert_eq!(requirements.network, "eip155:8453");
+    /// ```
+    #[builder]
+    pub fn new(
+        #[builder(into)] scheme: SchemeName,
+        #[builder(into)] network: String,
+        #[builder(into)] amount: AmountValue,
+        #[builder(into)] asset: String,
+        #[builder(into)] pay_to: String,
+        max_timeout_seconds: u64,
+        extra: Option<AnyJson>,
+    ) -> crate::errors::Result<Self> {
+        let invalid = |reason: &str| {
+            Err(crate::errors::Error::InvalidPaymentRequirements(
+                reason.to_string(),
+            ))
+        };
+
+        if amount.0 == 0 {
+            return invalid("amount must be greater than zero");
+        }
+        if max_timeout_seconds == 0 {
+            return invalid("max_timeout_seconds must be greater than zero");
+        }
+        for (field, value) in [
+            ("scheme", scheme.as_str()),
+            ("network", &network),
+            ("asset", &asset),
+            ("pay_to", &pay_to),
+        ] {
+            if value.is_empty() {
+                return invalid(&format!("{field} must not be empty"));
+            }
+        }
+
+        Ok(PaymentRequirements {
+            scheme,
+            network,
+            amount,
+            asset,
+            pay_to,
+            max_timeout_seconds,
+            extra,
+        })
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
 #[serde(rename_all = "camelCase")]
 pub struct PaymentResource {
     pub url: Url,
@@ -41,8 +130,20 @@ impl From<Resource> for PaymentResource {
     }
 }
 
-#[derive(Clone, Default)]
-pub struct Accepts(Vec<PaymentRequirements>);
+/// The accepted payment requirements of a resource.
+///
+/// Backed by a shared list, so that clones are cheap: they share the requirements, including
+/// their `extra`, instead of copying them. Build the list with [`push`](Accepts::push), from a
+/// `Vec`, with [`from_payments`](Accepts::from_payments) or with the [`accepts!`](crate::accepts)
+/// macro. The requirements are only copied when a shared list is modified or consumed.
+#[derive(Clone, PartialEq, Eq)]
+pub struct Accepts(Arc<Vec<PaymentRequirements>>);
+
+impl Default for Accepts {
+    fn default() -> Self {
+        Accepts::new()
+    }
+}
 
 impl AsRef<[PaymentRequirements]> for Accepts {
     fn as_ref(&self) -> &[PaymentRequirements] {
@@ -55,13 +156,13 @@ where
     T: Into<PaymentRequirements>,
 {
     fn from(value: T) -> Self {
-        Accepts(vec![value.into()])
+        Accepts(Arc::new(vec![value.into()]))
     }
 }
 
 impl From<Vec<PaymentRequirements>> for Accepts {
     fn from(value: Vec<PaymentRequirements>) -> Self {
-        Accepts(value)
+        Accepts(Arc::new(value))
     }
 }
 
@@ -69,8 +170,9 @@ impl IntoIterator for Accepts {
     type Item = PaymentRequirements;
     type IntoIter = std::vec::IntoIter<PaymentRequirements>;
 
+    /// Iterate over the requirements, copying them only if they are shared with other clones.
     fn into_iter(self) -> Self::IntoIter {
-        self.0.into_iter()
+        Arc::unwrap_or_clone(self.0).into_iter()
     }
 }
 
@@ -85,8 +187,7 @@ impl<'a> IntoIterator for &'a Accepts {
 
 impl FromIterator<PaymentRequirements> for Accepts {
     fn from_iter<T: IntoIterator<Item = PaymentRequirements>>(iter: T) -> Self {
-        let vec: Vec<PaymentRequirements> = iter.into_iter().collect();
-        Accepts(vec)
+        Accepts(Arc::new(iter.into_iter().collect()))
     }
 }
 
@@ -105,7 +206,7 @@ impl<'de> Deserialize<'de> for Accepts {
         D: serde::Deserializer<'de>,
     {
         let vec = Vec::<PaymentRequirements>::deserialize(deserializer)?;
-        Ok(Accepts(vec))
+        Ok(Accepts(Arc::new(vec)))
     }
 }
 
@@ -116,32 +217,179 @@ impl Debug for Accepts {
 }
 
 impl Accepts {
+    /// Append a payment requirement, copying the requirements only if they are shared.
     pub fn push(mut self, payment: impl Into<PaymentRequirements>) -> Self {
-        self.0.push(payment.into());
+        Arc::make_mut(&mut self.0).push(payment.into());
         self
     }
 
     pub fn new() -> Self {
-        Accepts(Vec::new())
+        Accepts(Arc::new(Vec::new()))
+    }
+
+    /// Collect payment requirements, e.g. those built from configuration.
+    ///
+    /// To mix payments of different types, such as builders of different schemes, convert them
+    /// first, or use the [`accepts!`](crate::accepts) macro.
+    pub fn from_payments(
+        payments: impl IntoIterator<Item = impl Into<PaymentRequirements>>,
+    ) -> Self {
+        payments.into_iter().map(Into::into).collect()
+    }
+
+    /// Returns `true` if no payment requirements are accepted.
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Returns the number of accepted payment requirements.
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    /// Returns an iterator over the accepted payment requirements.
+    pub fn iter(&self) -> std::slice::Iter<'_, PaymentRequirements> {
+        self.0.iter()
+    }
+
+    /// Returns `true` if both share the same requirements, e.g. one is a clone of the other.
+    pub fn ptr_eq(&self, other: &Accepts) -> bool {
+        Arc::ptr_eq(&self.0, &other.0)
     }
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+/// Build [`Accepts`] from a list of payments of possibly different types, anything
+/// `Into<PaymentRequirements>`.
+///
+/// ```
+/// use x402_core::{accepts, transport::PaymentRequirements};
+///
+/// let requirements = |network: &str| {
+///     PaymentRequirements::builder()
+///         .scheme("exact")
+///         .network(network)
+///         .amount(1000u64)
+///         .asset("0x833589fCD6eDb6E08f4c7C32D4f71b54bdA02913")
+///         .pay_to("0x3CB9B3bBfde8501f411bB69Ad3DC07908ED0dE20")
+///         .max_timeout_seconds(60)
+///         .build()
+///         .unwrap()
+/// };
+///
+/// let accepts = accepts![requirements("eip155:8453"), requirements("eip155:84532")];
+/// assert_eq!(accepts.len(), 2);
+/// assert!(accepts![].is_empty());
+/// ```
+#[macro_export]
+macro_rules! accepts {
+    () => {
+        $crate::transport::Accepts::new()
+    };
+    ($($payment:expr),+ $(,)?) => {
+        $crate::transport::Accepts::from(::std::vec![
+            $(::core::convert::Into::<$crate::transport::PaymentRequirements>::into($payment)),+
+        ])
+    };
+}
+
+/// The `PaymentRequired` object of a `402 Payment Required` response.
+///
+/// Servers not using the paywall can build the challenge with the builder, and send it both as
+/// the response body and, base64 encoded, as the `PAYMENT-REQUIRED` header:
+///
+/// ```
+/// use x402_core::{
+///     transport::{PaymentRequired, PaymentRequirements, PaymentResource},
+/// };
+///
+/// let requirements = PaymentRequirements::builder()
+///     .scheme("exact")
+///     .network("eip155:8453")
+///     .amount(1000u64)
+///     .asset("0x833589fCD6eDb6E08f4c7C32D4f71b54bdA02913")
+///     .pay_to("0x3CB9B3bBfde8501f411bB69Ad3DC07908ED0dE20")
+///     .max_timeout_seconds(60)
+///     .build()
+///     .unwrap();
+///
+/// let payment_required = PaymentRequired::builder()
+///     .error("PAYMENT-SIGNATURE header is required")
+///     .resource(PaymentResource {
+///         url: "https://example.com/resource".parse().unwrap(),
+///         description: "Protected resource".to_string(),
+///         mime_type: "application/json".to_string(),
+///     })
+///     .accepts(requirements)
+///     .build();
+///
+/// let header = payment_required.to_header().unwrap();
+/// let body = payment_required.to_json_body().unwrap();
+/// ```
+#[derive(Builder, Debug, Clone, Serialize, Deserialize, PartialEq)]
 #[serde(rename_all = "camelCase")]
 pub struct PaymentRequired {
+    #[builder(default = X402V2)]
     pub x402_version: X402V2,
+    /// The reason the payment is required, e.g. a missing or rejected payment.
+    #[builder(into)]
     pub error: String,
+    /// A machine-readable code of the error, e.g. [`PaymentRequired::STALE_REQUIREMENTS`].
+    #[builder(into)]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error_code: Option<String>,
+    /// The resource the payment is required for.
+    #[builder(into)]
     pub resource: PaymentResource,
+    /// The accepted payment requirements, of any scheme.
+    #[builder(into)]
     pub accepts: Accepts,
+    /// The extensions supported by the server.
+    #[builder(default)]
     pub extensions: Record<Extension>,
 }
 
+impl PaymentRequired {
+    /// The [`error_code`](Self::error_code) of a payment signed for requirements the server no
+    /// longer accepts, e.g. after a price change between the challenge and the payment.
+    ///
+    /// Buyers should re-read the `accepts` of the challenge and retry once.
+    pub const STALE_REQUIREMENTS: &str = "stale_requirements";
+
+    /// The machine-readable code of the error, if any.
+    pub fn error_code(&self) -> Option<&str> {
+        self.error_code.as_deref()
+    }
+
+    /// The base64 encoded `PAYMENT-REQUIRED` header value of the challenge.
+    ///
+    /// Byte-identical to the header sent by the paywall for the same challenge.
+    pub fn to_header(&self) -> crate::errors::Result<Base64EncodedHeader> {
+        Base64EncodedHeader::try_from(self)
+    }
+
+    /// The JSON response body of the challenge.
+    ///
+    /// Byte-identical to the body sent by the paywall for the same challenge.
+    pub fn to_json_body(&self) -> crate::errors::Result<Vec<u8>> {
+        Ok(serde_json::to_vec(self)?)
+    }
+}
+
 impl TryFrom<PaymentRequired> for Base64EncodedHeader {
     type Error = crate::errors::Error;
 
     /// Serialize PaymentRequired into `PAYMENT-REQUIRED` header format
     fn try_from(value: PaymentRequired) -> Result<Self, Self::Error> {
-        let json = serde_json::to_string(&value)?;
+        Base64EncodedHeader::try_from(&value)
+    }
+}
+
+impl TryFrom<&PaymentRequired> for Base64EncodedHeader {
+    type Error = crate::errors::Error;
+
+    /// Serialize PaymentRequired into `PAYMENT-REQUIRED` header format, without taking ownership
+    fn try_from(value: &PaymentRequired) -> Result<Self, Self::Error> {
+        let json = serde_json::to_vec(value)?;
         let encoded = BASE64_STANDARD.encode(json);
         Ok(Base64EncodedHeader(encoded))
     }
@@ -152,7 +400,16 @@ impl TryFrom<Base64EncodedHeader> for PaymentRequired {
 
     /// Deserialize `PAYMENT-REQUIRED` header into PaymentRequired
     fn try_from(value: Base64EncodedHeader) -> Result<Self, Self::Error> {
-        let decoded = BASE64_STANDARD.decode(&value.0)?;
+        PaymentRequired::try_from(value.0.as_str())
+    }
+}
+
+impl TryFrom<&str> for PaymentRequired {
+    type Error = crate::errors::Error;
+
+    /// Deserialize a borrowed `PAYMENT-REQUIRED` header value into PaymentRequired
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        let decoded = Base64EncodedHeader::decode_str(value)?;
         let json_str = String::from_utf8(decoded)?;
         let payment_required: PaymentRequired = serde_json::from_str(&json_str)?;
         Ok(payment_required)
@@ -185,19 +442,126 @@ impl TryFrom<Base64EncodedHeader> for PaymentPayload {
 
     /// Deserialize `PAYMENT-SIGNATURE` header into PaymentPayload
     fn try_from(value: Base64EncodedHeader) -> Result<Self, Self::Error> {
-        let decoded_bytes = BASE64_STANDARD.decode(&value.0)?;
-        let json_str = String::from_utf8(decoded_bytes)?;
-        let payload = serde_json::from_str(&json_str)?;
+        PaymentPayload::from_header(&value.0)
+    }
+}
+
+impl TryFrom<&str> for PaymentPayload {
+    type Error = crate::errors::Error;
+
+    /// Deserialize a borrowed `PAYMENT-SIGNATURE` header value into PaymentPayload
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        PaymentPayload::from_header(value)
+    }
+}
+
+impl PaymentPayload {
+    /// Assemble the payload of a payment from the typed payload signed for scheme `S`.
+    ///
+    /// `accepted` are the payment requirements the payload was signed for, chosen from the
+    /// `accepts` of the resource's `PaymentRequired` response. The result is sent as the
+    /// `PAYMENT-SIGNATURE` header once converted into a [`Base64EncodedHeader`].
+    ///
+    /// Returns an error if the typed payload fails to serialize.
+    pub fn for_scheme<S: Scheme>(
+        resource: PaymentResource,
+        accepted: PaymentRequirements,
+        payload: S::Payload,
+        extensions: Record<Extension>,
+    ) -> crate::errors::Result<Self>
+    where
+        S::Payload: Serialize,
+    {
+        Ok(PaymentPayload {
+            x402_version: X402V2,
+            resource,
+            accepted,
+            payload: serde_json::to_value(payload)?,
+            extensions,
+        })
+    }
+
+    /// Deserialize a borrowed `PAYMENT-SIGNATURE` header value into PaymentPayload
+    pub fn from_header(value: &str) -> Result<Self, crate::errors::Error> {
+        let decoded_bytes = Base64EncodedHeader::decode_str(value)?;
+        let payload = serde_json::from_slice(&decoded_bytes)?;
         Ok(payload)
     }
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+/// The settlement of a payment, sent to the client in the `PAYMENT-RESPONSE` header.
+///
+/// Build it with [`SettlementResponse::new`] for a successful settlement, or from a
+/// [`SettleSuccess`](crate::facilitator::SettleSuccess), e.g. in settlement jobs running outside
+/// the paywall:
+///
+/// ```
+/// use x402_core::{transport::SettlementResponse, types::Base64EncodedHeader};
+///
+/// let settlement = SettlementResponse::new(
+///     "0x857b06519E91e3A54538791bDbb0E22373e36b66",
+///     "0xdeadbeef",
+///     "eip155:8453",
+/// );
+/// assert_eq!(
+///     settlement.to_string(),
+///     "Payment by 0x857b06519E91e3A54538791bDbb0E22373e36b66 settled on eip155:8453 in transaction 0xdeadbeef"
+/// );
+///
+/// let header = Base64EncodedHeader::try_from(settlement).unwrap();
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct SettlementResponse {
     pub success: bool,
     pub transaction: String,
     pub network: String,
     pub payer: String,
+    /// The seller's order id for the payment, if the paywall was configured with one.
+    #[serde(default, rename = "orderId", skip_serializing_if = "Option::is_none")]
+    pub order_id: Option<String>,
+    /// How far the transaction had progressed towards finality when it was reported, if known.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub confirmation: Option<ConfirmationStatus>,
+}
+
+impl SettlementResponse {
+    /// A successful settlement of a payment by `payer` in `transaction` on `network`.
+    pub fn new(
+        payer: impl Into<String>,
+        transaction: impl Into<String>,
+        network: impl Into<String>,
+    ) -> Self {
+        SettlementResponse {
+            success: true,
+            transaction: transaction.into(),
+            network: network.into(),
+            payer: payer.into(),
+            order_id: None,
+            confirmation: None,
+        }
+    }
+}
+
+impl std::fmt::Display for SettlementResponse {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.success {
+            write!(
+                f,
+                "Payment by {} settled on {} in transaction {}",
+                self.payer, self.network, self.transaction
+            )?;
+        } else {
+            write!(
+                f,
+                "Payment by {} failed to settle on {}",
+                self.payer, self.network
+            )?;
+        }
+        if let Some(order_id) = &self.order_id {
+            write!(f, " for order {order_id}")?;
+        }
+        Ok(())
+    }
 }
 
 impl TryFrom<SettlementResponse> for Base64EncodedHeader {
@@ -216,7 +580,16 @@ impl TryFrom<Base64EncodedHeader> for SettlementResponse {
 
     /// Deserialize `PAYMENT-RESPONSE` header into SettlementResponse
     fn try_from(value: Base64EncodedHeader) -> Result<Self, Self::Error> {
-        let decoded_bytes = BASE64_STANDARD.decode(&value.0)?;
+        SettlementResponse::try_from(value.0.as_str())
+    }
+}
+
+impl TryFrom<&str> for SettlementResponse {
+    type Error = crate::errors::Error;
+
+    /// Deserialize a borrowed `PAYMENT-RESPONSE` header value into SettlementResponse
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        let decoded_bytes = Base64EncodedHeader::decode_str(value)?;
         let json_str = String::from_utf8(decoded_bytes)?;
         let response = serde_json::from_str(&json_str)?;
         Ok(response)
@@ -230,7 +603,7 @@ where
 {
     fn from(payment: Payment<S, A>) -> Self {
         PaymentRequirements {
-            scheme: S::SCHEME_NAME.to_string(),
+            scheme: SchemeName::from_static(S::SCHEME_NAME),
             network: payment.scheme.network().network_id().to_string(),
             amount: payment.amount,
             asset: payment.asset.address.to_string(),
@@ -240,3 +613,322 @@ where
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use crate::errors::Error;
+
+    use super::*;
+
+    fn valid() -> PaymentRequirements {
+        PaymentRequirements {
+            scheme: SchemeName::UPTO,
+            network: "eip155:8453".to_string(),
+            amount: AmountValue(1000),
+            asset: "0x833589fCD6eDb6E08f4c7C32D4f71b54bdA02913".to_string(),
+            pay_to: "0x3CB9B3bBfde8501f411bB69Ad3DC07908ED0dE20".to_string(),
+            max_timeout_seconds: 60,
+            extra: Some(json!({ "max": "5000" })),
+        }
+    }
+
+    fn build(fields: PaymentRequirements) -> crate::errors::Result<PaymentRequirements> {
+        PaymentRequirements::builder()
+            .scheme(fields.scheme)
+            .network(fields.network)
+            .amount(fields.amount)
+            .asset(fields.asset)
+            .pay_to(fields.pay_to)
+            .max_timeout_seconds(fields.max_timeout_seconds)
+            .maybe_extra(fields.extra)
+            .build()
+    }
+
+    fn rejection(fields: PaymentRequirements) -> String {
+        match build(fields) {
+            Err(Error::InvalidPaymentRequirements(reason)) => reason,
+            other => panic!("expected invalid payment requirements, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn accepts_clones_share_requirements() {
+        let accepts = Accepts::new().push(valid()).push(PaymentRequirements {
+            network: "eip155:1".to_string(),
+            ..valid()
+        });
+
+        // Cloning copies a pointer, not the requirements or their `extra`
+        let clone = accepts.clone();
+        assert!(clone.ptr_eq(&accepts));
+        assert!(std::ptr::eq(clone.as_ref(), accepts.as_ref()));
+
+        // Pushing to a clone leaves the shared requirements untouched
+        let extended = clone.push(valid());
+        assert!(!extended.ptr_eq(&accepts));
+        assert_eq!(extended.len(), 3);
+        assert_eq!(accepts.len(), 2);
+        assert_eq!(accepts.as_ref()[1].network, "eip155:1");
+
+        // Pushing to unshared requirements appends in place
+        let unshared = Accepts::new().push(valid());
+        let list = Arc::as_ptr(&unshared.0);
+        assert_eq!(Arc::as_ptr(&unshared.push(valid()).0), list);
+    }
+
+    #[test]
+    fn accepts_from_payments() {
+        let other = PaymentRequirements {
+            network: "eip155:1".to_string(),
+            ..valid()
+        };
+
+        let accepts = Accepts::from_payments([valid(), other.clone()]);
+
+        assert_eq!(accepts.as_ref(), [valid(), other.clone()]);
+        assert_eq!(crate::accepts![valid(), other], accepts);
+        assert!(crate::accepts![].is_empty());
+    }
+
+    #[test]
+    fn settlement_response_header_round_trip() {
+        let settlement = SettlementResponse {
+            order_id: Some("order-42".to_string()),
+            ..SettlementResponse::new(
+                "0x857b06519E91e3A54538791bDbb0E22373e36b66",
+                "0xdeadbeef",
+                "eip155:8453",
+            )
+        };
+
+        let header = Base64EncodedHeader::try_from(settlement.clone()).unwrap();
+
+        assert_eq!(SettlementResponse::try_from(header).unwrap(), settlement);
+        assert_eq!(
+            settlement.to_string(),
+            "Payment by 0x857b06519E91e3A54538791bDbb0E22373e36b66 settled on eip155:8453 in transaction 0xdeadbeef for order order-42"
+        );
+    }
+
+    #[test]
+    fn failed_settlement_response_display() {
+        let settlement = SettlementResponse {
+            success: false,
+            transaction: String::new(),
+            ..SettlementResponse::new("0xpayer", "", "eip155:8453")
+        };
+
+        assert_eq!(
+            settlement.to_string(),
+            "Payment by 0xpayer failed to settle on eip155:8453"
+        );
+    }
+
+    #[test]
+    fn build_valid_requirements() {
+        assert_eq!(build(valid()).unwrap(), valid());
+    }
+
+    #[test]
+    fn reject_zero_amount() {
+        let fields = PaymentRequirements {
+            amount: AmountValue(0),
+            ..valid()
+        };
+        assert_eq!(rejection(fields), "amount must be greater than zero");
+    }
+
+    #[test]
+    fn reject_zero_timeout() {
+        let fields = PaymentRequirements {
+            max_timeout_seconds: 0,
+            ..valid()
+        };
+        assert_eq!(
+            rejection(fields),
+            "max_timeout_seconds must be greater than zero"
+        );
+    }
+
+    #[test]
+    fn reject_empty_scheme() {
+        let fields = PaymentRequirements {
+            scheme: SchemeName::from(""),
+            ..valid()
+        };
+        assert_eq!(rejection(fields), "scheme must not be empty");
+    }
+
+    #[test]
+    fn scheme_names_keep_their_json() {
+        let json = json!({
+            "x402Version": 2,
+            "resource": {
+                "url": "https://example.com/resource",
+                "description": "Protected resource",
+                "mimeType": "application/json"
+            },
+            "accepted": {
+                "scheme": "Exact",
+                "network": "eip155:8453",
+                "amount": "1000",
+                "asset": "0x833589fCD6eDb6E08f4c7C32D4f71b54bdA02913",
+                "payTo": "0x3CB9B3bBfde8501f411bB69Ad3DC07908ED0dE20",
+                "maxTimeoutSeconds": 60,
+                "extra": null
+            },
+            "payload": {},
+            "extensions": {}
+        });
+
+        let payload: PaymentPayload = serde_json::from_value(json.clone()).unwrap();
+        assert_eq!(payload.accepted.scheme, SchemeName::EXACT);
+        assert_eq!(serde_json::to_value(&payload).unwrap(), json);
+
+        let kind: crate::facilitator::SupportedKinds = serde_json::from_value(json!({
+            "x402Version": 2,
+            "scheme": "exact",
+            "network": "eip155:8453"
+        }))
+        .unwrap();
+        assert_eq!(kind.scheme, payload.accepted.scheme);
+    }
+
+    #[test]
+    fn decode_standard_and_url_safe_payload() {
+        use base64::prelude::BASE64_URL_SAFE_NO_PAD;
+
+        let payload: PaymentPayload = serde_json::from_value(json!({
+            "x402Version": 2,
+            "resource": {
+                "url": "https://example.com/resource?id=42",
+                "description": "Which plan??? >>> Pro ~~~",
+                "mimeType": "application/json"
+            },
+            "accepted": valid(),
+            "payload": {},
+            "extensions": {}
+        }))
+        .unwrap();
+        let json = serde_json::to_vec(&payload).unwrap();
+
+        let standard = Base64EncodedHeader::try_from(payload.clone()).unwrap();
+        assert!(standard.0.contains(['+', '/']));
+        let url_safe = BASE64_URL_SAFE_NO_PAD.encode(&json);
+        assert!(url_safe.contains(['-', '_']));
+
+        for header in [standard.0.as_str(), url_safe.as_str()] {
+            let decoded = PaymentPayload::try_from(header).unwrap();
+            assert_eq!(decoded.resource, payload.resource);
+            assert_eq!(decoded.accepted, payload.accepted);
+        }
+
+        assert!(matches!(
+            PaymentPayload::try_from("not base64!"),
+            Err(Error::Base64DecodeError(_))
+        ));
+    }
+
+    #[test]
+    fn reject_empty_network() {
+        let fields = PaymentRequirements {
+            network: String::new(),
+            ..valid()
+        };
+        assert_eq!(rejection(fields), "network must not be empty");
+    }
+
+    #[test]
+    fn reject_empty_asset() {
+        let fields = PaymentRequirements {
+            asset: String::new(),
+            ..valid()
+        };
+        assert_eq!(rejection(fields), "asset must not be empty");
+    }
+
+    #[test]
+    fn reject_empty_pay_to() {
+        let fields = PaymentRequirements {
+            pay_to: String::new(),
+            ..valid()
+        };
+        assert_eq!(rejection(fields), "pay_to must not be empty");
+    }
+
+    #[test]
+    fn display_amount_of_known_asset() {
+        let registry = KnownAssetRegistry::new().register_decimals(
+            "eip155:8453",
+            "0x833589FCD6EDB6E08F4C7C32D4F71B54BDA02913",
+            6,
+            "USDC",
+        );
+
+        assert_eq!(valid().display_amount(&registry), "0.001000 USDC");
+
+        let unknown = PaymentRequirements {
+            network: "eip155:84532".to_string(),
+            ..valid()
+        };
+        assert_eq!(unknown.display_amount(&registry), "1000");
+    }
+
+    #[test]
+    fn payment_required_header_round_trip() {
+        let payment_required = PaymentRequired::builder()
+            .error("PAYMENT-SIGNATURE header is required")
+            .resource(PaymentResource {
+                url: "https://example.com/resource".parse().unwrap(),
+                description: "Protected resource".to_string(),
+                mime_type: "application/json".to_string(),
+            })
+            .accepts(valid())
+            .build();
+
+        assert_eq!(payment_required.x402_version, X402V2);
+        assert!(payment_required.extensions.is_empty());
+
+        let header = Base64EncodedHeader::try_from(&payment_required).unwrap();
+        assert_eq!(PaymentRequired::try_from(header).unwrap(), payment_required);
+    }
+
+    #[test]
+    fn payment_required_error_code() {
+        let resource = PaymentResource {
+            url: "https://example.com/resource".parse().unwrap(),
+            description: "Protected resource".to_string(),
+            mime_type: "application/json".to_string(),
+        };
+        let without = PaymentRequired::builder()
+            .error("PAYMENT-SIGNATURE header is required")
+            .resource(resource.clone())
+            .accepts(valid())
+            .build();
+        assert_eq!(without.error_code(), None);
+        assert!(
+            !serde_json::to_value(&without)
+                .unwrap()
+                .as_object()
+                .unwrap()
+                .contains_key("errorCode")
+        );
+
+        let stale = PaymentRequired::builder()
+            .error("PaymentRequirements in payload not accepted")
+            .error_code(PaymentRequired::STALE_REQUIREMENTS)
+            .resource(resource)
+            .accepts(valid())
+            .build();
+        let json = serde_json::to_value(&stale).unwrap();
+        assert_eq!(json["errorCode"], "stale_requirements");
+        assert_eq!(
+            serde_json::from_value::<PaymentRequired>(json)
+                .unwrap()
+                .error_code(),
+            Some(PaymentRequired::STALE_REQUIREMENTS)
+        );
+    }
+}
@@ -0,0 +1,248 @@
+//! X402 v1 transport types, for bridging v2 sellers to v1 clients and services.
+//!
+//! v1 settlement responses carry the `network` of the payment verbatim, so conversions from v2
+//! keep its CAIP-2 identifier, e.g. `eip155:84532`, rather than a v1 network name such as
+//! `base-sepolia`.
+
+use base64::{Engine, prelude::BASE64_STANDARD};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    facilitator::{SettleFailed, SettlePending, SettleResult, SettleSuccess},
+    transport::SettlementResponse,
+    types::Base64EncodedHeader,
+};
+
+/// The v1 settlement response, sent in the `X-PAYMENT-RESPONSE` header.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PaymentResponse {
+    pub success: bool,
+    /// The reason the settlement failed, if it did.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error_reason: Option<String>,
+    pub transaction: String,
+    pub network: String,
+    pub payer: String,
+}
+
+impl From<SettleSuccess> for PaymentResponse {
+    fn from(settled: SettleSuccess) -> Self {
+        PaymentResponse {
+            success: true,
+            error_reason: None,
+            transaction: settled.transaction,
+            network: settled.network,
+            payer: settled.payer,
+        }
+    }
+}
+
+/// Drops the `orderId` and `confirmation`, unknown to v1.
+impl From<SettlementResponse> for PaymentResponse {
+    fn from(response: SettlementResponse) -> Self {
+        PaymentResponse {
+            success: response.success,
+            error_reason: None,
+            transaction: response.transaction,
+            network: response.network,
+            payer: response.payer,
+        }
+    }
+}
+
+/// Drops the `errorReason`, unknown to v2 settlement responses.
+impl From<PaymentResponse> for SettlementResponse {
+    fn from(response: PaymentResponse) -> Self {
+        SettlementResponse {
+            success: response.success,
+            transaction: response.transaction,
+            network: response.network,
+            payer: response.payer,
+            order_id: None,
+            confirmation: None,
+        }
+    }
+}
+
+/// A successful response is a [`SettleSuccess`], one with a `pending` `errorReason` a
+/// [`SettlePending`] referencing its transaction, if any, and any other a [`SettleFailed`] with
+/// its `errorReason`.
+impl From<PaymentResponse> for SettleResult {
+    fn from(response: PaymentResponse) -> Self {
+        if response.success {
+            return SettleResult::Success(SettleSuccess {
+                payer: response.payer,
+                transaction: response.transaction,
+                network: response.network,
+                verified_signature: false,
+                signer: None,
+                confirmation: None,
+            });
+        }
+
+        if response
+            .error_reason
+            .as_deref()
+            .is_some_and(|reason| reason.eq_ignore_ascii_case("pending"))
+        {
+            return SettleResult::Pending(SettlePending {
+                poll_after: None,
+                reference: Some(response.transaction).filter(|transaction| !transaction.is_empty()),
+            });
+        }
+
+        SettleResult::Failed(SettleFailed {
+            error_reason: response.error_reason.unwrap_or_default(),
+            payer: Some(response.payer).filter(|payer| !payer.is_empty()),
+        })
+    }
+}
+
+impl TryFrom<PaymentResponse> for Base64EncodedHeader {
+    type Error = crate::errors::Error;
+
+    /// Serialize PaymentResponse into `X-PAYMENT-RESPONSE` header format
+    fn try_from(value: PaymentResponse) -> Result<Self, Self::Error> {
+        let json = serde_json::to_string(&value)?;
+        Ok(Base64EncodedHeader(BASE64_STANDARD.encode(json)))
+    }
+}
+
+impl TryFrom<&str> for PaymentResponse {
+    type Error = crate::errors::Error;
+
+    /// Deserialize a borrowed `X-PAYMENT-RESPONSE` header value into PaymentResponse
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        let decoded_bytes = Base64EncodedHeader::decode_str(value)?;
+        Ok(serde_json::from_slice(&decoded_bytes)?)
+    }
+}
+
+/// Re-encode a v2 `PAYMENT-RESPONSE` header value as a v1 `X-PAYMENT-RESPONSE` one.
+pub fn reencode_payment_response(header: &str) -> crate::errors::Result<Base64EncodedHeader> {
+    let response = SettlementResponse::try_from(header)?;
+    Base64EncodedHeader::try_from(PaymentResponse::from(response))
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use crate::facilitator::ConfirmationStatus;
+
+    use super::*;
+
+    fn settled() -> SettleSuccess {
+        SettleSuccess {
+            payer: "0x3CB9B3bBfde8501f411bB69Ad3DC07908ED0dE20".to_string(),
+            transaction: "0xdeadbeef".to_string(),
+            network: "eip155:84532".to_string(),
+            verified_signature: false,
+            signer: None,
+            confirmation: None,
+        }
+    }
+
+    #[test]
+    fn settle_success_round_trip() {
+        let response = PaymentResponse::from(settled());
+
+        assert_eq!(
+            serde_json::to_value(&response).unwrap(),
+            json!({
+                "success": true,
+                "transaction": "0xdeadbeef",
+                "network": "eip155:84532",
+                "payer": "0x3CB9B3bBfde8501f411bB69Ad3DC07908ED0dE20",
+            })
+        );
+
+        let SettleResult::Success(back) = SettleResult::from(response) else {
+            panic!("expected a successful settlement");
+        };
+        assert_eq!(back.payer, settled().payer);
+        assert_eq!(back.transaction, settled().transaction);
+        assert_eq!(back.network, settled().network);
+    }
+
+    #[test]
+    fn failed_response_is_settle_failed() {
+        let response = PaymentResponse {
+            success: false,
+            error_reason: Some("insufficient_funds".to_string()),
+            transaction: String::new(),
+            network: "eip155:84532".to_string(),
+            payer: String::new(),
+        };
+
+        let SettleResult::Failed(failed) = SettleResult::from(response) else {
+            panic!("expected a failed settlement");
+        };
+        assert_eq!(failed.error_reason, "insufficient_funds");
+        assert_eq!(failed.payer, None);
+    }
+
+    #[test]
+    fn pending_response_is_settle_pending() {
+        let response = PaymentResponse {
+            success: false,
+            error_reason: Some("pending".to_string()),
+            transaction: "0xdeadbeef".to_string(),
+            network: "eip155:84532".to_string(),
+            payer: String::new(),
+        };
+
+        let SettleResult::Pending(pending) = SettleResult::from(response) else {
+            panic!("expected a pending settlement");
+        };
+        assert_eq!(
+            pending,
+            SettlePending {
+                poll_after: None,
+                reference: Some("0xdeadbeef".to_string()),
+            }
+        );
+    }
+
+    #[test]
+    fn settlement_response_round_trip() {
+        let v2 = SettlementResponse {
+            success: true,
+            transaction: "0xdeadbeef".to_string(),
+            network: "eip155:84532".to_string(),
+            payer: "0x3CB9B3bBfde8501f411bB69Ad3DC07908ED0dE20".to_string(),
+            order_id: Some("order-42".to_string()),
+            confirmation: Some(ConfirmationStatus::Confirmed(1)),
+        };
+
+        let v1 = PaymentResponse::from(v2.clone());
+        assert_eq!(v1, PaymentResponse::from(settled()));
+
+        let back = SettlementResponse::from(v1);
+        assert_eq!(back.transaction, v2.transaction);
+        assert_eq!(back.network, v2.network);
+        assert_eq!(back.payer, v2.payer);
+        assert_eq!(back.order_id, None);
+        assert_eq!(back.confirmation, None);
+    }
+
+    #[test]
+    fn reencodes_v2_header_as_v1() {
+        let v2 = SettlementResponse {
+            order_id: Some("order-42".to_string()),
+            ..SettlementResponse::from(PaymentResponse::from(settled()))
+        };
+        let header = Base64EncodedHeader::try_from(v2).unwrap();
+
+        let v1 = reencode_payment_response(&header.0).unwrap();
+
+        let decoded = Base64EncodedHeader::decode_str(&v1.0).unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&decoded).unwrap();
+        assert!(json.get("orderId").is_none());
+        assert_eq!(
+            PaymentResponse::try_from(v1.0.as_str()).unwrap(),
+            PaymentResponse::from(settled())
+        );
+    }
+}
@@ -42,6 +42,102 @@ impl From<u128> for AmountValue {
     }
 }
 
+impl AmountValue {
+    /// The amount of `whole` tokens of an asset with the given decimals, in its smallest units.
+    ///
+    /// Usable in const contexts, where an overflowing amount fails to compile.
+    ///
+    /// ```
+    /// use x402_core::types::AmountValue;
+    ///
+    /// const PRICE: AmountValue = AmountValue::units(2, 6);
+    /// assert_eq!(PRICE, AmountValue(2_000_000));
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// If the amount overflows a `u128`, i.e. with more than 38 decimals, see
+    /// [`checked_units`](AmountValue::checked_units).
+    pub const fn units(whole: u64, decimals: u8) -> AmountValue {
+        match AmountValue::checked_units(whole, decimals) {
+            Some(amount) => amount,
+            None => panic!("amount overflows u128"),
+        }
+    }
+
+    /// The amount of `whole` tokens of an asset with the given decimals, or `None` if it
+    /// overflows a `u128`.
+    pub const fn checked_units(whole: u64, decimals: u8) -> Option<AmountValue> {
+        let Some(unit) = 10u128.checked_pow(decimals as u32) else {
+            return None;
+        };
+        match (whole as u128).checked_mul(unit) {
+            Some(amount) => Some(AmountValue(amount)),
+            None => None,
+        }
+    }
+
+    /// The amount of `whole` USDC, with its 6 decimals.
+    ///
+    /// ```
+    /// use x402_core::types::AmountValue;
+    ///
+    /// assert_eq!(AmountValue::usdc(1), AmountValue(1_000_000));
+    /// ```
+    pub const fn usdc(whole: u64) -> AmountValue {
+        AmountValue::units(whole, 6)
+    }
+
+    /// The given share of the amount in basis points, i.e. hundredths of a percent, rounded up to
+    /// the next smallest unit so that a non-zero share of a non-zero amount is never nothing.
+    ///
+    /// Used for percentage fees, so that every fee computed from a rate rounds the same way.
+    ///
+    /// ```
+    /// use x402_core::types::AmountValue;
+    ///
+    /// // 2.5% of 1040 is 26
+    /// assert_eq!(AmountValue(1040).basis_points_ceil(250), AmountValue(26));
+    /// // 2% of 1001 is 20.02, rounded up to 21
+    /// assert_eq!(AmountValue(1001).basis_points_ceil(200), AmountValue(21));
+    /// ```
+    pub fn basis_points_ceil(&self, basis_points: u32) -> AmountValue {
+        AmountValue(self.0.saturating_mul(basis_points.into()).div_ceil(10_000))
+    }
+
+    /// Format the amount in whole tokens of an asset with the given decimals and symbol.
+    ///
+    /// The fractional part always has exactly `decimals` digits, zero-padded and never rounded,
+    /// so that the displayed amount is exact. With `0` decimals, no decimal point is written.
+    /// An empty symbol is omitted.
+    ///
+    /// ```
+    /// use x402_core::types::AmountValue;
+    ///
+    /// assert_eq!(AmountValue(1000).display_with(6, "USDC"), "0.001000 USDC");
+    /// assert_eq!(AmountValue(2_500_000).display_with(6, "USDC"), "2.500000 USDC");
+    /// assert_eq!(AmountValue(42).display_with(0, ""), "42");
+    /// ```
+    pub fn display_with(&self, decimals: u8, symbol: &str) -> String {
+        let digits = self.0.to_string();
+        let decimals = decimals as usize;
+
+        let mut amount = if decimals == 0 {
+            digits
+        } else {
+            let padded = format!("{digits:0>width$}", width = decimals + 1);
+            let (whole, fraction) = padded.split_at(padded.len() - decimals);
+            format!("{whole}.{fraction}")
+        };
+
+        if !symbol.is_empty() {
+            amount.push(' ');
+            amount.push_str(symbol);
+        }
+        amount
+    }
+}
+
 impl Display for AmountValue {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(f, "{}", self.0)
@@ -67,3 +163,89 @@ impl<'de> Deserialize<'de> for AmountValue {
         Ok(AmountValue(value))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn units_of_whole_tokens() {
+        const TWO_USDC: AmountValue = AmountValue::units(2, 6);
+
+        assert_eq!(TWO_USDC, AmountValue(2_000_000));
+        assert_eq!(AmountValue::usdc(1), AmountValue(1_000_000));
+        assert_eq!(AmountValue::units(7, 0), AmountValue(7));
+        assert_eq!(
+            AmountValue::units(1, 18),
+            AmountValue(1_000_000_000_000_000_000)
+        );
+    }
+
+    #[test]
+    fn units_overflow() {
+        // The largest whole amount fits with 18 decimals
+        assert_eq!(
+            AmountValue::checked_units(u64::MAX, 18),
+            Some(AmountValue(u64::MAX as u128 * 10u128.pow(18)))
+        );
+        assert_eq!(AmountValue::checked_units(u64::MAX, 20), None);
+        assert_eq!(
+            AmountValue::checked_units(1, 38),
+            Some(AmountValue(10u128.pow(38)))
+        );
+        assert_eq!(AmountValue::checked_units(1, 39), None);
+        assert_eq!(AmountValue::checked_units(0, 39), None);
+    }
+
+    #[test]
+    #[should_panic(expected = "amount overflows u128")]
+    fn units_panics_on_overflow() {
+        AmountValue::units(u64::MAX, 20);
+    }
+
+    #[test]
+    fn display_without_decimals() {
+        assert_eq!(AmountValue(0).display_with(0, "PTS"), "0 PTS");
+        assert_eq!(AmountValue(1500).display_with(0, "PTS"), "1500 PTS");
+    }
+
+    #[test]
+    fn display_with_six_decimals() {
+        assert_eq!(AmountValue(0).display_with(6, "USDC"), "0.000000 USDC");
+        assert_eq!(AmountValue(1).display_with(6, "USDC"), "0.000001 USDC");
+        assert_eq!(AmountValue(1000).display_with(6, "USDC"), "0.001000 USDC");
+        assert_eq!(
+            AmountValue(123_456_789).display_with(6, "USDC"),
+            "123.456789 USDC"
+        );
+    }
+
+    #[test]
+    fn display_with_eighteen_decimals() {
+        assert_eq!(
+            AmountValue(1).display_with(18, "ETH"),
+            "0.000000000000000001 ETH"
+        );
+        assert_eq!(
+            AmountValue(1_234_000_000_000_000_000).display_with(18, "ETH"),
+            "1.234000000000000000 ETH"
+        );
+        assert_eq!(
+            AmountValue(u128::MAX).display_with(18, "ETH"),
+            "340282366920938463463.374607431768211455 ETH"
+        );
+    }
+
+    #[test]
+    fn display_keeps_trailing_zeros() {
+        assert_eq!(
+            AmountValue(1_000_000).display_with(6, "USDC"),
+            "1.000000 USDC"
+        );
+        assert_eq!(
+            AmountValue(10_000_000).display_with(6, "USDC"),
+            "10.000000 USDC"
+        );
+        assert_eq!(AmountValue(100).display_with(2, ""), "1.00");
+    }
+}
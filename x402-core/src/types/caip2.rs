@@ -0,0 +1,113 @@
+//! [`Caip2`] blockchain identifiers, e.g. `eip155:8453`.
+
+use std::{fmt::Display, str::FromStr};
+
+use serde::{Deserialize, Serialize};
+
+/// A CAIP-2 blockchain identifier, `namespace:reference`.
+///
+/// The namespace is 3 to 8 lowercase letters, digits or `-`, and the reference is 1 to 32
+/// letters, digits, `-` or `_`, as specified by
+/// [CAIP-2](https://github.com/ChainAgnostic/CAIPs/blob/main/CAIPs/caip-2.md).
+///
+/// # Example
+///
+/// ```
+/// use x402_core::types::Caip2;
+///
+/// let network: Caip2 = "eip155:8453".parse().unwrap();
+/// assert_eq!(network.namespace(), "eip155");
+/// assert_eq!(network.reference(), "8453");
+///
+/// assert!("base-sepolia".parse::<Caip2>().is_err());
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Caip2 {
+    namespace: String,
+    reference: String,
+}
+
+/// The reason a string is not a [`Caip2`] identifier.
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum Caip2Error {
+    #[error("'{0}' is not a CAIP-2 identifier: expected 'namespace:reference'")]
+    MissingSeparator(String),
+    #[error("Invalid CAIP-2 namespace '{0}': expected 3 to 8 characters of [-a-z0-9]")]
+    InvalidNamespace(String),
+    #[error("Invalid CAIP-2 reference '{0}': expected 1 to 32 characters of [-_a-zA-Z0-9]")]
+    InvalidReference(String),
+    #[error("Expected a CAIP-2 identifier in the '{expected}' namespace, got '{found}'")]
+    UnexpectedNamespace { expected: String, found: String },
+    #[error(
+        "Invalid Solana CAIP-2 reference '{0}': expected the first 32 base58 characters of the genesis hash"
+    )]
+    InvalidGenesisHash(String),
+}
+
+impl Caip2 {
+    /// The namespace, e.g. `eip155` or `solana`.
+    pub fn namespace(&self) -> &str {
+        &self.namespace
+    }
+
+    /// The reference within the namespace, e.g. the chain ID `8453`.
+    pub fn reference(&self) -> &str {
+        &self.reference
+    }
+}
+
+impl FromStr for Caip2 {
+    type Err = Caip2Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (namespace, reference) = s
+            .split_once(':')
+            .ok_or_else(|| Caip2Error::MissingSeparator(s.to_string()))?;
+
+        if !(3..=8).contains(&namespace.len())
+            || !namespace
+                .bytes()
+                .all(|b| b.is_ascii_lowercase() || b.is_ascii_digit() || b == b'-')
+        {
+            return Err(Caip2Error::InvalidNamespace(namespace.to_string()));
+        }
+        if !(1..=32).contains(&reference.len())
+            || !reference
+                .bytes()
+                .all(|b| b.is_ascii_alphanumeric() || b == b'-' || b == b'_')
+        {
+            return Err(Caip2Error::InvalidReference(reference.to_string()));
+        }
+
+        Ok(Caip2 {
+            namespace: namespace.to_string(),
+            reference: reference.to_string(),
+        })
+    }
+}
+
+impl Display for Caip2 {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}:{}", self.namespace, self.reference)
+    }
+}
+
+impl Serialize for Caip2 {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for Caip2 {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        String::deserialize(deserializer)?
+            .parse()
+            .map_err(serde::de::Error::custom)
+    }
+}
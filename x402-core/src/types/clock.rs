@@ -0,0 +1,65 @@
+//! [`Clock`] sources of the current time, e.g. for the validity window of signed payments.
+
+use std::time::{Duration, SystemTime};
+
+/// A source of the current time.
+///
+/// Signers and other time-dependent components take a clock rather than reading
+/// [`SystemTime::now`] directly, so that their expiry math can be tested with a [`FixedClock`].
+pub trait Clock: Send + Sync {
+    /// The current time.
+    fn now(&self) -> SystemTime;
+}
+
+/// The system clock, reading [`SystemTime::now`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> SystemTime {
+        SystemTime::now()
+    }
+}
+
+/// A clock stopped at a fixed time, for deterministic tests.
+///
+/// # Example
+///
+/// ```
+/// use std::time::{Duration, SystemTime};
+///
+/// use x402_core::types::{Clock, FixedClock};
+///
+/// let clock = FixedClock::from_unix_seconds(1_700_000_000);
+/// assert_eq!(
+///     clock.now().duration_since(SystemTime::UNIX_EPOCH).unwrap(),
+///     Duration::from_secs(1_700_000_000)
+/// );
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FixedClock(pub SystemTime);
+
+impl FixedClock {
+    /// A clock stopped at `seconds` after the Unix epoch.
+    pub fn from_unix_seconds(seconds: u64) -> Self {
+        FixedClock(SystemTime::UNIX_EPOCH + Duration::from_secs(seconds))
+    }
+}
+
+impl Clock for FixedClock {
+    fn now(&self) -> SystemTime {
+        self.0
+    }
+}
+
+impl<C: Clock + ?Sized> Clock for &C {
+    fn now(&self) -> SystemTime {
+        (**self).now()
+    }
+}
+
+impl<C: Clock + ?Sized> Clock for std::sync::Arc<C> {
+    fn now(&self) -> SystemTime {
+        (**self).now()
+    }
+}
@@ -2,6 +2,12 @@
 
 use std::fmt::{Debug, Display};
 
+use base64::{
+    Engine,
+    alphabet::URL_SAFE,
+    engine::{DecodePaddingMode, GeneralPurpose, GeneralPurposeConfig},
+    prelude::BASE64_STANDARD,
+};
 use serde::{Deserialize, Serialize};
 
 /// Represents an key-value pair in the X402 protocol. The key is a `String`.
@@ -10,6 +16,36 @@ pub type Record<V> = std::collections::HashMap<String, V>;
 /// Represents any JSON value. Used for serializing/deserializing arbitrary JSON data.
 pub type AnyJson = serde_json::Value;
 
+/// Deep-merge `merge` over the `base` extra of payment requirements.
+///
+/// Objects are merged key by key, recursively, with the keys of `merge` winning. Any other value,
+/// or an object merged over a non-object, replaces the base value.
+pub fn merge_extra(base: Option<AnyJson>, merge: Option<AnyJson>) -> Option<AnyJson> {
+    match (base, merge) {
+        (Some(mut base), Some(merge)) => {
+            merge_json(&mut base, merge);
+            Some(base)
+        }
+        (base, merge) => merge.or(base),
+    }
+}
+
+fn merge_json(base: &mut AnyJson, merge: AnyJson) {
+    match (base, merge) {
+        (AnyJson::Object(base), AnyJson::Object(merge)) => {
+            for (key, value) in merge {
+                match base.get_mut(&key) {
+                    Some(existing) => merge_json(existing, value),
+                    None => {
+                        base.insert(key, value);
+                    }
+                }
+            }
+        }
+        (base, merge) => *base = merge,
+    }
+}
+
 /// Represents the X402 protocol version 1. Any type's specific to version 1 can use this struct for its `x402Version` field.
 ///
 /// ```
@@ -33,7 +69,7 @@ pub type AnyJson = serde_json::Value;
 /// let json = serde_json::to_value(&example).unwrap();
 /// assert_eq!(json.get("x402Version").unwrap(), &serde_json::json!(1));
 /// ```
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub struct X402V1;
 
 /// Represents the X402 protocol version 2.
@@ -61,7 +97,7 @@ pub struct X402V1;
 /// let json = serde_json::to_value(&example).unwrap();
 /// assert_eq!(json.get("x402Version").unwrap(), &serde_json::json!(2));
 /// ```
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub struct X402V2;
 
 impl Serialize for X402V1 {
@@ -155,7 +191,9 @@ impl Display for X402V2 {
 /// let json_v2 = serde_json::to_value(&example_v2).unwrap();
 /// assert_eq!(json_v2.get("x402Version").unwrap(), &serde_json::json!(2));
 /// ```
-#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+///
+/// Versions are ordered by protocol version, `V1` before `V2`.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub enum X402Version {
     /// Version 1 of the X402 protocol: `"x402Version": 1`.
     V1(X402V1),
@@ -218,9 +256,34 @@ impl X402Version {
 }
 
 /// Represents a base64-encoded header value for X402 protocol headers.
+///
+/// Headers are encoded with standard base64, but decoded from URL-safe base64 as well, with or
+/// without padding, as sent by some clients.
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct Base64EncodedHeader(pub String);
 
+/// URL-safe base64, accepting values with or without padding.
+const BASE64_URL_SAFE: GeneralPurpose = GeneralPurpose::new(
+    &URL_SAFE,
+    GeneralPurposeConfig::new().with_decode_padding_mode(DecodePaddingMode::Indifferent),
+);
+
+impl Base64EncodedHeader {
+    /// Decode the header value, see [`decode_str`](Base64EncodedHeader::decode_str).
+    pub fn decode(&self) -> Result<Vec<u8>, base64::DecodeError> {
+        Self::decode_str(&self.0)
+    }
+
+    /// Decode a header value from standard base64, falling back to URL-safe base64.
+    ///
+    /// Returns the error of the standard decoding if both fail.
+    pub fn decode_str(value: &str) -> Result<Vec<u8>, base64::DecodeError> {
+        BASE64_STANDARD
+            .decode(value)
+            .or_else(|err| BASE64_URL_SAFE.decode(value).map_err(|_| err))
+    }
+}
+
 impl Serialize for Base64EncodedHeader {
     fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
     where
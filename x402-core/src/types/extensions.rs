@@ -52,7 +52,7 @@ use crate::types::{AnyJson, Record};
 ///
 /// The generic parameter `T` defaults to [`AnyJson`] for transport/type-erased use.
 /// Use a concrete type implementing [`ExtensionInfo`] for typed extension construction.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct Extension<T = AnyJson> {
     /// The information about the extension.
     pub info: T,
@@ -223,7 +223,7 @@ where
 }
 
 /// Represents the identifier for an extension in the X402 protocol.
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct ExtensionIdentifier(pub String);
 
 impl Display for ExtensionIdentifier {
@@ -0,0 +1,104 @@
+//! [`KnownAssetRegistry`] holds the decimals and symbols of assets, to display amounts.
+
+use crate::core::{Address, Asset};
+
+/// An asset on a network, with the decimals and symbol to display its amounts.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KnownAsset {
+    /// The CAIP-2 identifier of the network.
+    pub network: String,
+    /// The address of the asset.
+    pub address: String,
+    /// The number of decimals the asset uses.
+    pub decimals: u8,
+    /// The symbol of the asset.
+    pub symbol: String,
+}
+
+/// Assets keyed by network and address, to display amounts in whole tokens.
+///
+/// EVM addresses (`0x`-prefixed) are compared case-insensitively, other addresses are compared exactly.
+///
+/// # Example
+///
+/// ```
+/// use x402_core::{transport::PaymentRequirements, types::KnownAssetRegistry};
+///
+/// let registry = KnownAssetRegistry::new().register_decimals(
+///     "eip155:8453",
+///     "0x833589fCD6eDb6E08f4c7C32D4f71b54bdA02913",
+///     6,
+///     "USDC",
+/// );
+///
+/// let requirements = PaymentRequirements::builder()
+///     .scheme("exact")
+///     .network("eip155:8453")
+///     .amount(1000u64)
+///     .asset("0x833589fcd6edb6e08f4c7c32d4f71b54bda02913")
+///     .pay_to("0x3CB9B3bBfde8501f411bB69Ad3DC07908ED0dE20")
+///     .max_timeout_seconds(60)
+///     .build()
+///     .unwrap();
+///
+/// assert_eq!(requirements.display_amount(&registry), "0.001000 USDC");
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct KnownAssetRegistry {
+    pub assets: Vec<KnownAsset>,
+}
+
+impl KnownAssetRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Register an asset on the given network.
+    pub fn register<A: Address>(self, network: impl Into<String>, asset: Asset<A>) -> Self {
+        self.register_decimals(
+            network,
+            asset.address.to_string(),
+            asset.decimals,
+            asset.symbol,
+        )
+    }
+
+    /// Register the asset at the given address, with the given decimals and symbol.
+    pub fn register_decimals(
+        mut self,
+        network: impl Into<String>,
+        address: impl Into<String>,
+        decimals: u8,
+        symbol: impl Into<String>,
+    ) -> Self {
+        self.assets.push(KnownAsset {
+            network: network.into(),
+            address: address.into(),
+            decimals,
+            symbol: symbol.into(),
+        });
+        self
+    }
+
+    /// Look up the asset at the given address on the given network.
+    pub fn get(&self, network: &str, address: &str) -> Option<&KnownAsset> {
+        self.assets
+            .iter()
+            .find(|asset| asset.network == network && same_address(&asset.address, address))
+    }
+
+    /// Look up the asset with the given symbol on the given network, ignoring the case of the symbol.
+    pub fn find_symbol(&self, network: &str, symbol: &str) -> Option<&KnownAsset> {
+        self.assets
+            .iter()
+            .find(|asset| asset.network == network && asset.symbol.eq_ignore_ascii_case(symbol))
+    }
+}
+
+fn same_address(a: &str, b: &str) -> bool {
+    if a.starts_with("0x") && b.starts_with("0x") {
+        a.eq_ignore_ascii_case(b)
+    } else {
+        a == b
+    }
+}
@@ -1,11 +1,19 @@
 //! Core types used across the X402 Kit.
 
 mod amount;
+mod caip2;
+mod clock;
 mod common;
 mod extensions;
+mod known_assets;
 mod schema;
+mod scheme_name;
 
 pub use amount::*;
+pub use caip2::*;
+pub use clock::*;
 pub use common::*;
 pub use extensions::*;
+pub use known_assets::*;
 pub use schema::*;
+pub use scheme_name::*;
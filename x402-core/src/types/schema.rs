@@ -4,6 +4,7 @@
 
 use bon::Builder;
 use serde::{Deserialize, Serialize};
+use serde_json::json;
 
 use crate::types::Record;
 
@@ -35,6 +36,83 @@ pub struct FieldDefinition {
     pub properties: Option<Record<FieldDefinition>>,
 }
 
+impl FieldDefinition {
+    /// Translate the field definition into a JSON Schema.
+    ///
+    /// A `required` list names the required properties of this field. A `required: true` marks
+    /// the field itself as required, and is translated into the `required` list of its parent.
+    pub fn to_json_schema(&self) -> serde_json::Value {
+        let mut schema = serde_json::Map::new();
+
+        if let Some(field_type) = &self.field_type {
+            schema.insert("type".into(), json!(field_type));
+        }
+        if let Some(description) = &self.description {
+            schema.insert("description".into(), json!(description));
+        }
+        if let Some(field_enum) = &self.field_enum {
+            schema.insert("enum".into(), json!(field_enum));
+        }
+
+        let mut required = match &self.required {
+            Some(FieldRequired::VecString(names)) => names.clone(),
+            _ => Vec::new(),
+        };
+        if let Some(properties) = &self.properties {
+            schema.insert("properties".into(), properties_schema(properties));
+            for name in required_properties(properties) {
+                if !required.contains(&name) {
+                    required.push(name);
+                }
+            }
+        }
+        if !required.is_empty() {
+            schema.insert("required".into(), json!(required));
+        }
+
+        schema.into()
+    }
+
+    fn is_required(&self) -> bool {
+        matches!(self.required, Some(FieldRequired::Boolean(true)))
+    }
+}
+
+/// The JSON Schema `properties` of the given fields.
+fn properties_schema(fields: &Record<FieldDefinition>) -> serde_json::Value {
+    fields
+        .iter()
+        .map(|(name, field)| (name.clone(), field.to_json_schema()))
+        .collect::<serde_json::Map<_, _>>()
+        .into()
+}
+
+/// The names of the fields marked with `required: true`, sorted for a stable output.
+fn required_properties(fields: &Record<FieldDefinition>) -> Vec<String> {
+    let mut required = fields
+        .iter()
+        .filter(|(_, field)| field.is_required())
+        .map(|(name, _)| name.clone())
+        .collect::<Vec<_>>();
+    required.sort();
+    required
+}
+
+/// A JSON Schema of an object with the given fields as properties.
+fn object_schema(fields: &Record<FieldDefinition>) -> serde_json::Value {
+    let mut schema = json!({
+        "type": "object",
+        "properties": properties_schema(fields),
+    });
+
+    let required = required_properties(fields);
+    if !required.is_empty() {
+        schema["required"] = json!(required);
+    }
+
+    schema
+}
+
 impl TryFrom<serde_json::Value> for FieldDefinition {
     type Error = serde_json::Error;
 
@@ -116,6 +194,38 @@ impl Input {
             Input::Http(http_input) => Some(http_input),
         }
     }
+
+    /// Translate the input into a JSON Schema.
+    ///
+    /// The transport, method and body type are pinned with `const`, and the query parameters,
+    /// body fields and header fields are translated into object schemas.
+    pub fn to_json_schema(&self) -> serde_json::Value {
+        match self {
+            Input::Http(http_input) => {
+                let mut properties = serde_json::Map::new();
+                properties.insert("type".into(), json!({ "const": "http" }));
+                properties.insert("method".into(), json!({ "const": http_input.method }));
+                if let Some(body_type) = http_input.body_type {
+                    properties.insert("bodyType".into(), json!({ "const": body_type }));
+                }
+                if let Some(query_params) = &http_input.query_params {
+                    properties.insert("queryParams".into(), object_schema(query_params));
+                }
+                if let Some(body_fields) = &http_input.body_fields {
+                    properties.insert("bodyFields".into(), object_schema(body_fields));
+                }
+                if let Some(header_fields) = &http_input.header_fields {
+                    properties.insert("headerFields".into(), object_schema(header_fields));
+                }
+
+                json!({
+                    "type": "object",
+                    "properties": properties,
+                    "required": ["type", "method"],
+                })
+            }
+        }
+    }
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
@@ -176,12 +286,38 @@ impl OutputSchema {
             ))
             .build()
     }
+
+    /// Translate the schema into a Draft-07 JSON Schema document, with the expected input
+    /// and output of the resource as the `input` and `output` properties.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use x402_core::types::OutputSchema;
+    ///
+    /// let schema = OutputSchema::http_get_discoverable().to_json_schema();
+    ///
+    /// assert_eq!(schema["$schema"], "http://json-schema.org/draft-07/schema#");
+    /// assert_eq!(schema["properties"]["input"]["properties"]["method"]["const"], "GET");
+    /// ```
+    pub fn to_json_schema(&self) -> serde_json::Value {
+        let mut properties = serde_json::Map::new();
+        properties.insert("input".into(), self.input.to_json_schema());
+        if let Some(output) = &self.output {
+            properties.insert("output".into(), object_schema(output));
+        }
+
+        json!({
+            "$schema": "http://json-schema.org/draft-07/schema#",
+            "type": "object",
+            "properties": properties,
+            "required": ["input"],
+        })
+    }
 }
 
 #[cfg(test)]
 mod tests {
-    use serde_json::json;
-
     use super::*;
 
     fn setup_complex_input() -> Input {
@@ -384,4 +520,103 @@ mod tests {
             post_schema_json
         );
     }
+
+    #[test]
+    fn output_schema_to_json_schema() {
+        let output_schema = OutputSchema::builder()
+            .input(setup_complex_input())
+            .output([(
+                "response_field",
+                FieldDefinition::builder()
+                    .field_type("string")
+                    .description("A response field")
+                    .required(Required)
+                    .build(),
+            )])
+            .build();
+
+        let json_schema = json!({
+            "$schema": "http://json-schema.org/draft-07/schema#",
+            "type": "object",
+            "properties": {
+                "input": {
+                    "type": "object",
+                    "properties": {
+                        "type": { "const": "http" },
+                        "method": { "const": "POST" },
+                        "bodyType": { "const": "json" },
+                        "headerFields": {
+                            "type": "object",
+                            "properties": {
+                                "example_header": {
+                                    "type": "string",
+                                    "description": "An example header"
+                                }
+                            },
+                            "required": ["example_header"]
+                        },
+                        "queryParams": {
+                            "type": "object",
+                            "properties": {
+                                "exmple_query": {
+                                    "type": "string",
+                                    "description": "An example query parameter"
+                                }
+                            }
+                        },
+                        "bodyFields": {
+                            "type": "object",
+                            "properties": {
+                                "example": {
+                                    "type": "string",
+                                    "description": "An example field",
+                                    "properties": {
+                                        "nested_field": {
+                                            "type": "number",
+                                            "description": "A nested field"
+                                        },
+                                        "nested_field2": {
+                                            "type": "string",
+                                            "description": "Optional nested field",
+                                            "enum": ["a", "b", "c"]
+                                        }
+                                    },
+                                    "required": ["nested_field", "nested_field2"]
+                                }
+                            }
+                        }
+                    },
+                    "required": ["type", "method"]
+                },
+                "output": {
+                    "type": "object",
+                    "properties": {
+                        "response_field": {
+                            "type": "string",
+                            "description": "A response field"
+                        }
+                    },
+                    "required": ["response_field"]
+                }
+            },
+            "required": ["input"]
+        });
+
+        assert_eq!(output_schema.to_json_schema(), json_schema);
+    }
+
+    #[test]
+    fn json_schema_merges_required_forms() {
+        let field = FieldDefinition::builder()
+            .field_type("object")
+            .required(["b"])
+            .properties([
+                ("a", FieldDefinition::builder().required(Required).build()),
+                ("b", FieldDefinition::builder().required(Required).build()),
+                ("c", FieldDefinition::builder().build()),
+            ])
+            .build();
+
+        assert_eq!(field.to_json_schema()["required"], json!(["b", "a"]));
+    }
 }
@@ -0,0 +1,161 @@
+//! [`SchemeName`] payment scheme names, e.g. `exact`.
+
+use std::{
+    borrow::Cow,
+    convert::Infallible,
+    fmt::Display,
+    hash::{Hash, Hasher},
+    str::FromStr,
+};
+
+use serde::{Deserialize, Serialize};
+
+/// The name of a payment scheme, e.g. [`SchemeName::EXACT`].
+///
+/// Names compare and hash ASCII case-insensitively, so that `Exact` matches `exact`, but are
+/// displayed and serialized as given.
+///
+/// # Example
+///
+/// ```
+/// use x402_core::types::SchemeName;
+///
+/// let scheme: SchemeName = "Exact".parse().unwrap();
+/// assert_eq!(scheme, SchemeName::EXACT);
+/// assert_eq!(scheme, "exact");
+/// assert_eq!(scheme.to_string(), "Exact");
+/// ```
+#[derive(Debug, Clone, Eq)]
+pub struct SchemeName(Cow<'static, str>);
+
+impl SchemeName {
+    /// The `exact` scheme, paying a fixed amount.
+    pub const EXACT: SchemeName = SchemeName::from_static("exact");
+    /// The `upto` scheme, paying up to a maximum amount.
+    pub const UPTO: SchemeName = SchemeName::from_static("upto");
+    /// The `permit` scheme, authorizing the payee to pull the payment.
+    pub const PERMIT: SchemeName = SchemeName::from_static("permit");
+
+    /// A scheme name from a static string, without allocating.
+    pub const fn from_static(name: &'static str) -> Self {
+        SchemeName(Cow::Borrowed(name))
+    }
+
+    /// The name as given.
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    /// Whether the name is empty.
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+}
+
+impl PartialEq for SchemeName {
+    fn eq(&self, other: &Self) -> bool {
+        self.0.eq_ignore_ascii_case(&other.0)
+    }
+}
+
+impl PartialEq<str> for SchemeName {
+    fn eq(&self, other: &str) -> bool {
+        self.0.eq_ignore_ascii_case(other)
+    }
+}
+
+impl PartialEq<&str> for SchemeName {
+    fn eq(&self, other: &&str) -> bool {
+        self.0.eq_ignore_ascii_case(other)
+    }
+}
+
+impl Hash for SchemeName {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        for byte in self.0.bytes() {
+            state.write_u8(byte.to_ascii_lowercase());
+        }
+        state.write_u8(0xff);
+    }
+}
+
+impl AsRef<str> for SchemeName {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl From<&str> for SchemeName {
+    fn from(name: &str) -> Self {
+        SchemeName(Cow::Owned(name.to_string()))
+    }
+}
+
+impl From<String> for SchemeName {
+    fn from(name: String) -> Self {
+        SchemeName(Cow::Owned(name))
+    }
+}
+
+impl FromStr for SchemeName {
+    type Err = Infallible;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(SchemeName::from(s))
+    }
+}
+
+impl Display for SchemeName {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl Serialize for SchemeName {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        serializer.serialize_str(&self.0)
+    }
+}
+
+impl<'de> Deserialize<'de> for SchemeName {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        String::deserialize(deserializer).map(SchemeName::from)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use super::*;
+
+    #[test]
+    fn compare_case_insensitively() {
+        let upper: SchemeName = "EXACT".parse().unwrap();
+        assert_eq!(upper, SchemeName::EXACT);
+        assert_eq!(upper, "Exact");
+        assert_ne!(upper, SchemeName::UPTO);
+        assert_eq!(upper.as_str(), "EXACT");
+
+        let schemes = HashSet::from([SchemeName::EXACT, SchemeName::UPTO]);
+        assert!(schemes.contains(&upper));
+        assert!(!schemes.contains(&SchemeName::PERMIT));
+    }
+
+    #[test]
+    fn serialize_as_given() {
+        let scheme: SchemeName = serde_json::from_str("\"Exact\"").unwrap();
+        assert_eq!(scheme, SchemeName::EXACT);
+        assert_eq!(serde_json::to_string(&scheme).unwrap(), "\"Exact\"");
+        assert_eq!(
+            serde_json::to_string(&SchemeName::UPTO).unwrap(),
+            "\"upto\""
+        );
+    }
+}
@@ -32,8 +32,7 @@ async fn standard_paywall(
         .expect("AppState not configured")
         .clone();
 
-    let (http_req, payload) = req.into_parts();
-    let http_req_clone = http_req.clone();
+    let http_req = req.request().clone();
 
     let paywall = PayWall::builder()
         .facilitator(state.facilitator.clone())
@@ -55,17 +54,16 @@ async fn standard_paywall(
 
     // Run the paywall
     let response = paywall
-        .handle_payment(http_req, |http_req| async move {
-            let srv_req = ServiceRequest::from_parts(http_req, payload);
-            match next.call(srv_req).await {
-                Ok(res) => res.map_into_boxed_body().into_parts().1,
-                Err(err) => err.error_response().map_into_boxed_body(),
+        .handle_payment(req, |req| async move {
+            match next.call(req).await {
+                Ok(res) => res.map_into_boxed_body(),
+                Err(err) => ServiceResponse::from_err(err, http_req),
             }
         })
         .await
         .map_err(Error::from)?;
 
-    Ok(ServiceResponse::new(http_req_clone, response))
+    Ok(response)
 }
 
 async fn custom_paywall(
@@ -77,8 +75,7 @@ async fn custom_paywall(
         .expect("AppState not configured")
         .clone();
 
-    let (http_req, payload) = req.into_parts();
-    let http_req_clone = http_req.clone();
+    let http_req = req.request().clone();
 
     let paywall = PayWall::builder()
         .facilitator(state.facilitator.clone())
@@ -100,23 +97,22 @@ async fn custom_paywall(
 
     // Skip updating accepts from facilitator, skip verifying, and settle payment before running handler
     let response = paywall
-        .process_request(http_req)
+        .process_request(req)
         .map_err(Error::from)?
         .settle()
         .await
         .map_err(Error::from)?
-        .run_handler(|http_req| async move {
-            let srv_req = ServiceRequest::from_parts(http_req, payload);
-            match next.call(srv_req).await {
-                Ok(res) => res.map_into_boxed_body().into_parts().1,
-                Err(err) => err.error_response().map_into_boxed_body(),
+        .run_handler(|req| async move {
+            match next.call(req).await {
+                Ok(res) => res.map_into_boxed_body(),
+                Err(err) => ServiceResponse::from_err(err, http_req),
             }
         })
         .await
         .map_err(Error::from)?
         .response();
 
-    Ok(ServiceResponse::new(http_req_clone, response))
+    Ok(response)
 }
 
 async fn multi_payments_paywall(
@@ -128,8 +124,7 @@ async fn multi_payments_paywall(
         .expect("AppState not configured")
         .clone();
 
-    let (http_req, payload) = req.into_parts();
-    let http_req_clone = http_req.clone();
+    let http_req = req.request().clone();
 
     let paywall = PayWall::builder()
         .facilitator(state.facilitator.clone())
@@ -161,17 +156,16 @@ async fn multi_payments_paywall(
 
     // Run the paywall
     let response = paywall
-        .handle_payment(http_req, |http_req| async move {
-            let srv_req = ServiceRequest::from_parts(http_req, payload);
-            match next.call(srv_req).await {
-                Ok(res) => res.map_into_boxed_body().into_parts().1,
-                Err(err) => err.error_response().map_into_boxed_body(),
+        .handle_payment(req, |req| async move {
+            match next.call(req).await {
+                Ok(res) => res.map_into_boxed_body(),
+                Err(err) => ServiceResponse::from_err(err, http_req),
             }
         })
         .await
         .map_err(Error::from)?;
 
-    Ok(ServiceResponse::new(http_req_clone, response))
+    Ok(response)
 }
 
 /// Example handler for a protected resource.
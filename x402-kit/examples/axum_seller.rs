@@ -13,6 +13,7 @@ use url::Url;
 use url_macro::url;
 use x402_kit::{
     core::Resource,
+    facilitator::Refundable,
     facilitator_client::{FacilitatorClient, StandardFacilitatorClient},
     networks::{evm::assets::UsdcBaseSepolia, svm::assets::UsdcSolanaDevnet},
     paywall::{errors::ErrorResponse, paywall::PayWall, processor::PaymentState},
@@ -86,6 +87,53 @@ async fn custom_paywall(
     Ok(response)
 }
 
+async fn refundable_paywall(
+    State(state): State<PayWallState>,
+    req: Request,
+    next: Next,
+) -> Result<Response, ErrorResponse> {
+    let paywall = PayWall::builder()
+        .facilitator(state.facilitator.clone())
+        .accepts(
+            ExactEvm::builder()
+                .amount(1000)
+                .asset(UsdcBaseSepolia)
+                .pay_to(address!("0x3CB9B3bBfde8501f411bB69Ad3DC07908ED0dE20"))
+                .build(),
+        )
+        .resource(
+            Resource::builder()
+                .url(url!("https://example.com/resource/refundable"))
+                .description("X402 payment protected resource")
+                .mime_type("application/json")
+                .build(),
+        )
+        .build();
+
+    // Settle before running the handler, so that the payment is secured before doing the work
+    let processed = paywall
+        .process_request(req)?
+        .verify()
+        .await?
+        .settle()
+        .await?
+        .run_handler(|req| next.run(req))
+        .await?;
+
+    // Compensate the payer if the work failed after settlement
+    if processed.response.status().is_server_error()
+        && let Some(settled) = &processed.payment_state.settled
+    {
+        let amount = processed.payment_state.amount();
+        match state.facilitator.refund(settled, amount).await {
+            Ok(result) => tracing::info!(?result, "Refunded failed request"),
+            Err(err) => tracing::error!(%err, "Failed to refund failed request"),
+        }
+    }
+
+    Ok(processed.response())
+}
+
 async fn multi_payments_paywall(
     State(state): State<PayWallState>,
     req: Request,
@@ -158,6 +206,10 @@ async fn main() {
             "/resource/custom",
             post(example_handler).layer(from_fn_with_state(state.clone(), custom_paywall)),
         )
+        .route(
+            "/resource/refundable",
+            post(example_handler).layer(from_fn_with_state(state.clone(), refundable_paywall)),
+        )
         .route(
             "/resource/multi_payments",
             post(example_handler).layer(from_fn_with_state(state.clone(), multi_payments_paywall)),
@@ -0,0 +1,257 @@
+//! Balance checkers reading the payer's funds from an RPC endpoint before verification.
+//!
+//! [`EvmRpcBalanceChecker`] implements the paywall's [`BalanceChecker`] with an `alloy` provider,
+//! so that payments the payer cannot fund are rejected without a facilitator round-trip:
+//!
+//! ```no_run
+//! use alloy::providers::ProviderBuilder;
+//! use x402_kit::{
+//!     balance::EvmRpcBalanceChecker,
+//!     networks::evm::{ExplicitEvmNetwork, networks::BaseSepolia},
+//! };
+//!
+//! let provider = ProviderBuilder::new().connect_http("https://sepolia.base.org".parse().unwrap());
+//! let checker = EvmRpcBalanceChecker::new(provider, BaseSepolia::NETWORK);
+//! ```
+//!
+//! Available with the `evm-rpc` feature.
+
+use std::fmt::Debug;
+
+use alloy_core::{primitives::U256, sol, sol_types::SolCall};
+use alloy_provider::Provider;
+use alloy_rpc_types_eth::TransactionRequest;
+use x402_paywall::balance::{BalanceChecker, BalanceFuture};
+
+use crate::{
+    core::Scheme,
+    networks::evm::{EvmAddress, EvmNetwork},
+    schemes::{
+        exact_evm::{ExactEvmPayload, ExactEvmScheme},
+        permit_evm::{PermitEvmPayload, PermitEvmScheme},
+    },
+    transport::{PaymentPayload, PaymentRequirements},
+};
+
+sol! {
+    function balanceOf(address owner) external view returns (uint256);
+}
+
+/// Checks the payer's ERC-20 balance of the asset with an RPC provider for one EVM network.
+///
+/// Supports the `exact` and `permit` schemes. For `permit`, the value of the permit must also
+/// cover the amount, as it is the allowance granted to the spender. Payments on other networks
+/// or schemes, and failed RPC calls, are left to the facilitator.
+#[derive(Clone)]
+pub struct EvmRpcBalanceChecker<P> {
+    /// The provider to read balances with.
+    pub provider: P,
+    /// The network the provider is connected to.
+    pub network: EvmNetwork,
+}
+
+impl<P> Debug for EvmRpcBalanceChecker<P> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("EvmRpcBalanceChecker")
+            .field("network", &self.network)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<P: Provider> EvmRpcBalanceChecker<P> {
+    pub fn new(provider: P, network: EvmNetwork) -> Self {
+        EvmRpcBalanceChecker { provider, network }
+    }
+
+    /// The balance of the asset held by the owner, or `None` if the RPC call failed.
+    async fn balance_of(&self, asset: EvmAddress, owner: EvmAddress) -> Option<U256> {
+        let call = balanceOfCall { owner: owner.0 };
+        let request = TransactionRequest::default()
+            .to(asset.0)
+            .input(call.abi_encode().into());
+
+        let output = self.provider.call(request).await.ok()?;
+        balanceOfCall::abi_decode_returns(&output).ok()
+    }
+}
+
+/// The payer of an EVM payload, checking that a permit covers the amount.
+fn evm_payer(
+    payload: &PaymentPayload,
+    selected: &PaymentRequirements,
+) -> Option<Result<EvmAddress, String>> {
+    if selected.scheme == ExactEvmScheme::SCHEME_NAME {
+        let payload: ExactEvmPayload = serde_json::from_value(payload.payload.clone()).ok()?;
+        return Some(Ok(payload.authorization.from));
+    }
+
+    if selected.scheme == PermitEvmScheme::SCHEME_NAME {
+        let payload: PermitEvmPayload = serde_json::from_value(payload.payload.clone()).ok()?;
+        if payload.permit.value.0 < selected.amount.0 {
+            return Some(Err(format!(
+                "Permit value {} is below the amount {}",
+                payload.permit.value, selected.amount
+            )));
+        }
+        return Some(Ok(payload.permit.owner));
+    }
+
+    None
+}
+
+impl<P: Provider + 'static> BalanceChecker for EvmRpcBalanceChecker<P> {
+    fn check<'a>(
+        &'a self,
+        payload: &'a PaymentPayload,
+        selected: &'a PaymentRequirements,
+    ) -> BalanceFuture<'a> {
+        Box::pin(async move {
+            if selected.network != self.network.network_id {
+                return Ok(());
+            }
+            let Ok(asset) = selected.asset.parse::<EvmAddress>() else {
+                return Ok(());
+            };
+            let Some(payer) = evm_payer(payload, selected) else {
+                return Ok(());
+            };
+            let payer = payer?;
+            let Some(balance) = self.balance_of(asset, payer).await else {
+                return Ok(());
+            };
+
+            if balance < U256::from(selected.amount.0) {
+                return Err(format!(
+                    "Payer {payer} holds {balance} of asset {asset}, below the amount {}",
+                    selected.amount
+                ));
+            }
+
+            Ok(())
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloy::{providers::ProviderBuilder, transports::mock::Asserter};
+    use alloy_core::{primitives::Bytes, sol_types::SolValue};
+    use serde_json::json;
+
+    use crate::{
+        networks::evm::{ExplicitEvmNetwork, networks::BaseSepolia},
+        transport::PaymentResource,
+        types::{AmountValue, Record, X402V2},
+    };
+
+    use super::*;
+
+    const PAYER: &str = "0x857b06519E91e3A54538791bDbb0E22373e36b66";
+
+    fn requirements(scheme: &str) -> PaymentRequirements {
+        PaymentRequirements {
+            scheme: scheme.into(),
+            network: "eip155:84532".to_string(),
+            amount: AmountValue(1000),
+            asset: "0x036CbD53842c5426634e7929541eC2318f3dCF7e".to_string(),
+            pay_to: "0x3CB9B3bBfde8501f411bB69Ad3DC07908ED0dE20".to_string(),
+            max_timeout_seconds: 60,
+            extra: None,
+        }
+    }
+
+    fn payload(accepted: PaymentRequirements, payload: serde_json::Value) -> PaymentPayload {
+        PaymentPayload {
+            x402_version: X402V2,
+            resource: PaymentResource {
+                url: "https://example.com/resource".parse().unwrap(),
+                description: "Protected resource".to_string(),
+                mime_type: "application/json".to_string(),
+            },
+            accepted,
+            payload,
+            extensions: Record::new(),
+        }
+    }
+
+    fn exact_payload() -> PaymentPayload {
+        payload(
+            requirements("exact"),
+            json!({
+                "signature": format!("0x{}1b", "11".repeat(64)),
+                "authorization": {
+                    "from": PAYER,
+                    "to": "0x3CB9B3bBfde8501f411bB69Ad3DC07908ED0dE20",
+                    "value": "1000",
+                    "validAfter": "0",
+                    "validBefore": "9999999999",
+                    "nonce": format!("0x{}", "00".repeat(32)),
+                }
+            }),
+        )
+    }
+
+    fn checker(balances: &[u64]) -> EvmRpcBalanceChecker<impl Provider> {
+        let asserter = Asserter::new();
+        for balance in balances {
+            asserter.push_success(&Bytes::from(U256::from(*balance).abi_encode()));
+        }
+        let provider = ProviderBuilder::new().connect_mocked_client(asserter);
+
+        EvmRpcBalanceChecker::new(provider, BaseSepolia::NETWORK)
+    }
+
+    #[tokio::test]
+    async fn rejects_insufficient_balance() {
+        let reason = checker(&[999])
+            .check(&exact_payload(), &requirements("exact"))
+            .await
+            .unwrap_err();
+
+        assert!(reason.contains(&format!("Payer {PAYER} holds 999")));
+    }
+
+    #[tokio::test]
+    async fn accepts_sufficient_balance() {
+        checker(&[1000])
+            .check(&exact_payload(), &requirements("exact"))
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn skips_other_networks_and_rpc_errors() {
+        let mut other = requirements("exact");
+        other.network = "eip155:8453".to_string();
+        checker(&[0]).check(&exact_payload(), &other).await.unwrap();
+
+        checker(&[])
+            .check(&exact_payload(), &requirements("exact"))
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn rejects_permit_below_amount() {
+        let permit = payload(
+            requirements("permit"),
+            json!({
+                "signature": format!("0x{}1b", "11".repeat(64)),
+                "permit": {
+                    "owner": PAYER,
+                    "spender": "0x3CB9B3bBfde8501f411bB69Ad3DC07908ED0dE20",
+                    "value": "500",
+                    "nonce": "0",
+                    "deadline": "9999999999",
+                }
+            }),
+        );
+
+        let reason = checker(&[5000])
+            .check(&permit, &requirements("permit"))
+            .await
+            .unwrap_err();
+
+        assert_eq!(reason, "Permit value 500 is below the amount 1000");
+    }
+}
@@ -0,0 +1,517 @@
+//! A conformance suite for [`Facilitator`] implementations.
+//!
+//! [`run_conformance`] exercises the `supported`, `verify` and `settle` endpoints of a facilitator
+//! with a valid payment and with invalid ones, and checks that the responses hold the invariants
+//! `x402-kit` relies on. Each check is listed in the [`ConformanceReport`] as passed or failed.
+//!
+//! ```
+//! # async fn check(facilitator: x402_kit::testing::MockFacilitator) {
+//! use x402_kit::{
+//!     facilitator::conformance::{ConformanceFixtures, run_conformance},
+//!     testing,
+//! };
+//!
+//! let fixtures = ConformanceFixtures::exact_evm(&testing::requirements(), &testing::resource());
+//! let report = run_conformance(&facilitator, fixtures).await;
+//!
+//! for check in report.failures() {
+//!     eprintln!("{check}");
+//! }
+//! # }
+//! ```
+//!
+//! Available with the `test-utils` feature.
+
+use std::{
+    fmt::Display,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use alloy_signer_local::PrivateKeySigner;
+
+use crate::{
+    core::Resource,
+    facilitator::{Facilitator, PaymentRequest, SettleResult, VerifyResult},
+    schemes::exact_evm::{Nonce, TimestampSeconds},
+    testing::signed_payload_for,
+    transport::{PaymentPayload, PaymentRequirements, PaymentResource},
+    types::{Record, X402V2},
+};
+
+/// Payment requests exercised by [`run_conformance`].
+///
+/// All requests are for the same payment requirements. Only `valid` should be accepted.
+#[derive(Debug, Clone)]
+pub struct ConformanceFixtures {
+    /// A correctly signed payment, expected to verify and settle.
+    pub valid: PaymentRequest,
+    /// A payment whose signature does not match its authorization.
+    pub bad_signature: PaymentRequest,
+    /// A correctly signed payment whose authorization has expired.
+    pub expired: PaymentRequest,
+    /// A correctly signed payment accepting the requirements on another network.
+    pub wrong_network: PaymentRequest,
+}
+
+impl ConformanceFixtures {
+    /// Fixtures for exact EVM payment requirements, signed with a random key.
+    ///
+    /// The valid payment is signed by a fresh account holding no funds, so facilitators checking
+    /// balances will not accept it. Use a funded testnet account with your own fixtures instead.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the network, asset or `pay_to` of the requirements are not valid EVM identifiers.
+    pub fn exact_evm(requirements: &PaymentRequirements, resource: &Resource) -> Self {
+        let signer = PrivateKeySigner::random();
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or_default();
+        let request = |accepted: &PaymentRequirements, payload| PaymentRequest {
+            payment_payload: PaymentPayload {
+                x402_version: X402V2,
+                resource: PaymentResource::from(resource.clone()),
+                accepted: accepted.clone(),
+                payload,
+                extensions: Record::new(),
+            },
+            payment_requirements: requirements.clone(),
+            attestation: None,
+            idempotency_key: None,
+        };
+
+        let valid = signed_payload_for(
+            requirements,
+            &signer,
+            TimestampSeconds(now.saturating_sub(300)),
+            TimestampSeconds(now + requirements.max_timeout_seconds),
+        );
+
+        let mut bad_signature = valid.clone();
+        bad_signature.authorization.nonce = Nonce(rand::random());
+
+        let expired = signed_payload_for(
+            requirements,
+            &signer,
+            TimestampSeconds(now.saturating_sub(3600)),
+            TimestampSeconds(now.saturating_sub(1800)),
+        );
+
+        let mut other_network = requirements.clone();
+        other_network.network = if requirements.network == "eip155:1" {
+            "eip155:137".to_string()
+        } else {
+            "eip155:1".to_string()
+        };
+        let wrong_network = signed_payload_for(
+            &other_network,
+            &signer,
+            TimestampSeconds(now.saturating_sub(300)),
+            TimestampSeconds(now + requirements.max_timeout_seconds),
+        );
+
+        let json = |payload| serde_json::to_value(payload).expect("payload should serialize");
+        ConformanceFixtures {
+            valid: request(requirements, json(valid)),
+            bad_signature: request(requirements, json(bad_signature)),
+            expired: request(requirements, json(expired)),
+            wrong_network: request(&other_network, json(wrong_network)),
+        }
+    }
+}
+
+/// The result of a single conformance check.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConformanceCheck {
+    /// The name of the check, e.g. `verify.valid`.
+    pub name: &'static str,
+    /// The reason the check failed, or `None` if it passed.
+    pub failure: Option<String>,
+}
+
+impl ConformanceCheck {
+    /// Whether the check passed.
+    pub fn passed(&self) -> bool {
+        self.failure.is_none()
+    }
+}
+
+impl Display for ConformanceCheck {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.failure {
+            None => write!(f, "PASS {}", self.name),
+            Some(reason) => write!(f, "FAIL {}: {reason}", self.name),
+        }
+    }
+}
+
+/// The checks run by [`run_conformance`], in order.
+#[derive(Debug, Clone, Default)]
+pub struct ConformanceReport {
+    pub checks: Vec<ConformanceCheck>,
+}
+
+impl ConformanceReport {
+    /// Whether every check passed.
+    pub fn passed(&self) -> bool {
+        self.checks.iter().all(ConformanceCheck::passed)
+    }
+
+    /// The checks that failed.
+    pub fn failures(&self) -> impl Iterator<Item = &ConformanceCheck> {
+        self.checks.iter().filter(|check| !check.passed())
+    }
+
+    /// The check of the given name, if it was run.
+    pub fn check(&self, name: &str) -> Option<&ConformanceCheck> {
+        self.checks.iter().find(|check| check.name == name)
+    }
+
+    fn record(&mut self, name: &'static str, outcome: Result<(), String>) {
+        self.checks.push(ConformanceCheck {
+            name,
+            failure: outcome.err(),
+        });
+    }
+}
+
+impl Display for ConformanceReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for check in &self.checks {
+            writeln!(f, "{check}")?;
+        }
+        Ok(())
+    }
+}
+
+/// Run the conformance checks against the facilitator.
+///
+/// The checks are:
+///
+/// - `supported`: the supported kinds are non-empty and well-formed, i.e. have a scheme and a
+///   network, in CAIP-2 format for v2 kinds, and include the scheme and network of the fixtures.
+/// - `verify.valid`: the valid payment verifies with a non-empty payer.
+/// - `verify.bad_signature`, `verify.expired`, `verify.wrong_network`: the invalid payments are
+///   rejected with a non-empty reason.
+/// - `settle.bad_signature`: the payment with a bad signature fails to settle with a non-empty reason.
+/// - `settle.valid`: the valid payment settles with a non-empty transaction on the network of
+///   the requirements.
+///
+/// Facilitator errors fail the check they occur in, and never abort the run.
+pub async fn run_conformance<F: Facilitator>(
+    facilitator: &F,
+    fixtures: ConformanceFixtures,
+) -> ConformanceReport {
+    let mut report = ConformanceReport::default();
+    let requirements = fixtures.valid.payment_requirements.clone();
+
+    report.record(
+        "supported",
+        check_supported(facilitator, &requirements).await,
+    );
+    report.record(
+        "verify.valid",
+        check_verify_valid(facilitator, fixtures.valid.clone()).await,
+    );
+    for (name, request) in [
+        ("verify.bad_signature", fixtures.bad_signature.clone()),
+        ("verify.expired", fixtures.expired),
+        ("verify.wrong_network", fixtures.wrong_network),
+    ] {
+        report.record(name, check_verify_invalid(facilitator, request).await);
+    }
+    report.record(
+        "settle.bad_signature",
+        check_settle_invalid(facilitator, fixtures.bad_signature).await,
+    );
+    report.record(
+        "settle.valid",
+        check_settle_valid(facilitator, fixtures.valid).await,
+    );
+
+    report
+}
+
+async fn check_supported<F: Facilitator>(
+    facilitator: &F,
+    requirements: &PaymentRequirements,
+) -> Result<(), String> {
+    let supported = facilitator
+        .supported()
+        .await
+        .map_err(|err| format!("supported failed: {err}"))?;
+
+    if supported.kinds.is_empty() {
+        return Err("no supported kinds".to_string());
+    }
+    for kind in &supported.kinds {
+        if kind.scheme.is_empty() || kind.network.is_empty() {
+            return Err(format!(
+                "kind with empty scheme or network: '{}@{}'",
+                kind.scheme, kind.network
+            ));
+        }
+        if kind.x402_version.as_v2().is_some() && !is_caip2(&kind.network) {
+            return Err(format!(
+                "v2 kind network is not a CAIP-2 identifier: '{}'",
+                kind.network
+            ));
+        }
+    }
+    if !supported.kinds.iter().any(|kind| {
+        kind.x402_version.as_v2().is_some()
+            && kind.scheme == requirements.scheme
+            && kind.network == requirements.network
+    }) {
+        return Err(format!(
+            "fixtures kind '{}@{}' is not supported",
+            requirements.scheme, requirements.network
+        ));
+    }
+
+    Ok(())
+}
+
+async fn check_verify_valid<F: Facilitator>(
+    facilitator: &F,
+    request: PaymentRequest,
+) -> Result<(), String> {
+    match facilitator.verify(request).await {
+        Ok(VerifyResult::Valid(valid)) if valid.payer.is_empty() => {
+            Err("valid payment verified with an empty payer".to_string())
+        }
+        Ok(VerifyResult::Valid(_)) => Ok(()),
+        Ok(VerifyResult::Invalid(invalid)) => Err(format!(
+            "valid payment rejected: {}",
+            invalid.invalid_reason
+        )),
+        Err(err) => Err(format!("verify failed: {err}")),
+    }
+}
+
+async fn check_verify_invalid<F: Facilitator>(
+    facilitator: &F,
+    request: PaymentRequest,
+) -> Result<(), String> {
+    match facilitator.verify(request).await {
+        Ok(VerifyResult::Valid(_)) => Err("invalid payment verified".to_string()),
+        Ok(VerifyResult::Invalid(invalid)) if invalid.invalid_reason.is_empty() => {
+            Err("invalid payment rejected with an empty reason".to_string())
+        }
+        Ok(VerifyResult::Invalid(_)) => Ok(()),
+        Err(err) => Err(format!("verify failed: {err}")),
+    }
+}
+
+async fn check_settle_invalid<F: Facilitator>(
+    facilitator: &F,
+    request: PaymentRequest,
+) -> Result<(), String> {
+    match facilitator.settle(request).await {
+        Ok(SettleResult::Success(success)) => Err(format!(
+            "invalid payment settled: transaction '{}'",
+            success.transaction
+        )),
+        Ok(SettleResult::Failed(failed)) if failed.error_reason.is_empty() => {
+            Err("invalid payment failed with an empty reason".to_string())
+        }
+        Ok(SettleResult::Failed(_)) => Ok(()),
+        Ok(SettleResult::Pending(_)) => Err("invalid payment pending settlement".to_string()),
+        Ok(other) => Err(format!("unrecognized settle result: {other:?}")),
+        Err(err) => Err(format!("settle failed: {err}")),
+    }
+}
+
+async fn check_settle_valid<F: Facilitator>(
+    facilitator: &F,
+    request: PaymentRequest,
+) -> Result<(), String> {
+    let network = request.payment_requirements.network.clone();
+
+    match facilitator.settle(request).await {
+        Ok(SettleResult::Success(success)) if success.transaction.is_empty() => {
+            Err("valid payment settled with an empty transaction".to_string())
+        }
+        Ok(SettleResult::Success(success)) if success.network != network => Err(format!(
+            "valid payment settled on network '{}', expected '{network}'",
+            success.network
+        )),
+        // Facilitators confirming settlements asynchronously may accept them as pending
+        Ok(SettleResult::Success(_) | SettleResult::Pending(_)) => Ok(()),
+        Ok(SettleResult::Failed(failed)) => Err(format!(
+            "valid payment failed to settle: {}",
+            failed.error_reason
+        )),
+        Ok(other) => Err(format!("unrecognized settle result: {other:?}")),
+        Err(err) => Err(format!("settle failed: {err}")),
+    }
+}
+
+/// Whether the network is a CAIP-2 identifier, i.e. `namespace:reference`.
+fn is_caip2(network: &str) -> bool {
+    network
+        .split_once(':')
+        .is_some_and(|(namespace, reference)| !namespace.is_empty() && !reference.is_empty())
+}
+
+#[cfg(test)]
+mod tests {
+    use x402_core::facilitator::{
+        SettleFailed, SettleSuccess, SupportedResponse, VerifyInvalid, VerifyValid,
+    };
+
+    use crate::testing::{self, MockError, MockFacilitator};
+
+    use super::*;
+
+    fn fixtures() -> ConformanceFixtures {
+        ConformanceFixtures::exact_evm(&testing::requirements(), &testing::resource())
+    }
+
+    /// Accepts only the exact payment of the valid fixture.
+    struct StrictFacilitator {
+        mock: MockFacilitator,
+        valid: PaymentPayload,
+    }
+
+    impl StrictFacilitator {
+        fn accepts(&self, request: &PaymentRequest) -> bool {
+            serde_json::to_value(&request.payment_payload).unwrap()
+                == serde_json::to_value(&self.valid).unwrap()
+        }
+    }
+
+    impl Facilitator for StrictFacilitator {
+        type Error = MockError;
+
+        async fn supported(&self) -> Result<SupportedResponse, Self::Error> {
+            self.mock.supported().await
+        }
+
+        async fn verify(&self, request: PaymentRequest) -> Result<VerifyResult, Self::Error> {
+            if self.accepts(&request) {
+                return self.mock.verify(request).await;
+            }
+            Ok(VerifyResult::invalid(VerifyInvalid {
+                invalid_reason: "invalid_payload".to_string(),
+                payer: None,
+            }))
+        }
+
+        async fn settle(&self, request: PaymentRequest) -> Result<SettleResult, Self::Error> {
+            if self.accepts(&request) {
+                return self.mock.settle(request).await;
+            }
+            Ok(SettleResult::failed(SettleFailed {
+                error_reason: "invalid_payload".to_string(),
+                payer: None,
+            }))
+        }
+    }
+
+    #[tokio::test]
+    async fn conforming_facilitator_passes() {
+        let fixtures = fixtures();
+        let facilitator = StrictFacilitator {
+            mock: MockFacilitator::default(),
+            valid: fixtures.valid.payment_payload.clone(),
+        };
+
+        let report = run_conformance(&facilitator, fixtures).await;
+
+        assert!(report.passed(), "{report}");
+        assert_eq!(report.checks.len(), 7);
+    }
+
+    #[tokio::test]
+    async fn accepting_everything_fails_invalid_checks() {
+        let report = run_conformance(&MockFacilitator::default(), fixtures()).await;
+
+        let failed: Vec<_> = report.failures().map(|check| check.name).collect();
+        assert_eq!(
+            failed,
+            [
+                "verify.bad_signature",
+                "verify.expired",
+                "verify.wrong_network",
+                "settle.bad_signature"
+            ]
+        );
+        assert!(report.check("supported").unwrap().passed());
+        assert!(report.check("verify.valid").unwrap().passed());
+        assert!(report.check("settle.valid").unwrap().passed());
+    }
+
+    #[tokio::test]
+    async fn response_invariants_are_checked() {
+        let facilitator = MockFacilitator {
+            verify: VerifyResult::valid(VerifyValid {
+                payer: String::new(),
+                attestation: None,
+            }),
+            settle: SettleResult::success(SettleSuccess {
+                payer: testing::PAYER.to_string(),
+                transaction: String::new(),
+                network: "eip155:84532".to_string(),
+                verified_signature: false,
+                signer: None,
+                confirmation: None,
+            }),
+            supported: serde_json::from_value(serde_json::json!({
+                "kinds": [{ "x402Version": 2, "scheme": "exact", "network": "base-sepolia" }],
+                "extensions": [],
+                "signers": {}
+            }))
+            .unwrap(),
+            ..Default::default()
+        };
+
+        let report = run_conformance(&facilitator, fixtures()).await;
+
+        assert!(
+            report
+                .check("supported")
+                .unwrap()
+                .failure
+                .as_ref()
+                .unwrap()
+                .contains("CAIP-2")
+        );
+        assert!(
+            report
+                .check("verify.valid")
+                .unwrap()
+                .failure
+                .as_ref()
+                .unwrap()
+                .contains("empty payer")
+        );
+        assert!(
+            report
+                .check("settle.valid")
+                .unwrap()
+                .failure
+                .as_ref()
+                .unwrap()
+                .contains("empty transaction")
+        );
+    }
+
+    #[tokio::test]
+    async fn facilitator_errors_fail_checks() {
+        let facilitator = MockFacilitator {
+            unavailable: true,
+            ..Default::default()
+        };
+
+        let report = run_conformance(&facilitator, fixtures()).await;
+
+        assert_eq!(report.failures().count(), report.checks.len());
+        assert!(
+            report
+                .to_string()
+                .contains("FAIL supported: supported failed")
+        );
+    }
+}
@@ -1,18 +1,40 @@
-use http::{HeaderMap, HeaderName, HeaderValue};
+use std::{
+    sync::{Arc, Mutex, PoisonError},
+    time::Duration,
+};
+
+use alloy_primitives::{Address, Signature};
+use http::{HeaderMap, HeaderName, HeaderValue, Method, header};
 use serde::{Deserialize, Serialize};
 use url::Url;
 
 use crate::{
     facilitator::{
-        Facilitator, PaymentRequest, SettleFailed, SettleResult, SettleSuccess, SupportedResponse,
-        VerifyInvalid, VerifyResult, VerifyValid,
+        ConfirmationStatus, Facilitator, FacilitatorCapabilities, PaymentRequest, RefundFailed,
+        RefundResult, RefundSuccess, Refundable, SettleFailed, SettlePending, SettleResult,
+        SettleStatus, SettleSuccess, SignedSettlement, SupportedResponse, VerifyInvalid,
+        VerifyResult, VerifyValid, match_signers,
     },
     transport::{PaymentPayload, PaymentRequirements},
+    types::{AmountValue, AnyJson, Record},
 };
 
 /// A remote facilitator client that communicates over HTTP.
 ///
-/// You can customize the request and response types for verification and settlement
+/// You can customize the request and response types for verification and settlement, and the
+/// HTTP client sending the requests, see [`FacilitatorTransport`].
+///
+/// With the default `reqwest` transport, requests advertise `Accept-Encoding: gzip, br`, and
+/// compressed responses, e.g. large `supported` lists, are decoded transparently.
+///
+/// Settle responses carrying a [`signature`](IntoSettleResponse::signature) are checked against the
+/// `signers` of the last `supported` response, fetched on the first signed settlement, and the
+/// result is recorded in [`SettleSuccess::verified_signature`], with the matching
+/// [`signer`](SettleSuccess::signer).
+///
+/// Settled payments can be refunded with [`Refundable::refund`] against the
+/// [`refund_path`](Self::refund_path) endpoint, for facilitators offering this non-standard
+/// extension.
 ///
 /// # Type Parameters
 ///
@@ -20,28 +42,138 @@ use crate::{
 /// - `VRes`: The response type for verification, must be convertible into `FacilitatorVerifyResponse` and deserializable.
 /// - `SReq`: The request type for settlement, must be convertible from `FacilitatorPaymentRequest` and serializable.
 /// - `SRes`: The response type for settlement, must be convertible into `FacilitatorSettleResponse` and deserializable.
+/// - `T`: The HTTP transport, a `reqwest` client with the `facilitator-client` feature.
 #[derive(Debug, Clone)]
-pub struct FacilitatorClient<VReq, VRes, SReq, SRes>
+pub struct FacilitatorClient<VReq, VRes, SReq, SRes, T>
 where
     VReq: From<PaymentRequest> + Serialize,
     VRes: IntoVerifyResponse + for<'de> Deserialize<'de>,
     SReq: From<PaymentRequest> + Serialize,
     SRes: IntoSettleResponse + for<'de> Deserialize<'de>,
+    T: FacilitatorTransport,
 {
     pub base_url: Url,
-    pub client: reqwest_middleware::ClientWithMiddleware,
+    /// The transport sending the requests to the facilitator.
+    pub client: T,
     pub supported_headers: HeaderMap,
     pub verify_headers: HeaderMap,
     pub settle_headers: HeaderMap,
+    /// Transformation of the JSON body of verify requests, see
+    /// [`map_verify_request`](Self::map_verify_request).
+    pub verify_request_map: Option<RequestMap>,
+    /// Transformation of the JSON body of settle requests, see
+    /// [`map_settle_request`](Self::map_settle_request).
+    pub settle_request_map: Option<RequestMap>,
+    /// The capabilities reported by [`Facilitator::capabilities`].
+    ///
+    /// Only the settlement status is assumed by default, as the client queries `settle/status`.
+    pub capabilities: FacilitatorCapabilities,
+    /// The signers by network pattern of the last `supported` response.
+    pub signers: Arc<Mutex<Option<Record<Vec<String>>>>>,
+    /// The path of the refund endpoint relative to the base URL, `refund` by default.
+    pub refund_path: String,
     pub _phantom: std::marker::PhantomData<(VReq, VRes, SReq, SRes)>,
 }
 
+/// A transformation of the JSON body of a request, applied after serializing the typed request,
+/// see [`FacilitatorClient::map_verify_request`].
+#[derive(Clone)]
+pub struct RequestMap(Arc<dyn Fn(AnyJson) -> AnyJson + Send + Sync>);
+
+impl RequestMap {
+    pub fn new(map: impl Fn(AnyJson) -> AnyJson + Send + Sync + 'static) -> Self {
+        RequestMap(Arc::new(map))
+    }
+
+    /// Transform the body.
+    pub fn apply(&self, body: AnyJson) -> AnyJson {
+        (self.0)(body)
+    }
+}
+
+impl std::fmt::Debug for RequestMap {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("RequestMap")
+    }
+}
+
+/// The HTTP client carrying the requests of a [`FacilitatorClient`].
+///
+/// The client builds the requests, i.e. their URL, headers and JSON body, and decodes the
+/// responses, so a transport only needs to send a request and read its response, e.g. with
+/// `hyper` directly or over an internal RPC. The `reqwest` transport is implemented for
+/// [`reqwest_middleware::ClientWithMiddleware`] with the `facilitator-client` feature.
+///
+/// Errors are converted into [`FacilitatorClientError`], e.g. wrapped in
+/// [`FacilitatorClientError::Transport`].
+///
+/// # Example
+///
+/// ```
+/// use x402_kit::facilitator_client::{
+///     FacilitatorClientError, FacilitatorTransport, StandardFacilitatorClient,
+/// };
+///
+/// #[derive(Debug, Clone)]
+/// struct MyTransport;
+///
+/// impl FacilitatorTransport for MyTransport {
+///     type Error = FacilitatorClientError;
+///
+///     async fn send(
+///         &self,
+///         request: http::Request<Vec<u8>>,
+///     ) -> Result<http::Response<Vec<u8>>, Self::Error> {
+///         // Send the request with your own HTTP stack
+///         # let _ = request;
+///         Err(FacilitatorClientError::Transport("not connected".into()))
+///     }
+/// }
+///
+/// let client: StandardFacilitatorClient<MyTransport> =
+///     StandardFacilitatorClient::new("https://facilitator.example.com/".parse().unwrap(), MyTransport);
+/// ```
+pub trait FacilitatorTransport {
+    type Error: Into<FacilitatorClientError>;
+
+    /// Send the request to the facilitator, returning its response with any status.
+    fn send(
+        &self,
+        request: http::Request<Vec<u8>>,
+    ) -> impl Future<Output = Result<http::Response<Vec<u8>>, Self::Error>>;
+}
+
+#[cfg(feature = "facilitator-client")]
+impl FacilitatorTransport for reqwest_middleware::ClientWithMiddleware {
+    type Error = reqwest_middleware::Error;
+
+    async fn send(
+        &self,
+        request: http::Request<Vec<u8>>,
+    ) -> Result<http::Response<Vec<u8>>, Self::Error> {
+        let response = self.execute(request.try_into()?).await?;
+
+        let mut decoded = http::Response::new(Vec::new());
+        *decoded.status_mut() = response.status();
+        *decoded.headers_mut() = response.headers().clone();
+        *decoded.body_mut() = response.bytes().await?.to_vec();
+
+        Ok(decoded)
+    }
+}
+
 pub trait IntoVerifyResponse {
     fn into_verify_response(self) -> VerifyResult;
 }
 
 pub trait IntoSettleResponse {
     fn into_settle_response(self) -> SettleResult;
+
+    /// The facilitator's hex-encoded EIP-191 signature over the
+    /// [`SignedSettlement::message`] of the settlement.
+    fn signature(&self) -> Option<&str> {
+        None
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -49,6 +181,8 @@ pub trait IntoSettleResponse {
 pub struct DefaultPaymentRequest {
     pub payment_payload: PaymentPayload,
     pub payment_requirements: PaymentRequirements,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub attestation: Option<AnyJson>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -58,17 +192,100 @@ pub struct DefaultVerifyResponse {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub invalid_reason: Option<String>,
     pub payer: Option<String>,
+    /// Any additional fields returned by the facilitator, e.g. a `verificationId`.
+    ///
+    /// Kept as an opaque attestation of valid verifications and echoed on settle.
+    #[serde(flatten)]
+    pub extra: serde_json::Map<String, AnyJson>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct DefaultSettleResponse {
+    /// Missing in some pending responses, which only carry a `status`.
+    #[serde(default)]
     pub success: bool,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub error_reason: Option<String>,
     pub payer: Option<String>,
     pub transaction: Option<String>,
     pub network: Option<String>,
+    /// The facilitator's signature over the settlement, see [`IntoSettleResponse::signature`].
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub signature: Option<String>,
+    /// The finality of the transaction, e.g. `broadcast`, `confirmed` or `finalized`, if reported.
+    ///
+    /// Kept as JSON, so that unknown values are ignored instead of failing the settlement.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub status: Option<AnyJson>,
+    /// The number of confirmations of the transaction, if reported.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub confirmations: Option<AnyJson>,
+    /// Seconds to wait before polling a pending settlement, if reported.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub retry_after: Option<AnyJson>,
+    /// The facilitator's reference of a pending settlement, if reported instead of a transaction.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reference: Option<String>,
+}
+
+impl DefaultSettleResponse {
+    /// Whether the settlement was accepted but not executed yet, i.e. an unsuccessful response
+    /// with a `pending` `errorReason` or `status`.
+    pub fn is_pending(&self) -> bool {
+        let status = self.status.as_ref().and_then(AnyJson::as_str);
+
+        !self.success
+            && [self.error_reason.as_deref(), status]
+                .into_iter()
+                .flatten()
+                .any(|reason| reason.eq_ignore_ascii_case("pending"))
+    }
+
+    /// The pending settlement, with the `retryAfter` delay in seconds and the `reference`, or else
+    /// the `transaction`, of the response.
+    pub fn pending(&self) -> SettlePending {
+        let poll_after = self
+            .retry_after
+            .as_ref()
+            .and_then(|seconds| seconds.as_u64().or_else(|| seconds.as_str()?.parse().ok()));
+
+        SettlePending {
+            poll_after: poll_after.map(Duration::from_secs),
+            reference: self.reference.clone().or_else(|| self.transaction.clone()),
+        }
+    }
+
+    /// The confirmation status from the `status` and `confirmations` fields, if recognized.
+    ///
+    /// A `status` naming the finality takes precedence, e.g. `pending`, `confirmed` or
+    /// `finalized`; otherwise zero `confirmations` are read as broadcast.
+    pub fn confirmation(&self) -> Option<ConfirmationStatus> {
+        let confirmations = self.confirmations.as_ref().and_then(|confirmations| {
+            confirmations
+                .as_u64()
+                .or_else(|| confirmations.as_str()?.parse().ok())
+        });
+        let status = self
+            .status
+            .as_ref()
+            .and_then(AnyJson::as_str)
+            .map(str::to_ascii_lowercase);
+
+        match status.as_deref() {
+            Some("finalized" | "finalised" | "final") => Some(ConfirmationStatus::Finalized),
+            Some("broadcast" | "submitted" | "sent" | "pending") => {
+                Some(ConfirmationStatus::Broadcast)
+            }
+            Some("confirmed" | "included" | "mined") => Some(ConfirmationStatus::Confirmed(
+                confirmations.unwrap_or(1).max(1),
+            )),
+            _ => confirmations.map(|confirmations| match confirmations {
+                0 => ConfirmationStatus::Broadcast,
+                confirmations => ConfirmationStatus::Confirmed(confirmations),
+            }),
+        }
+    }
 }
 
 impl From<PaymentRequest> for DefaultPaymentRequest {
@@ -76,8 +293,66 @@ impl From<PaymentRequest> for DefaultPaymentRequest {
         DefaultPaymentRequest {
             payment_payload: request.payment_payload,
             payment_requirements: request.payment_requirements,
+            attestation: request.attestation,
+        }
+    }
+}
+
+/// The keys of the payment requirements' `extra` promoted by [`FlattenedPaymentRequest`]: the
+/// EIP-712 domain `name` and `version` of EVM assets, and the `feePayer` of SVM payments.
+pub const FLATTENED_EXTRA_KEYS: [&str; 3] = ["name", "version", "feePayer"];
+
+/// A [`DefaultPaymentRequest`] with the [known keys](FLATTENED_EXTRA_KEYS) of the payment
+/// requirements' `extra` promoted to top-level fields of the requirements.
+///
+/// An interop shim for nonstandard facilitators expecting e.g. `paymentRequirements.name`
+/// instead of `paymentRequirements.extra.name`. Other `extra` keys stay nested, and `extra` is
+/// omitted once empty. Enable it per client with [`FacilitatorClient::with_flattened_extra`].
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FlattenedPaymentRequest {
+    pub payment_payload: PaymentPayload,
+    #[serde(serialize_with = "serialize_flattened_extra")]
+    pub payment_requirements: PaymentRequirements,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub attestation: Option<AnyJson>,
+}
+
+impl From<PaymentRequest> for FlattenedPaymentRequest {
+    fn from(request: PaymentRequest) -> Self {
+        FlattenedPaymentRequest {
+            payment_payload: request.payment_payload,
+            payment_requirements: request.payment_requirements,
+            attestation: request.attestation,
+        }
+    }
+}
+
+fn serialize_flattened_extra<S: serde::Serializer>(
+    requirements: &PaymentRequirements,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    let mut value = serde_json::to_value(requirements).map_err(serde::ser::Error::custom)?;
+    if let Some(fields) = value.as_object_mut() {
+        let mut extra = match fields.remove("extra") {
+            Some(AnyJson::Object(extra)) => extra,
+            Some(AnyJson::Null) | None => serde_json::Map::new(),
+            Some(other) => {
+                fields.insert("extra".to_string(), other);
+                return value.serialize(serializer);
+            }
+        };
+        for key in FLATTENED_EXTRA_KEYS {
+            if let Some(promoted) = extra.remove(key) {
+                fields.insert(key.to_string(), promoted);
+            }
+        }
+        if !extra.is_empty() {
+            fields.insert("extra".to_string(), AnyJson::Object(extra));
         }
     }
+
+    value.serialize(serializer)
 }
 
 impl IntoVerifyResponse for DefaultVerifyResponse {
@@ -85,6 +360,7 @@ impl IntoVerifyResponse for DefaultVerifyResponse {
         if self.is_valid {
             VerifyResult::valid(VerifyValid {
                 payer: self.payer.unwrap_or_default(),
+                attestation: (!self.extra.is_empty()).then_some(AnyJson::Object(self.extra)),
             })
         } else {
             VerifyResult::invalid(VerifyInvalid {
@@ -98,11 +374,17 @@ impl IntoVerifyResponse for DefaultVerifyResponse {
 impl IntoSettleResponse for DefaultSettleResponse {
     fn into_settle_response(self) -> SettleResult {
         if self.success {
+            let confirmation = self.confirmation();
             SettleResult::success(SettleSuccess {
                 payer: self.payer.unwrap_or_default(),
                 transaction: self.transaction.unwrap_or_default(),
                 network: self.network.unwrap_or_default(),
+                verified_signature: false,
+                signer: None,
+                confirmation,
             })
+        } else if self.is_pending() {
+            SettleResult::pending(self.pending())
         } else {
             SettleResult::failed(SettleFailed {
                 error_reason: self.error_reason.unwrap_or_default(),
@@ -110,35 +392,108 @@ impl IntoSettleResponse for DefaultSettleResponse {
             })
         }
     }
+
+    fn signature(&self) -> Option<&str> {
+        self.signature.as_deref()
+    }
 }
 
+/// The body of refund requests, identifying the settlement to refund.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DefaultRefundRequest {
+    pub transaction: String,
+    pub network: String,
+    pub payer: String,
+    pub amount: AmountValue,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DefaultRefundResponse {
+    pub success: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error_reason: Option<String>,
+    pub transaction: Option<String>,
+    pub network: Option<String>,
+    pub amount: Option<AmountValue>,
+}
+
+impl DefaultRefundResponse {
+    /// Convert into a [`RefundResult`], defaulting to the requested network and amount.
+    fn into_refund_result(self, request: DefaultRefundRequest) -> RefundResult {
+        if self.success {
+            RefundResult::success(RefundSuccess {
+                transaction: self.transaction.unwrap_or_default(),
+                network: self.network.unwrap_or(request.network),
+                amount: self.amount.unwrap_or(request.amount),
+            })
+        } else {
+            RefundResult::failed(RefundFailed {
+                error_reason: self.error_reason.unwrap_or_default(),
+            })
+        }
+    }
+}
+
+/// A type alias for a RemoteFacilitatorClient using the default request and response types.
+#[cfg(feature = "facilitator-client")]
+pub type StandardFacilitatorClient<T = reqwest_middleware::ClientWithMiddleware> =
+    FacilitatorClient<
+        DefaultPaymentRequest,
+        DefaultVerifyResponse,
+        DefaultPaymentRequest,
+        DefaultSettleResponse,
+        T,
+    >;
+
 /// A type alias for a RemoteFacilitatorClient using the default request and response types.
-pub type StandardFacilitatorClient = FacilitatorClient<
+#[cfg(not(feature = "facilitator-client"))]
+pub type StandardFacilitatorClient<T> = FacilitatorClient<
     DefaultPaymentRequest,
     DefaultVerifyResponse,
     DefaultPaymentRequest,
     DefaultSettleResponse,
+    T,
 >;
 
-impl<VReq, VRes, SReq, SRes> FacilitatorClient<VReq, VRes, SReq, SRes>
+impl<VReq, VRes, SReq, SRes, T> FacilitatorClient<VReq, VRes, SReq, SRes, T>
 where
     VReq: From<PaymentRequest> + Serialize,
     VRes: IntoVerifyResponse + for<'de> Deserialize<'de>,
     SReq: From<PaymentRequest> + Serialize,
     SRes: IntoSettleResponse + for<'de> Deserialize<'de>,
+    T: FacilitatorTransport,
 {
-    pub fn new_from_url(base_url: Url) -> Self {
+    pub fn new_from_url(base_url: Url) -> Self
+    where
+        T: Default,
+    {
+        FacilitatorClient::new(base_url, T::default())
+    }
+
+    /// Create a client of the facilitator at the base URL, sending requests with the transport.
+    pub fn new(base_url: Url, client: T) -> Self {
         FacilitatorClient {
             base_url,
-            client: Default::default(),
+            client,
             supported_headers: HeaderMap::new(),
             verify_headers: HeaderMap::new(),
             settle_headers: HeaderMap::new(),
+            verify_request_map: None,
+            settle_request_map: None,
+            capabilities: FacilitatorCapabilities::builder()
+                .settlement_status(true)
+                .build(),
+            signers: Default::default(),
+            refund_path: "refund".to_string(),
             _phantom: std::marker::PhantomData,
         }
     }
 
-    pub fn with_verify_request_type<NewVReq>(self) -> FacilitatorClient<NewVReq, VRes, SReq, SRes>
+    pub fn with_verify_request_type<NewVReq>(
+        self,
+    ) -> FacilitatorClient<NewVReq, VRes, SReq, SRes, T>
     where
         NewVReq: From<PaymentRequest> + Serialize,
     {
@@ -148,11 +503,18 @@ where
             supported_headers: self.supported_headers,
             verify_headers: self.verify_headers,
             settle_headers: self.settle_headers,
+            verify_request_map: self.verify_request_map,
+            settle_request_map: self.settle_request_map,
+            capabilities: self.capabilities,
+            signers: self.signers,
+            refund_path: self.refund_path,
             _phantom: std::marker::PhantomData,
         }
     }
 
-    pub fn with_verify_response_type<NewVRes>(self) -> FacilitatorClient<VReq, NewVRes, SReq, SRes>
+    pub fn with_verify_response_type<NewVRes>(
+        self,
+    ) -> FacilitatorClient<VReq, NewVRes, SReq, SRes, T>
     where
         NewVRes: IntoVerifyResponse + for<'de> Deserialize<'de>,
     {
@@ -161,12 +523,19 @@ where
             base_url: self.base_url,
             verify_headers: self.verify_headers,
             settle_headers: self.settle_headers,
+            verify_request_map: self.verify_request_map,
+            settle_request_map: self.settle_request_map,
             client: self.client,
+            capabilities: self.capabilities,
+            signers: self.signers,
+            refund_path: self.refund_path,
             _phantom: std::marker::PhantomData,
         }
     }
 
-    pub fn with_settle_request_type<NewSReq>(self) -> FacilitatorClient<VReq, VRes, NewSReq, SRes>
+    pub fn with_settle_request_type<NewSReq>(
+        self,
+    ) -> FacilitatorClient<VReq, VRes, NewSReq, SRes, T>
     where
         NewSReq: From<PaymentRequest> + Serialize,
     {
@@ -175,12 +544,19 @@ where
             base_url: self.base_url,
             verify_headers: self.verify_headers,
             settle_headers: self.settle_headers,
+            verify_request_map: self.verify_request_map,
+            settle_request_map: self.settle_request_map,
             client: self.client,
+            capabilities: self.capabilities,
+            signers: self.signers,
+            refund_path: self.refund_path,
             _phantom: std::marker::PhantomData,
         }
     }
 
-    pub fn with_settle_response_type<NewSRes>(self) -> FacilitatorClient<VReq, VRes, SReq, NewSRes>
+    pub fn with_settle_response_type<NewSRes>(
+        self,
+    ) -> FacilitatorClient<VReq, VRes, SReq, NewSRes, T>
     where
         NewSRes: IntoSettleResponse + for<'de> Deserialize<'de>,
     {
@@ -189,11 +565,96 @@ where
             base_url: self.base_url,
             verify_headers: self.verify_headers,
             settle_headers: self.settle_headers,
+            verify_request_map: self.verify_request_map,
+            settle_request_map: self.settle_request_map,
             client: self.client,
+            capabilities: self.capabilities,
+            signers: self.signers,
+            refund_path: self.refund_path,
             _phantom: std::marker::PhantomData,
         }
     }
 
+    /// Send requests with another transport, keeping the rest of the configuration.
+    pub fn with_transport<NewT>(
+        self,
+        client: NewT,
+    ) -> FacilitatorClient<VReq, VRes, SReq, SRes, NewT>
+    where
+        NewT: FacilitatorTransport,
+    {
+        FacilitatorClient {
+            base_url: self.base_url,
+            client,
+            supported_headers: self.supported_headers,
+            verify_headers: self.verify_headers,
+            settle_headers: self.settle_headers,
+            verify_request_map: self.verify_request_map,
+            settle_request_map: self.settle_request_map,
+            capabilities: self.capabilities,
+            signers: self.signers,
+            refund_path: self.refund_path,
+            _phantom: std::marker::PhantomData,
+        }
+    }
+
+    /// Send verify and settle requests as [`FlattenedPaymentRequest`]s, promoting the known keys
+    /// of the payment requirements' `extra` to top-level fields for nonstandard facilitators.
+    pub fn with_flattened_extra(
+        self,
+    ) -> FacilitatorClient<FlattenedPaymentRequest, VRes, FlattenedPaymentRequest, SRes, T> {
+        self.with_verify_request_type::<FlattenedPaymentRequest>()
+            .with_settle_request_type::<FlattenedPaymentRequest>()
+    }
+
+    /// Declare the optional features supported by the remote facilitator.
+    pub fn with_capabilities(mut self, capabilities: FacilitatorCapabilities) -> Self {
+        self.capabilities = capabilities;
+        self
+    }
+
+    /// Refund payments at the given path relative to the base URL instead of `refund`.
+    pub fn with_refund_path(mut self, refund_path: impl Into<String>) -> Self {
+        self.refund_path = refund_path.into();
+        self
+    }
+
+    /// Transform the JSON body of verify requests after serializing them as `VReq`, replacing any
+    /// previous transformation.
+    ///
+    /// For nonstandard facilitators whose verify requests differ from the standard shape by a
+    /// field or two, where a whole request type would be heavy:
+    ///
+    /// ```
+    /// use serde_json::json;
+    /// use x402_kit::facilitator_client::StandardFacilitatorClient;
+    ///
+    /// let client = StandardFacilitatorClient::from_url(
+    ///     "https://facilitator.example.com/".parse().unwrap(),
+    /// )
+    /// .map_verify_request(|mut body| {
+    ///     body["x402Version"] = json!(1);
+    ///     body
+    /// });
+    /// ```
+    pub fn map_verify_request(
+        mut self,
+        map: impl Fn(AnyJson) -> AnyJson + Send + Sync + 'static,
+    ) -> Self {
+        self.verify_request_map = Some(RequestMap::new(map));
+        self
+    }
+
+    /// Transform the JSON body of settle requests after serializing them as `SReq`, replacing any
+    /// previous transformation, see [`map_verify_request`](Self::map_verify_request).
+    pub fn map_settle_request(
+        mut self,
+        map: impl Fn(AnyJson) -> AnyJson + Send + Sync + 'static,
+    ) -> Self {
+        self.settle_request_map = Some(RequestMap::new(map));
+        self
+    }
+
     pub fn header(mut self, key: &HeaderName, value: &HeaderValue) -> Self {
         self.supported_headers.insert(key, value.to_owned());
         self.verify_headers.insert(key, value.to_owned());
@@ -215,14 +676,50 @@ where
         self.settle_headers.insert(key, value.to_owned());
         self
     }
+
+    /// The signer advertised for the network of the settlement that made the signature over it,
+    /// if any.
+    async fn verify_signature(&self, settled: &SettleSuccess, signature: &str) -> Option<String> {
+        let cached = self
+            .signers
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clone();
+        let signers = match cached {
+            Some(signers) => signers,
+            None => match self.supported().await {
+                Ok(supported) => supported.signers,
+                Err(_) => return None,
+            },
+        };
+
+        let message = SignedSettlement::from(settled).message();
+        let Ok(signer) = signature
+            .parse::<Signature>()
+            .and_then(|signature| signature.recover_address_from_msg(message))
+        else {
+            return None;
+        };
+
+        match_signers(&signers, &settled.network)
+            .into_iter()
+            .find(|address| {
+                address
+                    .parse::<Address>()
+                    .is_ok_and(|address| address == signer)
+            })
+            .map(str::to_owned)
+    }
 }
 
+#[cfg(feature = "facilitator-client")]
 impl
     FacilitatorClient<
         DefaultPaymentRequest,
         DefaultVerifyResponse,
         DefaultPaymentRequest,
         DefaultSettleResponse,
+        reqwest_middleware::ClientWithMiddleware,
     >
 {
     pub fn from_url(base_url: Url) -> Self {
@@ -234,61 +731,935 @@ impl
 pub enum FacilitatorClientError {
     #[error("URL parse error: {0}")]
     UrlParseError(#[from] url::ParseError),
+    #[error("Invalid HTTP request: {0}")]
+    InvalidRequest(#[from] http::Error),
+    #[cfg(feature = "facilitator-client")]
     #[error("HTTP request error: {0}")]
     HttpRequestError(#[from] reqwest_middleware::reqwest::Error),
+    #[cfg(feature = "facilitator-client")]
     #[error("HTTP request error: {0}")]
     HttpRequestMiddlewareError(#[from] reqwest_middleware::Error),
+    /// An error of a custom [`FacilitatorTransport`].
+    #[error("Transport error: {0}")]
+    Transport(Box<dyn std::error::Error + Send + Sync>),
     #[error("JSON Serialization/Deserialization error: {0}")]
     SerdeJsonError(#[from] serde_json::Error),
+    #[error("Unexpected HTTP status {status}: {body}")]
+    UnexpectedStatus { status: u16, body: String },
 }
 
-impl<VReq, VRes, SReq, SRes> Facilitator for FacilitatorClient<VReq, VRes, SReq, SRes>
+impl<VReq, VRes, SReq, SRes, T> FacilitatorClient<VReq, VRes, SReq, SRes, T>
 where
     VReq: From<PaymentRequest> + Serialize,
     VRes: IntoVerifyResponse + for<'de> Deserialize<'de>,
     SReq: From<PaymentRequest> + Serialize,
     SRes: IntoSettleResponse + for<'de> Deserialize<'de>,
+    T: FacilitatorTransport,
+{
+    /// Send a request to the URL with the headers and the JSON body, if any, through the transport.
+    async fn send(
+        &self,
+        method: Method,
+        url: Url,
+        headers: HeaderMap,
+        body: Option<&impl Serialize>,
+    ) -> Result<http::Response<Vec<u8>>, FacilitatorClientError> {
+        let mut request = http::Request::builder().method(method).uri(url.as_str());
+        if let Some(request_headers) = request.headers_mut() {
+            *request_headers = headers;
+            if body.is_some() {
+                request_headers.insert(
+                    header::CONTENT_TYPE,
+                    HeaderValue::from_static("application/json"),
+                );
+            }
+        }
+        let body = body
+            .map(serde_json::to_vec)
+            .transpose()?
+            .unwrap_or_default();
+
+        self.client
+            .send(request.body(body)?)
+            .await
+            .map_err(Into::into)
+    }
+
+    /// The JSON body of a request, transformed by the map, if any.
+    fn request_body(
+        request: impl Serialize,
+        map: Option<&RequestMap>,
+    ) -> Result<AnyJson, FacilitatorClientError> {
+        let body = serde_json::to_value(request)?;
+
+        Ok(match map {
+            Some(map) => map.apply(body),
+            None => body,
+        })
+    }
+
+    /// Send a request and decode the JSON body of its response.
+    async fn send_json<R>(
+        &self,
+        method: Method,
+        url: Url,
+        headers: HeaderMap,
+        body: Option<&impl Serialize>,
+    ) -> Result<R, FacilitatorClientError>
+    where
+        R: for<'de> Deserialize<'de>,
+    {
+        let response = self.send(method, url, headers, body).await?;
+
+        Ok(serde_json::from_slice(response.body())?)
+    }
+}
+
+impl<VReq, VRes, SReq, SRes, T> Facilitator for FacilitatorClient<VReq, VRes, SReq, SRes, T>
+where
+    VReq: From<PaymentRequest> + Serialize,
+    VRes: IntoVerifyResponse + for<'de> Deserialize<'de>,
+    SReq: From<PaymentRequest> + Serialize,
+    SRes: IntoSettleResponse + for<'de> Deserialize<'de>,
+    T: FacilitatorTransport,
 {
     type Error = FacilitatorClientError;
 
     async fn supported(&self) -> Result<SupportedResponse, Self::Error> {
-        let supported = self
-            .client
-            .get(self.base_url.join("supported")?)
-            .headers(self.supported_headers.clone())
-            .send()
-            .await?
-            .json()
+        let supported: SupportedResponse = self
+            .send_json(
+                Method::GET,
+                self.base_url.join("supported")?,
+                self.supported_headers.clone(),
+                None::<&()>,
+            )
             .await?;
+        *self.signers.lock().unwrap_or_else(PoisonError::into_inner) =
+            Some(supported.signers.clone());
 
         Ok(supported)
     }
 
     async fn verify(&self, request: PaymentRequest) -> Result<VerifyResult, Self::Error> {
-        let result = self
-            .client
-            .post(self.base_url.join("verify")?)
-            .headers(self.verify_headers.clone())
-            .json(&VReq::from(request))
-            .send()
-            .await?
-            .json::<VRes>()
+        let result: VRes = self
+            .send_json(
+                Method::POST,
+                self.base_url.join("verify")?,
+                self.verify_headers.clone(),
+                Some(&Self::request_body(
+                    VReq::from(request),
+                    self.verify_request_map.as_ref(),
+                )?),
+            )
             .await?;
 
         Ok(result.into_verify_response())
     }
 
+    /// Post the payment to `settle`, with its idempotency key, if any, as the `Idempotency-Key`
+    /// header.
     async fn settle(&self, request: PaymentRequest) -> Result<SettleResult, Self::Error> {
-        let result = self
-            .client
-            .post(self.base_url.join("settle")?)
-            .headers(self.settle_headers.clone())
-            .json(&SReq::from(request))
-            .send()
-            .await?
-            .json::<SRes>()
+        let mut headers = self.settle_headers.clone();
+        if let Some(key) = &request.idempotency_key
+            && let Ok(value) = HeaderValue::from_str(&key.0)
+        {
+            headers.insert("idempotency-key", value);
+        }
+
+        let result: SRes = self
+            .send_json(
+                Method::POST,
+                self.base_url.join("settle")?,
+                headers,
+                Some(&Self::request_body(
+                    SReq::from(request),
+                    self.settle_request_map.as_ref(),
+                )?),
+            )
+            .await?;
+        let signature = result.signature().map(str::to_owned);
+
+        let mut result = result.into_settle_response();
+        if let (SettleResult::Success(settled), Some(signature)) = (&mut result, signature) {
+            settled.signer = self.verify_signature(settled, &signature).await;
+            settled.verified_signature = settled.signer.is_some();
+        }
+        Ok(result)
+    }
+
+    /// Query `settle/status` with the settled transaction and network.
+    async fn settle_status(&self, settled: &SettleSuccess) -> Result<SettleStatus, Self::Error> {
+        let mut url = self.base_url.join("settle/status")?;
+        url.query_pairs_mut()
+            .append_pair("transaction", &settled.transaction)
+            .append_pair("network", &settled.network);
+
+        self.send_json(Method::GET, url, self.settle_headers.clone(), None::<&()>)
+            .await
+    }
+
+    fn capabilities(&self) -> FacilitatorCapabilities {
+        self.capabilities
+    }
+}
+
+impl<VReq, VRes, SReq, SRes, T> Refundable for FacilitatorClient<VReq, VRes, SReq, SRes, T>
+where
+    VReq: From<PaymentRequest> + Serialize,
+    VRes: IntoVerifyResponse + for<'de> Deserialize<'de>,
+    SReq: From<PaymentRequest> + Serialize,
+    SRes: IntoSettleResponse + for<'de> Deserialize<'de>,
+    T: FacilitatorTransport,
+{
+    /// Post the settlement and amount to the [`refund_path`](Self::refund_path) endpoint.
+    ///
+    /// Error statuses are returned as [`FacilitatorClientError::UnexpectedStatus`], as facilitators
+    /// without refunds usually answer `404 Not Found`.
+    async fn refund(
+        &self,
+        original: &SettleSuccess,
+        amount: AmountValue,
+    ) -> Result<RefundResult, Self::Error> {
+        let request = DefaultRefundRequest {
+            transaction: original.transaction.clone(),
+            network: original.network.clone(),
+            payer: original.payer.clone(),
+            amount,
+        };
+
+        let response = self
+            .send(
+                Method::POST,
+                self.base_url.join(&self.refund_path)?,
+                self.settle_headers.clone(),
+                Some(&request),
+            )
             .await?;
+        let status = response.status();
+        if !status.is_success() {
+            return Err(FacilitatorClientError::UnexpectedStatus {
+                status: status.as_u16(),
+                body: String::from_utf8_lossy(response.body()).into_owned(),
+            });
+        }
+
+        let result: DefaultRefundResponse = serde_json::from_slice(response.body())?;
+        Ok(result.into_refund_result(request))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn payment_request() -> PaymentRequest {
+        let requirements: PaymentRequirements = serde_json::from_value(json!({
+            "scheme": "exact",
+            "network": "eip155:84532",
+            "amount": "1000",
+            "asset": "0x036CbD53842c5426634e7929541eC2318f3dCF7e",
+            "payTo": "0x3CB9B3bBfde8501f411bB69Ad3DC07908ED0dE20",
+            "maxTimeoutSeconds": 60
+        }))
+        .unwrap();
+
+        PaymentRequest {
+            payment_payload: serde_json::from_value(json!({
+                "x402Version": 2,
+                "resource": {
+                    "url": "https://example.com/resource",
+                    "description": "Protected resource",
+                    "mimeType": "application/json"
+                },
+                "accepted": requirements,
+                "payload": {},
+                "extensions": {}
+            }))
+            .unwrap(),
+            payment_requirements: requirements,
+            attestation: None,
+            idempotency_key: None,
+        }
+    }
+
+    #[test]
+    fn verify_response_without_extra_fields_has_no_attestation() {
+        let response: DefaultVerifyResponse = serde_json::from_value(json!({
+            "isValid": true,
+            "payer": "0x857b06519E91e3A54538791bDbb0E22373e36b66"
+        }))
+        .unwrap();
+
+        let valid = response.into_verify_response();
+        assert_eq!(valid.as_valid().unwrap().attestation, None);
+    }
+
+    #[test]
+    fn flattened_extra_serialization() {
+        let mut request = payment_request();
+        request.payment_requirements.extra = Some(json!({
+            "name": "USDC",
+            "version": "2",
+            "assetTransferMethod": "eip3009"
+        }));
+
+        let nested = serde_json::to_value(DefaultPaymentRequest::from(request.clone())).unwrap();
+        let requirements = &nested["paymentRequirements"];
+        assert_eq!(requirements["extra"]["name"], "USDC");
+        assert_eq!(requirements["extra"]["version"], "2");
+        assert_eq!(requirements.get("name"), None);
+
+        let flattened = serde_json::to_value(FlattenedPaymentRequest::from(request)).unwrap();
+        let requirements = &flattened["paymentRequirements"];
+        assert_eq!(requirements["name"], "USDC");
+        assert_eq!(requirements["version"], "2");
+        assert_eq!(requirements["amount"], "1000");
+        assert_eq!(
+            requirements["extra"],
+            json!({ "assetTransferMethod": "eip3009" })
+        );
+        // The payload is left as signed
+        assert_eq!(flattened["paymentPayload"], nested["paymentPayload"]);
+
+        // Without other keys, `extra` is omitted
+        let mut request = payment_request();
+        request.payment_requirements.extra = Some(json!({ "feePayer": "FeePayer111" }));
+        let flattened = serde_json::to_value(FlattenedPaymentRequest::from(request)).unwrap();
+        assert_eq!(flattened["paymentRequirements"]["feePayer"], "FeePayer111");
+        assert_eq!(flattened["paymentRequirements"].get("extra"), None);
+    }
+
+    #[test]
+    fn pending_settle_responses() {
+        let settle = |body: AnyJson| {
+            serde_json::from_value::<DefaultSettleResponse>(body)
+                .unwrap()
+                .into_settle_response()
+        };
+
+        let pending = settle(json!({
+            "success": false,
+            "errorReason": "pending",
+            "transaction": "0xdeadbeef",
+            "retryAfter": 2
+        }));
+        assert_eq!(
+            pending.as_pending(),
+            Some(&SettlePending {
+                poll_after: Some(Duration::from_secs(2)),
+                reference: Some("0xdeadbeef".to_string()),
+            })
+        );
+
+        let pending = settle(json!({ "status": "PENDING", "reference": "settle-42" }));
+        assert_eq!(
+            pending.as_pending(),
+            Some(&SettlePending {
+                poll_after: None,
+                reference: Some("settle-42".to_string()),
+            })
+        );
+
+        // Pending statuses of successful settlements only qualify their confirmation
+        let broadcast = settle(json!({
+            "success": true,
+            "transaction": "0xdeadbeef",
+            "network": "eip155:84532",
+            "status": "pending"
+        }));
+        assert_eq!(
+            broadcast.as_success().unwrap().confirmation,
+            Some(ConfirmationStatus::Broadcast)
+        );
+
+        let failed = settle(json!({ "success": false, "errorReason": "insufficient_funds" }));
+        assert_eq!(
+            failed.as_failed().unwrap().error_reason,
+            "insufficient_funds"
+        );
+    }
+
+    #[test]
+    fn settle_response_confirmation_is_tolerant() {
+        let confirmation = |fields: AnyJson| {
+            let mut body = json!({
+                "success": true,
+                "payer": "0x857b06519E91e3A54538791bDbb0E22373e36b66",
+                "transaction": "0xdeadbeef",
+                "network": "eip155:84532"
+            });
+            body.as_object_mut()
+                .unwrap()
+                .extend(fields.as_object().unwrap().clone());
+            let response: DefaultSettleResponse = serde_json::from_value(body).unwrap();
+            match response.into_settle_response() {
+                SettleResult::Success(settled) => settled.confirmation,
+                other => panic!("unexpected result: {other:?}"),
+            }
+        };
+
+        assert_eq!(confirmation(json!({})), None);
+        assert_eq!(
+            confirmation(json!({ "status": "submitted" })),
+            Some(ConfirmationStatus::Broadcast)
+        );
+        assert_eq!(
+            confirmation(json!({ "status": "Confirmed", "confirmations": 3 })),
+            Some(ConfirmationStatus::Confirmed(3))
+        );
+        assert_eq!(
+            confirmation(json!({ "status": "confirmed" })),
+            Some(ConfirmationStatus::Confirmed(1))
+        );
+        assert_eq!(
+            confirmation(json!({ "confirmations": "12" })),
+            Some(ConfirmationStatus::Confirmed(12))
+        );
+        assert_eq!(
+            confirmation(json!({ "confirmations": 0 })),
+            Some(ConfirmationStatus::Broadcast)
+        );
+        assert_eq!(
+            confirmation(json!({ "status": "finalized", "confirmations": 32 })),
+            Some(ConfirmationStatus::Finalized)
+        );
+        // Unknown or mistyped values are ignored
+        assert_eq!(
+            confirmation(json!({ "status": 1, "confirmations": null })),
+            None
+        );
+        assert_eq!(confirmation(json!({ "status": "unknown" })), None);
+    }
+
+    /// A transport answering from memory, recording the requests it receives.
+    #[derive(Debug, Clone, Default)]
+    struct InMemoryTransport {
+        requests: Arc<Mutex<Vec<http::Request<Vec<u8>>>>>,
+    }
+
+    impl FacilitatorTransport for InMemoryTransport {
+        type Error = FacilitatorClientError;
+
+        async fn send(
+            &self,
+            request: http::Request<Vec<u8>>,
+        ) -> Result<http::Response<Vec<u8>>, Self::Error> {
+            let body = match request.uri().path() {
+                "/facilitator/supported" => json!({
+                    "kinds": [{ "x402Version": 2, "scheme": "exact", "network": "eip155:84532" }],
+                    "extensions": [],
+                    "signers": { "eip155:*": ["0x857b06519E91e3A54538791bDbb0E22373e36b66"] }
+                }),
+                path => return Err(FacilitatorClientError::Transport(path.into())),
+            };
+            self.requests.lock().unwrap().push(request);
+
+            Ok(http::Response::new(serde_json::to_vec(&body).unwrap()))
+        }
+    }
+
+    #[tokio::test]
+    async fn supported_over_custom_transport() {
+        let transport = InMemoryTransport::default();
+        let client = StandardFacilitatorClient::new(
+            "memory://facilitator/facilitator/".parse().unwrap(),
+            transport.clone(),
+        )
+        .supported_header(
+            &HeaderName::from_static("x-api-key"),
+            &HeaderValue::from_static("secret"),
+        );
+
+        let supported = client.supported().await.unwrap();
+
+        assert_eq!(supported.kinds[0].network, "eip155:84532");
+        assert!(client.signers.lock().unwrap().is_some());
+        let request = transport.requests.lock().unwrap().pop().unwrap();
+        assert_eq!(request.method(), Method::GET);
+        assert_eq!(request.headers()["x-api-key"], "secret");
+
+        let err = client.verify(payment_request()).await.unwrap_err();
+        assert!(
+            matches!(&err, FacilitatorClientError::Transport(path) if path.to_string() == "/facilitator/verify"),
+            "unexpected error: {err}"
+        );
+    }
+
+    /// Tests of the `reqwest` client against facilitators served locally.
+    #[cfg(feature = "facilitator-client")]
+    mod client {
+        use alloy::signers::{SignerSync, local::PrivateKeySigner};
+        use axum::{
+            Json, Router,
+            extract::{Query, State},
+            routing::{get, post},
+        };
 
-        Ok(result.into_settle_response())
+        use crate::facilitator::SettlementKey;
+
+        use super::*;
+
+        /// Serve a facilitator at a local address, returning its base URL.
+        async fn serve(router: Router) -> Url {
+            let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+            let addr = listener.local_addr().unwrap();
+            tokio::spawn(async move { axum::serve(listener, router).await.unwrap() });
+
+            format!("http://{addr}/").parse().unwrap()
+        }
+
+        /// A facilitator attesting verifications and recording settle request bodies.
+        fn attesting_facilitator(settled: Arc<Mutex<Vec<AnyJson>>>) -> Router {
+            Router::new()
+                .route(
+                    "/verify",
+                    post(|| async {
+                        Json(json!({
+                            "isValid": true,
+                            "payer": "0x857b06519E91e3A54538791bDbb0E22373e36b66",
+                            "verificationId": "v-123"
+                        }))
+                    }),
+                )
+                .route(
+                    "/settle",
+                    post(
+                        |State(settled): State<Arc<Mutex<Vec<AnyJson>>>>,
+                         Json(body): Json<AnyJson>| async move {
+                            settled.lock().unwrap().push(body);
+                            Json(json!({
+                                "success": true,
+                                "payer": "0x857b06519E91e3A54538791bDbb0E22373e36b66",
+                                "transaction": "0xdeadbeef",
+                                "network": "eip155:84532"
+                            }))
+                        },
+                    ),
+                )
+                .with_state(settled)
+        }
+
+        #[test]
+        fn capabilities_override() {
+            let client =
+                StandardFacilitatorClient::from_url("https://example.com/".parse().unwrap());
+            assert_eq!(
+                client.capabilities(),
+                FacilitatorCapabilities::builder()
+                    .settlement_status(true)
+                    .build()
+            );
+
+            let client = client
+                .with_capabilities(
+                    FacilitatorCapabilities::builder()
+                        .settlement_status(true)
+                        .refunds(true)
+                        .build(),
+                )
+                .with_settle_response_type::<DefaultSettleResponse>();
+            let capabilities = client.capabilities();
+            assert!(capabilities.refunds);
+            assert!(!capabilities.partial_settlement);
+        }
+
+        #[tokio::test]
+        async fn settle_echoes_verify_attestation() {
+            let settled = Arc::new(Mutex::new(Vec::new()));
+            let client = StandardFacilitatorClient::from_url(
+                serve(attesting_facilitator(settled.clone())).await,
+            );
+
+            let verified = client.verify(payment_request()).await.unwrap();
+            let attestation = verified.as_valid().unwrap().attestation.clone();
+            assert_eq!(attestation, Some(json!({ "verificationId": "v-123" })));
+
+            let result = client
+                .settle(PaymentRequest {
+                    attestation,
+                    ..payment_request()
+                })
+                .await
+                .unwrap();
+            assert!(result.is_success());
+
+            let body = settled.lock().unwrap().pop().unwrap();
+            assert_eq!(body["attestation"], json!({ "verificationId": "v-123" }));
+            assert_eq!(body["paymentRequirements"]["amount"], "1000");
+        }
+
+        #[tokio::test]
+        async fn settle_sends_idempotency_key() {
+            let keys = Arc::new(Mutex::new(Vec::new()));
+            let router = Router::new()
+                .route(
+                    "/settle",
+                    post(
+                        |State(keys): State<Arc<Mutex<Vec<Option<String>>>>>,
+                         headers: HeaderMap| async move {
+                            keys.lock().unwrap().push(
+                                headers
+                                    .get("idempotency-key")
+                                    .map(|key| key.to_str().unwrap().to_string()),
+                            );
+                            Json(json!({
+                                "success": true,
+                                "payer": "0x857b06519E91e3A54538791bDbb0E22373e36b66",
+                                "transaction": "0xdeadbeef",
+                                "network": "eip155:84532"
+                            }))
+                        },
+                    ),
+                )
+                .with_state(keys.clone());
+            let client = StandardFacilitatorClient::from_url(serve(router).await);
+
+            let key = SettlementKey::derive(&payment_request().payment_payload);
+            client
+                .settle(PaymentRequest {
+                    idempotency_key: Some(key.clone()),
+                    ..payment_request()
+                })
+                .await
+                .unwrap();
+            client.settle(payment_request()).await.unwrap();
+
+            assert_eq!(*keys.lock().unwrap(), vec![Some(key.0), None]);
+        }
+
+        #[tokio::test]
+        async fn settle_without_attestation_omits_field() {
+            let settled = Arc::new(Mutex::new(Vec::new()));
+            let client = StandardFacilitatorClient::from_url(
+                serve(attesting_facilitator(settled.clone())).await,
+            );
+
+            client.settle(payment_request()).await.unwrap();
+
+            let body = settled.lock().unwrap().pop().unwrap();
+            assert!(body.get("attestation").is_none());
+        }
+
+        /// A facilitator advertising the signer for `eip155:*` and signing settlements with the given
+        /// message, adding `fields` to its settle responses.
+        async fn signing_facilitator(
+            signer: &PrivateKeySigner,
+            signed: &SignedSettlement,
+            fields: AnyJson,
+        ) -> Url {
+            let signature = signer
+                .sign_message_sync(&signed.message())
+                .unwrap()
+                .to_string();
+            let supported = json!({
+                "kinds": [{ "x402Version": 2, "scheme": "exact", "network": "eip155:84532" }],
+                "extensions": [],
+                "signers": { "eip155:*": [signer.address().to_string()] }
+            });
+            let mut response = json!({
+                "success": true,
+                "payer": "0x857b06519E91e3A54538791bDbb0E22373e36b66",
+                "transaction": "0xdeadbeef",
+                "network": "eip155:84532",
+                "signature": signature
+            });
+            response
+                .as_object_mut()
+                .unwrap()
+                .extend(fields.as_object().unwrap().clone());
+            let router = Router::new()
+                .route("/supported", get(|| async move { Json(supported) }))
+                .route("/settle", post(|| async move { Json(response) }));
+
+            serve(router).await
+        }
+
+        fn settlement(transaction: &str) -> SignedSettlement {
+            SignedSettlement {
+                success: true,
+                transaction: transaction.to_string(),
+                network: "eip155:84532".to_string(),
+                payer: "0x857b06519E91e3A54538791bDbb0E22373e36b66".to_string(),
+            }
+        }
+
+        #[tokio::test]
+        async fn settle_verifies_facilitator_signature() {
+            let signer = PrivateKeySigner::random();
+            let client = StandardFacilitatorClient::from_url(
+                signing_facilitator(&signer, &settlement("0xdeadbeef"), json!({})).await,
+            );
+
+            let result = client.settle(payment_request()).await.unwrap();
+            assert!(result.as_success().unwrap().verified_signature);
+            assert!(client.signers.lock().unwrap().is_some());
+        }
+
+        #[tokio::test]
+        async fn settle_verifies_signature_with_confirmation() {
+            let signer = PrivateKeySigner::random();
+            // The confirmation is reported alongside the signed fields
+            let client = StandardFacilitatorClient::from_url(
+                signing_facilitator(
+                    &signer,
+                    &settlement("0xdeadbeef"),
+                    json!({ "status": "confirmed", "confirmations": 3 }),
+                )
+                .await,
+            );
+
+            let result = client.settle(payment_request()).await.unwrap();
+            let settled = result.as_success().unwrap();
+            assert!(settled.verified_signature);
+            assert_eq!(settled.confirmation, Some(ConfirmationStatus::Confirmed(3)));
+        }
+
+        #[tokio::test]
+        async fn settle_rejects_tampered_signature() {
+            let signer = PrivateKeySigner::random();
+            // Signed over another transaction than the one returned
+            let client = StandardFacilitatorClient::from_url(
+                signing_facilitator(&signer, &settlement("0xfeedface"), json!({})).await,
+            );
+
+            let result = client.settle(payment_request()).await.unwrap();
+            assert!(!result.as_success().unwrap().verified_signature);
+
+            // Unsigned settlements are not verified
+            let settled = Arc::new(Mutex::new(Vec::new()));
+            let client =
+                StandardFacilitatorClient::from_url(serve(attesting_facilitator(settled)).await);
+            let result = client.settle(payment_request()).await.unwrap();
+            assert!(!result.as_success().unwrap().verified_signature);
+        }
+
+        #[tokio::test]
+        async fn supported_decodes_gzip_response() {
+            use std::io::Write;
+
+            use axum::http::{HeaderMap, header};
+            use flate2::{Compression, write::GzEncoder};
+
+            let router = Router::new().route(
+                "/supported",
+                get(|headers: HeaderMap| async move {
+                    let accepted = headers[header::ACCEPT_ENCODING].to_str().unwrap();
+                    assert!(accepted.contains("gzip") && accepted.contains("br"));
+
+                    let body = json!({
+                        "kinds": [
+                            { "x402Version": 2, "scheme": "exact", "network": "eip155:84532" },
+                            { "x402Version": 2, "scheme": "exact", "network": "eip155:8453" }
+                        ],
+                        "extensions": [],
+                        "signers": {}
+                    });
+                    let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+                    encoder
+                        .write_all(&serde_json::to_vec(&body).unwrap())
+                        .unwrap();
+
+                    (
+                        [
+                            (header::CONTENT_TYPE, "application/json"),
+                            (header::CONTENT_ENCODING, "gzip"),
+                        ],
+                        encoder.finish().unwrap(),
+                    )
+                }),
+            );
+            let client = StandardFacilitatorClient::from_url(serve(router).await);
+
+            let supported = client.supported().await.unwrap();
+
+            assert_eq!(supported.kinds.len(), 2);
+            assert_eq!(supported.kinds[1].network, "eip155:8453");
+        }
+
+        #[tokio::test]
+        async fn settle_status_queries_transaction() {
+            let router = Router::new().route(
+                "/settle/status",
+                get(|Query(query): Query<Record<String>>| async move {
+                    if query["transaction"] == "0xdeadbeef" && query["network"] == "eip155:84532" {
+                        Json(json!({ "status": "pending" }))
+                    } else {
+                        Json(json!({ "status": "failed", "errorReason": "unknown_transaction" }))
+                    }
+                }),
+            );
+            let client = StandardFacilitatorClient::from_url(serve(router).await);
+
+            let status = client
+                .settle_status(&SettleSuccess {
+                    payer: "0x857b06519E91e3A54538791bDbb0E22373e36b66".to_string(),
+                    transaction: "0xdeadbeef".to_string(),
+                    network: "eip155:84532".to_string(),
+                    verified_signature: false,
+                    signer: None,
+                    confirmation: None,
+                })
+                .await
+                .unwrap();
+
+            assert_eq!(status, SettleStatus::Pending);
+        }
+
+        fn settled() -> SettleSuccess {
+            SettleSuccess {
+                payer: "0x857b06519E91e3A54538791bDbb0E22373e36b66".to_string(),
+                transaction: "0xdeadbeef".to_string(),
+                network: "eip155:84532".to_string(),
+                verified_signature: false,
+                signer: None,
+                confirmation: None,
+            }
+        }
+
+        /// A facilitator refunding at most 1000 units of the `0xdeadbeef` settlement.
+        fn refunding_facilitator() -> Router {
+            Router::new().route(
+                "/payments/refund",
+                post(|Json(body): Json<AnyJson>| async move {
+                    assert_eq!(body["transaction"], "0xdeadbeef");
+                    assert_eq!(body["payer"], "0x857b06519E91e3A54538791bDbb0E22373e36b66");
+                    if body["amount"].as_str().unwrap().parse::<u128>().unwrap() <= 1000 {
+                        Json(json!({ "success": true, "transaction": "0xfeedface" }))
+                    } else {
+                        Json(
+                            json!({ "success": false, "errorReason": "amount_exceeds_settlement" }),
+                        )
+                    }
+                }),
+            )
+        }
+
+        #[tokio::test]
+        async fn refund_posts_settlement() {
+            let client = StandardFacilitatorClient::from_url(serve(refunding_facilitator()).await)
+                .with_refund_path("payments/refund");
+
+            let refunded = client.refund(&settled(), AmountValue(400)).await.unwrap();
+            let refunded = refunded.as_success().unwrap();
+            assert_eq!(refunded.transaction, "0xfeedface");
+            assert_eq!(refunded.network, "eip155:84532");
+            assert_eq!(refunded.amount, AmountValue(400));
+
+            let failed = client.refund(&settled(), AmountValue(2000)).await.unwrap();
+            assert_eq!(
+                failed.as_failed().unwrap().error_reason,
+                "amount_exceeds_settlement"
+            );
+        }
+
+        #[tokio::test]
+        async fn refund_maps_error_status() {
+            // The default `refund` path is not served
+            let client = StandardFacilitatorClient::from_url(serve(refunding_facilitator()).await);
+
+            let err = client
+                .refund(&settled(), AmountValue(400))
+                .await
+                .unwrap_err();
+            assert!(
+                matches!(
+                    err,
+                    FacilitatorClientError::UnexpectedStatus { status: 404, .. }
+                ),
+                "unexpected error: {err}"
+            );
+        }
+
+        /// A facilitator recording the headers and bodies of verify and settle requests.
+        fn recording_facilitator(requests: Arc<Mutex<Vec<(HeaderMap, AnyJson)>>>) -> Router {
+            type Requests = State<Arc<Mutex<Vec<(HeaderMap, AnyJson)>>>>;
+
+            Router::new()
+                .route(
+                    "/verify",
+                    post(
+                        |State(requests): Requests,
+                         headers: HeaderMap,
+                         Json(body): Json<AnyJson>| async move {
+                            requests.lock().unwrap().push((headers, body));
+                            Json(json!({
+                                "isValid": true,
+                                "payer": "0x857b06519E91e3A54538791bDbb0E22373e36b66"
+                            }))
+                        },
+                    ),
+                )
+                .route(
+                    "/settle",
+                    post(
+                        |State(requests): Requests,
+                         headers: HeaderMap,
+                         Json(body): Json<AnyJson>| async move {
+                            requests.lock().unwrap().push((headers, body));
+                            Json(json!({
+                                "success": true,
+                                "payer": "0x857b06519E91e3A54538791bDbb0E22373e36b66",
+                                "transaction": "0xdeadbeef",
+                                "network": "eip155:84532"
+                            }))
+                        },
+                    ),
+                )
+                .with_state(requests)
+        }
+
+        #[tokio::test]
+        async fn verify_request_map_shapes_verify_body_only() {
+            let requests = Arc::new(Mutex::new(Vec::new()));
+            let client = StandardFacilitatorClient::from_url(
+                serve(recording_facilitator(requests.clone())).await,
+            )
+            .map_verify_request(|mut body| {
+                body["x402Version"] = json!(1);
+                body
+            });
+
+            client.verify(payment_request()).await.unwrap();
+            client.settle(payment_request()).await.unwrap();
+
+            let requests = requests.lock().unwrap();
+            let (_, verify) = &requests[0];
+            assert_eq!(verify["x402Version"], 1);
+            assert_eq!(verify["paymentRequirements"]["network"], "eip155:84532");
+            let (_, settle) = &requests[1];
+            assert_eq!(
+                settle,
+                &serde_json::to_value(DefaultPaymentRequest::from(payment_request())).unwrap()
+            );
+        }
+
+        #[tokio::test]
+        async fn settle_request_map_and_headers_apply_to_settle_only() {
+            let requests = Arc::new(Mutex::new(Vec::new()));
+            let client = StandardFacilitatorClient::from_url(
+                serve(recording_facilitator(requests.clone())).await,
+            )
+            .settle_header(
+                &HeaderName::from_static("x-settle-mode"),
+                &HeaderValue::from_static("async"),
+            )
+            .map_settle_request(|body| json!({ "payment": body }));
+
+            client.verify(payment_request()).await.unwrap();
+            client.settle(payment_request()).await.unwrap();
+
+            let requests = requests.lock().unwrap();
+            let (headers, verify) = &requests[0];
+            assert!(headers.get("x-settle-mode").is_none());
+            assert!(verify.get("payment").is_none());
+            let (headers, settle) = &requests[1];
+            assert_eq!(headers["x-settle-mode"], "async");
+            assert_eq!(
+                settle["payment"],
+                serde_json::to_value(DefaultPaymentRequest::from(payment_request())).unwrap()
+            );
+        }
     }
 }
@@ -99,6 +99,32 @@
 //!     );
 //! ```
 //!
+//! Or collect payments of any scheme, and raw [`transport::PaymentRequirements`], with the
+//! [`accepts!`] macro:
+//!
+//! ```
+//! # use alloy::primitives::address;
+//! # use solana_pubkey::pubkey;
+//! use x402_kit::{
+//!     accepts,
+//!     networks::{evm::assets::UsdcBaseSepolia, svm::assets::UsdcSolanaDevnet},
+//!     schemes::{exact_evm::ExactEvm, exact_svm::ExactSvm},
+//! };
+//!
+//! let accepts = accepts![
+//!     ExactEvm::builder()
+//!         .amount(1000)
+//!         .asset(UsdcBaseSepolia)
+//!         .pay_to(address!("0x3CB9B3bBfde8501f411bB69Ad3DC07908ED0dE20"))
+//!         .build(),
+//!     ExactSvm::builder()
+//!         .amount(1000)
+//!         .asset(UsdcSolanaDevnet)
+//!         .pay_to(pubkey!("Ge3jkza5KRfXvaq3GELNLh6V1pjjdEKNpEdGXJgjjKUR"))
+//!         .build(),
+//! ];
+//! ```
+//!
 //! ## Core Components Overview
 //!
 //! ### For the X402 Protocol
@@ -110,13 +136,21 @@
 //! ### For Network-Specific Implementations
 //!
 //! - **[`networks`]**: Network-specific implementations, e.g., EVM / SVM assets and addresses.
-//! - **[`schemes`]**: Payment scheme implementations, e.g., Exact EVM / Exact SVM, and their signer logic.
+//! - **[`schemes`]**: Payment scheme implementations, e.g., Exact EVM / Exact SVM / Permit EVM, and their signer logic.
+//! - **[`signer_registry`]**: A registry of signers for buyers paying with any of several schemes and networks.
+//! - **`quote`**: Decimal-safe conversion of amounts between assets, with the `decimal` feature.
 //!
 //! ### Facilitator Utilities
 //!
 //! - **[`facilitator`]**: Traits and types for building X402 facilitators.
+//! - **[`ledger`]**: A facilitator wrapper recording settled payments to a ledger.
 //! - **[`facilitator_client`]**: Utilities for building X402 facilitator clients.
 //!
+//! ### For Sellers
+//!
+//! - **`paywall`**: The [`x402_paywall`] middleware, with the `paywall` feature or any of the
+//!   `axum`, `actix-web`, `metrics` and `tracing` features enabling it.
+//!
 //! ## Extend X402 Kit As You Like
 //!
 //! The main idea is you don't need to wait for the upstream library to support the network or asset in your case.
@@ -431,6 +465,14 @@
 //! }
 //! ```
 //!
+#![cfg_attr(
+    not(feature = "paywall"),
+    doc = "Without the `paywall` feature, the paywall is not re-exported:",
+    doc = "",
+    doc = "```compile_fail",
+    doc = "use x402_kit::paywall::paywall::PayWall;",
+    doc = "```"
+)]
 
 /// Core components for the X402 protocol.
 pub mod core {
@@ -442,14 +484,28 @@ pub mod transport {
     pub use x402_core::transport::*;
 }
 
+#[doc(inline)]
+pub use x402_core::accepts;
+
 /// Common types used across the X402 protocol.
 pub mod types {
     pub use x402_core::types::*;
+
+    pub use crate::networks::{AnyAddress, PayerAddress};
 }
 
 /// Facilitator traits and types.
 pub mod facilitator {
     pub use x402_core::facilitator::*;
+
+    /// Conformance checks for facilitator implementations.
+    #[cfg(feature = "test-utils")]
+    pub mod conformance;
+}
+
+/// Payment ledgers for recording settled payments.
+pub mod ledger {
+    pub use x402_core::ledger::*;
 }
 
 /// Errors used across X402 Kit.
@@ -458,6 +514,14 @@ pub mod errors {
 }
 
 /// X402 Paywall middleware for protecting HTTP resources.
+///
+/// Re-exports [`x402_paywall`] with the `paywall` feature, also enabled by the `axum`,
+/// `actix-web`, `metrics` and `tracing` features, which enable those of the paywall. Using this
+/// path rather than depending on `x402-paywall` directly keeps both crates at matching versions.
+///
+/// ```
+/// use x402_kit::paywall::{paywall::PayWall, processor::PaymentState};
+/// ```
 #[cfg(feature = "paywall")]
 pub mod paywall {
     pub use x402_paywall::*;
@@ -468,10 +532,21 @@ pub mod extensions {
     pub use x402_extensions::*;
 }
 
+/// Balance checkers for the paywall backed by RPC endpoints.
+#[cfg(feature = "evm-rpc")]
+pub mod balance;
 /// Facilitator client utilities.
-#[cfg(feature = "facilitator-client")]
+#[cfg(feature = "facilitator-transport")]
 pub mod facilitator_client;
 /// Network-specific implementations.
 pub mod networks;
+/// Decimal-safe conversion of amounts between assets.
+#[cfg(feature = "decimal")]
+pub mod quote;
 /// Payment scheme implementations.
 pub mod schemes;
+/// Runtime dispatch of scheme signers by scheme and network.
+pub mod signer_registry;
+/// Test utilities for paywalled applications.
+#[cfg(feature = "test-utils")]
+pub mod testing;
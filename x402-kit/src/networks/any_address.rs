@@ -0,0 +1,212 @@
+use std::{convert::Infallible, fmt::Display, str::FromStr};
+
+use serde::{Deserialize, Serialize};
+
+use crate::facilitator::{SettleSuccess, VerifyValid};
+
+use super::{evm::EvmAddress, svm::SvmAddress};
+
+/// An address of any network family, e.g. the payer of a payment on any network.
+///
+/// Parsed as an EVM address if `0x`-prefixed hex, then as an SVM address if base58, and kept as
+/// [`Other`](AnyAddress::Other) otherwise, so parsing never fails. Serialized as a plain string.
+///
+/// # Example
+///
+/// ```
+/// use x402_kit::types::AnyAddress;
+///
+/// let evm: AnyAddress = "0x3CB9B3bBfde8501f411bB69Ad3DC07908ED0dE20".parse().unwrap();
+/// assert!(evm.as_evm().is_some());
+///
+/// let svm: AnyAddress = "Ge3jkza5KRfXvaq3GELNLh6V1pjjdEKNpEdGXJgjjKUR".parse().unwrap();
+/// assert!(svm.as_svm().is_some());
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum AnyAddress {
+    Evm(EvmAddress),
+    Svm(SvmAddress),
+    /// An address of another network family, or an invalid one.
+    Other(String),
+}
+
+impl AnyAddress {
+    /// The EVM address, if this is one.
+    pub fn as_evm(&self) -> Option<&EvmAddress> {
+        match self {
+            AnyAddress::Evm(address) => Some(address),
+            _ => None,
+        }
+    }
+
+    /// The SVM address, if this is one.
+    pub fn as_svm(&self) -> Option<&SvmAddress> {
+        match self {
+            AnyAddress::Svm(address) => Some(address),
+            _ => None,
+        }
+    }
+}
+
+impl From<EvmAddress> for AnyAddress {
+    fn from(address: EvmAddress) -> Self {
+        AnyAddress::Evm(address)
+    }
+}
+
+impl From<SvmAddress> for AnyAddress {
+    fn from(address: SvmAddress) -> Self {
+        AnyAddress::Svm(address)
+    }
+}
+
+impl FromStr for AnyAddress {
+    type Err = Infallible;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.starts_with("0x")
+            && let Ok(address) = s.parse()
+        {
+            return Ok(AnyAddress::Evm(address));
+        }
+        if let Ok(address) = s.parse() {
+            return Ok(AnyAddress::Svm(address));
+        }
+
+        Ok(AnyAddress::Other(s.to_string()))
+    }
+}
+
+impl Display for AnyAddress {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AnyAddress::Evm(address) => Display::fmt(address, f),
+            AnyAddress::Svm(address) => Display::fmt(address, f),
+            AnyAddress::Other(address) => f.write_str(address),
+        }
+    }
+}
+
+impl Serialize for AnyAddress {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for AnyAddress {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        let s = String::deserialize(deserializer)?;
+        Ok(s.parse().unwrap_or_else(|never| match never {}))
+    }
+}
+
+/// The payer of a verification or settlement as an [`AnyAddress`].
+pub trait PayerAddress {
+    /// The payer, parsed from its string on each call.
+    fn payer_address(&self) -> AnyAddress;
+}
+
+impl PayerAddress for VerifyValid {
+    fn payer_address(&self) -> AnyAddress {
+        self.payer.parse().unwrap_or_else(|never| match never {})
+    }
+}
+
+impl PayerAddress for SettleSuccess {
+    fn payer_address(&self) -> AnyAddress {
+        self.payer.parse().unwrap_or_else(|never| match never {})
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloy_primitives::address;
+    use solana_pubkey::pubkey;
+
+    use super::*;
+
+    fn parse(s: &str) -> AnyAddress {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn parses_both_families() {
+        let evm = parse("0x3CB9B3bBfde8501f411bB69Ad3DC07908ED0dE20");
+        assert_eq!(
+            evm.as_evm(),
+            Some(&EvmAddress(address!(
+                "0x3CB9B3bBfde8501f411bB69Ad3DC07908ED0dE20"
+            )))
+        );
+        assert_eq!(parse("0x3cb9b3bbfde8501f411bb69ad3dc07908ed0de20"), evm);
+
+        let svm = parse("Ge3jkza5KRfXvaq3GELNLh6V1pjjdEKNpEdGXJgjjKUR");
+        assert_eq!(
+            svm.as_svm(),
+            Some(&SvmAddress(pubkey!(
+                "Ge3jkza5KRfXvaq3GELNLh6V1pjjdEKNpEdGXJgjjKUR"
+            )))
+        );
+        assert_eq!(svm.as_evm(), None);
+    }
+
+    #[test]
+    fn ambiguous_inputs_are_other() {
+        for input in [
+            // EVM hex without the `0x` prefix
+            "3CB9B3bBfde8501f411bB69Ad3DC07908ED0dE20",
+            // Too short for either family
+            "0x3CB9B3bB",
+            "Ge3jkza5KRfXvaq3",
+            "not an address",
+            "",
+        ] {
+            assert_eq!(
+                parse(input),
+                AnyAddress::Other(input.to_string()),
+                "{input}"
+            );
+        }
+    }
+
+    #[test]
+    fn serializes_as_plain_string() {
+        for input in [
+            "0x3CB9B3bBfde8501f411bB69Ad3DC07908ED0dE20",
+            "Ge3jkza5KRfXvaq3GELNLh6V1pjjdEKNpEdGXJgjjKUR",
+            "cosmos1abc",
+        ] {
+            let json = serde_json::to_value(parse(input)).unwrap();
+            assert_eq!(json, input);
+            assert_eq!(
+                serde_json::from_value::<AnyAddress>(json).unwrap(),
+                parse(input)
+            );
+        }
+    }
+
+    #[test]
+    fn payer_address_of_results() {
+        let valid = VerifyValid {
+            payer: "0x3CB9B3bBfde8501f411bB69Ad3DC07908ED0dE20".to_string(),
+            attestation: None,
+        };
+        assert!(valid.payer_address().as_evm().is_some());
+
+        let settled = SettleSuccess {
+            payer: "Ge3jkza5KRfXvaq3GELNLh6V1pjjdEKNpEdGXJgjjKUR".to_string(),
+            transaction: "tx".to_string(),
+            network: "solana:EtWTRABZaYq6iMfeYKouRu166VU2xqa1".to_string(),
+            verified_signature: false,
+            signer: None,
+            confirmation: None,
+        };
+        assert!(settled.payer_address().as_svm().is_some());
+    }
+}
@@ -1,5 +1,6 @@
 use std::{
     fmt::{Debug, Display},
+    hash::{Hash, Hasher},
     str::FromStr,
 };
 
@@ -14,6 +15,21 @@ pub struct EvmNetwork {
     pub network_id: &'static str,
 }
 
+/// EVM networks are identified by their chain ID.
+impl PartialEq for EvmNetwork {
+    fn eq(&self, other: &Self) -> bool {
+        self.chain_id == other.chain_id
+    }
+}
+
+impl Eq for EvmNetwork {}
+
+impl Hash for EvmNetwork {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.chain_id.hash(state);
+    }
+}
+
 impl NetworkFamily for EvmNetwork {
     fn network_name(&self) -> &str {
         self.name
@@ -1,2 +1,66 @@
 pub mod evm;
 pub mod svm;
+
+mod any_address;
+
+pub use any_address::*;
+
+use crate::types::KnownAssetRegistry;
+
+use self::{
+    evm::{ExplicitEvmAsset, ExplicitEvmNetwork, assets as evm_assets},
+    svm::{ExplicitSvmAsset, ExplicitSvmNetwork, assets as svm_assets},
+};
+
+/// The built-in assets of all networks, e.g. to display amounts in whole tokens.
+///
+/// Register custom assets on top with [`KnownAssetRegistry::register`].
+pub fn known_assets() -> KnownAssetRegistry {
+    fn evm<A: ExplicitEvmAsset>(registry: KnownAssetRegistry) -> KnownAssetRegistry {
+        registry.register(A::Network::NETWORK.network_id, A::ASSET)
+    }
+    fn svm<A: ExplicitSvmAsset>(registry: KnownAssetRegistry) -> KnownAssetRegistry {
+        registry.register(A::Network::NETWORK.caip_2_id, A::ASSET)
+    }
+
+    let registry = KnownAssetRegistry::new();
+    let registry = evm::<evm_assets::UsdcEthereum>(registry);
+    let registry = evm::<evm_assets::UsdcEthereumSepolia>(registry);
+    let registry = evm::<evm_assets::UsdcBase>(registry);
+    let registry = evm::<evm_assets::UsdcBaseSepolia>(registry);
+    let registry = svm::<svm_assets::UsdcSolana>(registry);
+    svm::<svm_assets::UsdcSolanaDevnet>(registry)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        schemes::{exact_evm::ExactEvm, exact_svm::ExactSvm},
+        transport::PaymentRequirements,
+    };
+
+    use super::*;
+
+    #[test]
+    fn known_assets_display_builtin_amounts() {
+        let evm: PaymentRequirements = ExactEvm::builder()
+            .asset(evm_assets::UsdcBaseSepolia)
+            .amount(1_500_000)
+            .pay_to(alloy_primitives::address!(
+                "0x3CB9B3bBfde8501f411bB69Ad3DC07908ED0dE20"
+            ))
+            .build()
+            .into();
+        let svm: PaymentRequirements = ExactSvm::builder()
+            .asset(svm_assets::UsdcSolanaDevnet)
+            .amount(1000)
+            .pay_to(solana_pubkey::pubkey!(
+                "Ge3jkza5KRfXvaq3GELNLh6V1pjjdEKNpEdGXJgjjKUR"
+            ))
+            .build()
+            .into();
+
+        assert_eq!(evm.display_amount(&known_assets()), "1.500000 USDC");
+        assert_eq!(svm.display_amount(&known_assets()), "0.001000 USDC");
+    }
+}
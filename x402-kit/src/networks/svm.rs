@@ -1,18 +1,76 @@
 use std::{
     fmt::{Debug, Display},
+    hash::{Hash, Hasher},
     str::FromStr,
 };
 
 use serde::{Deserialize, Serialize};
 use solana_pubkey::{ParsePubkeyError, Pubkey};
 
-use crate::core::{Address, NetworkFamily};
+use crate::{
+    core::{Address, NetworkFamily},
+    types::{Caip2, Caip2Error},
+};
 
+#[derive(Debug, Clone, Copy)]
 pub struct SvmNetwork {
     pub name: &'static str,
     pub caip_2_id: &'static str,
 }
 
+impl SvmNetwork {
+    /// Check that the [`caip_2_id`](SvmNetwork::caip_2_id) is a Solana CAIP-2 identifier:
+    /// `solana:` followed by the first 32 characters of the base58-encoded genesis hash.
+    ///
+    /// Meant for tests and builders of custom networks, as a typo in the ID silently mismatches
+    /// the facilitator's supported kinds.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use x402_kit::networks::svm::SvmNetwork;
+    ///
+    /// let network = SvmNetwork {
+    ///     name: "solana-devnet",
+    ///     caip_2_id: "solana:EtWTRABZaYq6iMfeYKouRu166VU2xqa1",
+    /// };
+    /// assert!(network.validate_caip2().is_ok());
+    /// ```
+    pub fn validate_caip2(&self) -> Result<(), Caip2Error> {
+        let caip2: Caip2 = self.caip_2_id.parse()?;
+        if caip2.namespace() != "solana" {
+            return Err(Caip2Error::UnexpectedNamespace {
+                expected: "solana".to_string(),
+                found: caip2.namespace().to_string(),
+            });
+        }
+
+        let reference = caip2.reference();
+        // Base58 excludes `0`, `I`, `O` and `l`
+        let is_base58 = |c: char| c.is_ascii_alphanumeric() && !matches!(c, '0' | 'I' | 'O' | 'l');
+        if reference.len() != 32 || !reference.chars().all(is_base58) {
+            return Err(Caip2Error::InvalidGenesisHash(reference.to_string()));
+        }
+
+        Ok(())
+    }
+}
+
+/// SVM networks are identified by their CAIP-2 ID.
+impl PartialEq for SvmNetwork {
+    fn eq(&self, other: &Self) -> bool {
+        self.caip_2_id == other.caip_2_id
+    }
+}
+
+impl Eq for SvmNetwork {}
+
+impl Hash for SvmNetwork {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.caip_2_id.hash(state);
+    }
+}
+
 impl NetworkFamily for SvmNetwork {
     fn network_name(&self) -> &str {
         self.name
@@ -123,6 +181,28 @@ impl Address for SvmAddress {
 
 pub type SvmAsset = crate::core::Asset<SvmAddress>;
 
+/// The SPL Token program, which owns the accounts of USDC and most other SPL tokens.
+pub const TOKEN_PROGRAM: SvmAddress = SvmAddress(solana_pubkey::pubkey!(
+    "TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA"
+));
+
+/// The Associated Token Account program.
+pub const ASSOCIATED_TOKEN_PROGRAM: SvmAddress = SvmAddress(solana_pubkey::pubkey!(
+    "ATokenGPvbdGVxr1b2hvZbsiqW5xWH25efTNsLJA8knL"
+));
+
+/// Derive the associated token account of `owner` for `mint`, owned by `token_program`, e.g.
+/// [`TOKEN_PROGRAM`].
+///
+/// This is pure program-derived address math, so the account may not exist on chain yet.
+pub fn derive_ata(owner: &SvmAddress, mint: &SvmAddress, token_program: &SvmAddress) -> SvmAddress {
+    let (ata, _bump) = Pubkey::find_program_address(
+        &[owner.0.as_ref(), token_program.0.as_ref(), mint.0.as_ref()],
+        &ASSOCIATED_TOKEN_PROGRAM.0,
+    );
+    SvmAddress(ata)
+}
+
 pub trait ExplicitSvmNetwork {
     const NETWORK: SvmNetwork;
 }
@@ -190,3 +270,81 @@ pub mod assets {
             create_usdc!(pubkey!("4zMMC9srt5Ri5X14GAgXhaHii3GnPAEERYPJgZJDncDU"));
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use solana_pubkey::pubkey;
+
+    use super::*;
+
+    #[test]
+    fn builtin_networks_are_valid_caip2() {
+        for network in [
+            networks::Solana::NETWORK,
+            networks::SolanaDevnet::NETWORK,
+            networks::SolanaTestnet::NETWORK,
+        ] {
+            assert_eq!(network.validate_caip2(), Ok(()), "{}", network.name);
+        }
+    }
+
+    #[test]
+    fn malformed_caip2_is_rejected() {
+        let network = |caip_2_id| SvmNetwork {
+            name: "custom",
+            caip_2_id,
+        };
+
+        assert!(matches!(
+            network("solana-devnet").validate_caip2(),
+            Err(Caip2Error::MissingSeparator(_))
+        ));
+        assert_eq!(
+            network("eip155:8453").validate_caip2(),
+            Err(Caip2Error::UnexpectedNamespace {
+                expected: "solana".to_string(),
+                found: "eip155".to_string(),
+            })
+        );
+        // `0` is not a base58 character
+        assert_eq!(
+            network("solana:EtWTRABZaYq6iMfeYKouRu166VU2xqa0").validate_caip2(),
+            Err(Caip2Error::InvalidGenesisHash(
+                "EtWTRABZaYq6iMfeYKouRu166VU2xqa0".to_string()
+            ))
+        );
+        // Truncated
+        assert_eq!(
+            network("solana:EtWTRABZaYq6iMfeYKouRu166VU2").validate_caip2(),
+            Err(Caip2Error::InvalidGenesisHash(
+                "EtWTRABZaYq6iMfeYKouRu166VU2".to_string()
+            ))
+        );
+    }
+
+    #[test]
+    fn derives_associated_token_accounts() {
+        for (owner, mint, ata) in [
+            (
+                pubkey!("Ge3jkza5KRfXvaq3GELNLh6V1pjjdEKNpEdGXJgjjKUR"),
+                pubkey!("4zMMC9srt5Ri5X14GAgXhaHii3GnPAEERYPJgZJDncDU"),
+                pubkey!("DSoccwgFrbdQmEcXH7FrVtj6sJ46FUZNfa7HH5UEDzmd"),
+            ),
+            (
+                pubkey!("Ge3jkza5KRfXvaq3GELNLh6V1pjjdEKNpEdGXJgjjKUR"),
+                pubkey!("EPjFWdd5AufqSSqeM2qN1xzybapC8G4wEGGkZwyTDt1v"),
+                pubkey!("428BjhBNzym248jQxM9Fg8mGpnQHuT9VypDr447cA6M9"),
+            ),
+            (
+                pubkey!("CKPKJWNdJEqa81x7CkZ14BVPiY6y16Sxs7owznqtWYp5"),
+                pubkey!("EPjFWdd5AufqSSqeM2qN1xzybapC8G4wEGGkZwyTDt1v"),
+                pubkey!("9fJvVo2wjcjuQ2wpss1Tjjw914DrkuikZ7aUd9ZwLgpx"),
+            ),
+        ] {
+            assert_eq!(
+                derive_ata(&SvmAddress(owner), &SvmAddress(mint), &TOKEN_PROGRAM),
+                SvmAddress(ata)
+            );
+        }
+    }
+}
@@ -0,0 +1,160 @@
+//! Decimal-safe conversion of amounts between assets, e.g. to compare prices or quote them in
+//! another asset.
+//!
+//! Amounts are converted with fixed-point decimal math, accounting for the decimals of both
+//! assets, so no precision is lost to floating point.
+//!
+//! Sourcing the exchange rate, e.g. from an oracle or a price API, and deciding how fresh it must
+//! be, is the caller's responsibility.
+//!
+//! Available with the `decimal` feature.
+
+use rust_decimal::prelude::ToPrimitive;
+
+pub use rust_decimal::Decimal;
+
+use crate::{
+    core::{Address, Asset},
+    types::AmountValue,
+};
+
+/// Convert an amount of the `from` asset into the equivalent amount of the `to` asset.
+///
+/// Both amounts are in the smallest units of their asset, and `rate` is the price of one whole
+/// `from` token in whole `to` tokens. The result is rounded down to the smallest unit of `to`, so
+/// that the quote never exceeds the exact equivalent.
+///
+/// Returns `None` if the rate is negative, or the amounts do not fit the 96 bits of a [`Decimal`]
+/// mantissa at the assets' decimals, e.g. above about 7.9 × 10¹⁰ whole tokens of an asset with
+/// 18 decimals.
+///
+/// # Example
+///
+/// ```
+/// use x402_kit::{
+///     core::Asset,
+///     networks::evm::{EvmAddress, ExplicitEvmAsset, assets::UsdcBase},
+///     quote::{Decimal, quote_equivalent},
+///     types::AmountValue,
+/// };
+///
+/// let dai = Asset {
+///     address: "0x50c5725949A6F0c72E6C4a641F24049A917DB0Cb".parse::<EvmAddress>().unwrap(),
+///     decimals: 18,
+///     name: "Dai Stablecoin",
+///     symbol: "DAI",
+/// };
+///
+/// // 1 USDC at 1 DAI per USDC
+/// let quoted = quote_equivalent(AmountValue(1_000_000), &UsdcBase::ASSET, &dai, Decimal::ONE);
+/// assert_eq!(quoted, Some(AmountValue(1_000_000_000_000_000_000)));
+/// ```
+pub fn quote_equivalent<F: Address, T: Address>(
+    amount: AmountValue,
+    from: &Asset<F>,
+    to: &Asset<T>,
+    rate: Decimal,
+) -> Option<AmountValue> {
+    if rate.is_sign_negative() {
+        return None;
+    }
+
+    let amount = i128::try_from(amount.0).ok()?;
+    let whole = Decimal::try_from_i128_with_scale(amount, from.decimals.into()).ok()?;
+    let unit =
+        Decimal::try_from_i128_with_scale(10i128.checked_pow(to.decimals.into())?, 0).ok()?;
+
+    let quoted = whole.checked_mul(rate)?.checked_mul(unit)?.floor();
+
+    quoted.to_u128().map(AmountValue)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::networks::{
+        evm::{EvmAddress, ExplicitEvmAsset, assets::UsdcBase},
+        svm::{ExplicitSvmAsset, assets::UsdcSolana},
+    };
+
+    use super::*;
+
+    fn dai() -> Asset<EvmAddress> {
+        Asset {
+            address: "0x50c5725949A6F0c72E6C4a641F24049A917DB0Cb"
+                .parse()
+                .unwrap(),
+            decimals: 18,
+            name: "Dai Stablecoin",
+            symbol: "DAI",
+        }
+    }
+
+    #[test]
+    fn usdc_to_18_decimals_at_par() {
+        assert_eq!(
+            quote_equivalent(
+                AmountValue(1_000_000),
+                &UsdcBase::ASSET,
+                &dai(),
+                Decimal::ONE
+            ),
+            Some(AmountValue(1_000_000_000_000_000_000))
+        );
+    }
+
+    #[test]
+    fn rounds_down_to_smallest_unit() {
+        // 1.5 DAI at 0.999999 USDC per DAI is 1.4999985 USDC
+        let rate = Decimal::new(999_999, 6);
+        assert_eq!(
+            quote_equivalent(
+                AmountValue(1_500_000_000_000_000_000),
+                &dai(),
+                &UsdcBase::ASSET,
+                rate
+            ),
+            Some(AmountValue(1_499_998))
+        );
+
+        // Less than the smallest unit of USDC
+        assert_eq!(
+            quote_equivalent(AmountValue(1), &dai(), &UsdcBase::ASSET, Decimal::ONE),
+            Some(AmountValue(0))
+        );
+    }
+
+    #[test]
+    fn converts_across_networks() {
+        assert_eq!(
+            quote_equivalent(
+                AmountValue(2_500_000),
+                &UsdcBase::ASSET,
+                &UsdcSolana::ASSET,
+                Decimal::ONE
+            ),
+            Some(AmountValue(2_500_000))
+        );
+    }
+
+    #[test]
+    fn rejects_negative_rates_and_overflow() {
+        assert_eq!(
+            quote_equivalent(
+                AmountValue(1_000_000),
+                &UsdcBase::ASSET,
+                &dai(),
+                Decimal::NEGATIVE_ONE
+            ),
+            None
+        );
+        assert_eq!(
+            quote_equivalent(
+                AmountValue(u128::MAX),
+                &dai(),
+                &UsdcBase::ASSET,
+                Decimal::ONE
+            ),
+            None
+        );
+    }
+}
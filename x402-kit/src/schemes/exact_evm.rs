@@ -1,9 +1,14 @@
 use bon::Builder;
 use serde::{Deserialize, Serialize};
+use serde_json::json;
 
 use crate::{
     core::{Payment, Scheme},
     networks::evm::{EvmAddress, EvmNetwork, EvmSignature, ExplicitEvmAsset, ExplicitEvmNetwork},
+    schemes::{
+        fees::{SchemeFee, apply_fee},
+        merge_extra,
+    },
     transport::PaymentRequirements,
     types::{AmountValue, AnyJson},
 };
@@ -11,8 +16,28 @@ use crate::{
 use std::{
     fmt::{Debug, Display},
     str::FromStr,
+    sync::Arc,
+    time::SystemTime,
 };
 
+/// The EIP-712 domain name and version of the asset, as advertised in the `extra` of the requirements.
+#[derive(Debug, Clone, Default, Deserialize)]
+pub(crate) struct Eip712DomainExtra {
+    pub name: String,
+    pub version: String,
+}
+
+impl Eip712DomainExtra {
+    /// Parse the domain from an `extra`, decoding it a second time if it is a JSON string, as
+    /// returned by some facilitators that double-encode the `extra`.
+    pub(crate) fn from_extra(extra: &AnyJson) -> Result<Self, serde_json::Error> {
+        match extra {
+            AnyJson::String(encoded) => serde_json::from_str(encoded),
+            extra => Self::deserialize(extra),
+        }
+    }
+}
+
 #[derive(Clone, Copy, PartialEq, Eq, Hash)]
 pub struct Nonce(pub [u8; 32]);
 
@@ -129,14 +154,124 @@ impl Scheme for ExactEvmScheme {
     }
 }
 
+/// Payments with tokens implementing EIP-3009 `transferWithAuthorization`.
+///
+/// The `extra` of the payment requirements defaults to the asset's EIP-712 domain.
+/// [`extra_override`](ExactEvm::extra_override) replaces it entirely, then
+/// [`extra_merge`](ExactEvm::extra_merge) is deep-merged over the result, see [`ExactEvm::extra`].
+///
+/// For invoice-style payments, [`from`](ExactEvm::from) restricts the payment to a single payer.
 #[derive(Builder, Debug, Clone)]
 pub struct ExactEvm<A: ExplicitEvmAsset> {
     pub asset: A,
     #[builder(into)]
     pub pay_to: EvmAddress,
+    /// The base amount, before the [`fee`](ExactEvm::fee).
     pub amount: u64,
+    /// Fee charged on top of the `amount`, recorded as `feeBreakdown` in the `extra`,
+    /// see [`total_amount`](ExactEvm::total_amount).
+    #[builder(with = |fee: impl SchemeFee + 'static| Arc::new(fee))]
+    pub fee: Option<Arc<dyn SchemeFee>>,
+    /// The only payer allowed to pay, advertised as `requiredPayer` in the `extra`.
+    ///
+    /// The paywall rejects payloads whose authorization is not signed `from` this address.
+    #[builder(into)]
+    pub from: Option<EvmAddress>,
     pub max_timeout_seconds_override: Option<u64>,
+    /// Replaces the default `extra` derived from the asset's EIP-712 domain.
     pub extra_override: Option<AnyJson>,
+    /// Deep-merged over the default `extra`, or over the `extra_override` if set.
+    pub extra_merge: Option<AnyJson>,
+}
+
+impl<A: ExplicitEvmAsset> ExactEvm<A> {
+    /// The amount to pay, including the [`fee`](ExactEvm::fee) if set.
+    pub fn total_amount(&self) -> AmountValue {
+        apply_fee(self.fee.as_deref(), self.amount.into()).0
+    }
+
+    /// The `extra` of the payment requirements.
+    ///
+    /// Starts from the `extra_override` if set, otherwise from the asset's EIP-712 domain, adds
+    /// the `requiredPayer` if [`from`](ExactEvm::from) is set and the `feeBreakdown` if
+    /// [`fee`](ExactEvm::fee) is set, and deep-merges the `extra_merge` over it: objects are
+    /// merged key by key, and any other value of the `extra_merge` takes precedence.
+    pub fn extra(&self) -> Option<AnyJson> {
+        let base = self
+            .extra_override
+            .clone()
+            .or_else(|| A::EIP712_DOMAIN.and_then(|v| serde_json::to_value(v).ok()));
+        let base = merge_extra(base, self.from.map(|from| json!({ "requiredPayer": from })));
+        let base = merge_extra(base, apply_fee(self.fee.as_deref(), self.amount.into()).1);
+
+        merge_extra(base, self.extra_merge.clone())
+    }
+
+    /// Prepare an authorization from `from` for this payment, valid from 5 minutes ago until
+    /// the payment times out.
+    ///
+    /// Use with [`prepare_typed_data`](ExactEvm::prepare_typed_data) to sign with an external wallet.
+    pub fn prepare_authorization(&self, from: EvmAddress, nonce: Nonce) -> ExactEvmAuthorization {
+        let now = SystemTime::now()
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or_default();
+
+        ExactEvmAuthorization {
+            from,
+            to: self.pay_to,
+            value: self.total_amount(),
+            valid_after: TimestampSeconds(now.saturating_sub(300)),
+            valid_before: TimestampSeconds(now + self.max_timeout_seconds_override.unwrap_or(300)),
+            nonce,
+        }
+    }
+
+    /// Prepare the EIP-712 typed data document of an authorization for this payment.
+    ///
+    /// The document can be handed to an external wallet, e.g. via `eth_signTypedData_v4`, so that
+    /// the crate never holds the key. The returned signature forms an [`ExactEvmPayload`] together
+    /// with the authorization.
+    ///
+    /// The domain name and version are taken from the [`extra`](ExactEvm::extra) if set there,
+    /// otherwise from the asset's EIP-712 domain.
+    pub fn prepare_typed_data(&self, authorization: &ExactEvmAuthorization) -> AnyJson {
+        let domain = self
+            .extra()
+            .and_then(|extra| Eip712DomainExtra::from_extra(&extra).ok())
+            .or(A::EIP712_DOMAIN.map(|domain| Eip712DomainExtra {
+                name: domain.name.to_string(),
+                version: domain.version.to_string(),
+            }))
+            .unwrap_or_default();
+
+        json!({
+            "types": {
+                "EIP712Domain": [
+                    { "name": "name", "type": "string" },
+                    { "name": "version", "type": "string" },
+                    { "name": "chainId", "type": "uint256" },
+                    { "name": "verifyingContract", "type": "address" }
+                ],
+                "TransferWithAuthorization": [
+                    { "name": "from", "type": "address" },
+                    { "name": "to", "type": "address" },
+                    { "name": "value", "type": "uint256" },
+                    { "name": "validAfter", "type": "uint256" },
+                    { "name": "validBefore", "type": "uint256" },
+                    { "name": "nonce", "type": "bytes32" }
+                ]
+            },
+            "primaryType": "TransferWithAuthorization",
+            "domain": {
+                "name": domain.name,
+                "version": domain.version,
+                "chainId": A::Network::NETWORK.chain_id,
+                "verifyingContract": A::ASSET.address
+            },
+            "message": authorization
+        })
+    }
 }
 
 impl<A: ExplicitEvmAsset> From<ExactEvm<A>> for Payment<ExactEvmScheme, EvmAddress> {
@@ -145,11 +280,9 @@ impl<A: ExplicitEvmAsset> From<ExactEvm<A>> for Payment<ExactEvmScheme, EvmAddre
             scheme: ExactEvmScheme(A::Network::NETWORK),
             pay_to: scheme.pay_to,
             asset: A::ASSET,
-            amount: scheme.amount.into(),
+            amount: scheme.total_amount(),
             max_timeout_seconds: scheme.max_timeout_seconds_override.unwrap_or(300),
-            extra: scheme
-                .extra_override
-                .or(A::EIP712_DOMAIN.and_then(|v| serde_json::to_value(v).ok())),
+            extra: scheme.extra(),
         }
     }
 }
@@ -165,7 +298,12 @@ mod tests {
     use alloy_primitives::address;
     use serde_json::json;
 
-    use crate::networks::evm::assets::UsdcBaseSepolia;
+    use crate::{
+        networks::evm::assets::UsdcBaseSepolia,
+        schemes::fees::PercentageFee,
+        transport::{PaymentPayload, PaymentResource},
+        types::{Base64EncodedHeader, Record},
+    };
 
     use super::*;
 
@@ -211,4 +349,204 @@ mod tests {
 
         assert_eq!(pr.extra, Some(json!({"foo": "bar"})));
     }
+
+    #[test]
+    fn test_extra_merge() {
+        let pr: PaymentRequirements = ExactEvm::builder()
+            .asset(UsdcBaseSepolia)
+            .amount(1000)
+            .pay_to(address!("0x3CB9B3bBfde8501f411bB69Ad3DC07908ED0dE20"))
+            .extra_merge(json!({"version": "3", "hint": {"facilitator": "example"}}))
+            .build()
+            .into();
+
+        assert_eq!(
+            pr.extra,
+            Some(json!({
+                "name": "USD Coin",
+                "version": "3",
+                "hint": {"facilitator": "example"}
+            }))
+        );
+    }
+
+    #[test]
+    fn test_extra_merge_over_override() {
+        let scheme = ExactEvm::builder()
+            .asset(UsdcBaseSepolia)
+            .amount(1000)
+            .pay_to(address!("0x3CB9B3bBfde8501f411bB69Ad3DC07908ED0dE20"))
+            .extra_override(json!({"name": "Custom", "version": "1", "hint": "plain"}))
+            .extra_merge(json!({"hint": {"facilitator": "example"}}))
+            .build();
+
+        // The object replaces the non-object value of the override
+        assert_eq!(
+            scheme.extra(),
+            Some(json!({
+                "name": "Custom",
+                "version": "1",
+                "hint": {"facilitator": "example"}
+            }))
+        );
+
+        let from = EvmAddress(address!("0x857b06519E91e3A54538791bDbb0E22373e36b66"));
+        let typed_data =
+            scheme.prepare_typed_data(&scheme.prepare_authorization(from, Nonce([0; 32])));
+        assert_eq!(typed_data["domain"]["name"], "Custom");
+        assert_eq!(typed_data["domain"]["version"], "1");
+    }
+
+    #[test]
+    fn test_payment_payload_for_scheme() {
+        let scheme = ExactEvm::builder()
+            .asset(UsdcBaseSepolia)
+            .amount(1000)
+            .pay_to(address!("0x3CB9B3bBfde8501f411bB69Ad3DC07908ED0dE20"))
+            .build();
+        let from = EvmAddress(address!("0x857b06519E91e3A54538791bDbb0E22373e36b66"));
+        let payload = ExactEvmPayload {
+            signature: EvmSignature(alloy_primitives::Signature::test_signature()),
+            authorization: scheme.prepare_authorization(from, Nonce([7; 32])),
+        };
+        let resource = PaymentResource {
+            url: "https://example.com/resource".parse().unwrap(),
+            description: "Resource".to_string(),
+            mime_type: "application/json".to_string(),
+        };
+        let accepted = PaymentRequirements::from(scheme);
+
+        let payment = PaymentPayload::for_scheme::<ExactEvmScheme>(
+            resource,
+            accepted.clone(),
+            payload.clone(),
+            Record::new(),
+        )
+        .unwrap();
+        let header = Base64EncodedHeader::try_from(payment).unwrap();
+        let decoded = PaymentPayload::try_from(header).unwrap();
+
+        assert_eq!(decoded.accepted, accepted);
+        assert_eq!(
+            decoded.payload["authorization"]["from"],
+            "0x857b06519E91e3A54538791bDbb0E22373e36b66"
+        );
+        assert_eq!(decoded.payload, serde_json::to_value(payload).unwrap());
+    }
+
+    #[test]
+    fn test_required_payer() {
+        let pr: PaymentRequirements = ExactEvm::builder()
+            .asset(UsdcBaseSepolia)
+            .amount(1000)
+            .pay_to(address!("0x3CB9B3bBfde8501f411bB69Ad3DC07908ED0dE20"))
+            .from(address!("0x857b06519E91e3A54538791bDbb0E22373e36b66"))
+            .build()
+            .into();
+
+        assert_eq!(
+            pr.extra,
+            Some(json!({
+                "name": "USD Coin",
+                "version": "2",
+                "requiredPayer": "0x857b06519E91e3A54538791bDbb0E22373e36b66"
+            }))
+        );
+    }
+
+    struct NoDomainToken;
+
+    impl ExplicitEvmAsset for NoDomainToken {
+        type Network = crate::networks::evm::networks::BaseSepolia;
+
+        const ASSET: crate::networks::evm::EvmAsset = crate::networks::evm::EvmAsset {
+            address: EvmAddress(address!("0x1111111111111111111111111111111111111111")),
+            decimals: 18,
+            name: "No Domain Token",
+            symbol: "NDT",
+        };
+        const EIP712_DOMAIN: Option<crate::networks::evm::Eip712Domain> = None;
+    }
+
+    #[test]
+    fn test_fee() {
+        let scheme = ExactEvm::builder()
+            .asset(UsdcBaseSepolia)
+            .amount(1_000_000)
+            .pay_to(address!("0x3CB9B3bBfde8501f411bB69Ad3DC07908ED0dE20"))
+            .fee(PercentageFee::percent(1))
+            .build();
+        let from = EvmAddress(address!("0x857b06519E91e3A54538791bDbb0E22373e36b66"));
+        let authorization = scheme.prepare_authorization(from, Nonce([7; 32]));
+        assert_eq!(authorization.value, AmountValue(1_010_000));
+
+        let pr: PaymentRequirements = scheme.into();
+        assert_eq!(pr.amount, AmountValue(1_010_000));
+        assert_eq!(
+            pr.extra,
+            Some(json!({
+                "name": "USD Coin",
+                "version": "2",
+                "feeBreakdown": { "baseAmount": "1000000", "feeAmount": "10000" }
+            }))
+        );
+    }
+
+    #[test]
+    fn test_extra_merge_without_domain() {
+        let scheme = ExactEvm::builder()
+            .asset(NoDomainToken)
+            .amount(1000)
+            .pay_to(address!("0x3CB9B3bBfde8501f411bB69Ad3DC07908ED0dE20"))
+            .build();
+        assert_eq!(scheme.extra(), None);
+
+        let pr: PaymentRequirements = ExactEvm {
+            extra_merge: Some(json!({"foo": "bar"})),
+            ..scheme
+        }
+        .into();
+        assert_eq!(pr.extra, Some(json!({"foo": "bar"})));
+    }
+
+    #[test]
+    fn test_prepare_typed_data() {
+        let scheme = ExactEvm::builder()
+            .asset(UsdcBaseSepolia)
+            .amount(1000)
+            .pay_to(address!("0x3CB9B3bBfde8501f411bB69Ad3DC07908ED0dE20"))
+            .build();
+        let from = EvmAddress(address!("0x857b06519E91e3A54538791bDbb0E22373e36b66"));
+        let authorization = scheme.prepare_authorization(from, Nonce([7; 32]));
+
+        let typed_data = scheme.prepare_typed_data(&authorization);
+
+        assert_eq!(typed_data["primaryType"], "TransferWithAuthorization");
+        assert_eq!(
+            typed_data["domain"],
+            json!({
+                "name": "USD Coin",
+                "version": "2",
+                "chainId": 84532,
+                "verifyingContract": "0x036CbD53842c5426634e7929541eC2318f3dCF7e"
+            })
+        );
+        assert_eq!(
+            typed_data["message"]["from"],
+            "0x857b06519E91e3A54538791bDbb0E22373e36b66"
+        );
+        assert_eq!(
+            typed_data["message"]["to"],
+            "0x3CB9B3bBfde8501f411bB69Ad3DC07908ED0dE20"
+        );
+        assert_eq!(typed_data["message"]["value"], "1000");
+        assert_eq!(
+            typed_data["message"]["nonce"],
+            format!("0x{}", "07".repeat(32))
+        );
+        assert_eq!(
+            authorization.valid_before.0 - authorization.valid_after.0,
+            600
+        );
+    }
 }
@@ -4,12 +4,12 @@ use alloy_core::{
 };
 use alloy_primitives::{FixedBytes, U256};
 use alloy_signer::{Error as AlloySignerError, Signer as AlloySigner};
-use serde::Deserialize;
 
 use crate::{
     core::{PaymentSelection, Scheme, SchemeSigner},
     networks::evm::{EvmAddress, EvmSignature, ExplicitEvmAsset, ExplicitEvmNetwork},
     schemes::exact_evm::*,
+    types::{Clock, SystemClock},
 };
 
 use std::{fmt::Debug, time::SystemTime};
@@ -21,7 +21,7 @@ pub trait AuthorizationSigner {
         &self,
         authorization: &Eip3009Authorization,
         asset_eip712_domain: &Eip712Domain,
-    ) -> impl Future<Output = Result<EvmSignature, Self::Error>>;
+    ) -> impl Future<Output = Result<EvmSignature, Self::Error>> + Send;
 }
 
 sol!(
@@ -51,7 +51,7 @@ impl From<ExactEvmAuthorization> for Eip3009Authorization {
     }
 }
 
-impl<S: AlloySigner> AuthorizationSigner for S {
+impl<S: AlloySigner + Sync> AuthorizationSigner for S {
     type Error = AlloySignerError;
 
     async fn sign_authorization(
@@ -66,9 +66,11 @@ impl<S: AlloySigner> AuthorizationSigner for S {
     }
 }
 
-pub struct ExactEvmSigner<S: AuthorizationSigner, A: ExplicitEvmAsset> {
+pub struct ExactEvmSigner<S: AuthorizationSigner, A: ExplicitEvmAsset, C: Clock = SystemClock> {
     pub signer: S,
     pub asset: A,
+    /// The clock the validity window of authorizations is computed from.
+    pub clock: C,
 }
 
 #[derive(Debug, thiserror::Error)]
@@ -77,12 +79,16 @@ pub enum ExactEvmSignError<S: AuthorizationSigner> {
     SignerError(S::Error),
     #[error("System time error: {0}")]
     SystemTimeError(#[from] std::time::SystemTimeError),
+    /// The `extra` of the requirements does not carry a valid EIP-712 domain name and version.
+    #[error("Invalid EIP-712 domain in payment requirements extra: {0}")]
+    InvalidExtra(serde_json::Error),
 }
 
-impl<S, A> SchemeSigner<EvmAddress> for ExactEvmSigner<S, A>
+impl<S, A, C> SchemeSigner<EvmAddress> for ExactEvmSigner<S, A, C>
 where
-    S: AuthorizationSigner + Debug,
-    A: ExplicitEvmAsset,
+    S: AuthorizationSigner + Debug + Sync,
+    A: ExplicitEvmAsset + Sync,
+    C: Clock + Sync,
 {
     type Scheme = ExactEvmScheme;
     type Error = ExactEvmSignError<S>;
@@ -91,22 +97,25 @@ where
         &self,
         selected: &PaymentSelection<EvmAddress>,
     ) -> Result<<Self::Scheme as Scheme>::Payload, Self::Error> {
-        let now = SystemTime::now()
+        let now = self
+            .clock
+            .now()
             .duration_since(SystemTime::UNIX_EPOCH)?
             .as_secs();
 
-        #[derive(Deserialize, Default)]
-        struct Eip712DomainExtra {
-            name: String,
-            version: String,
-        }
-
-        let eip712_domain_info = selected
-            .extra
-            .as_ref()
-            .and_then(|extra| serde_json::from_value::<Eip712DomainExtra>(extra.clone()).ok())
-            // Use empty string if not provided -- This doesn't work in many cases!
-            .unwrap_or_default();
+        let eip712_domain_info = match &selected.extra {
+            Some(extra) => {
+                Eip712DomainExtra::from_extra(extra).map_err(Self::Error::InvalidExtra)?
+            }
+            // Use the asset's domain if not provided, or empty strings if unknown -- This
+            // doesn't work in many cases!
+            None => A::EIP712_DOMAIN
+                .map(|domain| Eip712DomainExtra {
+                    name: domain.name.to_string(),
+                    version: domain.version.to_string(),
+                })
+                .unwrap_or_default(),
+        };
 
         let authorization = ExactEvmAuthorization {
             from: selected.pay_to,
@@ -147,41 +156,39 @@ mod tests {
     use crate::{
         core::Resource,
         networks::evm::{assets::UsdcBaseSepolia, networks::BaseSepolia},
-        types::{AmountValue, Record},
+        types::{AmountValue, FixedClock, Record},
     };
 
     use super::*;
 
-    #[tokio::test]
-    async fn test_signing() {
-        let signer = PrivateKeySigner::random();
-
-        let evm_signer = ExactEvmSigner {
-            signer,
-            asset: UsdcBaseSepolia,
-        };
-
+    fn selection(extra: Option<serde_json::Value>) -> PaymentSelection<EvmAddress> {
         let resource = Resource::builder()
             .url(Url::parse("https://example.com/payment").unwrap())
             .description("Payment for services".to_string())
             .mime_type("application/json".to_string())
             .build();
 
-        let payment = PaymentSelection {
+        PaymentSelection {
             amount: 1000u64.into(),
             resource,
             pay_to: EvmAddress(address!("0x3CB9B3bBfde8501f411bB69Ad3DC07908ED0dE20")),
             max_timeout_seconds: 60,
             asset: UsdcBaseSepolia::ASSET.address,
-            extra: Some(json!({
-                "name": "USD Coin",
-                "version": "2"
-            })),
+            extra,
             extensions: Record::new(),
+        }
+    }
+
+    /// Sign the selection, asserting that the signature is over the `USD Coin` version `2` domain.
+    async fn assert_signed_with_usdc_domain(extra: serde_json::Value) {
+        let evm_signer = ExactEvmSigner {
+            signer: PrivateKeySigner::random(),
+            asset: UsdcBaseSepolia,
+            clock: SystemClock,
         };
 
         let payload = evm_signer
-            .sign(&payment)
+            .sign(&selection(Some(extra)))
             .await
             .expect("Signing should succeed");
 
@@ -206,4 +213,52 @@ mod tests {
 
         assert_eq!(recovered_address, evm_signer.signer.address());
     }
+
+    #[tokio::test]
+    async fn test_signing() {
+        assert_signed_with_usdc_domain(json!({
+            "name": "USD Coin",
+            "version": "2"
+        }))
+        .await;
+    }
+
+    #[tokio::test]
+    async fn test_signing_double_encoded_extra() {
+        assert_signed_with_usdc_domain(json!(r#"{"name":"USD Coin","version":"2"}"#)).await;
+    }
+
+    #[tokio::test]
+    async fn test_signing_invalid_extra() {
+        let evm_signer = ExactEvmSigner {
+            signer: PrivateKeySigner::random(),
+            asset: UsdcBaseSepolia,
+            clock: SystemClock,
+        };
+
+        for extra in [json!("not json"), json!({ "name": "USD Coin" })] {
+            let err = evm_signer.sign(&selection(Some(extra))).await.unwrap_err();
+            assert!(matches!(err, ExactEvmSignError::InvalidExtra(_)));
+        }
+    }
+
+    #[tokio::test]
+    async fn validity_window_follows_clock() {
+        let evm_signer = ExactEvmSigner {
+            signer: PrivateKeySigner::random(),
+            asset: UsdcBaseSepolia,
+            clock: FixedClock::from_unix_seconds(1_700_000_000),
+        };
+
+        let payload = evm_signer.sign(&selection(None)).await.unwrap();
+
+        assert_eq!(
+            payload.authorization.valid_after,
+            TimestampSeconds(1_699_999_700)
+        );
+        assert_eq!(
+            payload.authorization.valid_before,
+            TimestampSeconds(1_700_000_060)
+        );
+    }
 }
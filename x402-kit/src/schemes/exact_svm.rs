@@ -1,10 +1,19 @@
+use std::sync::Arc;
+
 use bon::Builder;
 use serde::{Deserialize, Serialize};
 
 use crate::{
     core::{Payment, Scheme},
-    networks::svm::{ExplicitSvmAsset, ExplicitSvmNetwork, SvmAddress, SvmNetwork},
+    networks::svm::{
+        ExplicitSvmAsset, ExplicitSvmNetwork, SvmAddress, SvmNetwork, TOKEN_PROGRAM, derive_ata,
+    },
+    schemes::{
+        fees::{SchemeFee, apply_fee},
+        merge_extra,
+    },
     transport::PaymentRequirements,
+    types::AnyJson,
 };
 
 #[derive(Builder, Debug, Clone)]
@@ -12,19 +21,66 @@ pub struct ExactSvm<A: ExplicitSvmAsset> {
     pub asset: A,
     #[builder(into)]
     pub pay_to: SvmAddress,
+    /// The base amount, before the [`fee`](ExactSvm::fee).
     pub amount: u64,
+    /// Fee charged on top of the `amount`, recorded as `feeBreakdown` in the `extra`.
+    #[builder(with = |fee: impl SchemeFee + 'static| Arc::new(fee))]
+    pub fee: Option<Arc<dyn SchemeFee>>,
     pub max_timeout_seconds_override: Option<u64>,
+    /// The token account receiving the payment, recorded as `destinationAta` in the `extra`.
+    ///
+    /// Defaults to the associated token account of `pay_to` for the asset, see [`derive_ata`].
+    #[builder(into)]
+    pub destination_ata_override: Option<SvmAddress>,
+    /// The program owning the asset's token accounts, used to derive the destination account.
+    ///
+    /// Defaults to the SPL [`TOKEN_PROGRAM`].
+    #[builder(into)]
+    pub token_program_override: Option<SvmAddress>,
+    /// Whether the facilitator may pay the transaction fees, recorded as
+    /// `allowFacilitatorFeePayer` in the `extra`.
+    #[builder(default)]
+    pub allow_facilitator_fee_payer: bool,
+    /// Deep-merged over the default `extra`, which carries the `destinationAta`, the
+    /// `allowFacilitatorFeePayer` if set, and the `feeBreakdown`.
+    pub extra_merge: Option<AnyJson>,
+}
+
+impl<A: ExplicitSvmAsset> ExactSvm<A> {
+    /// The token account receiving the payment.
+    pub fn destination_ata(&self) -> SvmAddress {
+        self.destination_ata_override.unwrap_or_else(|| {
+            derive_ata(
+                &self.pay_to,
+                &A::ASSET.address,
+                self.token_program_override
+                    .as_ref()
+                    .unwrap_or(&TOKEN_PROGRAM),
+            )
+        })
+    }
+
+    fn default_extra(&self, breakdown: Option<AnyJson>) -> AnyJson {
+        let mut extra = serde_json::json!({ "destinationAta": self.destination_ata() });
+        if self.allow_facilitator_fee_payer {
+            extra["allowFacilitatorFeePayer"] = true.into();
+        }
+        merge_extra(Some(extra), breakdown).unwrap_or_default()
+    }
 }
 
 impl<A: ExplicitSvmAsset> From<ExactSvm<A>> for Payment<ExactSvmScheme, SvmAddress> {
     fn from(scheme: ExactSvm<A>) -> Self {
+        let (amount, breakdown) = apply_fee(scheme.fee.as_deref(), scheme.amount.into());
+        let extra = scheme.default_extra(breakdown);
+
         Payment {
             scheme: ExactSvmScheme(A::Network::NETWORK),
             pay_to: scheme.pay_to,
             asset: A::ASSET,
-            amount: scheme.amount.into(),
+            amount,
             max_timeout_seconds: scheme.max_timeout_seconds_override.unwrap_or(300),
-            extra: None,
+            extra: merge_extra(Some(extra), scheme.extra_merge),
         }
     }
 }
@@ -58,7 +114,8 @@ mod tests {
     use solana_pubkey::pubkey;
 
     use crate::{
-        networks::svm::assets::UsdcSolanaDevnet, schemes::exact_svm::ExactSvm,
+        networks::svm::{SvmAddress, assets::UsdcSolanaDevnet},
+        schemes::{exact_svm::ExactSvm, fees::PercentageFee},
         transport::PaymentRequirements,
     };
 
@@ -74,6 +131,84 @@ mod tests {
         assert_eq!(pr.scheme, "exact");
         assert_eq!(pr.network, "solana:EtWTRABZaYq6iMfeYKouRu166VU2xqa1");
         assert_eq!(pr.amount, 1000u64.into());
-        assert!(pr.extra.is_none());
+        assert_eq!(
+            pr.extra,
+            Some(serde_json::json!({
+                "destinationAta": "DSoccwgFrbdQmEcXH7FrVtj6sJ46FUZNfa7HH5UEDzmd"
+            }))
+        );
+    }
+
+    #[test]
+    fn test_destination_ata_and_fee_payer() {
+        let pr: PaymentRequirements = ExactSvm::builder()
+            .asset(UsdcSolanaDevnet)
+            .amount(1000)
+            .pay_to(pubkey!("Ge3jkza5KRfXvaq3GELNLh6V1pjjdEKNpEdGXJgjjKUR"))
+            .destination_ata_override(pubkey!("9fJvVo2wjcjuQ2wpss1Tjjw914DrkuikZ7aUd9ZwLgpx"))
+            .allow_facilitator_fee_payer(true)
+            .build()
+            .into();
+
+        assert_eq!(
+            pr.extra,
+            Some(serde_json::json!({
+                "destinationAta": "9fJvVo2wjcjuQ2wpss1Tjjw914DrkuikZ7aUd9ZwLgpx",
+                "allowFacilitatorFeePayer": true
+            }))
+        );
+
+        // A different token program derives a different account
+        let scheme = ExactSvm::builder()
+            .asset(UsdcSolanaDevnet)
+            .amount(1000)
+            .pay_to(pubkey!("Ge3jkza5KRfXvaq3GELNLh6V1pjjdEKNpEdGXJgjjKUR"))
+            .token_program_override(pubkey!("CKPKJWNdJEqa81x7CkZ14BVPiY6y16Sxs7owznqtWYp5"))
+            .build();
+        assert_ne!(
+            scheme.destination_ata(),
+            SvmAddress(pubkey!("DSoccwgFrbdQmEcXH7FrVtj6sJ46FUZNfa7HH5UEDzmd"))
+        );
+    }
+
+    #[test]
+    fn test_extra_merge() {
+        let pr: PaymentRequirements = ExactSvm::builder()
+            .asset(UsdcSolanaDevnet)
+            .amount(1000)
+            .pay_to(pubkey!("Ge3jkza5KRfXvaq3GELNLh6V1pjjdEKNpEdGXJgjjKUR"))
+            .extra_merge(serde_json::json!({"feePayer": "example"}))
+            .build()
+            .into();
+
+        assert_eq!(
+            pr.extra,
+            Some(serde_json::json!({
+                "destinationAta": "DSoccwgFrbdQmEcXH7FrVtj6sJ46FUZNfa7HH5UEDzmd",
+                "feePayer": "example"
+            }))
+        );
+    }
+
+    #[test]
+    fn test_fee() {
+        let pr: PaymentRequirements = ExactSvm::builder()
+            .asset(UsdcSolanaDevnet)
+            .amount(1_000_000)
+            .pay_to(pubkey!("Ge3jkza5KRfXvaq3GELNLh6V1pjjdEKNpEdGXJgjjKUR"))
+            .fee(PercentageFee::percent(1))
+            .extra_merge(serde_json::json!({"feePayer": "example"}))
+            .build()
+            .into();
+
+        assert_eq!(pr.amount, 1_010_000u64.into());
+        assert_eq!(
+            pr.extra,
+            Some(serde_json::json!({
+                "destinationAta": "DSoccwgFrbdQmEcXH7FrVtj6sJ46FUZNfa7HH5UEDzmd",
+                "feeBreakdown": { "baseAmount": "1000000", "feeAmount": "10000" },
+                "feePayer": "example"
+            }))
+        );
     }
 }
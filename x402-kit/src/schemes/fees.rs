@@ -0,0 +1,83 @@
+//! Service fees added on top of the base amount of a scheme, e.g. by aggregators.
+//!
+//! Set a [`SchemeFee`] with the `fee` of the [`ExactEvm`](super::exact_evm::ExactEvm) or
+//! [`ExactSvm`](super::exact_svm::ExactSvm) builders: the emitted `amount` includes the fee,
+//! and the `extra` records the breakdown:
+//!
+//! ```json
+//! { "feeBreakdown": { "baseAmount": "1000000", "feeAmount": "10000" } }
+//! ```
+//!
+//! Paywalls charging a fee on every accepted requirement use the paywall's `FeePolicy` instead.
+//! Set only one of them for the same fee, as the paywall's fee is charged on top of the amount
+//! emitted by the scheme, including its fee. Both round percentages with
+//! [`AmountValue::basis_points_ceil`].
+
+use std::fmt::Debug;
+
+use serde_json::json;
+
+use crate::types::{AmountValue, AnyJson};
+
+/// Computes the fee charged on top of the base amount of a payment.
+pub trait SchemeFee: Debug + Send + Sync {
+    /// The fee for the given base amount, in the smallest units of the asset.
+    fn fee(&self, base: AmountValue) -> AmountValue;
+}
+
+/// A percentage fee, rounded up to the next smallest unit so that a non-zero fee never
+/// rounds down to nothing.
+///
+/// # Example
+///
+/// ```
+/// use x402_kit::{
+///     schemes::fees::{PercentageFee, SchemeFee},
+///     types::AmountValue,
+/// };
+///
+/// assert_eq!(PercentageFee::percent(1).fee(AmountValue(1_000_000)), AmountValue(10_000));
+/// assert_eq!(PercentageFee::basis_points(250).fee(AmountValue(1)), AmountValue(1));
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PercentageFee {
+    /// The fee in basis points, i.e. hundredths of a percent.
+    pub basis_points: u32,
+}
+
+impl PercentageFee {
+    /// A fee in whole percent.
+    pub fn percent(percent: u32) -> Self {
+        Self::basis_points(percent.saturating_mul(100))
+    }
+
+    /// A fee in basis points, e.g. `250` for 2.5%.
+    pub fn basis_points(basis_points: u32) -> Self {
+        PercentageFee { basis_points }
+    }
+}
+
+impl SchemeFee for PercentageFee {
+    fn fee(&self, base: AmountValue) -> AmountValue {
+        base.basis_points_ceil(self.basis_points)
+    }
+}
+
+/// The amount including the fee, and the `feeBreakdown` to merge into the `extra` if there is a fee.
+pub(crate) fn apply_fee(
+    fee: Option<&dyn SchemeFee>,
+    base: AmountValue,
+) -> (AmountValue, Option<AnyJson>) {
+    let Some(fee) = fee else {
+        return (base, None);
+    };
+    let fee = fee.fee(base);
+    let breakdown = json!({
+        "feeBreakdown": {
+            "baseAmount": base,
+            "feeAmount": fee,
+        }
+    });
+
+    (AmountValue(base.0.saturating_add(fee.0)), Some(breakdown))
+}
@@ -1,7 +1,14 @@
 //! Schemes are defined here, for example, exact_evm, exact_svm, etc.
 
+pub(crate) use crate::types::merge_extra;
+
 pub mod exact_evm;
 pub mod exact_svm;
+pub mod fees;
+pub mod permit_evm;
 
 #[cfg(feature = "evm-signer")]
 pub mod exact_evm_signer;
+
+#[cfg(feature = "evm-signer")]
+pub mod permit_evm_signer;
@@ -0,0 +1,186 @@
+use bon::Builder;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+
+use crate::{
+    core::{Payment, Scheme},
+    networks::evm::{EvmAddress, EvmNetwork, EvmSignature, ExplicitEvmAsset, ExplicitEvmNetwork},
+    schemes::{exact_evm::TimestampSeconds, merge_extra},
+    transport::PaymentRequirements,
+    types::{AmountValue, AnyJson},
+};
+
+use std::fmt::{Debug, Display};
+
+/// The EIP-2612 nonce of the token owner, as returned by the token's `nonces(owner)`.
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+pub struct PermitNonce(pub u64);
+
+impl Display for PermitNonce {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl Debug for PermitNonce {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "PermitNonce({})", self.0)
+    }
+}
+
+impl Serialize for PermitNonce {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        serializer.serialize_str(&self.to_string())
+    }
+}
+
+impl<'de> Deserialize<'de> for PermitNonce {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        let s = String::deserialize(deserializer)?;
+        let nonce = s.parse::<u64>().map_err(serde::de::Error::custom)?;
+        Ok(PermitNonce(nonce))
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PermitEvmPayload {
+    pub signature: EvmSignature,
+    pub permit: PermitEvmAuthorization,
+}
+
+/// EIP-2612 permit allowing the `spender` to transfer `value` from the `owner`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PermitEvmAuthorization {
+    pub owner: EvmAddress,
+    pub spender: EvmAddress,
+    pub value: AmountValue,
+    pub nonce: PermitNonce,
+    pub deadline: TimestampSeconds,
+}
+
+/// Permit EVM Scheme information holder
+pub struct PermitEvmScheme(pub EvmNetwork);
+
+impl Scheme for PermitEvmScheme {
+    type Network = EvmNetwork;
+    type Payload = PermitEvmPayload;
+    const SCHEME_NAME: &'static str = "permit";
+
+    fn network(&self) -> &Self::Network {
+        &self.0
+    }
+}
+
+/// Payments with tokens implementing EIP-2612 permit instead of EIP-3009.
+///
+/// The `spender` is the contract or account that redeems the permit and transfers the funds
+/// to `pay_to`. It is advertised to the payer in the `extra` field, along with the asset's
+/// EIP-712 domain if known. [`extra_override`](PermitEvm::extra_override) replaces this default
+/// entirely, then [`extra_merge`](PermitEvm::extra_merge) is deep-merged over the result: objects
+/// are merged key by key, and any other value of the `extra_merge` takes precedence.
+#[derive(Builder, Debug, Clone)]
+pub struct PermitEvm<A: ExplicitEvmAsset> {
+    pub asset: A,
+    #[builder(into)]
+    pub pay_to: EvmAddress,
+    #[builder(into)]
+    pub spender: EvmAddress,
+    pub amount: u64,
+    pub max_timeout_seconds_override: Option<u64>,
+    /// Replaces the default `extra` advertising the spender and the asset's EIP-712 domain.
+    pub extra_override: Option<AnyJson>,
+    /// Deep-merged over the default `extra`, or over the `extra_override` if set.
+    pub extra_merge: Option<AnyJson>,
+}
+
+impl<A: ExplicitEvmAsset> From<PermitEvm<A>> for Payment<PermitEvmScheme, EvmAddress> {
+    fn from(scheme: PermitEvm<A>) -> Self {
+        let extra = scheme
+            .extra_override
+            .unwrap_or_else(|| match A::EIP712_DOMAIN {
+                Some(domain) => json!({
+                    "name": domain.name,
+                    "version": domain.version,
+                    "spender": scheme.spender,
+                }),
+                None => json!({ "spender": scheme.spender }),
+            });
+
+        Payment {
+            scheme: PermitEvmScheme(A::Network::NETWORK),
+            pay_to: scheme.pay_to,
+            asset: A::ASSET,
+            amount: scheme.amount.into(),
+            max_timeout_seconds: scheme.max_timeout_seconds_override.unwrap_or(300),
+            extra: merge_extra(Some(extra), scheme.extra_merge),
+        }
+    }
+}
+
+impl<A: ExplicitEvmAsset> From<PermitEvm<A>> for PaymentRequirements {
+    fn from(scheme: PermitEvm<A>) -> Self {
+        PaymentRequirements::from(Payment::from(scheme))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloy_primitives::address;
+
+    use crate::networks::evm::assets::UsdcBaseSepolia;
+
+    use super::*;
+
+    #[test]
+    fn test_build_payment_requirements() {
+        let pr: PaymentRequirements = PermitEvm::builder()
+            .asset(UsdcBaseSepolia)
+            .amount(1000)
+            .pay_to(address!("0x3CB9B3bBfde8501f411bB69Ad3DC07908ED0dE20"))
+            .spender(address!("0x857b06519E91e3A54538791bDbb0E22373e36b66"))
+            .build()
+            .into();
+
+        assert_eq!(pr.scheme, "permit");
+        assert_eq!(pr.network, "eip155:84532");
+        assert_eq!(pr.amount, 1000u64.into());
+        assert_eq!(
+            pr.extra,
+            Some(json!({
+                "name": "USD Coin",
+                "version": "2",
+                "spender": "0x857b06519E91e3A54538791bDbb0E22373e36b66",
+            }))
+        );
+    }
+
+    #[test]
+    fn test_extra_merge() {
+        let pr: PaymentRequirements = PermitEvm::builder()
+            .asset(UsdcBaseSepolia)
+            .amount(1000)
+            .pay_to(address!("0x3CB9B3bBfde8501f411bB69Ad3DC07908ED0dE20"))
+            .spender(address!("0x857b06519E91e3A54538791bDbb0E22373e36b66"))
+            .extra_merge(json!({"hint": "example"}))
+            .build()
+            .into();
+
+        assert_eq!(
+            pr.extra,
+            Some(json!({
+                "name": "USD Coin",
+                "version": "2",
+                "spender": "0x857b06519E91e3A54538791bDbb0E22373e36b66",
+                "hint": "example",
+            }))
+        );
+    }
+}
@@ -0,0 +1,266 @@
+use alloy_core::{
+    sol,
+    sol_types::{Eip712Domain, SolStruct, eip712_domain},
+};
+use alloy_primitives::U256;
+use alloy_signer::{Error as AlloySignerError, Signer as AlloySigner};
+use serde::Deserialize;
+
+use crate::{
+    core::{PaymentSelection, Scheme, SchemeSigner},
+    networks::evm::{EvmAddress, EvmSignature, ExplicitEvmAsset, ExplicitEvmNetwork},
+    schemes::{exact_evm::TimestampSeconds, permit_evm::*},
+};
+
+use std::{fmt::Debug, time::SystemTime};
+
+pub trait PermitSigner {
+    type Error: std::error::Error;
+
+    /// The address of the token owner signing the permit.
+    fn address(&self) -> EvmAddress;
+
+    fn sign_permit(
+        &self,
+        permit: &Permit,
+        asset_eip712_domain: &Eip712Domain,
+    ) -> impl Future<Output = Result<EvmSignature, Self::Error>> + Send;
+}
+
+sol!(
+    /// Represent EIP-2612 Permit struct
+    ///
+    /// For generating the EIP-712 signing hash
+    struct Permit {
+        address owner;
+        address spender;
+        uint256 value;
+        uint256 nonce;
+        uint256 deadline;
+    }
+);
+
+impl From<PermitEvmAuthorization> for Permit {
+    fn from(permit: PermitEvmAuthorization) -> Self {
+        Permit {
+            owner: permit.owner.0,
+            spender: permit.spender.0,
+            value: U256::from(permit.value.0),
+            nonce: U256::from(permit.nonce.0),
+            deadline: U256::from(permit.deadline.0),
+        }
+    }
+}
+
+impl<S: AlloySigner + Sync> PermitSigner for S {
+    type Error = AlloySignerError;
+
+    fn address(&self) -> EvmAddress {
+        EvmAddress(AlloySigner::address(self))
+    }
+
+    async fn sign_permit(
+        &self,
+        permit: &Permit,
+        domain: &Eip712Domain,
+    ) -> Result<EvmSignature, Self::Error> {
+        let eip712_hash = permit.eip712_signing_hash(domain);
+        let signature = self.sign_hash(&eip712_hash).await?;
+
+        Ok(EvmSignature(signature))
+    }
+}
+
+/// Signer for [`PermitEvmScheme`] payments.
+///
+/// EIP-2612 nonces are sequential per owner, so `nonce` must be the current value of the
+/// token's `nonces(owner)`, read from chain before signing.
+pub struct PermitEvmSigner<S: PermitSigner, A: ExplicitEvmAsset> {
+    pub signer: S,
+    pub asset: A,
+    pub nonce: u64,
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum PermitEvmSignError<S: PermitSigner> {
+    #[error("Signer error: {0}")]
+    SignerError(S::Error),
+    #[error("System time error: {0}")]
+    SystemTimeError(#[from] std::time::SystemTimeError),
+    /// The payment requirements `extra` lacks the token's EIP-712 `name` and `version`, or the
+    /// permit `spender`.
+    #[error("Missing permit name, version or spender in payment requirements extra")]
+    MissingExtra,
+}
+
+impl<S, A> SchemeSigner<EvmAddress> for PermitEvmSigner<S, A>
+where
+    S: PermitSigner + Debug + Sync,
+    A: ExplicitEvmAsset + Sync,
+{
+    type Scheme = PermitEvmScheme;
+    type Error = PermitEvmSignError<S>;
+
+    async fn sign(
+        &self,
+        selected: &PaymentSelection<EvmAddress>,
+    ) -> Result<<Self::Scheme as Scheme>::Payload, Self::Error> {
+        let now = SystemTime::now()
+            .duration_since(SystemTime::UNIX_EPOCH)?
+            .as_secs();
+
+        #[derive(Deserialize)]
+        struct PermitExtra {
+            name: String,
+            version: String,
+            spender: EvmAddress,
+        }
+
+        let extra = selected
+            .extra
+            .as_ref()
+            .and_then(|extra| serde_json::from_value::<PermitExtra>(extra.clone()).ok())
+            .ok_or(PermitEvmSignError::MissingExtra)?;
+
+        let permit = PermitEvmAuthorization {
+            owner: self.signer.address(),
+            spender: extra.spender,
+            value: selected.amount,
+            nonce: PermitNonce(self.nonce),
+            deadline: TimestampSeconds(now + selected.max_timeout_seconds),
+        };
+
+        let domain = eip712_domain!(
+            name: extra.name,
+            version: extra.version,
+            chain_id: A::Network::NETWORK.chain_id,
+            verifying_contract: A::ASSET.address.0,
+        );
+        let signature = self
+            .signer
+            .sign_permit(&permit.clone().into(), &domain)
+            .await
+            .map_err(Self::Error::SignerError)?;
+        Ok(PermitEvmPayload { signature, permit })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloy::signers::local::PrivateKeySigner;
+    use alloy_primitives::address;
+    use serde_json::json;
+    use url::Url;
+
+    use crate::{
+        core::Resource,
+        networks::evm::{assets::UsdcBaseSepolia, networks::BaseSepolia},
+        types::{AmountValue, Record},
+    };
+
+    use super::*;
+
+    fn selection(extra: Option<serde_json::Value>) -> PaymentSelection<EvmAddress> {
+        let resource = Resource::builder()
+            .url(Url::parse("https://example.com/payment").unwrap())
+            .description("Payment for services".to_string())
+            .mime_type("application/json".to_string())
+            .build();
+
+        PaymentSelection {
+            amount: 1000u64.into(),
+            resource,
+            pay_to: EvmAddress(address!("0x3CB9B3bBfde8501f411bB69Ad3DC07908ED0dE20")),
+            max_timeout_seconds: 60,
+            asset: UsdcBaseSepolia::ASSET.address,
+            extra,
+            extensions: Record::new(),
+        }
+    }
+
+    #[test]
+    fn test_permit_type() {
+        assert_eq!(
+            Permit::eip712_encode_type(),
+            "Permit(address owner,address spender,uint256 value,uint256 nonce,uint256 deadline)"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_signing() {
+        let permit_signer = PermitEvmSigner {
+            signer: PrivateKeySigner::random(),
+            asset: UsdcBaseSepolia,
+            nonce: 7,
+        };
+
+        let payload = permit_signer
+            .sign(&selection(Some(json!({
+                "name": "USD Coin",
+                "version": "2",
+                "spender": "0x857b06519E91e3A54538791bDbb0E22373e36b66",
+            }))))
+            .await
+            .expect("Signing should succeed");
+
+        assert_eq!(payload.permit.value, AmountValue(1000));
+        assert_eq!(payload.permit.nonce, PermitNonce(7));
+        assert_eq!(
+            payload.permit.spender,
+            EvmAddress(address!("0x857b06519E91e3A54538791bDbb0E22373e36b66"))
+        );
+
+        // Verify the signature
+        let domain = eip712_domain! {
+            name: "USD Coin".to_string(),
+            version: "2".to_string(),
+            chain_id: BaseSepolia::NETWORK.chain_id,
+            verifying_contract: UsdcBaseSepolia::ASSET.address.0,
+        };
+
+        let recovered_address = payload
+            .signature
+            .0
+            .recover_address_from_prehash(
+                &Permit::from(payload.permit.clone()).eip712_signing_hash(&domain),
+            )
+            .expect("Recovery should succeed");
+
+        assert_eq!(recovered_address, permit_signer.signer.address());
+        assert_eq!(payload.permit.owner.0, recovered_address);
+    }
+
+    #[tokio::test]
+    async fn test_missing_spender() {
+        let permit_signer = PermitEvmSigner {
+            signer: PrivateKeySigner::random(),
+            asset: UsdcBaseSepolia,
+            nonce: 0,
+        };
+
+        let result = permit_signer
+            .sign(&selection(Some(
+                json!({ "name": "USD Coin", "version": "2" }),
+            )))
+            .await;
+
+        assert!(matches!(result, Err(PermitEvmSignError::MissingExtra)));
+    }
+
+    #[tokio::test]
+    async fn test_missing_domain() {
+        let permit_signer = PermitEvmSigner {
+            signer: PrivateKeySigner::random(),
+            asset: UsdcBaseSepolia,
+            nonce: 0,
+        };
+
+        let result = permit_signer
+            .sign(&selection(Some(json!({
+                "spender": "0x857b06519E91e3A54538791bDbb0E22373e36b66",
+            }))))
+            .await;
+
+        assert!(matches!(result, Err(PermitEvmSignError::MissingExtra)));
+    }
+}
@@ -0,0 +1,462 @@
+//! A registry of scheme signers, dispatching by the scheme and network of payment requirements.
+//!
+//! Buyers that may be asked to pay with any of several schemes register one [`SchemeSigner`]
+//! per scheme and network, then sign whichever [`PaymentRequirements`] the server offers:
+//!
+//! ```
+//! use alloy::signers::local::PrivateKeySigner;
+//! use x402_kit::{
+//!     networks::evm::{ExplicitEvmNetwork, assets::UsdcBaseSepolia, networks::BaseSepolia},
+//!     schemes::exact_evm_signer::ExactEvmSigner,
+//!     signer_registry::SignerRegistry,
+//!     types::SystemClock,
+//! };
+//!
+//! let mut registry = SignerRegistry::new();
+//! registry.register(
+//!     BaseSepolia::NETWORK,
+//!     ExactEvmSigner {
+//!         signer: PrivateKeySigner::random(),
+//!         asset: UsdcBaseSepolia,
+//!         clock: SystemClock,
+//!     },
+//! );
+//!
+//! assert!(registry.contains("exact", "eip155:84532"));
+//! ```
+
+use std::{collections::HashMap, fmt::Debug, pin::Pin};
+
+use serde::Serialize;
+
+use crate::{
+    core::{Address, NetworkFamily, PaymentSelection, Resource, Scheme, SchemeSigner},
+    transport::{PaymentPayload, PaymentRequired, PaymentRequirements, PaymentResource},
+    types::{AnyJson, Record, SchemeName, X402V2},
+};
+
+/// Errors signing payment requirements with a [`SignerRegistry`].
+#[derive(Debug, thiserror::Error)]
+pub enum SignerRegistryError {
+    /// No signer is registered for the scheme and network of the requirements.
+    #[error("No signer registered for scheme '{scheme}' on network '{network}'")]
+    NoSignerFor { scheme: SchemeName, network: String },
+    /// An address of the requirements is not valid for the network family of the signer.
+    #[error("Invalid {field} address: {value}")]
+    InvalidAddress { field: &'static str, value: String },
+    /// The timeout of the requirements exceeds the [`max_timeout_seconds`](SignerRegistry::max_timeout_seconds)
+    /// of the registry.
+    #[error("Payment timeout of {max_timeout_seconds}s exceeds the maximum of {cap}s")]
+    TimeoutTooLong { max_timeout_seconds: u64, cap: u64 },
+    /// The signer failed to sign the payment.
+    #[error("Signer error: {0}")]
+    Signer(String),
+    /// The signed payload could not be serialized.
+    #[error("Serde JSON error: {0}")]
+    SerdeJsonError(#[from] serde_json::Error),
+}
+
+/// The future returned by [`ErasedSigner::sign_requirements`].
+pub type SignFuture<'a> =
+    Pin<Box<dyn Future<Output = Result<AnyJson, SignerRegistryError>> + Send + 'a>>;
+
+/// An object-safe [`SchemeSigner`], signing untyped payment requirements into a JSON payload.
+///
+/// Implemented for every [`ErasedSchemeSigner`]; there is usually no need to implement it directly.
+/// Signers are `Send + Sync` and sign on any thread, so a registry can be shared across tasks.
+pub trait ErasedSigner: Send + Sync {
+    /// Sign the payment requirements for the resource, producing the scheme payload as JSON.
+    fn sign_requirements<'a>(
+        &'a self,
+        requirements: &'a PaymentRequirements,
+        resource: &'a PaymentResource,
+    ) -> SignFuture<'a>;
+}
+
+/// Wraps a typed [`SchemeSigner`] as an [`ErasedSigner`].
+///
+/// The `pay_to` and `asset` of the requirements are parsed as addresses of type `A`.
+pub struct ErasedSchemeSigner<S, A> {
+    signer: S,
+    _address: std::marker::PhantomData<fn() -> A>,
+}
+
+impl<S, A> ErasedSchemeSigner<S, A> {
+    /// Wrap a typed signer.
+    pub fn new(signer: S) -> Self {
+        ErasedSchemeSigner {
+            signer,
+            _address: std::marker::PhantomData,
+        }
+    }
+}
+
+impl<S, A> ErasedSigner for ErasedSchemeSigner<S, A>
+where
+    S: SchemeSigner<A> + Send + Sync,
+    A: Address<Network = <S::Scheme as Scheme>::Network> + Send + Sync,
+    <S::Scheme as Scheme>::Payload: Serialize,
+{
+    fn sign_requirements<'a>(
+        &'a self,
+        requirements: &'a PaymentRequirements,
+        resource: &'a PaymentResource,
+    ) -> SignFuture<'a> {
+        Box::pin(async move {
+            let parse = |field: &'static str, value: &str| {
+                value
+                    .parse::<A>()
+                    .map_err(|_| SignerRegistryError::InvalidAddress {
+                        field,
+                        value: value.to_string(),
+                    })
+            };
+
+            let selection = PaymentSelection {
+                pay_to: parse("pay_to", &requirements.pay_to)?,
+                asset: parse("asset", &requirements.asset)?,
+                amount: requirements.amount,
+                max_timeout_seconds: requirements.max_timeout_seconds,
+                extra: requirements.extra.clone(),
+                resource: Resource {
+                    url: resource.url.clone(),
+                    description: resource.description.clone(),
+                    mime_type: resource.mime_type.clone(),
+                    output_schema: None,
+                },
+                extensions: Record::new(),
+            };
+
+            let payload = self
+                .signer
+                .sign(&selection)
+                .await
+                .map_err(|err| SignerRegistryError::Signer(err.to_string()))?;
+
+            Ok(serde_json::to_value(payload)?)
+        })
+    }
+}
+
+/// Signers keyed by scheme name and CAIP-2 network ID.
+///
+/// Register typed signers with [`register`](SignerRegistry::register), then
+/// [`sign`](SignerRegistry::sign) any payment requirements offered by a server with the signer
+/// of their scheme and network.
+#[derive(Default)]
+pub struct SignerRegistry {
+    signers: HashMap<(SchemeName, String), Box<dyn ErasedSigner>>,
+    max_timeout_seconds: Option<u64>,
+}
+
+impl Debug for SignerRegistry {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SignerRegistry")
+            .field("signers", &self.signers.keys().collect::<Vec<_>>())
+            .field("max_timeout_seconds", &self.max_timeout_seconds)
+            .finish()
+    }
+}
+
+impl SignerRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Register a typed signer for its scheme on the given network.
+    ///
+    /// Replaces any signer previously registered for the same scheme and network.
+    pub fn register<S, A>(
+        &mut self,
+        network: <S::Scheme as Scheme>::Network,
+        signer: S,
+    ) -> &mut Self
+    where
+        S: SchemeSigner<A> + Send + Sync + 'static,
+        A: Address<Network = <S::Scheme as Scheme>::Network> + Send + Sync + 'static,
+        <S::Scheme as Scheme>::Payload: Serialize,
+    {
+        self.register_erased(
+            SchemeName::from_static(<S::Scheme as Scheme>::SCHEME_NAME),
+            network.network_id(),
+            ErasedSchemeSigner::<S, A>::new(signer),
+        )
+    }
+
+    /// Register an erased signer for the given scheme name and CAIP-2 network ID.
+    ///
+    /// Replaces any signer previously registered for the same scheme and network.
+    pub fn register_erased(
+        &mut self,
+        scheme: impl Into<SchemeName>,
+        network: impl Into<String>,
+        signer: impl ErasedSigner + 'static,
+    ) -> &mut Self {
+        self.signers
+            .insert((scheme.into(), network.into()), Box::new(signer));
+        self
+    }
+
+    /// Refuse to sign requirements whose `max_timeout_seconds` exceeds the given cap.
+    ///
+    /// Authorizations stay valid for the timeout, so a server offering e.g. `60_000` seconds by
+    /// mistake would otherwise be handed a payment redeemable for hours.
+    pub fn max_timeout_seconds(&mut self, cap: u64) -> &mut Self {
+        self.max_timeout_seconds = Some(cap);
+        self
+    }
+
+    /// Whether a signer is registered for the scheme and network.
+    pub fn contains(&self, scheme: &str, network: &str) -> bool {
+        self.signers
+            .contains_key(&(SchemeName::from(scheme), network.to_string()))
+    }
+
+    /// Sign the payment requirements with the signer registered for their scheme and network.
+    ///
+    /// The resource is the one of the `PaymentRequired` response offering the requirements.
+    /// Returns [`SignerRegistryError::NoSignerFor`] if no signer is registered, and
+    /// [`SignerRegistryError::TimeoutTooLong`] if the timeout exceeds the registry's cap.
+    pub async fn sign(
+        &self,
+        requirements: &PaymentRequirements,
+        resource: &PaymentResource,
+    ) -> Result<PaymentPayload, SignerRegistryError> {
+        if let Some(cap) = self.max_timeout_seconds
+            && requirements.max_timeout_seconds > cap
+        {
+            return Err(SignerRegistryError::TimeoutTooLong {
+                max_timeout_seconds: requirements.max_timeout_seconds,
+                cap,
+            });
+        }
+
+        let signer = self
+            .signers
+            .get(&(requirements.scheme.clone(), requirements.network.clone()))
+            .ok_or_else(|| SignerRegistryError::NoSignerFor {
+                scheme: requirements.scheme.clone(),
+                network: requirements.network.clone(),
+            })?;
+
+        let payload = signer.sign_requirements(requirements, resource).await?;
+
+        Ok(PaymentPayload {
+            x402_version: X402V2,
+            resource: resource.clone(),
+            accepted: requirements.clone(),
+            payload,
+            extensions: Record::new(),
+        })
+    }
+
+    /// Sign the payment again for a challenge rejecting it as
+    /// [stale](PaymentRequired::STALE_REQUIREMENTS), e.g. after the server raised its price.
+    ///
+    /// Re-reads the `accepts` of the challenge for requirements with the scheme, network and asset
+    /// of the rejected payment, and signs those. Returns `None` if the challenge has another error
+    /// code or no such requirements, in which case the payment should not be retried.
+    ///
+    /// Retry at most once per payment, so that a server rejecting every payment as stale cannot
+    /// keep the buyer signing.
+    pub async fn resign_stale(
+        &self,
+        rejected: &PaymentPayload,
+        challenge: &PaymentRequired,
+    ) -> Option<Result<PaymentPayload, SignerRegistryError>> {
+        if challenge.error_code() != Some(PaymentRequired::STALE_REQUIREMENTS) {
+            return None;
+        }
+
+        let previous = &rejected.accepted;
+        let requirements = challenge.accepts.as_ref().iter().find(|requirements| {
+            requirements.scheme == previous.scheme
+                && requirements.network == previous.network
+                && requirements.asset == previous.asset
+        })?;
+
+        Some(self.sign(requirements, &challenge.resource).await)
+    }
+}
+
+#[cfg(all(test, feature = "evm-signer"))]
+mod tests {
+    use alloy::signers::local::PrivateKeySigner;
+    use alloy_core::sol_types::{SolStruct, eip712_domain};
+    use alloy_primitives::address;
+    use url::Url;
+
+    use crate::{
+        networks::evm::{
+            ExplicitEvmAsset, ExplicitEvmNetwork, assets::UsdcBaseSepolia, networks::BaseSepolia,
+        },
+        schemes::{
+            exact_evm::{ExactEvm, ExactEvmPayload},
+            exact_evm_signer::{Eip3009Authorization, ExactEvmSigner},
+        },
+        types::{AmountValue, SystemClock},
+    };
+
+    use super::*;
+
+    fn resource() -> PaymentResource {
+        PaymentResource {
+            url: Url::parse("https://example.com/resource").unwrap(),
+            description: "Protected resource".to_string(),
+            mime_type: "application/json".to_string(),
+        }
+    }
+
+    fn requirements() -> PaymentRequirements {
+        ExactEvm::builder()
+            .asset(UsdcBaseSepolia)
+            .amount(1000)
+            .pay_to(address!("0x3CB9B3bBfde8501f411bB69Ad3DC07908ED0dE20"))
+            .build()
+            .into()
+    }
+
+    fn registry() -> (SignerRegistry, PrivateKeySigner) {
+        let signer = PrivateKeySigner::random();
+        let mut registry = SignerRegistry::new();
+        registry.register(
+            BaseSepolia::NETWORK,
+            ExactEvmSigner {
+                signer: signer.clone(),
+                asset: UsdcBaseSepolia,
+                clock: SystemClock,
+            },
+        );
+
+        (registry, signer)
+    }
+
+    #[tokio::test]
+    async fn signs_matching_requirements() {
+        let (registry, signer) = registry();
+
+        let payload = registry.sign(&requirements(), &resource()).await.unwrap();
+
+        assert_eq!(payload.accepted, requirements());
+        assert_eq!(payload.resource.url, resource().url);
+
+        let payload: ExactEvmPayload = serde_json::from_value(payload.payload).unwrap();
+        assert_eq!(payload.authorization.value, requirements().amount);
+
+        let domain = eip712_domain!(
+            name: "USD Coin",
+            version: "2",
+            chain_id: BaseSepolia::NETWORK.chain_id,
+            verifying_contract: UsdcBaseSepolia::ASSET.address.0,
+        );
+        let recovered = payload
+            .signature
+            .0
+            .recover_address_from_prehash(
+                &Eip3009Authorization::from(payload.authorization).eip712_signing_hash(&domain),
+            )
+            .unwrap();
+        assert_eq!(recovered, signer.address());
+    }
+
+    #[tokio::test]
+    async fn signs_on_spawned_task() {
+        let (registry, _) = registry();
+        let registry = std::sync::Arc::new(registry);
+
+        let payload =
+            tokio::spawn(async move { registry.sign(&requirements(), &resource()).await })
+                .await
+                .unwrap()
+                .unwrap();
+
+        assert_eq!(payload.accepted, requirements());
+    }
+
+    #[tokio::test]
+    async fn rejects_unmatched_requirements() {
+        let (registry, _) = registry();
+        let mut other = requirements();
+        other.network = "eip155:8453".to_string();
+
+        let err = registry.sign(&other, &resource()).await.unwrap_err();
+
+        assert!(matches!(
+            err,
+            SignerRegistryError::NoSignerFor { ref scheme, ref network }
+                if scheme == "exact" && network == "eip155:8453"
+        ));
+    }
+
+    #[tokio::test]
+    async fn rejects_malformed_pay_to() {
+        let (registry, _) = registry();
+        let mut malformed = requirements();
+        malformed.pay_to = "0x3CB9B3bBfde8501f411bB69Ad3DC07908ED0dE2".to_string();
+
+        let err = registry.sign(&malformed, &resource()).await.unwrap_err();
+
+        // Reported as the invalid field, not as requirements without a signer
+        assert!(matches!(
+            err,
+            SignerRegistryError::InvalidAddress { field: "pay_to", ref value }
+                if *value == malformed.pay_to
+        ));
+        assert_eq!(
+            err.to_string(),
+            "Invalid pay_to address: 0x3CB9B3bBfde8501f411bB69Ad3DC07908ED0dE2"
+        );
+    }
+
+    #[tokio::test]
+    async fn refuses_timeout_above_cap() {
+        let (mut registry, _) = registry();
+        registry.max_timeout_seconds(600);
+
+        let mut long = requirements();
+        long.max_timeout_seconds = 60_000;
+        let err = registry.sign(&long, &resource()).await.unwrap_err();
+        assert!(matches!(
+            err,
+            SignerRegistryError::TimeoutTooLong {
+                max_timeout_seconds: 60_000,
+                cap: 600
+            }
+        ));
+
+        let mut capped = requirements();
+        capped.max_timeout_seconds = 600;
+        registry.sign(&capped, &resource()).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn resigns_stale_payment_at_new_price() {
+        let (registry, _) = registry();
+        let rejected = registry.sign(&requirements(), &resource()).await.unwrap();
+
+        let mut raised = requirements();
+        raised.amount = AmountValue(2000);
+        let challenge = PaymentRequired::builder()
+            .error("PaymentRequirements in payload not accepted")
+            .error_code(PaymentRequired::STALE_REQUIREMENTS)
+            .resource(resource())
+            .accepts(raised.clone())
+            .build();
+
+        let retried = registry
+            .resign_stale(&rejected, &challenge)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(retried.accepted, raised);
+        let payload: ExactEvmPayload = serde_json::from_value(retried.payload).unwrap();
+        assert_eq!(payload.authorization.value, AmountValue(2000));
+
+        // Other rejections are not retried
+        let other = PaymentRequired {
+            error_code: None,
+            ..challenge
+        };
+        assert!(registry.resign_stale(&rejected, &other).await.is_none());
+    }
+}
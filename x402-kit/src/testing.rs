@@ -0,0 +1,305 @@
+//! Test utilities for paywalled applications.
+//!
+//! Re-exports the [`MockFacilitator`] and request helpers of `x402_paywall::testing`, and adds
+//! helpers fabricating `PAYMENT-SIGNATURE` headers for the exact EVM and SVM schemes, so that
+//! the `402` and `200` paths of an application can be tested without any chain or facilitator.
+//!
+//! Available with the `test-utils` feature.
+
+use std::time::SystemTime;
+
+use alloy_core::sol_types::{SolStruct, eip712_domain};
+use alloy_signer::SignerSync;
+use alloy_signer_local::PrivateKeySigner;
+use base64::{Engine, prelude::BASE64_STANDARD};
+use serde::Deserialize;
+use solana_pubkey::Pubkey;
+
+pub use x402_paywall::testing::*;
+
+use crate::{
+    core::Resource,
+    networks::{
+        evm::{EvmAddress, EvmSignature},
+        svm::SvmAddress,
+    },
+    schemes::{
+        exact_evm::{ExactEvmAuthorization, ExactEvmPayload, Nonce, TimestampSeconds},
+        exact_evm_signer::Eip3009Authorization,
+        exact_svm::ExplicitSvmPayload,
+    },
+    transport::{PaymentPayload, PaymentRequirements, PaymentResource},
+    types::{Base64EncodedHeader, Record, X402V2},
+};
+
+/// Sign a `PAYMENT-SIGNATURE` header for exact EVM payment requirements with a random key.
+///
+/// The EIP-712 domain is built from the requirements: the chain ID from the `eip155:` network,
+/// the verifying contract from the asset, and the name and version from `extra`.
+///
+/// # Panics
+///
+/// Panics if the network, asset or `pay_to` of the requirements are not valid EVM identifiers.
+pub fn signed_header_for(
+    accepts: &PaymentRequirements,
+    resource: &Resource,
+) -> (Base64EncodedHeader, PrivateKeySigner) {
+    let signer = PrivateKeySigner::random();
+
+    let now = SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default();
+    let payload = signed_payload_for(
+        accepts,
+        &signer,
+        TimestampSeconds(now.saturating_sub(300)),
+        TimestampSeconds(now + accepts.max_timeout_seconds),
+    );
+
+    (header_for(accepts, resource, payload), signer)
+}
+
+/// Sign an exact EVM payload for the requirements, valid in the given time window.
+///
+/// # Panics
+///
+/// Panics if the network, asset or `pay_to` of the requirements are not valid EVM identifiers.
+pub(crate) fn signed_payload_for(
+    accepts: &PaymentRequirements,
+    signer: &PrivateKeySigner,
+    valid_after: TimestampSeconds,
+    valid_before: TimestampSeconds,
+) -> ExactEvmPayload {
+    #[derive(Deserialize, Default)]
+    struct Eip712DomainExtra {
+        name: String,
+        version: String,
+    }
+
+    let chain_id = accepts
+        .network
+        .strip_prefix("eip155:")
+        .and_then(|id| id.parse::<u64>().ok())
+        .expect("network should be an eip155 network");
+    let asset: EvmAddress = accepts
+        .asset
+        .parse()
+        .expect("asset should be an EVM address");
+    let pay_to: EvmAddress = accepts
+        .pay_to
+        .parse()
+        .expect("pay_to should be an EVM address");
+    let domain_info = accepts
+        .extra
+        .as_ref()
+        .and_then(|extra| serde_json::from_value::<Eip712DomainExtra>(extra.clone()).ok())
+        .unwrap_or_default();
+
+    let authorization = ExactEvmAuthorization {
+        from: EvmAddress(signer.address()),
+        to: pay_to,
+        value: accepts.amount,
+        valid_after,
+        valid_before,
+        nonce: Nonce(rand::random()),
+    };
+
+    let domain = eip712_domain!(
+        name: domain_info.name,
+        version: domain_info.version,
+        chain_id: chain_id,
+        verifying_contract: asset.0,
+    );
+    let hash = Eip3009Authorization::from(authorization.clone()).eip712_signing_hash(&domain);
+    let signature = signer
+        .sign_hash_sync(&hash)
+        .expect("local signing should succeed");
+
+    ExactEvmPayload {
+        signature: EvmSignature(signature),
+        authorization,
+    }
+}
+
+/// Build a `PAYMENT-SIGNATURE` header for exact SVM payment requirements from a random payer.
+///
+/// The payload is an unsigned but well-formed legacy transaction: a zeroed signature for the payer,
+/// the payer, `pay_to` and asset accounts, a zeroed blockhash and no instructions. It is only meant
+/// for facilitators that do not inspect the transaction, such as the [`MockFacilitator`].
+///
+/// # Panics
+///
+/// Panics if the asset or `pay_to` of the requirements are not valid SVM addresses.
+pub fn svm_header_for(
+    accepts: &PaymentRequirements,
+    resource: &Resource,
+) -> (Base64EncodedHeader, SvmAddress) {
+    let payer = SvmAddress(Pubkey::new_from_array(rand::random()));
+    let pay_to: SvmAddress = accepts
+        .pay_to
+        .parse()
+        .expect("pay_to should be an SVM address");
+    let asset: SvmAddress = accepts
+        .asset
+        .parse()
+        .expect("asset should be an SVM address");
+
+    let mut transaction = Vec::new();
+    // One zeroed signature, for the payer
+    transaction.push(1);
+    transaction.extend([0; 64]);
+    // Message header: 1 required signature, 0 read-only signed, 1 read-only unsigned account
+    transaction.extend([1, 0, 1]);
+    // Account keys
+    transaction.push(3);
+    for account in [payer, pay_to, asset] {
+        transaction.extend(account.0.to_bytes());
+    }
+    // Recent blockhash
+    transaction.extend([0; 32]);
+    // Instructions
+    transaction.push(0);
+
+    let payload = ExplicitSvmPayload {
+        transaction: BASE64_STANDARD.encode(transaction),
+    };
+
+    (header_for(accepts, resource, payload), payer)
+}
+
+fn header_for(
+    accepts: &PaymentRequirements,
+    resource: &Resource,
+    payload: impl serde::Serialize,
+) -> Base64EncodedHeader {
+    let payload = PaymentPayload {
+        x402_version: X402V2,
+        resource: PaymentResource::from(resource.clone()),
+        accepted: accepts.clone(),
+        payload: serde_json::to_value(payload).expect("payload should serialize"),
+        extensions: Record::new(),
+    };
+
+    Base64EncodedHeader::try_from(payload).expect("payload should encode")
+}
+
+#[cfg(test)]
+mod tests {
+    use alloy_primitives::address;
+    use http::StatusCode;
+    use serde_json::json;
+    use solana_pubkey::pubkey;
+    use x402_paywall::{HttpResponse, paywall::PayWall};
+
+    use crate::{
+        networks::{evm::assets::UsdcBaseSepolia, svm::assets::UsdcSolanaDevnet},
+        schemes::{exact_evm::ExactEvm, exact_svm::ExactSvm},
+    };
+
+    use super::*;
+
+    async fn handle(
+        paywall: PayWall<MockFacilitator>,
+        request: http::Request<()>,
+    ) -> Result<http::Response<()>, StatusCode> {
+        paywall
+            .handle_payment(request, |_| async { http::Response::new(()) })
+            .await
+            .map_err(|err| err.status)
+    }
+
+    #[tokio::test]
+    async fn test_evm_payment_paths() {
+        let accepts: PaymentRequirements = ExactEvm::builder()
+            .asset(UsdcBaseSepolia)
+            .amount(1000)
+            .pay_to(address!("0x3CB9B3bBfde8501f411bB69Ad3DC07908ED0dE20"))
+            .build()
+            .into();
+        let paywall = PayWall::builder()
+            .facilitator(MockFacilitator::default())
+            .accepts(accepts.clone())
+            .resource(resource())
+            .build();
+
+        let unpaid = http::Request::builder().body(()).unwrap();
+        assert_eq!(
+            handle(paywall.clone(), unpaid).await.unwrap_err(),
+            StatusCode::PAYMENT_REQUIRED
+        );
+
+        let (header, signer) = signed_header_for(&accepts, &resource());
+        let paid = request_with_payment(http::Request::builder(), &header)
+            .body(())
+            .unwrap();
+        let response = handle(paywall, paid).await.unwrap();
+        assert!(response.is_success());
+        assert!(response.headers().contains_key("payment-response"));
+
+        // The header carries a valid signature of the signer
+        let payload = PaymentPayload::try_from(header).unwrap();
+        let payload: ExactEvmPayload = serde_json::from_value(payload.payload).unwrap();
+        let domain = eip712_domain!(
+            name: "USD Coin",
+            version: "2",
+            chain_id: 84532,
+            verifying_contract: address!("0x036CbD53842c5426634e7929541eC2318f3dCF7e"),
+        );
+        let recovered = payload
+            .signature
+            .0
+            .recover_address_from_prehash(
+                &Eip3009Authorization::from(payload.authorization.clone())
+                    .eip712_signing_hash(&domain),
+            )
+            .unwrap();
+        assert_eq!(recovered, signer.address());
+        assert_eq!(payload.authorization.from.0, signer.address());
+    }
+
+    #[tokio::test]
+    async fn test_svm_payment_paths() {
+        let accepts: PaymentRequirements = ExactSvm::builder()
+            .asset(UsdcSolanaDevnet)
+            .amount(1000)
+            .pay_to(pubkey!("Ge3jkza5KRfXvaq3GELNLh6V1pjjdEKNpEdGXJgjjKUR"))
+            .build()
+            .into();
+        let facilitator = MockFacilitator {
+            supported: serde_json::from_value(json!({
+                "kinds": [
+                    { "x402Version": 2, "scheme": "exact", "network": accepts.network }
+                ],
+                "extensions": [],
+                "signers": {}
+            }))
+            .unwrap(),
+            ..Default::default()
+        };
+        let paywall = PayWall::builder()
+            .facilitator(facilitator)
+            .accepts(accepts.clone())
+            .resource(resource())
+            .build();
+
+        let unpaid = http::Request::builder().body(()).unwrap();
+        assert_eq!(
+            handle(paywall.clone(), unpaid).await.unwrap_err(),
+            StatusCode::PAYMENT_REQUIRED
+        );
+
+        let (header, payer) = svm_header_for(&accepts, &resource());
+        let paid = request_with_payment(http::Request::builder(), &header)
+            .body(())
+            .unwrap();
+        let response = handle(paywall, paid).await.unwrap();
+        assert!(response.is_success());
+
+        let payload = PaymentPayload::try_from(header).unwrap();
+        let payload: ExplicitSvmPayload = serde_json::from_value(payload.payload).unwrap();
+        let transaction = BASE64_STANDARD.decode(payload.transaction).unwrap();
+        assert_eq!(transaction.len(), 1 + 64 + 3 + 1 + 3 * 32 + 32 + 1);
+        assert_eq!(&transaction[69..101], payer.0.to_bytes().as_slice());
+    }
+}
@@ -0,0 +1,63 @@
+use alloy_primitives::address;
+use solana_pubkey::pubkey;
+use x402_kit::{
+    accepts,
+    networks::{evm::assets::UsdcBaseSepolia, svm::assets::UsdcSolanaDevnet},
+    schemes::{exact_evm::ExactEvm, exact_svm::ExactSvm},
+    transport::{Accepts, PaymentRequirements},
+};
+
+fn evm() -> PaymentRequirements {
+    ExactEvm::builder()
+        .amount(1000)
+        .asset(UsdcBaseSepolia)
+        .pay_to(address!("0x3CB9B3bBfde8501f411bB69Ad3DC07908ED0dE20"))
+        .build()
+        .into()
+}
+
+fn svm() -> PaymentRequirements {
+    ExactSvm::builder()
+        .amount(2000)
+        .asset(UsdcSolanaDevnet)
+        .pay_to(pubkey!("Ge3jkza5KRfXvaq3GELNLh6V1pjjdEKNpEdGXJgjjKUR"))
+        .build()
+        .into()
+}
+
+fn raw() -> PaymentRequirements {
+    PaymentRequirements {
+        network: "eip155:8453".to_string(),
+        ..evm()
+    }
+}
+
+#[test]
+fn accepts_macro_mixes_schemes_and_raw_requirements() {
+    let accepts = accepts![
+        ExactEvm::builder()
+            .amount(1000)
+            .asset(UsdcBaseSepolia)
+            .pay_to(address!("0x3CB9B3bBfde8501f411bB69Ad3DC07908ED0dE20"))
+            .build(),
+        ExactSvm::builder()
+            .amount(2000)
+            .asset(UsdcSolanaDevnet)
+            .pay_to(pubkey!("Ge3jkza5KRfXvaq3GELNLh6V1pjjdEKNpEdGXJgjjKUR"))
+            .build(),
+        raw(),
+    ];
+
+    assert_eq!(accepts.as_ref(), [evm(), svm(), raw()]);
+    assert_eq!(accepts, Accepts::new().push(evm()).push(svm()).push(raw()));
+}
+
+#[test]
+fn accepts_from_configured_payments() {
+    let configured = vec![evm(), svm(), raw()];
+
+    assert_eq!(
+        Accepts::from_payments(configured.clone()),
+        Accepts::from(configured)
+    );
+}
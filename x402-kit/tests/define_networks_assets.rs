@@ -169,3 +169,30 @@ fn test_define_network_family() {
     let address: MyAddress = "100".parse().unwrap();
     assert_eq!(address.to_string(), "100");
 }
+
+#[test]
+fn test_network_equality() {
+    use std::collections::HashSet;
+
+    use x402_kit::networks::{
+        evm::networks::{Base, Ethereum},
+        svm::networks::{Solana, SolanaDevnet},
+    };
+
+    assert_eq!(Base::NETWORK, Base::NETWORK);
+    assert_ne!(Base::NETWORK, Ethereum::NETWORK);
+    assert_eq!(
+        Base::NETWORK,
+        EvmNetwork {
+            name: "base-mainnet",
+            chain_id: 8453,
+            network_id: "eip155:8453",
+        }
+    );
+
+    assert_eq!(Solana::NETWORK, Solana::NETWORK);
+    assert_ne!(Solana::NETWORK, SolanaDevnet::NETWORK);
+
+    let networks: HashSet<EvmNetwork> = [Base::NETWORK, Base::NETWORK, Ethereum::NETWORK].into();
+    assert_eq!(networks.len(), 2);
+}
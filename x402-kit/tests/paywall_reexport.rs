@@ -0,0 +1,17 @@
+#![cfg(all(feature = "paywall", feature = "facilitator-client"))]
+
+use x402_kit::{
+    facilitator_client::StandardFacilitatorClient,
+    paywall::{errors::ErrorResponse, paywall::PayWall, processor::PaymentState},
+};
+
+#[test]
+fn test_paywall_reexport_paths() {
+    let names = [
+        std::any::type_name::<PayWall<StandardFacilitatorClient>>(),
+        std::any::type_name::<PaymentState>(),
+        std::any::type_name::<ErrorResponse>(),
+    ];
+
+    assert!(names.iter().all(|name| name.starts_with("x402_paywall::")));
+}
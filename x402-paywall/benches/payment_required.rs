@@ -0,0 +1,72 @@
+//! Benchmarks of the request processing hot path: unpaid requests answered with a
+//! `402 Payment Required`, and paid requests selecting the accepted payment requirements.
+//!
+//! Run with `cargo bench -p x402-paywall --features test-utils`.
+
+use std::hint::black_box;
+
+use criterion::{Criterion, criterion_group, criterion_main};
+use x402_core::{
+    transport::{Accepts, PaymentRequirements},
+    types::AmountValue,
+};
+use x402_paywall::{
+    paywall::PayWall,
+    testing::{self, MockFacilitator},
+};
+
+/// A paywall accepting several payment requirements, with the default ones last.
+fn paywall() -> PayWall<MockFacilitator> {
+    let accepts = (1..8)
+        .map(|i| PaymentRequirements {
+            amount: AmountValue(1000 * i),
+            network: format!("eip155:{i}"),
+            ..testing::requirements()
+        })
+        .chain([testing::requirements()])
+        .collect::<Accepts>();
+
+    PayWall::builder()
+        .facilitator(MockFacilitator::default())
+        .accepts(accepts)
+        .resource(testing::resource())
+        .build()
+}
+
+fn payment_required(c: &mut Criterion) {
+    let paywall = paywall();
+    let unpaid = || {
+        http::Request::builder()
+            .uri("https://example.com/resource")
+            .body(())
+            .unwrap()
+    };
+
+    c.bench_function("payment_required", |b| {
+        b.iter(|| black_box(paywall.process_request(unpaid()).err()))
+    });
+
+    c.bench_function("payment_required_into_response", |b| {
+        b.iter(|| {
+            let err = paywall.process_request(unpaid()).err().unwrap();
+            black_box(err.into_http_response::<Vec<u8>>())
+        })
+    });
+}
+
+fn process_paid_request(c: &mut Criterion) {
+    let paywall = paywall();
+    let header = testing::payment_header(testing::requirements());
+
+    c.bench_function("process_paid_request", |b| {
+        b.iter(|| {
+            let request = testing::request_with_payment(http::Request::builder(), &header)
+                .body(())
+                .unwrap();
+            black_box(paywall.process_request(request).is_ok())
+        })
+    });
+}
+
+criterion_group!(benches, payment_required, process_paid_request);
+criterion_main!(benches);
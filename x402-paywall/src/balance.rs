@@ -0,0 +1,110 @@
+//! Balance checks rejecting payments the payer obviously cannot fund, before verification.
+//!
+//! A [`BalanceChecker`] is consulted by [`RequestProcessor::check_balance`](crate::processor::RequestProcessor::check_balance)
+//! with the payload and the selected payment requirements, e.g. to read the payer's token balance
+//! from an RPC endpoint. This saves a facilitator round-trip for payments that would fail anyway.
+
+use std::{fmt::Debug, pin::Pin};
+
+use x402_core::transport::{PaymentPayload, PaymentRequirements};
+
+/// The future returned by [`BalanceChecker::check`].
+pub type BalanceFuture<'a> = Pin<Box<dyn Future<Output = Result<(), String>> + Send + 'a>>;
+
+/// A check that the payer can fund a payment.
+pub trait BalanceChecker: Debug + Send + Sync {
+    /// Check that the payer of the payload can fund the selected payment requirements.
+    ///
+    /// Returns the reason as an error if the payment cannot be funded. Checkers should return
+    /// `Ok(())` when they cannot tell, e.g. for unknown schemes or on RPC errors, so that the
+    /// facilitator has the final say.
+    fn check<'a>(
+        &'a self,
+        payload: &'a PaymentPayload,
+        selected: &'a PaymentRequirements,
+    ) -> BalanceFuture<'a>;
+}
+
+#[cfg(test)]
+mod tests {
+    use http::StatusCode;
+
+    use crate::{
+        errors::PaymentError,
+        paywall::PayWall,
+        testing::{self, MockFacilitator},
+    };
+
+    use super::*;
+
+    /// Reports every payer as funded up to the given amount.
+    #[derive(Debug)]
+    struct FixedBalance(u128);
+
+    impl BalanceChecker for FixedBalance {
+        fn check<'a>(
+            &'a self,
+            _payload: &'a PaymentPayload,
+            selected: &'a PaymentRequirements,
+        ) -> BalanceFuture<'a> {
+            Box::pin(async move {
+                if selected.amount.0 > self.0 {
+                    Err(format!(
+                        "Balance {} is below the amount {}",
+                        self.0, selected.amount
+                    ))
+                } else {
+                    Ok(())
+                }
+            })
+        }
+    }
+
+    fn paywall(balance: u128) -> PayWall<MockFacilitator> {
+        PayWall::builder()
+            .facilitator(MockFacilitator::default())
+            .accepts(testing::requirements())
+            .resource(testing::resource())
+            .skip_supported_update(true)
+            .balance_checker(FixedBalance(balance))
+            .build()
+    }
+
+    #[tokio::test]
+    async fn insufficient_balance_is_rejected_before_facilitator() {
+        let paywall = paywall(999);
+        let calls = paywall.facilitator.calls.clone();
+
+        let err = paywall
+            .handle_payment(testing::paid_request(testing::requirements()), |_| async {
+                http::Response::new(())
+            })
+            .await
+            .unwrap_err();
+
+        assert_eq!(err.status, StatusCode::BAD_REQUEST);
+        assert_eq!(
+            err.cause,
+            PaymentError::InsufficientFunds("Balance 999 is below the amount 1000".to_string())
+        );
+        assert_eq!(calls.supported(), 0);
+        assert_eq!(calls.verify(), 0);
+        assert_eq!(calls.settle(), 0);
+    }
+
+    #[tokio::test]
+    async fn sufficient_balance_settles() {
+        let paywall = paywall(1000);
+        let calls = paywall.facilitator.calls.clone();
+
+        paywall
+            .handle_payment(testing::paid_request(testing::requirements()), |_| async {
+                http::Response::new(())
+            })
+            .await
+            .unwrap();
+
+        assert_eq!(calls.verify(), 1);
+        assert_eq!(calls.settle(), 1);
+    }
+}
@@ -0,0 +1,202 @@
+//! Sanity caps on the amounts charged for known assets.
+//!
+//! The asset of [`PaymentRequirements`] is just an address, so a misplaced decimal point in a
+//! configured or computed price goes unnoticed. [`AmountCaps`] maps known assets to their decimals
+//! and a maximum in whole tokens, and [`PayWall::process_request`](crate::paywall::PayWall::process_request)
+//! rejects payments whose selected amount exceeds it.
+
+use x402_core::{
+    core::{Address, Asset},
+    transport::PaymentRequirements,
+};
+
+/// The maximum amount of an asset on a network.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AssetCap {
+    /// The network of the asset (CAIP-2 identifier).
+    pub network: String,
+    /// The address of the asset.
+    pub address: String,
+    /// The number of decimals the asset uses.
+    pub decimals: u8,
+    /// The maximum amount in whole tokens.
+    pub max_tokens: u64,
+}
+
+impl AssetCap {
+    /// The maximum amount in smallest units.
+    pub fn max_amount(&self) -> u128 {
+        10u128
+            .checked_pow(self.decimals.into())
+            .map_or(u128::MAX, |unit| {
+                unit.saturating_mul(self.max_tokens.into())
+            })
+    }
+
+    fn matches(&self, requirements: &PaymentRequirements) -> bool {
+        self.network == requirements.network && same_address(&self.address, &requirements.asset)
+    }
+}
+
+/// Per-asset caps on the amount of a payment, in whole tokens.
+///
+/// Payments in assets without a cap are not checked.
+///
+/// # Example
+///
+/// ```
+/// use x402_paywall::caps::AmountCaps;
+///
+/// // At most 10 USDC on Base Sepolia
+/// let caps = AmountCaps::new().cap_decimals(
+///     "eip155:84532",
+///     "0x036CbD53842c5426634e7929541eC2318f3dCF7e",
+///     6,
+///     10,
+/// );
+///
+/// assert_eq!(caps.caps[0].max_amount(), 10_000_000);
+/// ```
+///
+/// With `x402-kit`, the built-in assets can be capped directly, e.g.
+/// `AmountCaps::new().cap("eip155:8453", UsdcBase::ASSET, 10)`.
+#[derive(Debug, Clone, Default)]
+pub struct AmountCaps {
+    pub caps: Vec<AssetCap>,
+}
+
+impl AmountCaps {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Cap the amount of a known asset on the given network.
+    pub fn cap<A: Address>(
+        self,
+        network: impl Into<String>,
+        asset: Asset<A>,
+        max_tokens: u64,
+    ) -> Self {
+        self.cap_decimals(
+            network,
+            asset.address.to_string(),
+            asset.decimals,
+            max_tokens,
+        )
+    }
+
+    /// Cap the amount of the asset at the given address, using the given number of decimals.
+    ///
+    /// EVM addresses (`0x`-prefixed) are compared case-insensitively, other addresses are compared exactly.
+    pub fn cap_decimals(
+        mut self,
+        network: impl Into<String>,
+        address: impl Into<String>,
+        decimals: u8,
+        max_tokens: u64,
+    ) -> Self {
+        self.caps.push(AssetCap {
+            network: network.into(),
+            address: address.into(),
+            decimals,
+            max_tokens,
+        });
+        self
+    }
+
+    /// Check the amount of the given payment requirements against the cap of its asset.
+    ///
+    /// Returns the reason if the amount exceeds the cap.
+    pub fn check(&self, requirements: &PaymentRequirements) -> Result<(), String> {
+        match self.caps.iter().find(|cap| cap.matches(requirements)) {
+            Some(cap) if requirements.amount.0 > cap.max_amount() => Err(format!(
+                "Amount {} of asset {} on {} exceeds the cap of {} tokens",
+                requirements.amount.0, requirements.asset, requirements.network, cap.max_tokens
+            )),
+            _ => Ok(()),
+        }
+    }
+}
+
+fn same_address(a: &str, b: &str) -> bool {
+    if a.starts_with("0x") && b.starts_with("0x") {
+        a.eq_ignore_ascii_case(b)
+    } else {
+        a == b
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use http::StatusCode;
+    use x402_core::types::AmountValue;
+
+    use crate::{
+        errors::PaymentError,
+        paywall::PayWall,
+        testing::{self, MockFacilitator},
+    };
+
+    use super::*;
+
+    fn usdc_caps() -> AmountCaps {
+        AmountCaps::new().cap_decimals(
+            "eip155:84532",
+            "0x036cbd53842c5426634e7929541ec2318f3dcf7e",
+            6,
+            1,
+        )
+    }
+
+    fn paywall(requirements: PaymentRequirements) -> PayWall<MockFacilitator> {
+        PayWall::builder()
+            .facilitator(MockFacilitator::default())
+            .accepts(requirements)
+            .resource(testing::resource())
+            .amount_caps(usdc_caps())
+            .build()
+    }
+
+    #[test]
+    fn amount_within_cap_passes() {
+        let requirements = PaymentRequirements {
+            amount: AmountValue(1_000_000),
+            ..testing::requirements()
+        };
+        let paywall = paywall(requirements.clone());
+
+        let processor = paywall
+            .process_request(testing::paid_request(requirements))
+            .unwrap();
+        assert_eq!(processor.payment_state.amount(), AmountValue(1_000_000));
+    }
+
+    #[test]
+    fn amount_above_cap_is_rejected() {
+        let requirements = PaymentRequirements {
+            amount: AmountValue(1_000_001),
+            ..testing::requirements()
+        };
+        let paywall = paywall(requirements.clone());
+
+        let err = paywall
+            .process_request(testing::paid_request(requirements))
+            .err()
+            .unwrap();
+
+        assert_eq!(err.status, StatusCode::INTERNAL_SERVER_ERROR);
+        assert!(matches!(err.cause, PaymentError::Misconfigured(_)));
+        assert!(err.body.error.contains("exceeds the cap of 1 tokens"));
+    }
+
+    #[test]
+    fn uncapped_assets_are_not_checked() {
+        let requirements = PaymentRequirements {
+            network: "eip155:1".to_string(),
+            amount: AmountValue(u128::MAX),
+            ..testing::requirements()
+        };
+
+        assert_eq!(usdc_caps().check(&requirements), Ok(()));
+    }
+}
@@ -0,0 +1,409 @@
+//! Paywalls configured from a file or the environment, e.g. TOML, without recompiling.
+//!
+//! A [`PayWallConfigFile`] names the accepted assets by their symbol, e.g. `USDC`, which
+//! [`PayWall::from_config`] resolves against a [`KnownAssetRegistry`], such as the built-in
+//! assets of `x402_kit::networks::known_assets()`.
+//!
+//! # Example
+//!
+//! ```toml
+//! facilitator_url = "https://facilitator.example.com/"
+//! flow_timeout_seconds = 30
+//!
+//! [resource]
+//! url = "https://api.example.com/premium"
+//! description = "Premium content"
+//! mime_type = "application/json"
+//!
+//! [[accepts]]
+//! network = "eip155:8453"
+//! asset = "USDC"
+//! amount = "10000"
+//! pay_to = "0x3CB9B3bBfde8501f411bB69Ad3DC07908ED0dE20"
+//! max_timeout_seconds = 60
+//! # The EIP-712 domain of the asset, required by the `exact` scheme on EVM networks
+//! extra = { name = "USD Coin", version = "2" }
+//!
+//! [[accepts]]
+//! network = "solana:5eykt4UsFv8P8NJdTREpY1vzqKqZKvdp"
+//! asset = "USDC"
+//! amount = "10000"
+//! pay_to = "Ge3jkza5KRfXvaq3GELNLh6V1pjjdEKNpEdGXJgjjKUR"
+//! ```
+
+use std::fmt::Display;
+
+use serde::{Deserialize, Serialize};
+use url::Url;
+use x402_core::{
+    core::Resource,
+    facilitator::Facilitator,
+    transport::{Accepts, PaymentRequirements},
+    types::{AmountValue, AnyJson, KnownAssetRegistry, SchemeName},
+};
+
+use crate::paywall::PayWall;
+
+/// The configuration of a [`PayWall`], e.g. parsed from a TOML file, see the [module](self) docs.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PayWallConfigFile {
+    /// The URL of the facilitator, for building the facilitator passed to
+    /// [`PayWall::from_config`], e.g. a `FacilitatorClient`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub facilitator_url: Option<Url>,
+    /// The resource the paywall serves.
+    pub resource: ResourceConfig,
+    /// The accepted payments.
+    pub accepts: Vec<AcceptConfig>,
+    /// Maximum duration in seconds of the payment flow before settlement, see
+    /// [`PayWall::flow_timeout`]. Ignored without the `tokio` feature.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub flow_timeout_seconds: Option<u64>,
+}
+
+/// The [`Resource`] of a [`PayWallConfigFile`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ResourceConfig {
+    /// The URL of the resource.
+    pub url: Url,
+    /// Description of the resource.
+    #[serde(default)]
+    pub description: String,
+    /// MIME type of the resource, `application/json` by default.
+    #[serde(default = "default_mime_type")]
+    pub mime_type: String,
+}
+
+/// An accepted payment of a [`PayWallConfigFile`], in an asset named by its symbol.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AcceptConfig {
+    /// The payment scheme, `exact` by default.
+    #[serde(default = "default_scheme")]
+    pub scheme: SchemeName,
+    /// The network of the asset (CAIP-2 identifier).
+    pub network: String,
+    /// The symbol of the asset, e.g. `USDC`, compared case-insensitively.
+    pub asset: String,
+    /// The amount, in the asset's smallest units.
+    pub amount: AmountValue,
+    /// The address receiving the payment.
+    pub pay_to: String,
+    /// Seconds the payment authorization stays valid, 60 by default.
+    #[serde(default = "default_max_timeout_seconds")]
+    pub max_timeout_seconds: u64,
+    /// The scheme-specific `extra` of the payment requirements, e.g. the EIP-712 domain of the
+    /// asset for the `exact` scheme on EVM networks.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub extra: Option<AnyJson>,
+}
+
+fn default_mime_type() -> String {
+    "application/json".to_string()
+}
+
+fn default_scheme() -> SchemeName {
+    SchemeName::EXACT
+}
+
+fn default_max_timeout_seconds() -> u64 {
+    60
+}
+
+/// The reason a [`PayWallConfigFile`] does not describe a valid paywall.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ConfigError {
+    /// No asset is known on the network.
+    UnknownNetwork(String),
+    /// No asset with the symbol is known on the network.
+    UnknownAsset { network: String, symbol: String },
+    /// The `pay_to` address is not an address of the network.
+    InvalidAddress { network: String, address: String },
+    /// The resolved payment requirements are invalid, e.g. with a zero amount.
+    InvalidRequirements(String),
+}
+
+impl Display for ConfigError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ConfigError::UnknownNetwork(network) => {
+                write!(f, "No assets are known on network '{network}'")
+            }
+            ConfigError::UnknownAsset { network, symbol } => {
+                write!(f, "Unknown asset '{symbol}' on network '{network}'")
+            }
+            ConfigError::InvalidAddress { network, address } => {
+                write!(
+                    f,
+                    "Invalid pay_to address '{address}' on network '{network}'"
+                )
+            }
+            ConfigError::InvalidRequirements(reason) => {
+                write!(f, "Invalid payment requirements: {reason}")
+            }
+        }
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
+impl AcceptConfig {
+    /// Resolve the asset against the registry into payment requirements.
+    pub fn resolve(
+        &self,
+        known_assets: &KnownAssetRegistry,
+    ) -> Result<PaymentRequirements, ConfigError> {
+        let asset = known_assets
+            .find_symbol(&self.network, &self.asset)
+            .ok_or_else(|| {
+                if known_assets
+                    .assets
+                    .iter()
+                    .any(|a| a.network == self.network)
+                {
+                    ConfigError::UnknownAsset {
+                        network: self.network.clone(),
+                        symbol: self.asset.clone(),
+                    }
+                } else {
+                    ConfigError::UnknownNetwork(self.network.clone())
+                }
+            })?;
+
+        if !is_address_of(&self.network, &self.pay_to) {
+            return Err(ConfigError::InvalidAddress {
+                network: self.network.clone(),
+                address: self.pay_to.clone(),
+            });
+        }
+
+        PaymentRequirements::builder()
+            .scheme(self.scheme.clone())
+            .network(self.network.clone())
+            .amount(self.amount)
+            .asset(asset.address.clone())
+            .pay_to(self.pay_to.clone())
+            .max_timeout_seconds(self.max_timeout_seconds)
+            .maybe_extra(self.extra.clone())
+            .build()
+            .map_err(|err| ConfigError::InvalidRequirements(err.to_string()))
+    }
+}
+
+/// Whether the address is well-formed for the namespace of the network, e.g. `0x` and 40 hex
+/// digits on `eip155` networks. Addresses of other namespaces only need to be non-empty.
+fn is_address_of(network: &str, address: &str) -> bool {
+    match network.split_once(':').map(|(namespace, _)| namespace) {
+        Some("eip155") => address
+            .strip_prefix("0x")
+            .is_some_and(|hex| hex.len() == 40 && hex.bytes().all(|b| b.is_ascii_hexdigit())),
+        Some("solana") => {
+            (32..=44).contains(&address.len())
+                && address
+                    .bytes()
+                    .all(|b| b.is_ascii_alphanumeric() && !matches!(b, b'0' | b'O' | b'I' | b'l'))
+        }
+        _ => !address.is_empty(),
+    }
+}
+
+impl<F: Facilitator> PayWall<F> {
+    /// Build a paywall from its configuration, resolving the accepted assets against the registry.
+    ///
+    /// The registry also becomes the [`known_assets`](PayWall::known_assets) of the paywall.
+    /// Other settings keep their defaults, and can be changed on the returned paywall.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use x402_core::types::KnownAssetRegistry;
+    /// use x402_paywall::{config::PayWallConfigFile, paywall::PayWall};
+    /// # use x402_core::{facilitator::*, transport::*};
+    /// # #[derive(Clone)]
+    /// # struct MyFacilitator;
+    /// # impl Facilitator for MyFacilitator {
+    /// #     type Error = std::convert::Infallible;
+    /// #     async fn supported(&self) -> Result<SupportedResponse, Self::Error> { unimplemented!() }
+    /// #     async fn verify(&self, _: PaymentRequest) -> Result<VerifyResult, Self::Error> { unimplemented!() }
+    /// #     async fn settle(&self, _: PaymentRequest) -> Result<SettleResult, Self::Error> { unimplemented!() }
+    /// # }
+    ///
+    /// let config: PayWallConfigFile = serde_json::from_value(serde_json::json!({
+    ///     "resource": { "url": "https://api.example.com/premium" },
+    ///     "accepts": [{
+    ///         "network": "eip155:8453",
+    ///         "asset": "USDC",
+    ///         "amount": "10000",
+    ///         "pay_to": "0x3CB9B3bBfde8501f411bB69Ad3DC07908ED0dE20",
+    ///     }],
+    /// }))
+    /// .unwrap();
+    ///
+    /// let known_assets = KnownAssetRegistry::new().register_decimals(
+    ///     "eip155:8453",
+    ///     "0x833589fCD6eDb6E08f4c7C32D4f71b54bdA02913",
+    ///     6,
+    ///     "USDC",
+    /// );
+    ///
+    /// let paywall = PayWall::from_config(&config, MyFacilitator, known_assets).unwrap();
+    /// assert_eq!(paywall.accepts.len(), 1);
+    /// ```
+    pub fn from_config(
+        config: &PayWallConfigFile,
+        facilitator: F,
+        known_assets: KnownAssetRegistry,
+    ) -> Result<Self, ConfigError> {
+        let accepts = config
+            .accepts
+            .iter()
+            .map(|accept| accept.resolve(&known_assets))
+            .collect::<Result<Accepts, _>>()?;
+
+        let resource = Resource::builder()
+            .url(config.resource.url.clone())
+            .description(config.resource.description.clone())
+            .mime_type(config.resource.mime_type.clone())
+            .build();
+
+        #[allow(unused_mut)]
+        let mut paywall = PayWall::builder()
+            .facilitator(facilitator)
+            .resource(resource)
+            .accepts(accepts)
+            .known_assets(known_assets)
+            .build();
+        #[cfg(feature = "tokio")]
+        {
+            paywall.flow_timeout = config
+                .flow_timeout_seconds
+                .map(std::time::Duration::from_secs);
+        }
+
+        Ok(paywall)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use crate::testing::{self, MockFacilitator};
+
+    use super::*;
+
+    const CONFIG: &str = r#"
+        facilitator_url = "https://facilitator.example.com/"
+        flow_timeout_seconds = 30
+
+        [resource]
+        url = "https://example.com/resource"
+        description = "Protected resource"
+
+        [[accepts]]
+        network = "eip155:84532"
+        asset = "usdc"
+        amount = "1000"
+        pay_to = "0x3CB9B3bBfde8501f411bB69Ad3DC07908ED0dE20"
+        extra = { name = "USDC", version = "2" }
+    "#;
+
+    fn known_assets() -> KnownAssetRegistry {
+        KnownAssetRegistry::new()
+            .register_decimals(
+                "eip155:84532",
+                "0x036CbD53842c5426634e7929541eC2318f3dCF7e",
+                6,
+                "USDC",
+            )
+            .register_decimals(
+                "solana:EtWTRABZaYq6iMfeYKouRu166VU2xqa1",
+                "4zMMC9srt5Ri5X14GAgXhaHii3GnPAEERYPJgZJDncDU",
+                6,
+                "USDC",
+            )
+    }
+
+    fn config() -> PayWallConfigFile {
+        toml::from_str(CONFIG).unwrap()
+    }
+
+    #[tokio::test]
+    async fn paywall_from_toml_config() {
+        let config = config();
+        assert_eq!(
+            config.facilitator_url.as_ref().map(Url::as_str),
+            Some("https://facilitator.example.com/")
+        );
+        assert_eq!(config.accepts[0].scheme, SchemeName::EXACT);
+        assert_eq!(config.accepts[0].max_timeout_seconds, 60);
+        assert_eq!(config.resource.mime_type, "application/json");
+
+        let paywall =
+            PayWall::from_config(&config, MockFacilitator::default(), known_assets()).unwrap();
+        assert_eq!(paywall.accepts.as_ref(), [testing::requirements()]);
+        assert_eq!(paywall.resource, testing::resource());
+        #[cfg(feature = "tokio")]
+        assert_eq!(
+            paywall.flow_timeout,
+            Some(std::time::Duration::from_secs(30))
+        );
+
+        let response = paywall
+            .handle_payment(testing::paid_request(testing::requirements()), |_| async {
+                http::Response::new(())
+            })
+            .await
+            .unwrap();
+        assert_eq!(response.status(), http::StatusCode::OK);
+        assert!(response.headers().contains_key("payment-response"));
+    }
+
+    #[test]
+    fn config_round_trips() {
+        let config = config();
+        let json = serde_json::to_value(&config).unwrap();
+        assert_eq!(
+            json["accepts"][0]["extra"],
+            json!({ "name": "USDC", "version": "2" })
+        );
+        assert_eq!(
+            serde_json::from_value::<PayWallConfigFile>(json).unwrap(),
+            config
+        );
+    }
+
+    #[test]
+    fn reject_unresolvable_accepts() {
+        let resolve = |network: &str, asset: &str, pay_to: &str| {
+            let mut config = config();
+            config.accepts[0].network = network.to_string();
+            config.accepts[0].asset = asset.to_string();
+            config.accepts[0].pay_to = pay_to.to_string();
+            PayWall::from_config(&config, MockFacilitator::default(), known_assets())
+                .map(|_| ())
+                .unwrap_err()
+        };
+        let evm_payee = "0x3CB9B3bBfde8501f411bB69Ad3DC07908ED0dE20";
+
+        assert_eq!(
+            resolve("eip155:1", "USDC", evm_payee),
+            ConfigError::UnknownNetwork("eip155:1".to_string())
+        );
+        assert_eq!(
+            resolve("eip155:84532", "DAI", evm_payee).to_string(),
+            "Unknown asset 'DAI' on network 'eip155:84532'"
+        );
+        assert_eq!(
+            resolve("eip155:84532", "USDC", "0x3CB9B3bB"),
+            ConfigError::InvalidAddress {
+                network: "eip155:84532".to_string(),
+                address: "0x3CB9B3bB".to_string(),
+            }
+        );
+        assert!(matches!(
+            resolve("solana:EtWTRABZaYq6iMfeYKouRu166VU2xqa1", "USDC", evm_payee),
+            ConfigError::InvalidAddress { .. }
+        ));
+    }
+}
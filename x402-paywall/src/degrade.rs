@@ -0,0 +1,284 @@
+//! Graceful degradation of a paywall while its facilitator is unreachable.
+//!
+//! By default, every paid request fails with a `500 Internal Server Error` during a facilitator
+//! outage. Serving requests without a verified payment is a business decision, so a
+//! [`PayWall`](crate::paywall::PayWall) only degrades with an explicit
+//! [`facilitator_unreachable`](crate::paywall::PayWall::facilitator_unreachable) policy.
+//!
+//! A facilitator is unreachable when a call fails, e.g. on a connection error or a `5xx` status.
+//! Facilitators answering that a payment is invalid or failed are reachable, so those payments
+//! are rejected as usual.
+
+use std::sync::{
+    Arc, Mutex, PoisonError,
+    atomic::{AtomicUsize, Ordering},
+};
+
+use x402_core::{facilitator::SupportedResponse, types::AmountValue};
+
+/// What a paywall does while its facilitator is unreachable.
+///
+/// # Example
+///
+/// ```
+/// use x402_core::types::AmountValue;
+/// use x402_paywall::degrade::Degrade;
+///
+/// // Serve up to 10 concurrent requests of at most 0.01 USDC without verifying their payment
+/// let degrade = Degrade::AllowUnverified {
+///     max_amount: AmountValue(10_000),
+///     max_concurrent: 10,
+///     log: true,
+/// };
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Degrade {
+    /// Fail with a `503 Service Unavailable` instead of a `500 Internal Server Error`, so that
+    /// clients and load balancers treat the outage as transient.
+    RejectWith503,
+    /// Run the handler without verifying or settling the payment, recording it to the
+    /// [`ledger`](crate::paywall::PayWall::ledger) as a
+    /// [`liability`](crate::ledger::PaymentReceipt::liability).
+    ///
+    /// Only payments of at most `max_amount` are served, and at most `max_concurrent` of them at
+    /// once across the paywall and its clones. Other payments fail as usual. With `log`, each
+    /// unverified payment is logged as a warning with the `tracing` feature.
+    ///
+    /// The accepted payment requirements are filtered with the last supported payment kinds
+    /// fetched from the facilitator, as with [`UseCachedSupported`](Degrade::UseCachedSupported),
+    /// or not at all if none were fetched yet.
+    AllowUnverified {
+        max_amount: AmountValue,
+        max_concurrent: usize,
+        log: bool,
+    },
+    /// Filter the accepted payment requirements with the last supported payment kinds fetched from
+    /// the facilitator when fetching them fails.
+    ///
+    /// Verification and settlement still fail as usual, so this only helps when the `supported`
+    /// endpoint alone is down, or its failures are transient.
+    UseCachedSupported,
+}
+
+/// The last supported payment kinds fetched from the facilitator, to fall back on and to diff
+/// against the next ones, see [`PayWall::on_supported_change`](crate::paywall::PayWall::on_supported_change).
+///
+/// Shared by clones of the paywall, like the settlement keys.
+#[derive(Clone, Default)]
+pub(crate) struct SupportedCache(Arc<Mutex<Option<SupportedResponse>>>);
+
+impl SupportedCache {
+    pub(crate) fn get(&self) -> Option<SupportedResponse> {
+        self.0
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clone()
+    }
+
+    /// Cache the supported payment kinds, returning the previously cached ones.
+    pub(crate) fn replace(&self, supported: &SupportedResponse) -> Option<SupportedResponse> {
+        self.0
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .replace(supported.clone())
+    }
+}
+
+/// The number of unverified payments being served, see [`Degrade::AllowUnverified`].
+#[derive(Clone, Default)]
+pub(crate) struct UnverifiedSlots(Arc<AtomicUsize>);
+
+impl UnverifiedSlots {
+    /// Take one of the `max_concurrent` slots, or `None` if all are taken.
+    pub(crate) fn take(&self, max_concurrent: usize) -> Option<UnverifiedAccess> {
+        self.0
+            .fetch_update(Ordering::AcqRel, Ordering::Acquire, |taken| {
+                (taken < max_concurrent).then_some(taken + 1)
+            })
+            .ok()
+            .map(|_| UnverifiedAccess {
+                _slot: Arc::new(UnverifiedSlot(self.clone())),
+            })
+    }
+}
+
+/// Marks a payment served without verification or settlement while the facilitator was
+/// unreachable, see [`Degrade::AllowUnverified`].
+///
+/// Holds one of the `max_concurrent` slots until it and all its clones, e.g. the one in the
+/// request extensions, are dropped.
+#[derive(Clone)]
+pub struct UnverifiedAccess {
+    _slot: Arc<UnverifiedSlot>,
+}
+
+impl std::fmt::Debug for UnverifiedAccess {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("UnverifiedAccess")
+    }
+}
+
+/// Releases a slot of [`UnverifiedSlots`] when dropped.
+struct UnverifiedSlot(UnverifiedSlots);
+
+impl Drop for UnverifiedSlot {
+    fn drop(&mut self) {
+        self.0.0.fetch_sub(1, Ordering::AcqRel);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use http::StatusCode;
+
+    use crate::{
+        ledger::VecLedger,
+        paywall::PayWall,
+        testing::{self, MockFacilitator},
+    };
+
+    use super::*;
+
+    fn unavailable() -> MockFacilitator {
+        MockFacilitator {
+            unavailable: true,
+            ..Default::default()
+        }
+    }
+
+    fn allow_unverified(max_amount: u128, max_concurrent: usize) -> Degrade {
+        Degrade::AllowUnverified {
+            max_amount: AmountValue(max_amount),
+            max_concurrent,
+            log: true,
+        }
+    }
+
+    fn paywall(facilitator: MockFacilitator, degrade: Option<Degrade>) -> PayWall<MockFacilitator> {
+        PayWall::builder()
+            .facilitator(facilitator)
+            .accepts(testing::requirements())
+            .resource(testing::resource())
+            .maybe_on_facilitator_unreachable(degrade)
+            .build()
+    }
+
+    async fn pay(paywall: PayWall<MockFacilitator>) -> Result<http::Response<()>, StatusCode> {
+        paywall
+            .handle_payment(testing::paid_request(testing::requirements()), |_| async {
+                http::Response::new(())
+            })
+            .await
+            .map_err(|err| err.status)
+    }
+
+    #[tokio::test]
+    async fn outage_is_a_server_error_by_default() {
+        assert_eq!(
+            pay(paywall(unavailable(), None)).await.unwrap_err(),
+            StatusCode::INTERNAL_SERVER_ERROR
+        );
+    }
+
+    #[tokio::test]
+    async fn reject_with_503() {
+        assert_eq!(
+            pay(paywall(unavailable(), Some(Degrade::RejectWith503)))
+                .await
+                .unwrap_err(),
+            StatusCode::SERVICE_UNAVAILABLE
+        );
+    }
+
+    #[tokio::test]
+    async fn allow_unverified_runs_handler_and_records_liability() {
+        let facilitator = unavailable();
+        let calls = facilitator.calls.clone();
+        let ledger = Arc::new(VecLedger::new());
+        let paywall = PayWall::builder()
+            .facilitator(facilitator)
+            .accepts(testing::requirements())
+            .resource(testing::resource())
+            .on_facilitator_unreachable(allow_unverified(1000, 1))
+            .ledger(ledger.clone())
+            .build();
+
+        let response = pay(paywall).await.unwrap();
+
+        assert_eq!(response.status(), StatusCode::OK);
+        assert!(!response.headers().contains_key("payment-response"));
+        assert_eq!(calls.settle(), 0);
+
+        let entries = ledger.receipts();
+        assert_eq!(entries.len(), 1);
+        assert!(entries[0].unverified);
+        assert!(entries[0].transaction.is_empty());
+        assert_eq!(entries[0].amount, AmountValue(1000));
+    }
+
+    #[tokio::test]
+    async fn allow_unverified_is_bounded_by_amount() {
+        assert_eq!(
+            pay(paywall(unavailable(), Some(allow_unverified(999, 1))))
+                .await
+                .unwrap_err(),
+            StatusCode::INTERNAL_SERVER_ERROR
+        );
+    }
+
+    #[tokio::test]
+    async fn allow_unverified_is_bounded_by_concurrency() {
+        let paywall = paywall(unavailable(), Some(allow_unverified(1000, 1)));
+        let verify = || async {
+            paywall
+                .process_request(testing::paid_request(testing::requirements()))
+                .unwrap()
+                .verify()
+                .await
+        };
+
+        let first = verify().await.ok().unwrap();
+        assert!(first.payment_state.unverified.is_some());
+        let state = first.payment_state.clone();
+
+        let Err(err) = verify().await else {
+            panic!("all unverified payment slots should be taken");
+        };
+        assert_eq!(err.status, StatusCode::INTERNAL_SERVER_ERROR);
+
+        // The slot is held until every clone of the payment state is dropped
+        drop(first);
+        assert!(verify().await.is_err());
+        drop(state);
+        assert!(verify().await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn use_cached_supported() {
+        let paywall = paywall(
+            MockFacilitator::default(),
+            Some(Degrade::UseCachedSupported),
+        );
+        let mut down = paywall.clone();
+        down.facilitator.unavailable = true;
+
+        // Nothing cached yet
+        assert_eq!(
+            down.clone().update_accepts().await.unwrap_err().status,
+            StatusCode::INTERNAL_SERVER_ERROR
+        );
+
+        paywall.update_accepts().await.unwrap();
+        let updated = down.clone().update_accepts().await.unwrap();
+        assert_eq!(updated.accepts.as_ref(), [testing::requirements()]);
+
+        // Verification still fails as usual
+        assert_eq!(
+            pay(down).await.unwrap_err(),
+            StatusCode::INTERNAL_SERVER_ERROR
+        );
+    }
+}
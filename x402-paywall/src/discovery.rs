@@ -0,0 +1,227 @@
+//! Resource discovery for the resources served by a paywall.
+//!
+//! Facilitators with the `bazaar` extension discover resources by crawling their 402 responses.
+//! A [`DiscoveryDocument`] lists them explicitly instead, in the same shape as the facilitator's
+//! `/discovery/resources` listing, so that indexers can ingest all resources of a server at once.
+//!
+//! With the `axum` feature, [`discovery_routes`] serves the document at [`WELL_KNOWN_PATH`].
+
+use serde::Serialize;
+use x402_core::{
+    core::Resource,
+    facilitator::Facilitator,
+    transport::{Accepts, PaymentResource},
+    types::{OutputSchema, X402V2},
+};
+
+use crate::paywall::PayWall;
+
+/// The path the discovery document is served at.
+pub const WELL_KNOWN_PATH: &str = "/.well-known/x402";
+
+/// A resource served by this server and the payments it accepts.
+#[derive(Debug, Clone)]
+pub struct DiscoveryEntry {
+    /// The protected resource.
+    pub resource: Resource,
+    /// The accepted payment requirements.
+    pub accepts: Accepts,
+}
+
+impl From<(Resource, Accepts)> for DiscoveryEntry {
+    fn from((resource, accepts): (Resource, Accepts)) -> Self {
+        DiscoveryEntry { resource, accepts }
+    }
+}
+
+impl<F: Facilitator> From<&PayWall<F>> for DiscoveryEntry {
+    fn from(paywall: &PayWall<F>) -> Self {
+        DiscoveryEntry {
+            resource: paywall.resource.clone(),
+            accepts: paywall.advertised_accepts(),
+        }
+    }
+}
+
+/// The discovery document listing all resources of a server.
+///
+/// # Example
+///
+/// ```
+/// use x402_core::{core::Resource, transport::Accepts};
+/// use x402_paywall::discovery::DiscoveryDocument;
+///
+/// let resource = Resource::builder()
+///     .url("https://example.com/weather".parse().unwrap())
+///     .description("Weather report")
+///     .mime_type("application/json")
+///     .build();
+///
+/// let document = DiscoveryDocument::new([(resource, Accepts::new())]);
+/// let json = serde_json::to_value(&document).unwrap();
+///
+/// assert_eq!(json["items"][0]["resource"], "https://example.com/weather");
+/// assert_eq!(json["pagination"]["total"], 1);
+/// ```
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DiscoveryDocument {
+    pub x402_version: X402V2,
+    pub items: Vec<DiscoveryItem>,
+    pub pagination: DiscoveryPagination,
+}
+
+/// A single resource in a [`DiscoveryDocument`].
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DiscoveryItem {
+    /// The resource URL.
+    pub resource: String,
+    /// The resource type, always `http`.
+    #[serde(rename = "type")]
+    pub resource_type: &'static str,
+    pub x402_version: X402V2,
+    pub accepts: Accepts,
+    pub metadata: DiscoveryMetadata,
+}
+
+/// Descriptive metadata of a [`DiscoveryItem`].
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DiscoveryMetadata {
+    pub description: String,
+    pub mime_type: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub output_schema: Option<OutputSchema>,
+}
+
+/// Pagination of a [`DiscoveryDocument`], which always lists all resources on one page.
+#[derive(Debug, Clone, Serialize)]
+pub struct DiscoveryPagination {
+    pub limit: usize,
+    pub offset: usize,
+    pub total: usize,
+}
+
+impl DiscoveryDocument {
+    /// Render the discovery document for the given entries.
+    pub fn new<E: Into<DiscoveryEntry>>(entries: impl IntoIterator<Item = E>) -> Self {
+        let items = entries
+            .into_iter()
+            .map(Into::into)
+            .map(DiscoveryItem::from)
+            .collect::<Vec<_>>();
+        let total = items.len();
+
+        DiscoveryDocument {
+            x402_version: X402V2,
+            items,
+            pagination: DiscoveryPagination {
+                limit: total,
+                offset: 0,
+                total,
+            },
+        }
+    }
+}
+
+impl From<DiscoveryEntry> for DiscoveryItem {
+    fn from(entry: DiscoveryEntry) -> Self {
+        let output_schema = entry.resource.output_schema.clone();
+        let PaymentResource {
+            url,
+            description,
+            mime_type,
+        } = entry.resource.into();
+
+        DiscoveryItem {
+            resource: url.to_string(),
+            resource_type: "http",
+            x402_version: X402V2,
+            accepts: entry.accepts,
+            metadata: DiscoveryMetadata {
+                description,
+                mime_type,
+                output_schema,
+            },
+        }
+    }
+}
+
+/// An axum router serving the discovery document for the given entries at [`WELL_KNOWN_PATH`].
+///
+/// The document is rendered once, when the router is created.
+#[cfg(feature = "axum")]
+pub fn discovery_routes<S, E>(entries: impl IntoIterator<Item = E>) -> axum::Router<S>
+where
+    S: Clone + Send + Sync + 'static,
+    E: Into<DiscoveryEntry>,
+{
+    let document = serde_json::to_value(DiscoveryDocument::new(entries)).unwrap_or_default();
+
+    axum::Router::new().route(
+        WELL_KNOWN_PATH,
+        axum::routing::get(move || async move { axum::Json(document) }),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use x402_core::types::OutputSchema;
+
+    use crate::testing::{self, MockFacilitator};
+
+    use super::*;
+
+    fn entries() -> Vec<DiscoveryEntry> {
+        let paywall = PayWall::builder()
+            .facilitator(MockFacilitator::default())
+            .accepts(testing::requirements())
+            .resource(testing::resource())
+            .build();
+
+        let search = Resource::builder()
+            .url("https://example.com/search".parse().unwrap())
+            .description("Search the catalog")
+            .mime_type("application/json")
+            .output_schema(OutputSchema::http_get_discoverable())
+            .build();
+
+        vec![(&paywall).into(), (search, Accepts::new()).into()]
+    }
+
+    #[test]
+    fn document_matches_golden_file() {
+        let document = serde_json::to_value(DiscoveryDocument::new(entries())).unwrap();
+        let golden: serde_json::Value =
+            serde_json::from_str(include_str!("../tests/fixtures/discovery.json")).unwrap();
+
+        assert_eq!(document, golden);
+    }
+
+    #[cfg(feature = "axum")]
+    #[tokio::test]
+    async fn serves_document() {
+        use http_body_util::BodyExt;
+        use tower::ServiceExt;
+
+        let response = discovery_routes::<(), _>(entries())
+            .oneshot(
+                http::Request::get(WELL_KNOWN_PATH)
+                    .body(axum::body::Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), http::StatusCode::OK);
+
+        let bytes = response.into_body().collect().await.unwrap().to_bytes();
+        let document: serde_json::Value = serde_json::from_slice(&bytes).unwrap();
+        assert_eq!(document["pagination"]["total"], 2);
+        assert_eq!(
+            document["items"][1]["resource"],
+            "https://example.com/search"
+        );
+    }
+}
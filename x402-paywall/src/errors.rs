@@ -3,9 +3,10 @@
 use std::fmt::Display;
 
 use http::{HeaderName, HeaderValue, StatusCode};
+use serde::{Deserialize, Serialize};
 use x402_core::{
     transport::{Accepts, PaymentRequired, PaymentResource},
-    types::{Base64EncodedHeader, Extension, Record, X402V2},
+    types::{Extension, Record},
 };
 
 /// Represents an error response from the paywall.
@@ -19,6 +20,173 @@ pub struct ErrorResponse {
     ///
     /// Body is **Boxed** to reduce size of the struct.
     pub body: Box<PaymentRequired>,
+    /// The typed cause of the error, for reacting to failures programmatically.
+    pub cause: PaymentError,
+    /// The format of the rendered body.
+    pub format: ErrorFormat,
+    /// Seconds after which the client may retry, sent as `Retry-After` on `5xx` responses only.
+    pub retry_after: Option<u64>,
+    /// Send the hash of the `PAYMENT-REQUIRED` header as a strong `ETag`,
+    /// see [`PayWall::challenge_etag`](crate::paywall::PayWall::challenge_etag).
+    pub etag: bool,
+}
+
+/// The body format of a rendered [`ErrorResponse`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ErrorFormat {
+    /// The `PaymentRequired` object, as `application/json`.
+    #[default]
+    PaymentRequired,
+    /// An RFC 9457 [`Problem`] embedding the `PaymentRequired` object, as `application/problem+json`.
+    Problem,
+}
+
+/// An RFC 9457 problem details object, with the `PaymentRequired` object as the `x402` extension member.
+///
+/// # Example
+///
+/// ```json
+/// {
+///   "type": "urn:x402:error:facilitator_error",
+///   "title": "Internal Server Error",
+///   "status": 500,
+///   "detail": "Failed to get supported payment kinds: connection refused",
+///   "x402": { "x402Version": 2, "error": "...", "resource": { ... }, "accepts": [ ... ] }
+/// }
+/// ```
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Problem {
+    /// A URI identifying the problem type, `urn:x402:error:` followed by the [`PaymentError::code`].
+    #[serde(rename = "type")]
+    pub problem_type: String,
+    /// The reason phrase of the status code.
+    pub title: String,
+    /// The HTTP status code.
+    pub status: u16,
+    /// The error message, same as the `error` of the `PaymentRequired` object.
+    pub detail: String,
+    /// The `PaymentRequired` object.
+    pub x402: PaymentRequired,
+}
+
+/// The cause of a paywall [`ErrorResponse`].
+///
+/// The [`Display`] output is the `error` message sent to the client in the response body.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum PaymentError {
+    /// No `PAYMENT-SIGNATURE` header was provided.
+    MissingSignature,
+    /// The `PAYMENT-SIGNATURE` header could not be decoded or parsed.
+    MalformedHeader(String),
+    /// The payment requirements in the payload are not among the accepted ones.
+    UnsupportedOption,
+    /// The payload lacks extensions required by a paywall with
+    /// [`strict_extensions`](crate::paywall::PayWall::strict_extensions), or with
+    /// [`require_extension_strict`](crate::paywall::PayWallBuilder::require_extension_strict).
+    MissingExtensions(Vec<String>),
+    /// The info of an extension required with
+    /// [`require_typed_extension_strict`](crate::paywall::PayWallBuilder::require_typed_extension_strict)
+    /// does not match its type.
+    InvalidExtension(String),
+    /// The payload parses but violates the protocol, as checked by a paywall in
+    /// [`strict`](crate::paywall::PayWall::strict) mode.
+    NonConformant(String),
+    /// The facilitator rejected the payment during verification.
+    VerifyFailed(String),
+    /// The payer was denied by the [`PayerPolicy`](crate::policy::PayerPolicy), or is not the
+    /// `requiredPayer` of invoice-style payment requirements.
+    PayerDenied(String),
+    /// The [`BalanceChecker`](crate::balance::BalanceChecker) found the payer unable to fund the payment.
+    InsufficientFunds(String),
+    /// The facilitator failed to settle the payment.
+    SettleFailed(String),
+    /// Settlement would start after the paywall's
+    /// [`settlement_deadline`](crate::paywall::PayWall::settlement_deadline) since verification.
+    SettlementDeadlineExceeded,
+    /// The facilitator could not be reached or returned an error.
+    Facilitator(String),
+    /// The paywall has no payment requirements it can advertise, or the selected amount
+    /// exceeds its [`AmountCaps`](crate::caps::AmountCaps).
+    Misconfigured(String),
+    /// The payment flow did not complete in time.
+    Timeout,
+    /// The settlement transaction was not confirmed in time.
+    SettlementUnconfirmed(String),
+    /// The request failed the pre-checks before payment processing.
+    RequestRejected(String),
+    /// The [`AcceptsSource`](crate::source::AcceptsSource) failed to look up the accepted payment
+    /// requirements of the request, or the [`PricingSource`](crate::pricing::PricingSource) failed
+    /// to price them.
+    AcceptsUnavailable(String),
+    /// The [`on_settled`](crate::paywall::PayWallBuilder::on_settled) callback failed after the
+    /// payment was settled.
+    SettledHookFailed(String),
+}
+
+impl PaymentError {
+    /// A stable, machine-readable code of the error kind.
+    pub fn code(&self) -> &'static str {
+        match self {
+            PaymentError::MissingSignature => "missing_signature",
+            PaymentError::MalformedHeader(_) => "malformed_header",
+            PaymentError::UnsupportedOption => "unsupported_option",
+            PaymentError::MissingExtensions(_) => "missing_extensions",
+            PaymentError::InvalidExtension(_) => "invalid_extension",
+            PaymentError::NonConformant(_) => "non_conformant_payload",
+            PaymentError::VerifyFailed(_) => "verify_failed",
+            PaymentError::PayerDenied(_) => "payer_denied",
+            PaymentError::InsufficientFunds(_) => "insufficient_funds",
+            PaymentError::SettleFailed(_) => "settle_failed",
+            PaymentError::SettlementDeadlineExceeded => "settlement_deadline_exceeded",
+            PaymentError::Facilitator(_) => "facilitator_error",
+            PaymentError::Misconfigured(_) => "misconfigured",
+            PaymentError::Timeout => "timeout",
+            PaymentError::SettlementUnconfirmed(_) => "settlement_unconfirmed",
+            PaymentError::RequestRejected(_) => "request_rejected",
+            PaymentError::AcceptsUnavailable(_) => "accepts_unavailable",
+            PaymentError::SettledHookFailed(_) => "settled_hook_failed",
+        }
+    }
+}
+
+impl Display for PaymentError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PaymentError::MissingSignature => f.write_str("PAYMENT-SIGNATURE header is required"),
+            PaymentError::UnsupportedOption => {
+                f.write_str("PaymentRequirements in payload not accepted")
+            }
+            PaymentError::MissingExtensions(keys) => {
+                write!(
+                    f,
+                    "Payment payload is missing required extensions: {}",
+                    keys.join(", ")
+                )
+            }
+            PaymentError::Timeout => f.write_str("Payment flow did not complete in time"),
+            PaymentError::SettlementDeadlineExceeded => f.write_str("Settlement deadline exceeded"),
+            PaymentError::SettlementUnconfirmed(transaction) => {
+                write!(
+                    f,
+                    "Settlement transaction {transaction} was not confirmed in time"
+                )
+            }
+            PaymentError::MalformedHeader(reason)
+            | PaymentError::InvalidExtension(reason)
+            | PaymentError::NonConformant(reason)
+            | PaymentError::VerifyFailed(reason)
+            | PaymentError::PayerDenied(reason)
+            | PaymentError::InsufficientFunds(reason)
+            | PaymentError::SettleFailed(reason)
+            | PaymentError::Facilitator(reason)
+            | PaymentError::Misconfigured(reason)
+            | PaymentError::RequestRejected(reason)
+            | PaymentError::AcceptsUnavailable(reason)
+            | PaymentError::SettledHookFailed(reason) => f.write_str(reason),
+        }
+    }
 }
 
 impl Display for ErrorResponse {
@@ -28,143 +196,444 @@ impl Display for ErrorResponse {
 }
 
 impl ErrorResponse {
+    /// Render the body in the given format.
+    pub fn with_format(mut self, format: ErrorFormat) -> Self {
+        self.format = format;
+        self
+    }
+
+    /// Replace the `error` message of the body, re-encoding the payment header with it.
+    pub fn with_message(mut self, message: String) -> Self {
+        if self.body.error == message {
+            return self;
+        }
+        self.body.error = message;
+        self.reencode_header();
+        self
+    }
+
+    /// Set the machine-readable `errorCode` of the body, re-encoding the payment header with it,
+    /// e.g. [`PaymentRequired::STALE_REQUIREMENTS`].
+    pub fn with_error_code(mut self, code: impl Into<String>) -> Self {
+        self.body.error_code = Some(code.into());
+        self.reencode_header();
+        self
+    }
+
+    /// Encode the payment header again from the changed body.
+    fn reencode_header(&mut self) {
+        let header = encode_header(&self.body);
+        self.header = match self.header {
+            ErrorResponseHeader::PaymentRequired(_) => ErrorResponseHeader::PaymentRequired(header),
+            ErrorResponseHeader::PaymentResponse(_) => ErrorResponseHeader::PaymentResponse(header),
+            ErrorResponseHeader::None => ErrorResponseHeader::None,
+        };
+    }
+
+    /// Send the hash of the `PAYMENT-REQUIRED` header as a strong `ETag`.
+    pub fn with_etag(mut self) -> Self {
+        self.etag = true;
+        self
+    }
+
+    /// The `ETag` sent with the response, if enabled and the response has a `PAYMENT-REQUIRED` header.
+    ///
+    /// The header encodes the whole challenge, so its 64-bit FNV-1a hash changes with the resource,
+    /// the accepts, or the extensions, and is stable across processes and restarts.
+    pub fn etag_header(&self) -> Option<HeaderValue> {
+        let ErrorResponseHeader::PaymentRequired(header) = &self.header else {
+            return None;
+        };
+        if !self.etag {
+            return None;
+        }
+
+        let hash = header
+            .as_bytes()
+            .iter()
+            .fold(0xcbf29ce484222325_u64, |hash, byte| {
+                (hash ^ u64::from(*byte)).wrapping_mul(0x100000001b3)
+            });
+        Some(
+            HeaderValue::try_from(format!("\"{hash:016x}\"")).expect("hex is a valid header value"),
+        )
+    }
+
+    /// Turn the response into a `304 Not Modified`, keeping its headers but sending no body.
+    ///
+    /// For clients revalidating a cached challenge with a matching `If-None-Match`.
+    pub fn not_modified(mut self) -> Self {
+        self.status = StatusCode::NOT_MODIFIED;
+        self
+    }
+
+    /// Advise the client to retry after the given number of seconds.
+    ///
+    /// The `Retry-After` header is only sent on `5xx` responses, e.g. facilitator outages or
+    /// timeouts, since retrying a rejected payment unchanged cannot succeed.
+    pub fn with_retry_after(mut self, seconds: u64) -> Self {
+        self.retry_after = Some(seconds);
+        self
+    }
+
+    /// The HTTP status code of the response.
+    ///
+    /// Each constructor maps to a fixed status:
+    ///
+    /// | Constructor                                      | Status |
+    /// |--------------------------------------------------|--------|
+    /// | [`payment_required`](Self::payment_required)     | `402`  |
+    /// | [`invalid_payment`](Self::invalid_payment)       | `400`  |
+    /// | [`payment_failed`](Self::payment_failed)         | `402`  |
+    /// | [`server_error`](Self::server_error)             | `500`  |
+    /// | [`timeout`](Self::timeout)                       | `504`  |
+    /// | [`service_unavailable`](Self::service_unavailable) | `503`  |
+    /// | [`unsupported_media_type`](Self::unsupported_media_type) | `415`  |
+    /// | [`payload_too_large`](Self::payload_too_large)   | `413`  |
+    ///
+    /// A challenge turned [`not_modified`](Self::not_modified) has status `304`.
+    ///
+    /// Both the Axum and Actix Web conversions respond with this status.
+    pub fn status_code(&self) -> StatusCode {
+        self.status
+    }
+
+    /// The body as an RFC 9457 [`Problem`].
+    pub fn problem(&self) -> Problem {
+        Problem {
+            problem_type: format!("urn:x402:error:{}", self.cause.code()),
+            title: self
+                .status_code()
+                .canonical_reason()
+                .unwrap_or_default()
+                .to_string(),
+            status: self.status_code().as_u16(),
+            detail: self.body.error.clone(),
+            x402: (*self.body).clone(),
+        }
+    }
+
+    /// The `Retry-After` seconds to send, if any.
+    fn retry_after_header(&self) -> Option<u64> {
+        self.retry_after
+            .filter(|_| self.status_code().is_server_error())
+    }
+
+    /// The content type and the serialized body in the configured [`ErrorFormat`].
+    ///
+    /// Returns `None` for `304 Not Modified` responses, which have no body.
+    fn rendered_body(&self) -> Option<(&'static str, Vec<u8>)> {
+        if self.status_code() == StatusCode::NOT_MODIFIED {
+            return None;
+        }
+
+        Some(match self.format {
+            ErrorFormat::PaymentRequired => (
+                "application/json",
+                self.body.to_json_body().unwrap_or_default(),
+            ),
+            ErrorFormat::Problem => (
+                "application/problem+json",
+                serde_json::to_vec(&self.problem()).unwrap_or_default(),
+            ),
+        })
+    }
+
     /// Payment needed to access resource
     pub fn payment_required(
         resource: PaymentResource,
         accepts: Accepts,
         extensions: Record<Extension>,
     ) -> ErrorResponse {
-        let payment_required = PaymentRequired {
-            x402_version: X402V2,
-            error: "PAYMENT-SIGNATURE header is required".to_string(),
-            resource,
-            accepts,
-            extensions,
-        };
+        let payment_required = PaymentRequired::builder()
+            .error(PaymentError::MissingSignature.to_string())
+            .resource(resource)
+            .accepts(accepts)
+            .extensions(extensions)
+            .build();
 
-        let header = Base64EncodedHeader::try_from(payment_required.clone()).unwrap_or(
-            Base64EncodedHeader("Failed to encode base64 PaymentRequired payload".to_string()),
-        );
+        let header = encode_header(&payment_required);
+
+        ErrorResponse::payment_required_encoded(payment_required, header)
+    }
 
+    /// Payment needed to access resource, with the `PAYMENT-REQUIRED` header already encoded from the body
+    pub(crate) fn payment_required_encoded(
+        payment_required: PaymentRequired,
+        header: HeaderValue,
+    ) -> ErrorResponse {
         ErrorResponse {
             status: StatusCode::PAYMENT_REQUIRED,
             header: ErrorResponseHeader::PaymentRequired(header),
             body: Box::new(payment_required),
+            cause: PaymentError::MissingSignature,
+            format: ErrorFormat::default(),
+            retry_after: None,
+            etag: false,
         }
     }
 
     /// Malformed payment payload or requirements
     pub fn invalid_payment(
-        reason: impl Display,
+        cause: PaymentError,
         resource: PaymentResource,
         accepts: Accepts,
         extensions: Record<Extension>,
     ) -> ErrorResponse {
-        let payment_required = PaymentRequired {
-            x402_version: X402V2,
-            error: reason.to_string(),
-            resource,
-            accepts,
-            extensions,
-        };
+        let payment_required = PaymentRequired::builder()
+            .error(cause.to_string())
+            .resource(resource)
+            .accepts(accepts)
+            .extensions(extensions)
+            .build();
 
-        let header = Base64EncodedHeader::try_from(payment_required.clone()).unwrap_or(
-            Base64EncodedHeader("Failed to encode base64 PaymentRequired payload".to_string()),
-        );
+        let header = encode_header(&payment_required);
 
         ErrorResponse {
             status: StatusCode::BAD_REQUEST,
             header: ErrorResponseHeader::PaymentResponse(header),
             body: Box::new(payment_required),
+            cause,
+            format: ErrorFormat::default(),
+            retry_after: None,
+            etag: false,
         }
     }
 
     /// Payment verification or settlement failed
     pub fn payment_failed(
-        reason: impl Display,
+        cause: PaymentError,
         resource: PaymentResource,
         accepts: Accepts,
         extensions: Record<Extension>,
     ) -> ErrorResponse {
-        let payment_required = PaymentRequired {
-            x402_version: X402V2,
-            error: reason.to_string(),
-            resource,
-            accepts,
-            extensions,
-        };
+        let payment_required = PaymentRequired::builder()
+            .error(cause.to_string())
+            .resource(resource)
+            .accepts(accepts)
+            .extensions(extensions)
+            .build();
 
-        let header = Base64EncodedHeader::try_from(payment_required.clone()).unwrap_or(
-            Base64EncodedHeader("Failed to encode base64 PaymentRequired payload".to_string()),
-        );
+        let header = encode_header(&payment_required);
 
         ErrorResponse {
             status: StatusCode::PAYMENT_REQUIRED,
             header: ErrorResponseHeader::PaymentResponse(header),
             body: Box::new(payment_required),
+            cause,
+            format: ErrorFormat::default(),
+            retry_after: None,
+            etag: false,
         }
     }
 
     /// Internal server error during payment processing
     pub fn server_error(
-        reason: impl Display,
+        cause: PaymentError,
         resource: PaymentResource,
         accepts: Accepts,
         extensions: Record<Extension>,
     ) -> ErrorResponse {
-        let payment_required = PaymentRequired {
-            x402_version: X402V2,
-            error: reason.to_string(),
-            resource,
-            accepts,
-            extensions,
-        };
+        let payment_required = PaymentRequired::builder()
+            .error(cause.to_string())
+            .resource(resource)
+            .accepts(accepts)
+            .extensions(extensions)
+            .build();
 
-        let header = Base64EncodedHeader::try_from(payment_required.clone()).unwrap_or(
-            Base64EncodedHeader("Failed to encode base64 PaymentRequired payload".to_string()),
-        );
+        let header = encode_header(&payment_required);
 
         ErrorResponse {
             status: StatusCode::INTERNAL_SERVER_ERROR,
             header: ErrorResponseHeader::PaymentResponse(header),
             body: Box::new(payment_required),
+            cause,
+            format: ErrorFormat::default(),
+            retry_after: None,
+            etag: false,
+        }
+    }
+
+    /// The payment flow did not complete in time
+    pub fn timeout(
+        cause: PaymentError,
+        resource: PaymentResource,
+        accepts: Accepts,
+        extensions: Record<Extension>,
+    ) -> ErrorResponse {
+        let payment_required = PaymentRequired::builder()
+            .error(cause.to_string())
+            .resource(resource)
+            .accepts(accepts)
+            .extensions(extensions)
+            .build();
+
+        let header = encode_header(&payment_required);
+
+        ErrorResponse {
+            status: StatusCode::GATEWAY_TIMEOUT,
+            header: ErrorResponseHeader::PaymentResponse(header),
+            body: Box::new(payment_required),
+            cause,
+            format: ErrorFormat::default(),
+            retry_after: None,
+            etag: false,
+        }
+    }
+
+    /// The request content type is not accepted by the resource
+    pub fn unsupported_media_type(
+        cause: PaymentError,
+        resource: PaymentResource,
+        accepts: Accepts,
+        extensions: Record<Extension>,
+    ) -> ErrorResponse {
+        let payment_required = PaymentRequired::builder()
+            .error(cause.to_string())
+            .resource(resource)
+            .accepts(accepts)
+            .extensions(extensions)
+            .build();
+
+        ErrorResponse {
+            status: StatusCode::UNSUPPORTED_MEDIA_TYPE,
+            header: ErrorResponseHeader::None,
+            body: Box::new(payment_required),
+            cause,
+            format: ErrorFormat::default(),
+            retry_after: None,
+            etag: false,
+        }
+    }
+
+    /// The facilitator is unreachable, see [`Degrade::RejectWith503`](crate::degrade::Degrade::RejectWith503)
+    pub fn service_unavailable(
+        cause: PaymentError,
+        resource: PaymentResource,
+        accepts: Accepts,
+        extensions: Record<Extension>,
+    ) -> ErrorResponse {
+        let payment_required = PaymentRequired::builder()
+            .error(cause.to_string())
+            .resource(resource)
+            .accepts(accepts)
+            .extensions(extensions)
+            .build();
+
+        let header = encode_header(&payment_required);
+
+        ErrorResponse {
+            status: StatusCode::SERVICE_UNAVAILABLE,
+            header: ErrorResponseHeader::PaymentResponse(header),
+            body: Box::new(payment_required),
+            cause,
+            format: ErrorFormat::default(),
+            retry_after: None,
+            etag: false,
+        }
+    }
+
+    /// The request body is larger than the resource accepts
+    pub fn payload_too_large(
+        cause: PaymentError,
+        resource: PaymentResource,
+        accepts: Accepts,
+        extensions: Record<Extension>,
+    ) -> ErrorResponse {
+        let payment_required = PaymentRequired::builder()
+            .error(cause.to_string())
+            .resource(resource)
+            .accepts(accepts)
+            .extensions(extensions)
+            .build();
+
+        ErrorResponse {
+            status: StatusCode::PAYLOAD_TOO_LARGE,
+            header: ErrorResponseHeader::None,
+            body: Box::new(payment_required),
+            cause,
+            format: ErrorFormat::default(),
+            retry_after: None,
+            etag: false,
         }
     }
 }
 
+/// Encode the `PaymentRequired` object into a base64 header value.
+pub(crate) fn encode_header(payment_required: &PaymentRequired) -> HeaderValue {
+    payment_required
+        .to_header()
+        .ok()
+        .and_then(|header| HeaderValue::try_from(header.0).ok())
+        .unwrap_or(HeaderValue::from_static(
+            "Failed to encode base64 PaymentRequired payload",
+        ))
+}
+
 /// Represents the type of error header to include in a paywall error response.
+///
+/// The base64 encoded `PaymentRequired` object is held as a [`HeaderValue`], so that cloning
+/// an error response does not copy it.
 #[derive(Debug, Clone)]
+#[non_exhaustive]
 pub enum ErrorResponseHeader {
     /// `PAYMENT-REQUIRED` header.
-    PaymentRequired(Base64EncodedHeader),
+    PaymentRequired(HeaderValue),
     /// `PAYMENT-RESPONSE` header.
-    PaymentResponse(Base64EncodedHeader),
+    PaymentResponse(HeaderValue),
+    /// No payment header, for plain HTTP errors rejected before payment processing.
+    None,
 }
 
 impl ErrorResponseHeader {
     /// Get the header value to include in the response.
     ///
-    /// Returns `None` if the header value could not be created.
+    /// Returns `None` if there is no header.
     pub fn header_value(self) -> Option<(HeaderName, HeaderValue)> {
         match self {
-            ErrorResponseHeader::PaymentRequired(Base64EncodedHeader(s)) => {
-                HeaderValue::from_str(&s)
-                    .ok()
-                    .map(|v| (HeaderName::from_static("payment-required"), v))
+            ErrorResponseHeader::PaymentRequired(v) => {
+                Some((HeaderName::from_static("payment-required"), v))
             }
-            ErrorResponseHeader::PaymentResponse(Base64EncodedHeader(s)) => {
-                HeaderValue::from_str(&s)
-                    .ok()
-                    .map(|v| (HeaderName::from_static("payment-response"), v))
+            ErrorResponseHeader::PaymentResponse(v) => {
+                Some((HeaderName::from_static("payment-response"), v))
             }
+            ErrorResponseHeader::None => None,
         }
     }
 }
 
-#[cfg(feature = "axum")]
-impl axum::response::IntoResponse for ErrorResponse {
-    fn into_response(self) -> axum::response::Response {
-        let mut response = (self.status, axum::extract::Json(self.body)).into_response();
+impl ErrorResponse {
+    /// Convert into a plain [`http::Response`] with a body in the configured [`ErrorFormat`].
+    ///
+    /// Sets the status, the `PAYMENT-REQUIRED` or `PAYMENT-RESPONSE` header if any, the
+    /// `Retry-After` header on `5xx` responses if set, the `ETag` if set, and the content type
+    /// of the format. `304 Not Modified` responses have an empty body and no content type.
+    /// Works with any body type constructible from bytes, e.g. `Vec<u8>` or `http_body_util::Full<Bytes>`.
+    pub fn into_http_response<B: From<Vec<u8>>>(self) -> http::Response<B> {
+        let rendered = self.rendered_body();
+        let retry_after = self.retry_after_header();
+        let etag = self.etag_header();
+
+        let mut response = match rendered {
+            Some((content_type, body)) => {
+                let mut response = http::Response::new(B::from(body));
+                response.headers_mut().insert(
+                    http::header::CONTENT_TYPE,
+                    HeaderValue::from_static(content_type),
+                );
+                response
+            }
+            None => http::Response::new(B::from(Vec::new())),
+        };
+        *response.status_mut() = self.status_code();
+        if let Some(etag) = etag {
+            response.headers_mut().insert(http::header::ETAG, etag);
+        }
+        if let Some(seconds) = retry_after {
+            response
+                .headers_mut()
+                .insert(http::header::RETRY_AFTER, HeaderValue::from(seconds));
+        }
         if let Some((name, val)) = self.header.header_value() {
             response.headers_mut().insert(name, val);
         }
@@ -172,16 +641,24 @@ impl axum::response::IntoResponse for ErrorResponse {
     }
 }
 
+#[cfg(feature = "axum")]
+impl axum::response::IntoResponse for ErrorResponse {
+    fn into_response(self) -> axum::response::Response {
+        self.into_http_response()
+    }
+}
+
 #[cfg(feature = "actix-web")]
 impl ErrorResponse {
-    fn actix_header(&self) -> (&'static str, &str) {
+    fn actix_header(&self) -> Option<(&'static str, &[u8])> {
         match &self.header {
-            ErrorResponseHeader::PaymentRequired(base64_encoded_header) => {
-                ("payment-required", &base64_encoded_header.0)
+            ErrorResponseHeader::PaymentRequired(value) => {
+                Some(("payment-required", value.as_bytes()))
             }
-            ErrorResponseHeader::PaymentResponse(base64_encoded_header) => {
-                ("payment-response", &base64_encoded_header.0)
+            ErrorResponseHeader::PaymentResponse(value) => {
+                Some(("payment-response", value.as_bytes()))
             }
+            ErrorResponseHeader::None => None,
         }
     }
 }
@@ -189,12 +666,232 @@ impl ErrorResponse {
 #[cfg(feature = "actix-web")]
 impl actix_web::ResponseError for ErrorResponse {
     fn status_code(&self) -> actix_web::http::StatusCode {
-        actix_web::http::StatusCode::from_u16(self.status.as_u16()).unwrap()
+        // Actix Web re-exports its own `http` version, so convert through the numeric code.
+        actix_web::http::StatusCode::from_u16(ErrorResponse::status_code(self).as_u16()).unwrap()
     }
 
     fn error_response(&self) -> actix_web::HttpResponse<actix_web::body::BoxBody> {
-        actix_web::HttpResponseBuilder::new(self.status_code())
-            .insert_header(self.actix_header())
-            .json(&self.body)
+        let mut builder =
+            actix_web::HttpResponseBuilder::new(actix_web::ResponseError::status_code(self));
+        if let Some(header) = self.actix_header() {
+            builder.insert_header(header);
+        }
+        if let Some(etag) = self.etag_header() {
+            builder.insert_header(("etag", etag.as_bytes()));
+        }
+        if let Some(seconds) = self.retry_after_header() {
+            builder.insert_header(("retry-after", seconds));
+        }
+        match self.rendered_body() {
+            Some((content_type, body)) => builder.content_type(content_type).body(body),
+            None => builder.finish(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bytes::Bytes;
+    use http_body_util::{BodyExt, Full};
+    use x402_core::transport::PaymentRequired;
+
+    use crate::testing;
+
+    use super::*;
+
+    fn payment_required() -> ErrorResponse {
+        ErrorResponse::payment_required(
+            testing::resource().into(),
+            testing::requirements().into(),
+            Record::new(),
+        )
+    }
+
+    #[test]
+    fn into_vec_response() {
+        let response: http::Response<Vec<u8>> = payment_required().into_http_response();
+
+        assert_eq!(response.status(), StatusCode::PAYMENT_REQUIRED);
+        assert_eq!(
+            response.headers()[http::header::CONTENT_TYPE],
+            "application/json"
+        );
+        assert!(response.headers().contains_key("payment-required"));
+        assert!(!response.headers().contains_key("payment-response"));
+
+        let body: PaymentRequired = serde_json::from_slice(response.body()).unwrap();
+        assert_eq!(body.error, "PAYMENT-SIGNATURE header is required");
+        assert_eq!(body.accepts.as_ref(), [testing::requirements()]);
+    }
+
+    #[test]
+    fn neutral_response_header_matches_body() {
+        let response: http::Response<Vec<u8>> = payment_required().into_http_response();
+
+        assert_eq!(response.status(), StatusCode::PAYMENT_REQUIRED);
+        let header = response.headers()["payment-required"].to_str().unwrap();
+        let from_header = PaymentRequired::try_from(header).unwrap();
+        let from_body: PaymentRequired = serde_json::from_slice(response.body()).unwrap();
+        assert_eq!(
+            serde_json::to_value(from_header).unwrap(),
+            serde_json::to_value(from_body).unwrap()
+        );
+    }
+
+    #[tokio::test]
+    async fn into_full_bytes_response() {
+        let err = ErrorResponse::payment_failed(
+            PaymentError::SettleFailed("insufficient_funds".to_string()),
+            testing::resource().into(),
+            testing::requirements().into(),
+            Record::new(),
+        );
+
+        let response: http::Response<Full<Bytes>> = err.into_http_response();
+
+        assert_eq!(response.status(), StatusCode::PAYMENT_REQUIRED);
+        assert!(response.headers().contains_key("payment-response"));
+
+        let bytes = response.into_body().collect().await.unwrap().to_bytes();
+        let body: PaymentRequired = serde_json::from_slice(&bytes).unwrap();
+        assert_eq!(body.error, "insufficient_funds");
+    }
+
+    fn server_error() -> ErrorResponse {
+        ErrorResponse::server_error(
+            PaymentError::Facilitator("connection refused".to_string()),
+            testing::resource().into(),
+            testing::requirements().into(),
+            Record::new(),
+        )
+    }
+
+    #[test]
+    fn problem_json_response() {
+        let response: http::Response<Vec<u8>> = server_error()
+            .with_format(ErrorFormat::Problem)
+            .into_http_response();
+
+        assert_eq!(response.status(), StatusCode::INTERNAL_SERVER_ERROR);
+        assert_eq!(
+            response.headers()[http::header::CONTENT_TYPE],
+            "application/problem+json"
+        );
+        assert!(response.headers().contains_key("payment-response"));
+
+        let body: serde_json::Value = serde_json::from_slice(response.body()).unwrap();
+        assert_eq!(body["type"], "urn:x402:error:facilitator_error");
+        assert_eq!(body["title"], "Internal Server Error");
+        assert_eq!(body["status"], 500);
+        assert_eq!(body["detail"], "connection refused");
+        assert_eq!(body["x402"]["x402Version"], 2);
+        assert_eq!(body["x402"]["error"], "connection refused");
+        assert_eq!(
+            body["x402"]["accepts"][0],
+            serde_json::to_value(testing::requirements()).unwrap()
+        );
+    }
+
+    #[test]
+    fn retry_after_only_on_server_errors() {
+        let response: http::Response<Vec<u8>> =
+            server_error().with_retry_after(30).into_http_response();
+        assert_eq!(response.headers()[http::header::RETRY_AFTER], "30");
+
+        let timeout: http::Response<Vec<u8>> = ErrorResponse::timeout(
+            PaymentError::Timeout,
+            testing::resource().into(),
+            testing::requirements().into(),
+            Record::new(),
+        )
+        .with_retry_after(5)
+        .into_http_response();
+        assert_eq!(timeout.headers()[http::header::RETRY_AFTER], "5");
+
+        let rejected: http::Response<Vec<u8>> = ErrorResponse::payment_failed(
+            PaymentError::VerifyFailed("invalid_signature".to_string()),
+            testing::resource().into(),
+            testing::requirements().into(),
+            Record::new(),
+        )
+        .with_retry_after(30)
+        .into_http_response();
+        assert_eq!(rejected.status(), StatusCode::PAYMENT_REQUIRED);
+        assert!(!rejected.headers().contains_key(http::header::RETRY_AFTER));
+
+        let required: http::Response<Vec<u8>> =
+            payment_required().with_retry_after(30).into_http_response();
+        assert!(!required.headers().contains_key(http::header::RETRY_AFTER));
+    }
+
+    type Constructor =
+        fn(PaymentError, PaymentResource, Accepts, Record<Extension>) -> ErrorResponse;
+
+    #[test]
+    fn status_code_per_constructor() {
+        let constructors: [(Constructor, StatusCode); 7] = [
+            (ErrorResponse::invalid_payment, StatusCode::BAD_REQUEST),
+            (ErrorResponse::payment_failed, StatusCode::PAYMENT_REQUIRED),
+            (
+                ErrorResponse::server_error,
+                StatusCode::INTERNAL_SERVER_ERROR,
+            ),
+            (ErrorResponse::timeout, StatusCode::GATEWAY_TIMEOUT),
+            (
+                ErrorResponse::service_unavailable,
+                StatusCode::SERVICE_UNAVAILABLE,
+            ),
+            (
+                ErrorResponse::unsupported_media_type,
+                StatusCode::UNSUPPORTED_MEDIA_TYPE,
+            ),
+            (
+                ErrorResponse::payload_too_large,
+                StatusCode::PAYLOAD_TOO_LARGE,
+            ),
+        ];
+
+        assert_eq!(
+            payment_required().status_code(),
+            StatusCode::PAYMENT_REQUIRED
+        );
+        for (constructor, status) in constructors {
+            let error = constructor(
+                PaymentError::Timeout,
+                testing::resource().into(),
+                testing::requirements().into(),
+                Record::new(),
+            );
+            assert_eq!(error.status_code(), status);
+
+            let response: http::Response<Vec<u8>> = error.clone().into_http_response();
+            assert_eq!(response.status(), status);
+
+            #[cfg(feature = "actix-web")]
+            assert_eq!(
+                actix_web::ResponseError::error_response(&error)
+                    .status()
+                    .as_u16(),
+                status.as_u16()
+            );
+        }
+    }
+
+    #[cfg(feature = "actix-web")]
+    #[test]
+    fn actix_error_response() {
+        use actix_web::ResponseError;
+
+        let response = server_error()
+            .with_format(ErrorFormat::Problem)
+            .with_retry_after(30)
+            .error_response();
+
+        assert_eq!(response.status().as_u16(), 500);
+        assert_eq!(
+            response.headers().get("content-type").unwrap(),
+            "application/problem+json"
+        );
+        assert_eq!(response.headers().get("retry-after").unwrap(), "30");
     }
 }
@@ -0,0 +1,173 @@
+//! Fees charged on top of the base price of a resource.
+//!
+//! A [`FeePolicy`] on a [`PayWall`](crate::paywall::PayWall) is applied to every accepted payment
+//! requirement, both when advertising them to clients and when matching the requirements of
+//! incoming payloads, so that the advertised and the accepted amounts always agree.
+
+use std::collections::HashMap;
+
+use x402_core::{transport::PaymentRequirements, types::AmountValue};
+
+/// A percentage fee plus flat per-network surcharges on top of the base amount.
+///
+/// All amounts are in the smallest units of the asset. The percentage fee is rounded up to
+/// the next smallest unit, so that a non-zero fee never rounds down to nothing, see
+/// [`AmountValue::basis_points_ceil`].
+///
+/// # Example
+///
+/// ```
+/// use x402_core::types::AmountValue;
+/// use x402_paywall::fees::FeePolicy;
+///
+/// let fees = FeePolicy::percent(2).plus_flat_for("eip155:1", 50_000);
+///
+/// // 2% of 1_000_001 is 20_000.02, rounded up to 20_001
+/// assert_eq!(
+///     fees.amount_for(AmountValue(1_000_001), "eip155:8453"),
+///     AmountValue(1_020_002)
+/// );
+/// // The flat surcharge only applies on Ethereum mainnet
+/// assert_eq!(
+///     fees.amount_for(AmountValue(1_000_000), "eip155:1"),
+///     AmountValue(1_070_000)
+/// );
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct FeePolicy {
+    /// Percentage fee in basis points, i.e. hundredths of a percent.
+    pub basis_points: u32,
+    /// Flat surcharges by network (CAIP-2 identifier).
+    pub flat: HashMap<String, u64>,
+}
+
+impl FeePolicy {
+    /// A percentage fee in whole percent.
+    pub fn percent(percent: u32) -> Self {
+        Self::basis_points(percent.saturating_mul(100))
+    }
+
+    /// A percentage fee in basis points, e.g. `250` for 2.5%.
+    pub fn basis_points(basis_points: u32) -> Self {
+        FeePolicy {
+            basis_points,
+            flat: HashMap::new(),
+        }
+    }
+
+    /// Add a flat surcharge for payments on the given network, replacing any previous one.
+    pub fn plus_flat_for(mut self, network: impl Into<String>, amount: u64) -> Self {
+        self.flat.insert(network.into(), amount);
+        self
+    }
+
+    /// The fee for the given base amount on the given network.
+    pub fn fee_for(&self, base: AmountValue, network: &str) -> AmountValue {
+        let percentage = base.basis_points_ceil(self.basis_points).0;
+        let flat = self.flat.get(network).copied().unwrap_or_default();
+
+        AmountValue(percentage.saturating_add(flat.into()))
+    }
+
+    /// The amount to charge for the given base amount on the given network, including fees.
+    pub fn amount_for(&self, base: AmountValue, network: &str) -> AmountValue {
+        AmountValue(base.0.saturating_add(self.fee_for(base, network).0))
+    }
+
+    /// Apply the fees to the amount of the given payment requirements.
+    pub fn apply(&self, mut requirements: PaymentRequirements) -> PaymentRequirements {
+        requirements.amount = self.amount_for(requirements.amount, &requirements.network);
+        requirements
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        paywall::PayWall,
+        testing::{self, MockFacilitator},
+    };
+
+    use super::*;
+
+    #[test]
+    fn percentage_rounds_up() {
+        let fees = FeePolicy::percent(2);
+
+        assert_eq!(
+            fees.fee_for(AmountValue(1000), "eip155:8453"),
+            AmountValue(20)
+        );
+        assert_eq!(
+            fees.fee_for(AmountValue(1001), "eip155:8453"),
+            AmountValue(21)
+        );
+        assert_eq!(fees.fee_for(AmountValue(1), "eip155:8453"), AmountValue(1));
+        assert_eq!(fees.fee_for(AmountValue(0), "eip155:8453"), AmountValue(0));
+
+        let fees = FeePolicy::basis_points(250);
+        assert_eq!(
+            fees.fee_for(AmountValue(1000), "eip155:8453"),
+            AmountValue(25)
+        );
+        assert_eq!(
+            fees.fee_for(AmountValue(1040), "eip155:8453"),
+            AmountValue(26)
+        );
+    }
+
+    #[test]
+    fn flat_fee_per_network() {
+        let fees = FeePolicy::default()
+            .plus_flat_for("eip155:1", 50_000)
+            .plus_flat_for("solana:5eykt4UsFv8P8NJdTREpY1vzqKqZKvdp", 100);
+
+        assert_eq!(
+            fees.amount_for(AmountValue(1000), "eip155:1"),
+            AmountValue(51_000)
+        );
+        assert_eq!(
+            fees.amount_for(AmountValue(1000), "solana:5eykt4UsFv8P8NJdTREpY1vzqKqZKvdp"),
+            AmountValue(1100)
+        );
+        assert_eq!(
+            fees.amount_for(AmountValue(1000), "eip155:8453"),
+            AmountValue(1000)
+        );
+    }
+
+    #[test]
+    fn percentage_and_flat_fee_add_up() {
+        let fees = FeePolicy::percent(2).plus_flat_for("eip155:84532", 5);
+        let requirements = fees.apply(testing::requirements());
+
+        assert_eq!(requirements.amount, AmountValue(1025));
+        assert_eq!(requirements.network, "eip155:84532");
+    }
+
+    #[tokio::test]
+    async fn advertised_and_accepted_amounts_agree() {
+        let fees = FeePolicy::percent(2);
+        let paywall = PayWall::builder()
+            .facilitator(MockFacilitator::default())
+            .accepts(testing::requirements())
+            .resource(testing::resource())
+            .fees(fees.clone())
+            .build();
+
+        let err = paywall.payment_required();
+        let advertised = err.body.accepts.as_ref()[0].clone();
+        assert_eq!(advertised.amount, AmountValue(1020));
+
+        let processor = paywall
+            .process_request(testing::paid_request(advertised.clone()))
+            .unwrap();
+        assert_eq!(processor.payment_state.amount(), AmountValue(1020));
+
+        let err = paywall
+            .process_request(testing::paid_request(testing::requirements()))
+            .err()
+            .unwrap();
+        assert_eq!(err.status, http::StatusCode::BAD_REQUEST);
+    }
+}
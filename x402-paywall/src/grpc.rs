@@ -0,0 +1,174 @@
+//! Payments carried in gRPC metadata, for paywalled `tonic` services.
+//!
+//! gRPC metadata travels as HTTP/2 headers, so the payment rides in the same `PAYMENT-SIGNATURE`
+//! and `PAYMENT-RESPONSE` keys, lowercased, as ASCII metadata. [`tonic::Request`] implements
+//! [`HttpRequest`] and [`tonic::Response`] implements [`HttpResponse`], so a paywall can process
+//! gRPC calls with [`PayWall::handle_payment`](crate::paywall::PayWall::handle_payment) like any
+//! other request:
+//!
+//! ```
+//! use x402_core::facilitator::Facilitator;
+//! use x402_paywall::{errors::ErrorResponse, paywall::PayWall};
+//!
+//! async fn get_report<F: Facilitator>(
+//!     paywall: PayWall<F>,
+//!     request: tonic::Request<String>,
+//! ) -> Result<tonic::Response<String>, ErrorResponse> {
+//!     paywall
+//!         .handle_payment(request, |request| async move {
+//!             tonic::Response::new(format!("Report for {}", request.get_ref()))
+//!         })
+//!         .await
+//! }
+//! ```
+//!
+//! Services handling the payment step by step can use [`payment_payload`] and
+//! [`insert_payment_response`] instead.
+//!
+//! Available with the `tonic` feature.
+
+use tonic::metadata::{MetadataKey, MetadataMap, MetadataValue};
+use x402_core::{transport::PaymentPayload, types::Base64EncodedHeader};
+
+use crate::{HttpRequest, HttpResponse, InvalidHeaderValue};
+
+/// The metadata key of the payment sent by the client.
+pub const PAYMENT_SIGNATURE: &str = "payment-signature";
+
+/// The metadata key of the settlement response sent to the client.
+pub const PAYMENT_RESPONSE: &str = "payment-response";
+
+/// The payment payload of the `payment-signature` metadata, or `None` if absent.
+pub fn payment_payload(
+    metadata: &MetadataMap,
+) -> x402_core::errors::Result<Option<PaymentPayload>> {
+    let Some(value) = metadata.get(PAYMENT_SIGNATURE) else {
+        return Ok(None);
+    };
+    let value = String::from_utf8(value.as_bytes().to_vec())?;
+
+    PaymentPayload::try_from(value.as_str()).map(Some)
+}
+
+/// Set the `payment-response` metadata to the encoded settlement response, replacing any
+/// previous value.
+pub fn insert_payment_response(
+    metadata: &mut MetadataMap,
+    header: &Base64EncodedHeader,
+) -> Result<(), InvalidHeaderValue> {
+    let value = MetadataValue::try_from(header.0.as_str()).map_err(|_| InvalidHeaderValue)?;
+    metadata.insert(PAYMENT_RESPONSE, value);
+    Ok(())
+}
+
+impl<T> HttpRequest for tonic::Request<T> {
+    fn get_header(&self, name: &str) -> Option<&[u8]> {
+        self.metadata().get(name).map(|v| v.as_bytes())
+    }
+
+    fn insert_extension<E: Clone + Send + Sync + 'static>(&mut self, ext: E) -> Option<E> {
+        self.extensions_mut().insert(ext)
+    }
+}
+
+/// gRPC failures are returned as a `tonic::Status` rather than a response, so every response is
+/// successful.
+impl<T> HttpResponse for tonic::Response<T> {
+    fn is_success(&self) -> bool {
+        true
+    }
+
+    fn insert_header(
+        &mut self,
+        name: &'static str,
+        value: &[u8],
+    ) -> Result<(), InvalidHeaderValue> {
+        let value = MetadataValue::try_from(value).map_err(|_| InvalidHeaderValue)?;
+        self.metadata_mut()
+            .insert(MetadataKey::from_static(name), value);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use x402_core::transport::SettlementResponse;
+
+    use crate::{
+        paywall::PayWall,
+        processor::PaymentState,
+        testing::{self, MockFacilitator},
+    };
+
+    use super::*;
+
+    fn metadata_with_payment() -> MetadataMap {
+        let mut metadata = MetadataMap::new();
+        metadata.insert(
+            PAYMENT_SIGNATURE,
+            testing::payment_header(testing::requirements())
+                .0
+                .parse()
+                .unwrap(),
+        );
+        metadata
+    }
+
+    #[test]
+    fn extracts_payment_payload() {
+        let payload = payment_payload(&metadata_with_payment()).unwrap().unwrap();
+
+        assert_eq!(payload.accepted, testing::requirements());
+        assert!(payment_payload(&MetadataMap::new()).unwrap().is_none());
+    }
+
+    #[test]
+    fn rejects_malformed_payment() {
+        let mut metadata = MetadataMap::new();
+        metadata.insert(PAYMENT_SIGNATURE, "not-base64!".parse().unwrap());
+
+        assert!(payment_payload(&metadata).is_err());
+    }
+
+    #[tokio::test]
+    async fn paywalled_call_carries_payment_response() {
+        let paywall = PayWall::builder()
+            .facilitator(MockFacilitator::default())
+            .accepts(testing::requirements())
+            .resource(testing::resource())
+            .build();
+        let request = tonic::Request::from_parts(
+            metadata_with_payment(),
+            Default::default(),
+            "report".to_string(),
+        );
+
+        let response = paywall
+            .handle_payment(request, |request| async move {
+                let state = request.extensions().get::<PaymentState>().unwrap();
+                assert!(state.verified.is_some());
+                tonic::Response::new(request.into_inner())
+            })
+            .await
+            .unwrap();
+
+        let header = response.metadata().get(PAYMENT_RESPONSE).unwrap();
+        let settlement = SettlementResponse::try_from(header.to_str().unwrap()).unwrap();
+        assert_eq!(settlement.transaction, "0xdeadbeef");
+        assert_eq!(response.into_inner(), "report");
+    }
+
+    #[test]
+    fn inserts_payment_response() {
+        let settlement = SettlementResponse::new(testing::PAYER, "0xdeadbeef", "eip155:84532");
+        let header = Base64EncodedHeader::try_from(settlement).unwrap();
+        let mut metadata = MetadataMap::new();
+
+        insert_payment_response(&mut metadata, &header).unwrap();
+
+        assert_eq!(
+            metadata.get(PAYMENT_RESPONSE).unwrap().to_str().unwrap(),
+            header.0
+        );
+    }
+}
@@ -0,0 +1,307 @@
+//! A journal of settlements in flight, so that settlements whose response is lost, e.g. to a
+//! process crash, can be retried on restart.
+//!
+//! A [`SettlementJournal`] on a [`PayWall`](crate::paywall::PayWall) records every settlement as
+//! pending before the facilitator is asked to settle it, and marks it as settled once the
+//! facilitator answers, whether the settlement succeeded or failed. Settlements left pending are
+//! the ones whose outcome is unknown: on startup, list them with
+//! [`list_pending`](SettlementJournal::list_pending) and settle them again with
+//! [`retry_pending`]. Retries carry the original settlement key as their idempotency key, so a
+//! payment already settled is not settled twice.
+//!
+//! Journal failures do not fail the request, as the settlement can proceed without them. They
+//! are logged with the `tracing` feature.
+
+use std::{
+    fmt::{Debug, Display},
+    fs,
+    io::ErrorKind,
+    path::PathBuf,
+    pin::Pin,
+    sync::{Mutex, PoisonError},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use serde::{Deserialize, Serialize};
+use x402_core::facilitator::{Facilitator, PaymentRequest, SettleResult, SettlementKey};
+
+/// The future returned by the methods of [`SettlementJournal`].
+pub type JournalFuture<'a, T> = Pin<Box<dyn Future<Output = Result<T, JournalError>> + Send + 'a>>;
+
+/// A settlement initiated but not known to be answered by the facilitator.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PendingSettlement {
+    /// The key of the settlement, also sent as the idempotency key of the request.
+    pub settlement_key: SettlementKey,
+    /// The settle request sent to the facilitator.
+    pub request: PaymentRequest,
+    /// Unix timestamp in seconds when the settlement was initiated.
+    pub timestamp: u64,
+}
+
+impl PendingSettlement {
+    /// Create a pending settlement of the request, keyed by its idempotency key and timestamped
+    /// now.
+    ///
+    /// Returns `None` if the request has no idempotency key.
+    pub fn new(request: PaymentRequest) -> Option<Self> {
+        Some(PendingSettlement {
+            settlement_key: request.idempotency_key.clone()?,
+            request,
+            timestamp: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|d| d.as_secs())
+                .unwrap_or_default(),
+        })
+    }
+}
+
+/// The reason a [`SettlementJournal`] failed, e.g. an unwritable file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct JournalError(pub String);
+
+impl Display for JournalError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Settlement journal failed: {}", self.0)
+    }
+}
+
+impl std::error::Error for JournalError {}
+
+/// A durable record of the settlements in flight.
+pub trait SettlementJournal: Debug + Send + Sync {
+    /// Record a settlement as pending, before the facilitator is asked to settle it.
+    fn record_pending<'a>(&'a self, pending: PendingSettlement) -> JournalFuture<'a, ()>;
+
+    /// Mark the settlement as answered by the facilitator, removing it from the pending ones.
+    fn mark_settled<'a>(&'a self, settlement_key: &'a SettlementKey) -> JournalFuture<'a, ()>;
+
+    /// All settlements recorded as pending and not marked as settled, oldest first.
+    fn list_pending<'a>(&'a self) -> JournalFuture<'a, Vec<PendingSettlement>>;
+}
+
+/// A [`SettlementJournal`] kept as a JSON array in a single file, e.g. for examples and
+/// single-process servers.
+///
+/// The file is rewritten on every change, through a temporary file renamed over it so that a
+/// crash mid-write leaves the previous journal intact. File I/O is blocking.
+#[derive(Debug)]
+pub struct FileJournal {
+    path: PathBuf,
+    lock: Mutex<()>,
+}
+
+impl FileJournal {
+    /// A journal kept in the file at `path`, created on the first recorded settlement.
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        FileJournal {
+            path: path.into(),
+            lock: Mutex::new(()),
+        }
+    }
+
+    fn read(&self) -> Result<Vec<PendingSettlement>, JournalError> {
+        match fs::read(&self.path) {
+            Ok(bytes) => {
+                serde_json::from_slice(&bytes).map_err(|err| JournalError(err.to_string()))
+            }
+            Err(err) if err.kind() == ErrorKind::NotFound => Ok(Vec::new()),
+            Err(err) => Err(JournalError(err.to_string())),
+        }
+    }
+
+    fn write(&self, pending: &[PendingSettlement]) -> Result<(), JournalError> {
+        let json =
+            serde_json::to_vec_pretty(pending).map_err(|err| JournalError(err.to_string()))?;
+        let tmp = self.path.with_extension("tmp");
+        fs::write(&tmp, json)
+            .and_then(|()| fs::rename(&tmp, &self.path))
+            .map_err(|err| JournalError(err.to_string()))
+    }
+
+    fn update(&self, change: impl FnOnce(&mut Vec<PendingSettlement>)) -> Result<(), JournalError> {
+        let _guard = self.lock.lock().unwrap_or_else(PoisonError::into_inner);
+        let mut pending = self.read()?;
+        change(&mut pending);
+        self.write(&pending)
+    }
+}
+
+impl SettlementJournal for FileJournal {
+    fn record_pending<'a>(&'a self, pending: PendingSettlement) -> JournalFuture<'a, ()> {
+        let result = self.update(|all| {
+            all.retain(|p| p.settlement_key != pending.settlement_key);
+            all.push(pending);
+        });
+
+        Box::pin(async { result })
+    }
+
+    fn mark_settled<'a>(&'a self, settlement_key: &'a SettlementKey) -> JournalFuture<'a, ()> {
+        let result = self.update(|all| all.retain(|p| &p.settlement_key != settlement_key));
+
+        Box::pin(async { result })
+    }
+
+    fn list_pending<'a>(&'a self) -> JournalFuture<'a, Vec<PendingSettlement>> {
+        let result = {
+            let _guard = self.lock.lock().unwrap_or_else(PoisonError::into_inner);
+            self.read()
+        };
+
+        Box::pin(async { result })
+    }
+}
+
+/// Settle every pending settlement of the journal again with the facilitator, e.g. on startup.
+///
+/// Settlements the facilitator answers, successfully or not, are marked as settled, while those
+/// failing with a facilitator error stay pending for the next retry. Returns each pending
+/// settlement with the facilitator's answer.
+pub async fn retry_pending<F: Facilitator>(
+    journal: &dyn SettlementJournal,
+    facilitator: &F,
+) -> Result<Vec<(PendingSettlement, Result<SettleResult, F::Error>)>, JournalError> {
+    let mut outcomes = Vec::new();
+
+    for pending in journal.list_pending().await? {
+        let result = facilitator.settle(pending.request.clone()).await;
+        if result.is_ok() {
+            journal.mark_settled(&pending.settlement_key).await?;
+        }
+
+        #[cfg(feature = "tracing")]
+        tracing::info!(
+            "Retried pending settlement: settlement_key='{}', answered={}",
+            pending.settlement_key,
+            result.is_ok()
+        );
+
+        outcomes.push((pending, result));
+    }
+
+    Ok(outcomes)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::Ordering;
+
+    use x402_core::transport::PaymentPayload;
+
+    use crate::{
+        paywall::PayWall,
+        testing::{self, MockFacilitator},
+    };
+
+    use super::*;
+
+    /// A journal file unique to the test, removed if left over from a previous run.
+    fn journal_path(name: &str) -> PathBuf {
+        let path =
+            std::env::temp_dir().join(format!("x402-journal-{}-{name}.json", std::process::id()));
+        let _ = fs::remove_file(&path);
+        path
+    }
+
+    fn paywall(facilitator: MockFacilitator, journal: FileJournal) -> PayWall<MockFacilitator> {
+        PayWall::builder()
+            .facilitator(facilitator)
+            .accepts(testing::requirements())
+            .resource(testing::resource())
+            .journal(journal)
+            .build()
+    }
+
+    #[tokio::test]
+    async fn answered_settlements_are_not_pending() {
+        let path = journal_path("answered");
+
+        paywall(MockFacilitator::default(), FileJournal::new(&path))
+            .handle_payment(testing::paid_request(testing::requirements()), |_| async {
+                http::Response::new(())
+            })
+            .await
+            .unwrap();
+
+        assert!(
+            FileJournal::new(&path)
+                .list_pending()
+                .await
+                .unwrap()
+                .is_empty()
+        );
+        let _ = fs::remove_file(path);
+    }
+
+    #[tokio::test]
+    async fn lost_settlement_is_retried_after_restart() {
+        let path = journal_path("lost");
+        let unavailable = MockFacilitator {
+            unavailable: true,
+            ..Default::default()
+        };
+
+        // The settle response is lost, as if the process crashed while settling
+        let paywall = paywall(unavailable, FileJournal::new(&path));
+        let err = paywall
+            .process_request(testing::paid_request(testing::requirements()))
+            .unwrap()
+            .settle()
+            .await
+            .err()
+            .unwrap();
+        assert_eq!(err.status, http::StatusCode::INTERNAL_SERVER_ERROR);
+
+        // On restart, the settlement is still pending
+        let journal = FileJournal::new(&path);
+        let pending = journal.list_pending().await.unwrap();
+        assert_eq!(pending.len(), 1);
+        assert_eq!(
+            pending[0].request.idempotency_key.as_ref(),
+            Some(&pending[0].settlement_key)
+        );
+
+        let facilitator = MockFacilitator::default();
+        let outcomes = retry_pending(&journal, &facilitator).await.unwrap();
+
+        assert_eq!(outcomes.len(), 1);
+        assert!(matches!(outcomes[0].1, Ok(SettleResult::Success(_))));
+        assert_eq!(facilitator.calls.settle.load(Ordering::SeqCst), 1);
+        assert_eq!(
+            facilitator.calls.settle_requests.lock().unwrap()[0].idempotency_key,
+            Some(pending[0].settlement_key.clone())
+        );
+        assert!(journal.list_pending().await.unwrap().is_empty());
+        let _ = fs::remove_file(path);
+    }
+
+    #[tokio::test]
+    async fn failed_retries_stay_pending() {
+        let path = journal_path("failed");
+        let journal = FileJournal::new(&path);
+        let payload =
+            PaymentPayload::try_from(testing::payment_header(testing::requirements())).unwrap();
+        let request = PaymentRequest {
+            idempotency_key: Some(SettlementKey::derive(&payload)),
+            payment_requirements: payload.accepted.clone(),
+            payment_payload: payload,
+            attestation: None,
+        };
+        journal
+            .record_pending(PendingSettlement::new(request).unwrap())
+            .await
+            .unwrap();
+
+        let unavailable = MockFacilitator {
+            unavailable: true,
+            ..Default::default()
+        };
+        let outcomes = retry_pending(&journal, &unavailable).await.unwrap();
+
+        assert!(outcomes[0].1.is_err());
+        assert_eq!(journal.list_pending().await.unwrap().len(), 1);
+        let _ = fs::remove_file(path);
+    }
+}
@@ -0,0 +1,258 @@
+//! A seller-side ledger of settled payments, e.g. for reconciliation by finance.
+//!
+//! The paywall records to the same [`Ledger`] trait and [`PaymentReceipt`] as the core
+//! [`LedgerFacilitator`](x402_core::ledger::LedgerFacilitator), rather than to a paywall-specific
+//! ledger and entry type, so that one ledger implementation serves both.
+//!
+//! A [`Ledger`] on a [`PayWall`](crate::paywall::PayWall) records a [`PaymentReceipt`] for
+//! every payment the paywall settles, whether before the handler with
+//! [`RequestProcessor::settle`](crate::processor::RequestProcessor::settle) or after it with
+//! [`ResponseProcessor::settle`](crate::processor::ResponseProcessor::settle). Payments are
+//! recorded once, as repeated settle calls are no-ops. Receipts carry the URL of the paid
+//! resource and the paywall's order id, if any.
+//!
+//! Payments served unverified while the facilitator was unreachable, see
+//! [`Degrade::AllowUnverified`](crate::degrade::Degrade::AllowUnverified), are recorded as
+//! liabilities, see [`PaymentReceipt::liability`].
+//!
+//! The payment is already settled when it is recorded, so ledger failures do not fail the
+//! request. They are reported to the [`on_ledger_error`](crate::paywall::PayWall::on_ledger_error)
+//! hook, logged with the `tracing` feature, and counted in `x402_ledger_errors_total` with the
+//! `metrics` feature.
+//!
+//! ```
+//! use std::sync::Arc;
+//!
+//! use x402_paywall::ledger::VecLedger;
+//!
+//! // Keep a handle to read the receipts recorded by the paywall
+//! let ledger = Arc::new(VecLedger::new());
+//! # let _ = ledger.receipts();
+//! ```
+
+use std::{error::Error, fmt::Debug, pin::Pin};
+
+pub use x402_core::{
+    facilitator::PaymentReceipt,
+    ledger::{Ledger, LedgerErrorHook, VecLedger},
+};
+
+/// The error of an [`ErasedLedger`].
+pub type LedgerError = Box<dyn Error + Send + Sync>;
+
+/// The future returned by [`ErasedLedger::record`], failing with the error of the ledger.
+pub type LedgerFuture<'a> = Pin<Box<dyn Future<Output = Result<(), LedgerError>> + Send + 'a>>;
+
+/// A [`Ledger`] with its error type erased, as held by the paywall.
+///
+/// Implemented for every [`Ledger`] that is `Debug`, `Send` and `Sync`, with a `Send + Sync`
+/// error.
+pub trait ErasedLedger: Debug + Send + Sync {
+    /// Record a payment receipt.
+    fn record<'a>(&'a self, receipt: PaymentReceipt) -> LedgerFuture<'a>;
+}
+
+impl<L> ErasedLedger for L
+where
+    L: Ledger + Debug + Send + Sync,
+    L::Error: Send + Sync + 'static,
+{
+    fn record<'a>(&'a self, receipt: PaymentReceipt) -> LedgerFuture<'a> {
+        Box::pin(async move {
+            Ledger::record(self, receipt)
+                .await
+                .map_err(|err| Box::new(err) as LedgerError)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use x402_core::{
+        facilitator::{SettleFailed, SettleResult},
+        types::AmountValue,
+    };
+
+    use crate::{
+        paywall::PayWall,
+        testing::{self, MockFacilitator},
+    };
+
+    use super::*;
+
+    /// Fails to record every payment.
+    #[derive(Debug)]
+    struct Unavailable;
+
+    #[derive(Debug)]
+    struct ConnectionRefused;
+
+    impl std::fmt::Display for ConnectionRefused {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            f.write_str("connection refused")
+        }
+    }
+
+    impl std::error::Error for ConnectionRefused {}
+
+    impl Ledger for Unavailable {
+        type Error = ConnectionRefused;
+
+        async fn record(&self, _receipt: PaymentReceipt) -> Result<(), Self::Error> {
+            Err(ConnectionRefused)
+        }
+    }
+
+    fn paywall(
+        facilitator: MockFacilitator,
+        ledger: impl ErasedLedger + 'static,
+    ) -> PayWall<MockFacilitator> {
+        PayWall::builder()
+            .facilitator(facilitator)
+            .accepts(testing::requirements())
+            .resource(testing::resource())
+            .ledger(ledger)
+            .build()
+    }
+
+    async fn pay(paywall: PayWall<MockFacilitator>) -> http::StatusCode {
+        match paywall
+            .handle_payment(testing::paid_request(testing::requirements()), |_| async {
+                http::Response::new(())
+            })
+            .await
+        {
+            Ok(response) => response.status(),
+            Err(err) => err.status,
+        }
+    }
+
+    #[tokio::test]
+    async fn records_one_entry_per_settlement() {
+        let ledger = Arc::new(VecLedger::new());
+
+        assert_eq!(
+            pay(paywall(MockFacilitator::default(), ledger.clone())).await,
+            http::StatusCode::OK
+        );
+
+        let receipts = ledger.receipts();
+        assert_eq!(receipts.len(), 1);
+        let entry = &receipts[0];
+        assert_eq!(
+            entry.resource.as_deref(),
+            Some("https://example.com/resource")
+        );
+        assert_eq!(entry.payer, testing::PAYER);
+        assert_eq!(entry.amount, AmountValue(1000));
+        assert_eq!(entry.asset, testing::requirements().asset);
+        assert_eq!(entry.network, "eip155:84532");
+        assert_eq!(entry.transaction, "0xdeadbeef");
+        assert!(entry.timestamp > 0);
+
+        let json = serde_json::to_value(entry).unwrap();
+        assert_eq!(json["payTo"], testing::requirements().pay_to);
+        assert_eq!(
+            serde_json::from_value::<PaymentReceipt>(json).unwrap(),
+            *entry
+        );
+    }
+
+    #[tokio::test]
+    async fn settle_before_and_after_handler_records_once() {
+        let ledger = Arc::new(VecLedger::new());
+        let paywall = paywall(MockFacilitator::default(), ledger.clone());
+
+        paywall
+            .process_request(testing::paid_request(testing::requirements()))
+            .unwrap()
+            .settle()
+            .await
+            .unwrap()
+            .settle()
+            .await
+            .unwrap()
+            .run_handler(|_| async { http::Response::new(()) })
+            .await
+            .unwrap()
+            .settle_on_success()
+            .await
+            .unwrap();
+
+        assert_eq!(ledger.receipts().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn retry_after_failed_settlement_records_once() {
+        let ledger = Arc::new(VecLedger::new());
+        let failing = MockFacilitator {
+            settle: SettleResult::failed(SettleFailed {
+                error_reason: "transaction_reverted".to_string(),
+                payer: None,
+            }),
+            ..Default::default()
+        };
+
+        assert_eq!(
+            pay(paywall(failing, ledger.clone())).await,
+            http::StatusCode::PAYMENT_REQUIRED
+        );
+        assert!(ledger.receipts().is_empty());
+
+        assert_eq!(
+            pay(paywall(MockFacilitator::default(), ledger.clone())).await,
+            http::StatusCode::OK
+        );
+        assert_eq!(ledger.receipts().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn ledger_failure_calls_hook() {
+        let failures = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let reported = failures.clone();
+        let paywall = PayWall::builder()
+            .facilitator(MockFacilitator::default())
+            .accepts(testing::requirements())
+            .resource(testing::resource())
+            .ledger(Unavailable)
+            .on_ledger_error(
+                move |err: &dyn std::error::Error, receipt: &PaymentReceipt| {
+                    reported
+                        .lock()
+                        .unwrap()
+                        .push((err.to_string(), receipt.clone()));
+                },
+            )
+            .build();
+
+        assert_eq!(pay(paywall).await, http::StatusCode::OK);
+
+        let failures = failures.lock().unwrap();
+        assert_eq!(failures.len(), 1);
+        assert_eq!(failures[0].0, "connection refused");
+        assert_eq!(failures[0].1.transaction, "0xdeadbeef");
+        assert_eq!(
+            failures[0].1.resource.as_deref(),
+            Some("https://example.com/resource")
+        );
+    }
+
+    #[cfg(feature = "tracing")]
+    #[tokio::test]
+    #[tracing_test::traced_test]
+    async fn ledger_failure_does_not_fail_request() {
+        let response = paywall(MockFacilitator::default(), Unavailable)
+            .handle_payment(testing::paid_request(testing::requirements()), |_| async {
+                http::Response::new(())
+            })
+            .await
+            .unwrap();
+
+        assert!(response.headers().contains_key("payment-response"));
+        assert!(logs_contain(
+            "Failed to record settled payment to the ledger: transaction='0xdeadbeef'"
+        ));
+    }
+}
@@ -11,20 +11,57 @@
 //! - [`paywall`]: The main [`PayWall`](paywall::PayWall) struct and payment flow logic.
 //! - [`processor`]: Payment processing types including [`RequestProcessor`](processor::RequestProcessor)
 //!   and [`PaymentState`](processor::PaymentState).
+//! - [`balance`]: Balance checks rejecting payments the payer cannot fund before verification.
+//! - [`caps`]: Per-asset caps on payment amounts, in whole tokens.
+//! - [`config`]: Paywalls configured from a file, e.g. TOML, with assets named by their symbol.
+//! - [`degrade`]: Graceful degradation policies for when the facilitator is unreachable.
+//! - [`discovery`]: A discovery document listing the resources and accepted payments of a server.
 //! - [`errors`]: Error types for payment failures and HTTP error responses.
+//! - [`fees`]: Fee policies charging a percentage or flat fee on top of the base price.
+//! - `grpc`: Payments carried in the metadata of `tonic` gRPC calls (requires the `tonic` feature).
+//! - [`journal`]: A journal of settlements in flight, retried on restart if their response was lost.
+//! - [`ledger`]: A seller-side ledger recording every settled payment, e.g. for reconciliation.
+//! - [`messages`]: Customizable `error` messages of error responses, e.g. translated ones.
+//! - `multipart`: Validation of `multipart/form-data` requests against the declared body fields
+//!   (requires the `multipart` feature).
+//! - [`order`]: The order in which accepted payment requirements are offered to clients.
+//! - [`payee`]: Per-request `pay_to` routing for paywalls serving many tenants.
+//! - [`policy`]: Payer policies for denying payers or granting them free access.
+//! - [`pricing`]: Live prices of the accepted payment requirements from an external pricing service.
+//! - [`source`]: Per-request resources and accepted payment requirements looked up asynchronously,
+//!   with a time-to-live cache.
+//! - [`resource`]: Resource templates for deriving the resource URL from the incoming request.
+//! - `testing`: A mock facilitator and request helpers for testing paywalled applications
+//!   (requires the `test-utils` feature).
 //!
 //! ## Payment Flow
 //!
 //! The standard payment flow using [`PayWall::handle_payment`](paywall::PayWall::handle_payment):
 //!
-//! 1. **Update Accepts**: Filter payment requirements based on facilitator support.
+//! 1. **Update Accepts**: Filter payment requirements based on facilitator support, and price them
+//!    if a pricing source is set.
 //! 2. **Process Request**: Extract and validate the `PAYMENT-SIGNATURE` header.
-//! 3. **Verify**: Verify the payment signature with the facilitator.
-//! 4. **Run Handler**: Execute the resource handler.
-//! 5. **Settle**: Settle the payment on successful response.
+//! 3. **Check Balance**: Reject payments the payer cannot fund, if a balance checker is set.
+//! 4. **Verify**: Verify the payment signature with the facilitator.
+//! 5. **Run Handler**: Execute the resource handler.
+//! 6. **Settle**: Settle the payment on successful response.
 //!
 //! For custom flows, use the step-by-step API directly. See [`PayWall`](paywall::PayWall) for details.
 //!
+//! ## Metrics
+//!
+//! With the `metrics` feature, facilitator calls are recorded through the
+//! [`metrics`](https://docs.rs/metrics) facade, so any exporter, e.g. Prometheus, can be installed:
+//!
+//! - `x402_verify_total`: counter of verifications, labelled with `scheme`, `network` and
+//!   `outcome` (`valid`, `invalid` or `error`).
+//! - `x402_settle_total`: counter of settlements, labelled with `scheme`, `network` and
+//!   `outcome` (`success`, `failed` or `error`).
+//! - `x402_settle_amount`: histogram of settled amounts in the asset's smallest units, labelled
+//!   with `scheme`, `network` and `asset`.
+//! - `x402_ledger_errors_total`: counter of settled payments the [`ledger`] failed to record,
+//!   labelled with `scheme` and `network`.
+//!
 //! ## Framework Integration
 //!
 //! While framework-agnostic, `x402-paywall` works seamlessly with any HTTP framework.
@@ -33,26 +70,128 @@
 //!
 //! ## Error Handling
 //!
-//! [`ErrorResponse`](errors::ErrorResponse) implements `IntoResponse` for Axum and can be
-//! easily adapted to other frameworks. It returns appropriate HTTP status codes:
+//! [`ErrorResponse`](errors::ErrorResponse) implements `IntoResponse` for Axum with the `axum`
+//! feature, and converts into a plain `http::Response` with
+//! [`into_http_response`](errors::ErrorResponse::into_http_response) for other frameworks. It
+//! returns appropriate HTTP status codes:
 //!
 //! - `402 Payment Required`: No payment signature provided.
 //! - `400 Bad Request`: Invalid payment payload or unsupported requirements.
+//! - `413 Payload Too Large` / `415 Unsupported Media Type`: The request failed the
+//!   [`check_request`](paywall::PayWall::check_request) pre-checks.
 //! - `500 Internal Server Error`: Facilitator communication failures.
+//! - `504 Gateway Timeout`: The payment flow exceeded [`PayWall::flow_timeout`](paywall::PayWall::flow_timeout).
+//!
+//! Each response also carries a typed [`PaymentError`](errors::PaymentError) cause, so callers can
+//! react to specific failures, e.g. alert only on facilitator errors.
+//!
+//! Bodies are the `PaymentRequired` object by default, or an RFC 9457 `application/problem+json`
+//! document with [`ErrorFormat::Problem`](errors::ErrorFormat::Problem). Set
+//! [`PayWall::retry_after`](paywall::PayWall::retry_after) to advise clients when to retry
+//! `5xx` failures.
 
 use std::fmt::Display;
 
+pub mod balance;
+pub mod caps;
+pub mod config;
+pub mod degrade;
+pub mod discovery;
 pub mod errors;
+pub mod fees;
+#[cfg(feature = "tonic")]
+pub mod grpc;
+pub mod journal;
+pub mod ledger;
+pub mod messages;
+#[cfg(feature = "multipart")]
+pub mod multipart;
+pub mod order;
+pub mod payee;
 pub mod paywall;
+pub mod policy;
+pub mod pricing;
 pub mod processor;
+pub mod resource;
+pub mod source;
 
+#[cfg(any(test, feature = "test-utils"))]
+pub mod testing;
+
+/// An HTTP request the paywall can process.
+///
+/// Implemented for [`http::Request`], with the `actix-web` feature for
+/// `actix_web::HttpRequest` and `actix_web::dev::ServiceRequest`, and with the `tonic` feature
+/// for `tonic::Request`, so the same request type flows from the framework into the paywall and
+/// on into the resource handler.
 pub trait HttpRequest {
+    /// The raw value of the named header, if present.
     fn get_header(&self, name: &str) -> Option<&[u8]>;
+
+    /// Insert a typed extension, returning the previous value of the same type.
+    ///
+    /// Used to hand the [`PaymentState`](processor::PaymentState) to the resource handler.
     fn insert_extension<T: Clone + Send + Sync + 'static>(&mut self, ext: T) -> Option<T>;
+
+    /// The method of the request, e.g. `GET`, if known.
+    fn method(&self) -> Option<&str> {
+        None
+    }
+
+    /// The URI scheme of the request, if known.
+    fn scheme(&self) -> Option<&str> {
+        None
+    }
+
+    /// The URI authority of the request, i.e. the `:authority` of HTTP/2 requests or the host of
+    /// absolute-form HTTP/1 requests, if known.
+    fn authority(&self) -> Option<&str> {
+        None
+    }
+
+    /// The URI path of the request, if known.
+    fn path(&self) -> Option<&str> {
+        None
+    }
+
+    /// The query string of the request URI, without the leading `?`, if any.
+    fn query(&self) -> Option<&str> {
+        None
+    }
+
+    /// Replace the query string of the request URI, removing it if `None`.
+    ///
+    /// Returns `false` if the request type does not support changing its URI, or the query is
+    /// invalid. Used to strip the payment from the query, see
+    /// [`PayWall::payment_query_param`](paywall::PayWall::payment_query_param).
+    fn set_query(&mut self, _query: Option<&str>) -> bool {
+        false
+    }
+
+    /// A copy of the method, URI, version and headers of the request, if available.
+    ///
+    /// Extensions are not copied. Used to resolve the payee with a
+    /// [`PayToResolver`](payee::PayToResolver).
+    fn request_parts(&self) -> Option<http::request::Parts> {
+        None
+    }
 }
 
+/// An HTTP response returned by a paywalled resource handler.
+///
+/// Implemented for [`http::Response`], with the `actix-web` feature for
+/// `actix_web::HttpResponse` and `actix_web::dev::ServiceResponse`, and with the `tonic` feature
+/// for `tonic::Response`.
 pub trait HttpResponse {
+    /// Whether the handler succeeded, i.e. the status is `2xx`.
+    ///
+    /// Payments are only settled for successful responses.
     fn is_success(&self) -> bool;
+
+    /// Insert a header, replacing any existing value.
+    ///
+    /// Returns [`InvalidHeaderValue`] if the value is not a valid header value, in which
+    /// case the response is left unchanged.
     fn insert_header(&mut self, name: &'static str, value: &[u8])
     -> Result<(), InvalidHeaderValue>;
 }
@@ -65,8 +204,63 @@ impl<R> HttpRequest for http::Request<R> {
     fn insert_extension<T: Clone + Send + Sync + 'static>(&mut self, ext: T) -> Option<T> {
         self.extensions_mut().insert(ext)
     }
+
+    fn method(&self) -> Option<&str> {
+        Some(self.method().as_str())
+    }
+
+    fn scheme(&self) -> Option<&str> {
+        self.uri().scheme_str()
+    }
+
+    fn authority(&self) -> Option<&str> {
+        self.uri().authority().map(|authority| authority.as_str())
+    }
+
+    fn path(&self) -> Option<&str> {
+        Some(self.uri().path())
+    }
+
+    fn query(&self) -> Option<&str> {
+        self.uri().query()
+    }
+
+    fn set_query(&mut self, query: Option<&str>) -> bool {
+        let Some(path_and_query) = with_query(self.uri().path(), query) else {
+            return false;
+        };
+        let mut parts = self.uri().clone().into_parts();
+        parts.path_and_query = Some(path_and_query);
+
+        match http::Uri::from_parts(parts) {
+            Ok(uri) => {
+                *self.uri_mut() = uri;
+                true
+            }
+            Err(_) => false,
+        }
+    }
+
+    fn request_parts(&self) -> Option<http::request::Parts> {
+        let mut request = http::Request::new(());
+        *request.method_mut() = self.method().clone();
+        *request.uri_mut() = self.uri().clone();
+        *request.version_mut() = self.version();
+        *request.headers_mut() = self.headers().clone();
+
+        Some(request.into_parts().0)
+    }
 }
 
+/// The path and query of a URI with the given path and query string.
+fn with_query<T: std::str::FromStr>(path: &str, query: Option<&str>) -> Option<T> {
+    match query {
+        Some(query) => format!("{path}?{query}").parse().ok(),
+        None => path.parse().ok(),
+    }
+}
+
+/// The header value passed to [`HttpResponse::insert_header`] was invalid.
 #[derive(Debug)]
 pub struct InvalidHeaderValue;
 
@@ -94,9 +288,39 @@ impl<R> HttpResponse for http::Response<R> {
 
 #[cfg(feature = "actix-web")]
 mod actix_impl {
-    use actix_web::HttpMessage;
+    use actix_web::{
+        HttpMessage,
+        dev::{RequestHead, ServiceRequest, ServiceResponse},
+        http::{
+            Uri,
+            header::{HeaderMap, HeaderName, HeaderValue},
+        },
+    };
 
     use super::*;
+
+    fn insert_header(
+        headers: &mut HeaderMap,
+        name: &'static str,
+        value: &[u8],
+    ) -> Result<(), InvalidHeaderValue> {
+        let value = HeaderValue::from_bytes(value).map_err(|_| InvalidHeaderValue)?;
+        headers.insert(HeaderName::from_static(name), value);
+        Ok(())
+    }
+
+    /// Convert the request head, as actix-web uses its own version of the `http` types.
+    fn request_parts(head: &RequestHead) -> Option<http::request::Parts> {
+        let builder = http::Request::builder()
+            .method(head.method.as_str())
+            .uri(head.uri.to_string());
+        let builder = head.headers.iter().fold(builder, |builder, (name, value)| {
+            builder.header(name.as_str(), value.as_bytes())
+        });
+
+        builder.body(()).ok().map(|request| request.into_parts().0)
+    }
+
     impl HttpRequest for actix_web::HttpRequest {
         fn get_header(&self, name: &str) -> Option<&[u8]> {
             self.headers().get(name).map(|v| v.as_bytes())
@@ -105,6 +329,80 @@ mod actix_impl {
         fn insert_extension<T: Clone + Send + Sync + 'static>(&mut self, ext: T) -> Option<T> {
             self.extensions_mut().insert(ext)
         }
+
+        fn method(&self) -> Option<&str> {
+            Some(self.method().as_str())
+        }
+
+        fn scheme(&self) -> Option<&str> {
+            self.uri().scheme_str()
+        }
+
+        fn authority(&self) -> Option<&str> {
+            self.uri().authority().map(|authority| authority.as_str())
+        }
+
+        fn path(&self) -> Option<&str> {
+            Some(self.uri().path())
+        }
+
+        fn query(&self) -> Option<&str> {
+            self.uri().query()
+        }
+
+        fn request_parts(&self) -> Option<http::request::Parts> {
+            request_parts(self.head())
+        }
+    }
+
+    impl HttpRequest for ServiceRequest {
+        fn get_header(&self, name: &str) -> Option<&[u8]> {
+            self.headers().get(name).map(|v| v.as_bytes())
+        }
+
+        fn insert_extension<T: Clone + Send + Sync + 'static>(&mut self, ext: T) -> Option<T> {
+            self.extensions_mut().insert(ext)
+        }
+
+        fn method(&self) -> Option<&str> {
+            Some(self.method().as_str())
+        }
+
+        fn scheme(&self) -> Option<&str> {
+            self.uri().scheme_str()
+        }
+
+        fn authority(&self) -> Option<&str> {
+            self.uri().authority().map(|authority| authority.as_str())
+        }
+
+        fn path(&self) -> Option<&str> {
+            Some(self.uri().path())
+        }
+
+        fn query(&self) -> Option<&str> {
+            self.uri().query()
+        }
+
+        fn set_query(&mut self, query: Option<&str>) -> bool {
+            let Some(path_and_query) = with_query(self.uri().path(), query) else {
+                return false;
+            };
+            let mut parts = self.uri().clone().into_parts();
+            parts.path_and_query = Some(path_and_query);
+
+            match Uri::from_parts(parts) {
+                Ok(uri) => {
+                    self.head_mut().uri = uri;
+                    true
+                }
+                Err(_) => false,
+            }
+        }
+
+        fn request_parts(&self) -> Option<http::request::Parts> {
+            request_parts(self.head())
+        }
     }
 
     impl<B> HttpResponse for actix_web::HttpResponse<B> {
@@ -117,11 +415,173 @@ mod actix_impl {
             name: &'static str,
             value: &[u8],
         ) -> Result<(), InvalidHeaderValue> {
-            let value = actix_web::http::header::HeaderValue::from_bytes(value)
-                .map_err(|_| InvalidHeaderValue)?;
-            let name = actix_web::http::header::HeaderName::from_static(name);
-            self.headers_mut().insert(name, value);
-            Ok(())
+            insert_header(self.headers_mut(), name, value)
+        }
+    }
+
+    impl<B> HttpResponse for ServiceResponse<B> {
+        fn is_success(&self) -> bool {
+            self.status().is_success()
         }
+
+        fn insert_header(
+            &mut self,
+            name: &'static str,
+            value: &[u8],
+        ) -> Result<(), InvalidHeaderValue> {
+            insert_header(self.headers_mut(), name, value)
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use actix_web::test::TestRequest;
+
+        use super::*;
+
+        fn request() -> TestRequest {
+            TestRequest::get()
+                .uri("http://example.com/premium")
+                .insert_header(("PAYMENT-SIGNATURE", "signature"))
+        }
+
+        fn assert_request(request: &mut impl HttpRequest) {
+            assert_eq!(
+                request.get_header("payment-signature"),
+                Some(&b"signature"[..])
+            );
+            assert_eq!(request.get_header("host"), None);
+            assert_eq!(HttpRequest::method(request), Some("GET"));
+            assert_eq!(request.scheme(), Some("http"));
+            assert_eq!(request.path(), Some("/premium"));
+
+            let parts = request.request_parts().unwrap();
+            assert_eq!(parts.method, http::Method::GET);
+            assert_eq!(parts.uri, "http://example.com/premium");
+            assert_eq!(parts.headers["payment-signature"], "signature");
+
+            assert_eq!(request.insert_extension(1u8), None);
+            assert_eq!(request.insert_extension(2u8), Some(1));
+        }
+
+        fn assert_response(response: &mut impl HttpResponse) {
+            assert!(
+                response
+                    .insert_header("payment-response", b"receipt")
+                    .is_ok()
+            );
+            assert!(
+                response
+                    .insert_header("payment-response", b"bad\n")
+                    .is_err()
+            );
+        }
+
+        #[test]
+        fn http_request() {
+            assert_request(&mut request().to_http_request());
+        }
+
+        #[test]
+        fn service_request() {
+            let mut request = request().to_srv_request();
+            assert_request(&mut request);
+            assert_eq!(request.extensions().get::<u8>(), Some(&2));
+
+            assert!(request.set_query(Some("id=1")));
+            assert_eq!(request.query(), Some("id=1"));
+            assert_eq!(request.query_string(), "id=1");
+            assert!(request.set_query(None));
+            assert_eq!(request.uri(), "http://example.com/premium");
+        }
+
+        #[test]
+        fn http_response() {
+            let mut response = actix_web::HttpResponse::Ok().finish();
+            assert!(response.is_success());
+            assert_response(&mut response);
+            assert_eq!(
+                response.headers().get("payment-response").unwrap(),
+                "receipt"
+            );
+
+            assert!(!actix_web::HttpResponse::BadRequest().finish().is_success());
+        }
+
+        #[test]
+        fn service_response() {
+            let mut response = request().to_srv_response(actix_web::HttpResponse::Ok().finish());
+            assert!(response.is_success());
+            assert_response(&mut response);
+            assert_eq!(
+                response.headers().get("payment-response").unwrap(),
+                "receipt"
+            );
+
+            let response =
+                request().to_srv_response(actix_web::HttpResponse::BadGateway().finish());
+            assert!(!response.is_success());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn http_request() {
+        let mut request = http::Request::builder()
+            .uri("https://example.com/premium?id=1")
+            .header("PAYMENT-SIGNATURE", "signature")
+            .body(())
+            .unwrap();
+
+        assert_eq!(
+            request.get_header("payment-signature"),
+            Some(&b"signature"[..])
+        );
+        assert_eq!(request.get_header("host"), None);
+        assert_eq!(HttpRequest::method(&request), Some("GET"));
+        assert_eq!(request.scheme(), Some("https"));
+        assert_eq!(request.path(), Some("/premium"));
+        assert_eq!(request.insert_extension(1u8), None);
+        assert_eq!(request.insert_extension(2u8), Some(1));
+        assert_eq!(request.extensions().get::<u8>(), Some(&2));
+
+        let parts = request.request_parts().unwrap();
+        assert_eq!(parts.uri, "https://example.com/premium?id=1");
+        assert_eq!(parts.headers["payment-signature"], "signature");
+        assert!(parts.extensions.get::<u8>().is_none());
+
+        assert_eq!(request.query(), Some("id=1"));
+        assert!(request.set_query(Some("id=2&lang=en")));
+        assert_eq!(request.uri(), "https://example.com/premium?id=2&lang=en");
+        assert!(request.set_query(None));
+        assert_eq!(request.uri(), "https://example.com/premium");
+    }
+
+    #[test]
+    fn http_response() {
+        let mut response = http::Response::new(());
+        assert!(response.is_success());
+
+        assert!(
+            response
+                .insert_header("payment-response", b"receipt")
+                .is_ok()
+        );
+        assert!(
+            response
+                .insert_header("payment-response", b"bad\n")
+                .is_err()
+        );
+        assert_eq!(
+            response.headers().get("payment-response").unwrap(),
+            "receipt"
+        );
+
+        *response.status_mut() = http::StatusCode::INTERNAL_SERVER_ERROR;
+        assert!(!response.is_success());
     }
 }
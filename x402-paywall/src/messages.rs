@@ -0,0 +1,218 @@
+//! The `error` messages of the paywall's error responses.
+//!
+//! Some clients show the `error` of the `PaymentRequired` body to end users, so [`Messages`] on a
+//! [`PayWall`](crate::paywall::PayWall) lets servers translate them, or hide details such as the
+//! facilitator's reasons for rejecting a payment. The message is sent both in the body and in the
+//! encoded `PAYMENT-REQUIRED` or `PAYMENT-RESPONSE` header, while the typed
+//! [`cause`](crate::errors::ErrorResponse::cause) of the error is left untouched for logging.
+
+use std::borrow::Cow;
+
+use crate::errors::PaymentError;
+
+/// The `error` messages of the paywall's error responses.
+///
+/// The defaults are the [`Display`](std::fmt::Display) output of the [`PaymentError`] causing
+/// the response.
+///
+/// # Example
+///
+/// ```
+/// use x402_paywall::{errors::PaymentError, messages::Messages};
+///
+/// let messages = Messages {
+///     payment_required: "お支払いが必要です".to_string(),
+///     payment_failed: |cause| match cause {
+///         PaymentError::SettleFailed(_) => "決済に失敗しました".to_string(),
+///         _ => "お支払いを確認できませんでした".to_string(),
+///     },
+///     // Do not reveal why the facilitator rejected the payment
+///     redact_reason: |_| "rejected".to_string(),
+///     ..Default::default()
+/// };
+/// ```
+#[derive(Debug, Clone)]
+pub struct Messages {
+    /// The message of the `402` challenge to requests without a `PAYMENT-SIGNATURE` header.
+    pub payment_required: String,
+    /// The message of `400` responses to malformed, unaccepted, or denied payments.
+    pub invalid_payment: fn(&PaymentError) -> String,
+    /// The message of `402` responses to payments the facilitator failed to verify or settle.
+    pub payment_failed: fn(&PaymentError) -> String,
+    /// The message of `5xx` responses, i.e. server errors and timeouts.
+    pub server_error: fn(&PaymentError) -> String,
+    /// The message of requests rejected before payment processing, with `415` or `413`.
+    pub request_rejected: fn(&PaymentError) -> String,
+    /// Redacts the reasons the facilitator gave for failing to verify or settle a payment,
+    /// before they are passed to the message of the response.
+    pub redact_reason: fn(&str) -> String,
+}
+
+impl Default for Messages {
+    fn default() -> Self {
+        Messages {
+            payment_required: PaymentError::MissingSignature.to_string(),
+            invalid_payment: PaymentError::to_string,
+            payment_failed: PaymentError::to_string,
+            server_error: PaymentError::to_string,
+            request_rejected: PaymentError::to_string,
+            redact_reason: str::to_string,
+        }
+    }
+}
+
+impl Messages {
+    /// The message for the cause, with the facilitator's reasons redacted.
+    pub fn format(&self, message: fn(&PaymentError) -> String, cause: &PaymentError) -> String {
+        message(&self.redacted(cause))
+    }
+
+    fn redacted<'a>(&self, cause: &'a PaymentError) -> Cow<'a, PaymentError> {
+        match cause {
+            PaymentError::VerifyFailed(reason) => {
+                Cow::Owned(PaymentError::VerifyFailed((self.redact_reason)(reason)))
+            }
+            PaymentError::SettleFailed(reason) => {
+                Cow::Owned(PaymentError::SettleFailed((self.redact_reason)(reason)))
+            }
+            cause => Cow::Borrowed(cause),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use x402_core::{
+        facilitator::{SettleFailed, SettleResult, VerifyInvalid, VerifyResult},
+        transport::PaymentRequired,
+        types::Base64EncodedHeader,
+    };
+
+    use crate::{
+        errors::{ErrorResponse, ErrorResponseHeader},
+        paywall::PayWall,
+        testing::{self, MockFacilitator},
+    };
+
+    use super::*;
+
+    fn japanese() -> Messages {
+        Messages {
+            payment_required: "お支払いが必要です".to_string(),
+            invalid_payment: |_| "無効なお支払いです".to_string(),
+            payment_failed: |cause| format!("お支払いに失敗しました: {cause}"),
+            redact_reason: |_| "rejected".to_string(),
+            ..Default::default()
+        }
+    }
+
+    fn paywall(facilitator: MockFacilitator) -> PayWall<MockFacilitator> {
+        PayWall::builder()
+            .facilitator(facilitator)
+            .accepts(testing::requirements())
+            .resource(testing::resource())
+            .messages(japanese())
+            .build()
+    }
+
+    /// The `error` of the body and of the `PaymentRequired` encoded in the header.
+    fn messages(err: ErrorResponse) -> (String, String) {
+        let header = match err.header {
+            ErrorResponseHeader::PaymentRequired(header)
+            | ErrorResponseHeader::PaymentResponse(header) => header,
+            ErrorResponseHeader::None => panic!("missing header"),
+        };
+        let header =
+            PaymentRequired::try_from(Base64EncodedHeader(header.to_str().unwrap().to_string()))
+                .unwrap();
+
+        (err.body.error, header.error)
+    }
+
+    #[test]
+    fn defaults_match_payment_errors() {
+        let paywall = PayWall::builder()
+            .facilitator(MockFacilitator::default())
+            .accepts(testing::requirements())
+            .resource(testing::resource())
+            .build();
+
+        let (body, header) = messages(paywall.payment_required());
+        assert_eq!(body, "PAYMENT-SIGNATURE header is required");
+        assert_eq!(header, body);
+
+        let (body, header) = messages(
+            paywall.payment_failed(PaymentError::VerifyFailed("invalid_signature".into())),
+        );
+        assert_eq!(body, "invalid_signature");
+        assert_eq!(header, body);
+    }
+
+    #[tokio::test]
+    async fn custom_messages_in_body_and_header() {
+        let paywall = paywall(MockFacilitator::default());
+
+        let (body, header) = messages(paywall.payment_required());
+        assert_eq!(body, "お支払いが必要です");
+        assert_eq!(header, body);
+
+        let err = paywall
+            .handle_payment(
+                testing::request_with_payment(
+                    http::Request::builder(),
+                    &Base64EncodedHeader("not base64".to_string()),
+                )
+                .body(())
+                .unwrap(),
+                |_| async { http::Response::new(()) },
+            )
+            .await
+            .unwrap_err();
+        assert!(matches!(err.cause, PaymentError::MalformedHeader(_)));
+        let (body, header) = messages(err);
+        assert_eq!(body, "無効なお支払いです");
+        assert_eq!(header, body);
+    }
+
+    #[tokio::test]
+    async fn facilitator_reasons_are_redacted() {
+        let facilitator = MockFacilitator {
+            verify: VerifyResult::invalid(VerifyInvalid {
+                invalid_reason: "insufficient_funds".to_string(),
+                payer: None,
+            }),
+            ..Default::default()
+        };
+        let err = paywall(facilitator)
+            .handle_payment(testing::paid_request(testing::requirements()), |_| async {
+                http::Response::new(())
+            })
+            .await
+            .unwrap_err();
+
+        // The cause keeps the reason for logging
+        assert_eq!(
+            err.cause,
+            PaymentError::VerifyFailed("insufficient_funds".to_string())
+        );
+        let (body, header) = messages(err);
+        assert_eq!(body, "お支払いに失敗しました: rejected");
+        assert_eq!(header, body);
+
+        let facilitator = MockFacilitator {
+            settle: SettleResult::failed(SettleFailed {
+                error_reason: "transaction_reverted".to_string(),
+                payer: None,
+            }),
+            ..Default::default()
+        };
+        let err = paywall(facilitator)
+            .handle_payment(testing::paid_request(testing::requirements()), |_| async {
+                http::Response::new(())
+            })
+            .await
+            .unwrap_err();
+        let (body, _) = messages(err);
+        assert_eq!(body, "お支払いに失敗しました: rejected");
+    }
+}
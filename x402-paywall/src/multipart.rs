@@ -0,0 +1,186 @@
+//! Validation of `multipart/form-data` requests against the declared body fields, e.g. for
+//! paywalled file-upload endpoints.
+//!
+//! Declare the fields of the upload in the [`HttpInput`](x402_core::types::HttpInput) of the
+//! resource's output schema, with a [`MultipartFormData`](x402_core::types::InputBodyType::MultipartFormData)
+//! body type, then check incoming requests with [`validate_multipart`] before accepting their
+//! payment, so that clients are not charged for uploads missing a required field:
+//!
+//! ```
+//! use x402_core::types::{FieldDefinition, HttpInput, InputBodyType, Method, Required};
+//!
+//! let input = HttpInput::builder()
+//!     .discoverable(true)
+//!     .method(Method::Post)
+//!     .body_type(InputBodyType::MultipartFormData)
+//!     .body_fields([
+//!         (
+//!             "file",
+//!             FieldDefinition::builder()
+//!                 .field_type("binary")
+//!                 .description("The document to convert")
+//!                 .required(Required)
+//!                 .build(),
+//!         ),
+//!         ("title", FieldDefinition::builder().field_type("string").build()),
+//!     ])
+//!     .build();
+//! ```
+//!
+//! Only the presence of the required fields is checked, not their content.
+//!
+//! Available with the `multipart` feature.
+
+use std::{convert::Infallible, fmt::Display};
+
+use bytes::Bytes;
+use http::{HeaderMap, header::CONTENT_TYPE};
+use x402_core::types::{FieldDefinition, FieldRequired, Record};
+
+/// The reason a request failed [`validate_multipart`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum MultipartError {
+    /// The `Content-Type` is not `multipart/form-data` with a boundary.
+    NotMultipart,
+    /// The body is not valid `multipart/form-data`.
+    Malformed(String),
+    /// The body lacks fields marked with `required: true`, sorted by name.
+    MissingFields(Vec<String>),
+}
+
+impl Display for MultipartError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            MultipartError::NotMultipart => {
+                f.write_str("Content-Type must be multipart/form-data with a boundary")
+            }
+            MultipartError::Malformed(reason) => write!(f, "Malformed multipart body: {reason}"),
+            MultipartError::MissingFields(names) => {
+                write!(f, "Missing required fields: {}", names.join(", "))
+            }
+        }
+    }
+}
+
+impl std::error::Error for MultipartError {}
+
+/// Check that the `multipart/form-data` body carries every field of `body_fields` marked with
+/// `required: true`.
+///
+/// The boundary is read from the `Content-Type` of `headers`. Fields not declared in
+/// `body_fields` are allowed.
+pub async fn validate_multipart(
+    headers: &HeaderMap,
+    body: impl Into<Bytes>,
+    body_fields: &Record<FieldDefinition>,
+) -> Result<(), MultipartError> {
+    let boundary = headers
+        .get(CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|content_type| multer::parse_boundary(content_type).ok())
+        .ok_or(MultipartError::NotMultipart)?;
+
+    let body = body.into();
+    let mut multipart = multer::Multipart::new(
+        futures_util::stream::once(async move { Ok::<_, Infallible>(body) }),
+        boundary,
+    );
+
+    let mut present = Vec::new();
+    while let Some(field) = multipart
+        .next_field()
+        .await
+        .map_err(|err| MultipartError::Malformed(err.to_string()))?
+    {
+        if let Some(name) = field.name() {
+            present.push(name.to_string());
+        }
+    }
+
+    let mut missing = body_fields
+        .iter()
+        .filter(|(_, field)| matches!(field.required, Some(FieldRequired::Boolean(true))))
+        .map(|(name, _)| name)
+        .filter(|name| !present.contains(name))
+        .cloned()
+        .collect::<Vec<_>>();
+    missing.sort();
+
+    if missing.is_empty() {
+        Ok(())
+    } else {
+        Err(MultipartError::MissingFields(missing))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use http::HeaderValue;
+    use x402_core::types::Required;
+
+    use super::*;
+
+    fn headers(content_type: &'static str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(CONTENT_TYPE, HeaderValue::from_static(content_type));
+        headers
+    }
+
+    fn upload_fields() -> Record<FieldDefinition> {
+        [
+            (
+                "file".to_string(),
+                FieldDefinition::builder()
+                    .field_type("binary")
+                    .required(Required)
+                    .build(),
+            ),
+            (
+                "title".to_string(),
+                FieldDefinition::builder().field_type("string").build(),
+            ),
+        ]
+        .into_iter()
+        .collect()
+    }
+
+    const CONTENT_TYPE_WITH_BOUNDARY: &str = "multipart/form-data; boundary=X-BOUNDARY";
+
+    #[tokio::test]
+    async fn required_file_is_present() {
+        let body = "--X-BOUNDARY\r\n\
+            Content-Disposition: form-data; name=\"file\"; filename=\"doc.pdf\"\r\n\
+            Content-Type: application/pdf\r\n\r\n\
+            %PDF-1.7\r\n\
+            --X-BOUNDARY--\r\n";
+
+        validate_multipart(&headers(CONTENT_TYPE_WITH_BOUNDARY), body, &upload_fields())
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn required_file_is_absent() {
+        let body = "--X-BOUNDARY\r\n\
+            Content-Disposition: form-data; name=\"title\"\r\n\r\n\
+            Quarterly report\r\n\
+            --X-BOUNDARY--\r\n";
+
+        let err = validate_multipart(&headers(CONTENT_TYPE_WITH_BOUNDARY), body, &upload_fields())
+            .await
+            .unwrap_err();
+
+        assert_eq!(err, MultipartError::MissingFields(vec!["file".to_string()]));
+        assert_eq!(err.to_string(), "Missing required fields: file");
+    }
+
+    #[tokio::test]
+    async fn rejects_other_content_types() {
+        let err = validate_multipart(&headers("application/json"), "{}", &upload_fields())
+            .await
+            .unwrap_err();
+
+        assert_eq!(err, MultipartError::NotMultipart);
+    }
+}
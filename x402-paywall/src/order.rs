@@ -0,0 +1,203 @@
+//! The order in which accepted payment requirements are offered to clients.
+//!
+//! Clients commonly pay with the first entry of the `accepts` list, so an [`AcceptsOrder`] on a
+//! [`PayWall`](crate::paywall::PayWall) decides which payment option is preferred. It is applied
+//! to the advertised accepts of every error response, after fees and after filtering by the
+//! facilitator's supported kinds, so that the order is the same for every response.
+
+use x402_core::transport::{Accepts, PaymentRequirements};
+
+/// The order of the accepted payment requirements advertised by the paywall.
+///
+/// All orders are stable: requirements that compare equal keep their configured order.
+///
+/// # Example
+///
+/// ```
+/// use x402_paywall::order::AcceptsOrder;
+///
+/// // Offer Base first, then Solana, then every other network in the configured order
+/// let order = AcceptsOrder::PreferNetworks(vec![
+///     "eip155:8453".to_string(),
+///     "solana:5eykt4UsFv8P8NJdTREpY1vzqKqZKvdp".to_string(),
+/// ]);
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum AcceptsOrder {
+    /// The order the requirements were configured in.
+    #[default]
+    AsConfigured,
+    /// Lowest amount first, in the smallest units of each asset.
+    CheapestFirst,
+    /// Requirements on the given networks (CAIP-2 identifiers) first, in the order of the list.
+    PreferNetworks(Vec<String>),
+}
+
+impl AcceptsOrder {
+    /// Sort the accepted payment requirements.
+    ///
+    /// The configured order returns the requirements unchanged, without copying them.
+    pub fn apply(&self, accepts: Accepts) -> Accepts {
+        match self {
+            AcceptsOrder::AsConfigured => accepts,
+            AcceptsOrder::CheapestFirst => sorted_by_key(accepts, |pr| pr.amount.0),
+            AcceptsOrder::PreferNetworks(networks) => sorted_by_key(accepts, |pr| {
+                networks
+                    .iter()
+                    .position(|network| *network == pr.network)
+                    .unwrap_or(networks.len())
+            }),
+        }
+    }
+}
+
+fn sorted_by_key<K: Ord>(accepts: Accepts, key: impl FnMut(&PaymentRequirements) -> K) -> Accepts {
+    let mut accepts: Vec<PaymentRequirements> = accepts.into_iter().collect();
+    accepts.sort_by_key(key);
+    accepts.into()
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+    use x402_core::types::AmountValue;
+
+    use crate::{
+        paywall::PayWall,
+        testing::{self, MockFacilitator},
+    };
+
+    use super::*;
+
+    fn requirements(network: &str, amount: u128) -> PaymentRequirements {
+        PaymentRequirements {
+            network: network.to_string(),
+            amount: AmountValue(amount),
+            extra: None,
+            ..testing::requirements()
+        }
+    }
+
+    fn advertised(order: AcceptsOrder) -> serde_json::Value {
+        let paywall = PayWall::builder()
+            .facilitator(MockFacilitator::default())
+            .accepts(vec![
+                requirements("eip155:1", 3000),
+                requirements("eip155:8453", 1000),
+                requirements("eip155:84532", 2000),
+                requirements("eip155:11155111", 1000),
+            ])
+            .resource(testing::resource())
+            .accepts_order(order)
+            .build();
+
+        let accepts = serde_json::to_value(&paywall.payment_required().body.accepts).unwrap();
+        accepts
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|pr| json!([pr["network"], pr["amount"]]))
+            .collect()
+    }
+
+    #[test]
+    fn as_configured_shares_requirements() {
+        let accepts = Accepts::from(vec![
+            requirements("eip155:1", 3000),
+            requirements("eip155:8453", 1000),
+        ]);
+
+        assert!(
+            AcceptsOrder::AsConfigured
+                .apply(accepts.clone())
+                .ptr_eq(&accepts)
+        );
+    }
+
+    #[test]
+    fn as_configured() {
+        assert_eq!(
+            advertised(AcceptsOrder::AsConfigured),
+            json!([
+                ["eip155:1", "3000"],
+                ["eip155:8453", "1000"],
+                ["eip155:84532", "2000"],
+                ["eip155:11155111", "1000"],
+            ])
+        );
+    }
+
+    #[test]
+    fn cheapest_first() {
+        assert_eq!(
+            advertised(AcceptsOrder::CheapestFirst),
+            json!([
+                ["eip155:8453", "1000"],
+                ["eip155:11155111", "1000"],
+                ["eip155:84532", "2000"],
+                ["eip155:1", "3000"],
+            ])
+        );
+    }
+
+    #[test]
+    fn prefer_networks() {
+        assert_eq!(
+            advertised(AcceptsOrder::PreferNetworks(vec![
+                "eip155:84532".to_string(),
+                "eip155:1".to_string(),
+            ])),
+            json!([
+                ["eip155:84532", "2000"],
+                ["eip155:1", "3000"],
+                ["eip155:8453", "1000"],
+                ["eip155:11155111", "1000"],
+            ])
+        );
+    }
+
+    #[tokio::test]
+    async fn order_is_stable_across_update_accepts() {
+        // The facilitator lists the supported networks in another order
+        let facilitator = MockFacilitator {
+            supported: serde_json::from_value(json!({
+                "kinds": [
+                    { "x402Version": 2, "scheme": "exact", "network": "eip155:84532" },
+                    { "x402Version": 2, "scheme": "exact", "network": "eip155:1" },
+                    { "x402Version": 2, "scheme": "exact", "network": "eip155:8453" }
+                ],
+                "extensions": [],
+                "signers": {}
+            }))
+            .unwrap(),
+            ..Default::default()
+        };
+        let paywall = PayWall::builder()
+            .facilitator(facilitator)
+            .accepts(vec![
+                requirements("eip155:1", 3000),
+                requirements("eip155:8453", 1000),
+                requirements("eip155:84532", 2000),
+            ])
+            .resource(testing::resource())
+            .accepts_order(AcceptsOrder::CheapestFirst)
+            .build();
+
+        let before = paywall.payment_required().body.accepts;
+        let paywall = paywall.update_accepts().await.unwrap();
+        let after = paywall.payment_required().body.accepts;
+
+        assert_eq!(before, after);
+        assert_eq!(
+            after.iter().map(|pr| pr.amount.0).collect::<Vec<_>>(),
+            [1000, 2000, 3000]
+        );
+
+        // Payloads match the advertised requirements regardless of their position
+        let processor = paywall
+            .process_request(testing::paid_request(requirements("eip155:1", 3000)))
+            .unwrap();
+        assert_eq!(processor.selected.network, "eip155:1");
+    }
+}
@@ -0,0 +1,210 @@
+//! Per-request `pay_to` routing for multi-tenant paywalls.
+//!
+//! A gateway in front of many creators pays each creator directly: a [`PayToResolver`] on the
+//! [`PayWall`](crate::paywall::PayWall) picks the payee from the incoming request, and the `pay_to`
+//! of every accepted payment requirement is rewritten before the requirements are advertised or
+//! matched against the payload.
+
+use std::fmt::{Debug, Display};
+
+use bon::Builder;
+use x402_core::transport::PaymentRequirements;
+
+/// The payee addresses resolved for a request, by network family.
+///
+/// # Example
+///
+/// ```
+/// use x402_paywall::payee::ResolvedPayee;
+///
+/// let payee = ResolvedPayee::builder()
+///     .evm("0x3CB9B3bBfde8501f411bB69Ad3DC07908ED0dE20")
+///     .build();
+///
+/// assert!(payee.svm.is_none());
+/// ```
+#[derive(Builder, Debug, Clone, Default, PartialEq, Eq)]
+pub struct ResolvedPayee {
+    /// The payee on EVM (`eip155:`) networks.
+    #[builder(with = |address: impl Display| address.to_string())]
+    pub evm: Option<String>,
+    /// The payee on SVM (`solana:`) networks.
+    #[builder(with = |address: impl Display| address.to_string())]
+    pub svm: Option<String>,
+}
+
+impl ResolvedPayee {
+    /// The payee for the network family of the given network (CAIP-2 identifier).
+    pub fn for_network(&self, network: &str) -> Option<&str> {
+        match network.split_once(':').map(|(namespace, _)| namespace) {
+            Some("eip155") => self.evm.as_deref(),
+            Some("solana") => self.svm.as_deref(),
+            _ => None,
+        }
+    }
+
+    /// Rewrite the `pay_to` of the given payment requirements.
+    ///
+    /// Returns the reason if no payee is resolved for the network family of the requirements.
+    pub fn apply(
+        &self,
+        mut requirements: PaymentRequirements,
+    ) -> Result<PaymentRequirements, String> {
+        let pay_to = self.for_network(&requirements.network).ok_or_else(|| {
+            format!(
+                "No pay_to address resolved for {}@{}",
+                requirements.scheme, requirements.network
+            )
+        })?;
+
+        requirements.pay_to = pay_to.to_string();
+        Ok(requirements)
+    }
+}
+
+/// A hook resolving the payee of a request, e.g. from the tenant in its host or path.
+pub trait PayToResolver: Debug + Send + Sync {
+    /// Resolve the payee for the given request.
+    ///
+    /// Returns `None` to keep the configured `pay_to` addresses.
+    fn resolve(&self, parts: &http::request::Parts) -> Option<ResolvedPayee>;
+}
+
+#[cfg(test)]
+mod tests {
+    use http::StatusCode;
+
+    use crate::{
+        errors::{ErrorResponse, PaymentError},
+        paywall::PayWall,
+        processor::PaymentState,
+        testing::{self, MockFacilitator},
+    };
+
+    use super::*;
+
+    const ALICE: &str = "0x00000000000000000000000000000000000A11CE";
+    const BOB: &str = "0x0000000000000000000000000000000000000B0B";
+
+    /// Routes `/alice/..` and `/bob/..` to their payees, and `/carol/..` to a payee without an EVM address.
+    #[derive(Debug)]
+    struct TenantResolver;
+
+    impl PayToResolver for TenantResolver {
+        fn resolve(&self, parts: &http::request::Parts) -> Option<ResolvedPayee> {
+            match parts.uri.path().split('/').nth(1)? {
+                "alice" => Some(ResolvedPayee::builder().evm(ALICE).build()),
+                "bob" => Some(ResolvedPayee::builder().evm(BOB).build()),
+                "carol" => Some(
+                    ResolvedPayee::builder()
+                        .svm("Ge3jkza5KRfXvaq3GELNLh6V1pjjdEKNpEdGXJgjjKUR")
+                        .build(),
+                ),
+                _ => None,
+            }
+        }
+    }
+
+    fn paywall() -> PayWall<MockFacilitator> {
+        PayWall::builder()
+            .facilitator(MockFacilitator::default())
+            .accepts(testing::requirements())
+            .resource(testing::resource())
+            .pay_to_resolver(TenantResolver)
+            .build()
+    }
+
+    fn request(path: &str, accepted: Option<PaymentRequirements>) -> http::Request<()> {
+        let builder = http::Request::builder().uri(format!("https://example.com{path}"));
+        match accepted {
+            Some(accepted) => {
+                testing::request_with_payment(builder, &testing::payment_header(accepted))
+            }
+            None => builder,
+        }
+        .body(())
+        .unwrap()
+    }
+
+    async fn handle(request: http::Request<()>) -> Result<http::Response<()>, ErrorResponse> {
+        paywall()
+            .handle_payment(request, |_| async { http::Response::new(()) })
+            .await
+    }
+
+    fn tenant_requirements(pay_to: &str) -> PaymentRequirements {
+        PaymentRequirements {
+            pay_to: pay_to.to_string(),
+            ..testing::requirements()
+        }
+    }
+
+    #[tokio::test]
+    async fn tenants_are_challenged_with_their_payee() {
+        let alice = handle(request("/alice/report", None)).await.unwrap_err();
+        let bob = handle(request("/bob/report", None)).await.unwrap_err();
+
+        assert_eq!(alice.status, StatusCode::PAYMENT_REQUIRED);
+        assert_eq!(alice.body.accepts.as_ref()[0].pay_to, ALICE);
+        assert_eq!(bob.status, StatusCode::PAYMENT_REQUIRED);
+        assert_eq!(bob.body.accepts.as_ref()[0].pay_to, BOB);
+    }
+
+    #[tokio::test]
+    async fn selection_matches_rewritten_payee() {
+        let paywall = paywall();
+        let calls = paywall.facilitator.calls.clone();
+
+        let response = paywall
+            .handle_payment(
+                request("/bob/report", Some(tenant_requirements(BOB))),
+                |req: http::Request<()>| async move {
+                    http::Response::new(req.extensions().get::<PaymentState>().cloned())
+                },
+            )
+            .await
+            .unwrap();
+
+        let state = response.body().as_ref().unwrap();
+        assert_eq!(state.selected.pay_to, BOB);
+        assert_eq!(
+            calls
+                .last_settle_request()
+                .unwrap()
+                .payment_requirements
+                .pay_to,
+            BOB
+        );
+
+        // Paying another tenant, or the configured payee, does not match the rewritten requirements
+        for pay_to in [ALICE, testing::requirements().pay_to.as_str()] {
+            let err = handle(request("/bob/report", Some(tenant_requirements(pay_to))))
+                .await
+                .unwrap_err();
+            assert_eq!(err.status, StatusCode::BAD_REQUEST);
+            assert!(matches!(err.cause, PaymentError::UnsupportedOption));
+        }
+    }
+
+    #[tokio::test]
+    async fn unresolved_request_keeps_configured_payee() {
+        let err = handle(request("/public/report", None)).await.unwrap_err();
+
+        assert_eq!(err.status, StatusCode::PAYMENT_REQUIRED);
+        assert_eq!(err.body.accepts.as_ref()[0], testing::requirements());
+    }
+
+    #[tokio::test]
+    async fn missing_family_payee_is_rejected() {
+        let err = handle(request("/carol/report", Some(tenant_requirements(BOB))))
+            .await
+            .unwrap_err();
+
+        assert_eq!(err.status, StatusCode::INTERNAL_SERVER_ERROR);
+        assert!(matches!(err.cause, PaymentError::Misconfigured(_)));
+        assert_eq!(
+            err.body.error,
+            "No pay_to address resolved for exact@eip155:84532"
+        );
+    }
+}
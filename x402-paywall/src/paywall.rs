@@ -2,22 +2,131 @@
 //!
 //! For details, see the [`PayWall`] struct documentation.
 
-use std::fmt::Display;
+use std::{
+    collections::HashSet,
+    fmt::Display,
+    ops::RangeInclusive,
+    pin::Pin,
+    sync::{Arc, Mutex, PoisonError},
+};
+
+#[cfg(not(feature = "tokio"))]
+use std::time::Instant;
+#[cfg(feature = "tokio")]
+use tokio::time::Instant;
 
 use bon::Builder;
+use http::HeaderValue;
+use serde::de::DeserializeOwned;
 use x402_core::{
     core::Resource,
-    facilitator::{Facilitator, SupportedResponse},
-    transport::{Accepts, PaymentPayload},
-    types::{Base64EncodedHeader, Extension, Record},
+    facilitator::{Facilitator, SettleSuccess, SettlementKey, SupportedDiff, SupportedResponse},
+    transport::{Accepts, PaymentPayload, PaymentRequired, PaymentRequirements},
+    types::{
+        AnyJson, Base64EncodedHeader, Extension, ExtensionInfo, KnownAssetRegistry, Record,
+        merge_extra,
+    },
 };
 
 use crate::{
     HttpRequest, HttpResponse,
-    errors::ErrorResponse,
-    processor::{PaymentState, RequestProcessor},
+    balance::BalanceChecker,
+    caps::AmountCaps,
+    degrade::{Degrade, SupportedCache, UnverifiedSlots},
+    errors::{ErrorFormat, ErrorResponse, PaymentError, encode_header},
+    fees::FeePolicy,
+    journal::SettlementJournal,
+    ledger::{ErasedLedger, LedgerErrorHook, PaymentReceipt},
+    messages::Messages,
+    order::AcceptsOrder,
+    payee::PayToResolver,
+    policy::PayerPolicy,
+    pricing::{PricingContext, PricingSource},
+    processor::{PaymentResponseHeader, PaymentState, RequestProcessor},
+    resource::ResourceTemplate,
+    source::AcceptsSource,
 };
 
+/// An extension every payload must carry, see [`PayWallBuilder::require_extension_strict`].
+#[derive(Debug, Clone)]
+pub struct RequiredExtension {
+    /// The identifier of the extension, i.e. its key in the payload's `extensions`.
+    pub id: String,
+    /// Check of the extension's `info`, rejecting it with a reason.
+    pub validate: Option<ExtensionValidator>,
+}
+
+/// Checks the `info` of a [`RequiredExtension`], rejecting it with a reason.
+pub type ExtensionValidator = fn(&AnyJson) -> Result<(), String>;
+
+impl<F: Facilitator, S: pay_wall_builder::State> PayWallBuilder<F, S> {
+    /// Reject payloads without the extension with an invalid payment, regardless of
+    /// [`strict_extensions`](PayWall::strict_extensions).
+    ///
+    /// The extension is usually also among the advertised [`extensions`](PayWall::extensions),
+    /// so that clients know to provide it. Call once per required extension.
+    pub fn require_extension_strict(mut self, id: impl Into<String>) -> Self {
+        self.required_extensions.push(RequiredExtension {
+            id: id.into(),
+            validate: None,
+        });
+        self
+    }
+
+    /// Reject payloads without the extension `T`, or whose `info` does not deserialize into `T`,
+    /// with an invalid payment.
+    ///
+    /// Like [`require_extension_strict`](Self::require_extension_strict) with [`ExtensionInfo::ID`],
+    /// also checking the `info` against the extension's type.
+    pub fn require_typed_extension_strict<T: ExtensionInfo + DeserializeOwned>(mut self) -> Self {
+        self.required_extensions.push(RequiredExtension {
+            id: T::ID.to_string(),
+            validate: Some(|info| {
+                serde_json::from_value::<T>(info.clone())
+                    .map(|_| ())
+                    .map_err(|err| format!("Extension {} is invalid: {err}", T::ID))
+            }),
+        });
+        self
+    }
+
+    /// Run `callback` after every successful settlement, e.g. to grant an entitlement or enqueue
+    /// fulfillment, with the settlement and the selected payment requirements.
+    ///
+    /// The callback runs once the settlement is confirmed and recorded to the
+    /// [`ledger`](PayWall::ledger), before the response is built. Its error fails the request
+    /// with a `500 Internal Server Error` and a [`PaymentError::SettledHookFailed`], although the
+    /// payment is settled.
+    pub fn on_settled<Fun, Fut, E>(mut self, callback: Fun) -> Self
+    where
+        Fun: Fn(&SettleSuccess, &PaymentRequirements) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<(), E>> + Send + 'static,
+        E: Display,
+    {
+        self.on_settled = Some(Arc::new(
+            move |settled: &SettleSuccess, selected: &PaymentRequirements| -> SettledFuture {
+                let call = callback(settled, selected);
+                Box::pin(async move { call.await.map_err(|err| err.to_string()) })
+            },
+        ));
+        self
+    }
+}
+
+/// The future returned by the callback of [`PayWall::on_settled`].
+pub type SettledFuture = Pin<Box<dyn Future<Output = Result<(), String>> + Send>>;
+
+/// The callback of [`PayWall::on_settled`], see [`PayWallBuilder::on_settled`].
+pub type SettledCallback =
+    Arc<dyn Fn(&SettleSuccess, &PaymentRequirements) -> SettledFuture + Send + Sync>;
+
+/// The callback of [`PayWall::on_supported_change`].
+pub type SupportedChangeCallback = Arc<dyn Fn(&SupportedDiff) + Send + Sync>;
+
+/// The extension advertising the [`order_id`](PayWall::order_id) of a paywall, with an info of
+/// `{ "orderId": "..." }`.
+pub const ORDER_ID_EXTENSION: &str = "orderId";
+
 /// A HTTP paywall that uses a facilitator to verify and settle payments.
 ///
 /// `PayWall` provides a flexible and composable API for protecting resources with X402 payments.
@@ -45,11 +154,13 @@ use crate::{
 ///    based on what the facilitator supports.
 /// 2. **Process Request** ([`process_request`](PayWall::process_request)): Extracts and validates the
 ///    `PAYMENT-SIGNATURE` header, creating a [`RequestProcessor`].
-/// 3. **Verify** ([`RequestProcessor::verify`](crate::processor::RequestProcessor::verify)): Verifies the
+/// 3. **Check Balance** ([`RequestProcessor::check_balance`](crate::processor::RequestProcessor::check_balance)):
+///    Rejects payments the payer cannot fund, if a [`balance_checker`](PayWall::balance_checker) is set.
+/// 4. **Verify** ([`RequestProcessor::verify`](crate::processor::RequestProcessor::verify)): Verifies the
 ///    payment signature with the facilitator.
-/// 4. **Run Handler** ([`RequestProcessor::run_handler`](crate::processor::RequestProcessor::run_handler)):
+/// 5. **Run Handler** ([`RequestProcessor::run_handler`](crate::processor::RequestProcessor::run_handler)):
 ///    Executes the resource handler, injecting [`PaymentState`] into request extensions.
-/// 5. **Settle on Success** ([`ResponseProcessor::settle_on_success`](crate::processor::ResponseProcessor::settle_on_success)):
+/// 6. **Settle on Success** ([`ResponseProcessor::settle_on_success`](crate::processor::ResponseProcessor::settle_on_success)):
 ///    Settles the payment only if the handler returned a successful response.
 ///
 /// ## Custom Payment Flow
@@ -57,61 +168,660 @@ use crate::{
 /// For more control, use the step-by-step API directly. You can skip steps, reorder them,
 /// or add custom logic between steps. For example, you might skip verification, settle before
 /// running the handler, or add logging between steps.
-#[derive(Builder, Debug, Clone)]
+#[derive(Builder, Clone)]
 pub struct PayWall<F: Facilitator> {
+    /// Extensions every payload must carry, see [`PayWallBuilder::require_extension_strict`].
+    #[builder(field)]
+    pub required_extensions: Vec<RequiredExtension>,
+    /// Callback run after every successful settlement, see [`PayWallBuilder::on_settled`].
+    #[builder(field)]
+    pub on_settled: Option<SettledCallback>,
     /// The facilitator to use for payment verification and settlement.
     pub facilitator: F,
     /// The resource this paywall serves.
+    ///
+    /// If [`resource_from_request`](PayWall::resource_template) is set, this is only used
+    /// for requests the resource cannot be derived from.
     pub resource: Resource,
-    /// The accepted payment requirements.
+    /// Template deriving the resource URL from the incoming request, see [`ResourceTemplate`].
+    #[builder(name = resource_from_request)]
+    pub resource_template: Option<ResourceTemplate>,
+    /// The accepted payment requirements, at their base price.
+    ///
+    /// Clients are offered these with the [`fee_policy`](PayWall::fee_policy) applied,
+    /// see [`advertised_accepts`](PayWall::advertised_accepts).
     #[builder(into)]
     pub accepts: Accepts,
+    /// Source replacing the [`resource`](PayWall::resource) and the [`accepts`](PayWall::accepts)
+    /// with the ones looked up for each request, see [`with_request_accepts`](PayWall::with_request_accepts).
+    #[builder(with = |source: impl AcceptsSource + 'static| Arc::new(source))]
+    pub accepts_source: Option<Arc<dyn AcceptsSource>>,
+    /// Bounds of the `max_timeout_seconds` of the advertised payment requirements, e.g. `30..=600`.
+    ///
+    /// Guards against typos in the scheme builders, e.g. `60_000` seconds letting authorizations
+    /// stay valid for hours, which facilitators capping the timeout would fail to verify.
+    #[builder(name = clamp_timeout)]
+    pub timeout_clamp: Option<RangeInclusive<u64>>,
+    /// Fees charged on top of the base price of every accepted payment requirement.
+    #[builder(name = fees)]
+    pub fee_policy: Option<FeePolicy>,
+    /// The order of the advertised payment requirements, see [`AcceptsOrder`].
+    #[builder(default)]
+    pub accepts_order: AcceptsOrder,
+    /// Hook resolving the `pay_to` of the accepted payment requirements per request,
+    /// see [`with_request_payee`](PayWall::with_request_payee).
+    #[builder(with = |resolver: impl PayToResolver + 'static| Arc::new(resolver))]
+    pub pay_to_resolver: Option<Arc<dyn PayToResolver>>,
+    /// Source of the live price of the accepted payment requirements, applied when the accepts
+    /// are updated, see [`PricingSource`].
+    #[builder(with = |source: impl PricingSource + 'static| Arc::new(source))]
+    pub pricing_source: Option<Arc<dyn PricingSource>>,
+    /// Caps on the amount of payments in known assets, checked when processing a request.
+    pub amount_caps: Option<AmountCaps>,
+    /// Assets whose amounts are displayed in whole tokens in the tracing output, e.g. `0.001000 USDC`.
+    ///
+    /// Amounts of other assets are displayed in their smallest units.
+    #[builder(default)]
+    pub known_assets: KnownAssetRegistry,
+    /// Check consulted before verification to reject payments the payer cannot fund,
+    /// see [`RequestProcessor::check_balance`].
+    #[builder(with = |checker: impl BalanceChecker + 'static| Arc::new(checker))]
+    pub balance_checker: Option<Arc<dyn BalanceChecker>>,
     /// Additional extensions to use.
     #[builder(default)]
     pub extensions: Record<Extension>,
+    /// The seller's order id for the payment, carried from the challenge to the settlement for
+    /// reconciliation.
+    ///
+    /// Advertised in the [`ORDER_ID_EXTENSION`] extension, then recorded in
+    /// [`PaymentState::order_id`] and echoed in the `PAYMENT-RESPONSE` header, receipts, ledger
+    /// entries and the `x402.payment` span.
+    #[builder(into)]
+    pub order_id: Option<String>,
+    /// Reject payloads missing any of the [`advertised_extensions`](PayWall::advertised_extensions)
+    /// with an invalid payment, e.g. to require a KYC attestation from every buyer.
+    ///
+    /// Off by default, as clients may ignore extensions they do not support.
+    #[builder(default)]
+    pub strict_extensions: bool,
+    /// Reject payloads that parse but violate the protocol with an invalid payment, each with a
+    /// specific [`PaymentError::NonConformant`] reason:
+    ///
+    /// - an `x402Version` other than the one of the accepted requirements, i.e. `2`;
+    /// - a `resource.url` other than the [`resource`](PayWall::resource) of the paywall;
+    /// - extensions the paywall did not [advertise](PayWall::advertised_extensions).
+    ///
+    /// Off by default, as lenient clients are common: payloads of another version are rejected
+    /// as malformed, and the resource and extra extensions are ignored.
+    #[builder(default)]
+    pub strict: bool,
+    /// Reject verifications whose payer differs from the signer of the payload, as reported by a
+    /// misbehaving or malicious facilitator.
+    ///
+    /// The signer is the `authorization.from` of `exact` payloads or the `permit.owner` of
+    /// `permit` payloads on EVM networks; other payloads are not checked.
+    #[builder(default)]
+    pub verify_payer_consistency: bool,
+    /// Request methods passed through to the handler by [`handle_payment`](PayWall::handle_payment)
+    /// without requiring or settling a payment, `HEAD` and `OPTIONS` by default.
+    ///
+    /// Clients send `HEAD` to check the metadata of a resource, and proxies send `OPTIONS`
+    /// preflights without a payment, so charging for them makes no sense. The handler still runs
+    /// for these methods, so it must not serve paid content to them. Set an empty list to require
+    /// a payment for every method.
+    #[builder(default = vec![http::Method::HEAD, http::Method::OPTIONS], into)]
+    pub pass_through_methods: Vec<http::Method>,
+    /// Content type required for requests, checked before any payment processing.
+    ///
+    /// Requests without a matching `Content-Type` header are rejected with `415 Unsupported Media Type`.
+    #[builder(into, name = require_content_type)]
+    pub required_content_type: Option<String>,
+    /// Maximum request body size in bytes, checked before any payment processing.
+    ///
+    /// Only the `Content-Length` header is checked since the body is not read, so requests
+    /// without it pass. Larger requests are rejected with `413 Payload Too Large`.
+    pub max_body_hint: Option<u64>,
+    /// Maximum size in bytes of the `PAYMENT-SIGNATURE` header, 16 KiB by default.
+    ///
+    /// Larger headers are rejected with `400 Bad Request` before being decoded.
+    #[builder(default = 16 * 1024)]
+    pub max_payment_header_size: usize,
+    /// Query parameter carrying the payment for clients that cannot set headers, e.g. webhooks,
+    /// read by [`process_request`](PayWall::process_request) when `PAYMENT-SIGNATURE` is absent.
+    ///
+    /// The value is the same base64 payload as the header, percent-encoded, and is subject to the
+    /// same [`max_payment_header_size`](PayWall::max_payment_header_size). The parameter is
+    /// stripped from the request URI before the handler runs, for request types supporting it
+    /// (see [`HttpRequest::set_query`]).
+    ///
+    /// # Security
+    ///
+    /// Unlike headers, URLs end up in access logs, proxy logs, browser history and `Referer`
+    /// headers, often before the request reaches the paywall. Anyone reading the payment there
+    /// before it is settled can present it to the paywall first, so only enable this for
+    /// integrations that cannot set headers, over HTTPS, and keep the timeout of the accepted
+    /// requirements short.
+    #[builder(into, name = accept_query_param)]
+    pub payment_query_param: Option<String>,
+    /// Skip updating the accepted payment requirements from the facilitator in
+    /// [`handle_payment`](PayWall::handle_payment).
+    ///
+    /// Saves the `supported` round-trip when the configured requirements are known to be
+    /// supported by the facilitator, e.g. with `extra` such as the fee payer already set.
+    #[builder(default)]
+    pub skip_supported_update: bool,
+    /// Called with the changes between consecutive `supported` responses of the facilitator,
+    /// e.g. to alert when a network is removed, see [`SupportedDiff`].
+    ///
+    /// Not called for the first response, nor for unchanged ones. Each change is reported once
+    /// across the paywall and its clones.
+    #[builder(with = |callback: impl Fn(&SupportedDiff) + Send + Sync + 'static| Arc::new(callback))]
+    pub on_supported_change: Option<SupportedChangeCallback>,
+    /// Signers the facilitator is expected to settle with, e.g. its relayer addresses, to guard
+    /// against a facilitator URL pointing to an impostor.
+    ///
+    /// When set, [`update_accepts`](PayWall::update_accepts) fails with a server error if the
+    /// `signers` the facilitator advertises for a configured network include none of them, and
+    /// settlements whose signature was verified by another signer are rejected. EVM addresses are
+    /// compared case-insensitively.
+    #[builder(default, with = |signers: impl IntoIterator<Item = impl Into<String>>| {
+        signers.into_iter().map(Into::into).collect()
+    })]
+    pub expected_signers: Vec<String>,
+    /// Ledger recording every payment the paywall settles, see [`ledger`](crate::ledger).
+    #[builder(with = |ledger: impl ErasedLedger + 'static| Arc::new(ledger))]
+    pub ledger: Option<Arc<dyn ErasedLedger>>,
+    /// Called with the ledger error and the receipt whenever the [`ledger`](PayWall::ledger)
+    /// fails to record a payment, e.g. to record it later, as the request does not fail.
+    #[builder(with = |hook: impl Fn(&dyn std::error::Error, &PaymentReceipt) + Send + Sync + 'static| Arc::new(hook))]
+    pub on_ledger_error: Option<LedgerErrorHook>,
+    /// Journal of settlements in flight, retried on restart if their response was lost, see
+    /// [`SettlementJournal`].
+    #[builder(with = |journal: impl SettlementJournal + 'static| Arc::new(journal))]
+    pub journal: Option<Arc<dyn SettlementJournal>>,
+    /// Policy consulted after verification to allow, deny, or grant free access to the payer.
+    #[builder(with = |policy: impl PayerPolicy + 'static| Arc::new(policy))]
+    pub payer_policy: Option<Arc<dyn PayerPolicy>>,
+    /// What to do while the facilitator is unreachable, see [`Degrade`].
+    ///
+    /// Requests fail with a `500 Internal Server Error` during outages by default.
+    #[builder(name = on_facilitator_unreachable)]
+    pub facilitator_unreachable: Option<Degrade>,
+    /// Send an `ETag` with the `402` challenge, and answer requests without a payment whose
+    /// `If-None-Match` matches it with a `304 Not Modified`.
+    ///
+    /// The `304` carries the `PAYMENT-REQUIRED` header but no body, so that clients polling a
+    /// paywalled resource can skip decoding an unchanged challenge. The `ETag` changes with the
+    /// price, the resource, or the extensions.
+    #[builder(default)]
+    pub challenge_etag: bool,
+    /// The body format of error responses, see [`ErrorFormat`].
+    #[builder(default)]
+    pub error_format: ErrorFormat,
+    /// The `error` messages of error responses, e.g. translated or with the facilitator's
+    /// reasons redacted, see [`Messages`].
+    #[builder(default)]
+    pub messages: Messages,
+    /// Seconds after which clients may retry, sent as `Retry-After` on `5xx` error responses.
+    ///
+    /// Facilitator outages and timeouts are transient, while rejected payments cannot succeed
+    /// when retried unchanged, so `4xx` responses never carry the header.
+    pub retry_after: Option<u64>,
+    /// The header carrying the settlement response, `PAYMENT-RESPONSE` by default.
+    ///
+    /// Set to [`PaymentResponseHeader::Both`] to also write `X-PAYMENT-RESPONSE` for x402 v1 clients.
+    #[builder(default)]
+    pub payment_response_header: PaymentResponseHeader,
+    /// Maximum time between successful verification and the start of settlement.
+    ///
+    /// Payments whose settlement would start later, e.g. after a slow resource handler, fail with
+    /// `402 Payment Required` and are never settled. Payments that were not verified, such as
+    /// those served with free access or unverified, are not subject to the deadline.
+    pub settlement_deadline: Option<std::time::Duration>,
+    /// Maximum duration of the [`handle_payment`](PayWall::handle_payment) flow before settlement.
+    ///
+    /// The timeout covers updating accepts, verification, and the resource handler. Once settlement
+    /// has started it is allowed to complete: cancelling an in-flight settlement could leave the
+    /// payer charged on-chain while the client receives an error. Flows exceeding the timeout
+    /// before settlement fail with a `504 Gateway Timeout` and are never settled.
+    #[cfg(feature = "tokio")]
+    pub flow_timeout: Option<std::time::Duration>,
+    /// Poll the facilitator's settlement status until the transaction is confirmed, before
+    /// attaching the `PAYMENT-RESPONSE` header.
+    ///
+    /// For facilitators that accept settlements immediately and confirm them asynchronously.
+    /// Settlements not confirmed within [`SettlementPolling::deadline`] fail with a
+    /// `504 Gateway Timeout`.
+    #[cfg(feature = "tokio")]
+    pub settlement_polling: Option<SettlementPolling>,
+    /// Settle before running the resource handler, and deliver the resource only once the
+    /// settlement reaches this confirmation status, e.g. `ConfirmationStatus::Confirmed(1)`.
+    ///
+    /// For high-value resources that must not be delivered for a merely broadcast transaction.
+    /// The status reported with the settlement is polled with
+    /// [`Facilitator::settle_status`] until it is reached, with the backoff and deadline of
+    /// [`settlement_polling`](PayWall::settlement_polling) if set, or its defaults.
+    #[cfg(feature = "tokio")]
+    pub require_confirmation: Option<x402_core::facilitator::ConfirmationStatus>,
+    /// What to do when the facilitator reports a settlement as pending, see [`OnPending`].
+    ///
+    /// Pending settlements fail with a `402 Payment Required` by default, like failed ones.
+    #[builder(default)]
+    pub on_pending: OnPending,
+    /// The encoded `PAYMENT-REQUIRED` header of the last challenge, see [`PayWall::payment_required`].
+    #[builder(skip)]
+    payment_required_cache: PaymentRequiredCache,
+    /// The settlement keys of the payments being settled, see [`RequestProcessor::settle`].
+    #[builder(skip)]
+    pub(crate) settling: SettlingKeys,
+    /// The last supported payment kinds fetched from the facilitator, see [`Degrade`].
+    #[builder(skip)]
+    supported_cache: SupportedCache,
+    /// The unverified payments being served, see [`Degrade::AllowUnverified`].
+    #[builder(skip)]
+    pub(crate) unverified_slots: UnverifiedSlots,
+}
+
+/// The [`SettlementKey`]s of the payments being settled.
+///
+/// Shared by clones of the paywall, so that concurrent retries of the same payment, e.g. by a
+/// client that timed out waiting for the response, are not settled twice.
+#[derive(Clone, Default)]
+pub(crate) struct SettlingKeys(Arc<Mutex<HashSet<SettlementKey>>>);
+
+impl SettlingKeys {
+    /// Mark the payment as being settled until the returned guard is dropped.
+    ///
+    /// Returns `None` if the payment is already being settled.
+    pub(crate) fn begin(&self, key: &SettlementKey) -> Option<SettlingGuard> {
+        let inserted = self
+            .0
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .insert(key.clone());
+
+        inserted.then(|| SettlingGuard {
+            keys: self.clone(),
+            key: key.clone(),
+        })
+    }
+}
+
+/// Releases the settlement key of a payment when dropped.
+pub(crate) struct SettlingGuard {
+    keys: SettlingKeys,
+    key: SettlementKey,
+}
+
+impl Drop for SettlingGuard {
+    fn drop(&mut self) {
+        self.keys
+            .0
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .remove(&self.key);
+    }
+}
+
+/// The last `PaymentRequired` challenge with its encoded `PAYMENT-REQUIRED` header.
+///
+/// Shared by clones of the paywall, so that the paywalls cloned per request reuse the header
+/// as long as the resource, the advertised accepts, and the extensions do not change.
+#[derive(Clone, Default)]
+struct PaymentRequiredCache(Arc<Mutex<Option<(PaymentRequired, HeaderValue)>>>);
+
+impl PaymentRequiredCache {
+    /// The header of the challenge, encoded only if it differs from the cached one.
+    fn header_for(&self, payment_required: &PaymentRequired) -> HeaderValue {
+        let cached = self.0.lock().unwrap_or_else(PoisonError::into_inner);
+        if let Some((snapshot, header)) = cached.as_ref()
+            && snapshot == payment_required
+        {
+            return header.clone();
+        }
+        drop(cached);
+
+        let header = encode_header(payment_required);
+        *self.0.lock().unwrap_or_else(PoisonError::into_inner) =
+            Some((payment_required.clone(), header.clone()));
+        header
+    }
+}
+
+/// Whether the `key=value` pair of a query string has the given key, once percent-decoded.
+fn query_key_is(pair: &str, name: &str) -> bool {
+    let key = pair.split_once('=').map_or(pair, |(key, _)| key);
+    url::form_urlencoded::parse(key.as_bytes())
+        .next()
+        .is_some_and(|(key, _)| key == name)
+}
+
+/// Whether an `If-None-Match` header matches the `ETag`, with the weak comparison of RFC 9110.
+fn etag_matches(etag: &HeaderValue, if_none_match: &[u8]) -> bool {
+    let Ok(if_none_match) = str::from_utf8(if_none_match) else {
+        return false;
+    };
+    let etag = etag.to_str().unwrap_or_default();
+
+    if_none_match
+        .split(',')
+        .map(str::trim)
+        .any(|tag| tag == "*" || tag.strip_prefix("W/").unwrap_or(tag) == etag)
+}
+
+/// Backoff and deadline for polling the settlement status, see [`PayWall::settlement_polling`].
+#[cfg(feature = "tokio")]
+#[derive(Builder, Debug, Clone)]
+pub struct SettlementPolling {
+    /// Delay after the first pending status, doubled after each further one.
+    #[builder(default = std::time::Duration::from_millis(250))]
+    pub initial_backoff: std::time::Duration,
+    /// Upper bound of the delay between status queries.
+    #[builder(default = std::time::Duration::from_secs(2))]
+    pub max_backoff: std::time::Duration,
+    /// Maximum time to wait for the settlement to be confirmed.
+    #[builder(default = std::time::Duration::from_secs(30))]
+    pub deadline: std::time::Duration,
+}
+
+/// What a paywall does when the facilitator reports a settlement as
+/// [`Pending`](x402_core::facilitator::SettleResult::Pending), see [`PayWall::on_pending`].
+///
+/// # Example
+///
+/// ```
+/// use std::time::Duration;
+///
+/// use x402_paywall::paywall::OnPending;
+///
+/// // Poll the settlement status up to 5 times, a second apart
+/// let on_pending = OnPending::Poll {
+///     attempts: 5,
+///     interval: Duration::from_secs(1),
+/// };
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum OnPending {
+    /// Fail with a `402 Payment Required`, as for a failed settlement.
+    #[default]
+    Fail,
+    /// Treat the payment as settled with a [`Broadcast`](x402_core::facilitator::ConfirmationStatus::Broadcast)
+    /// confirmation, flagging it as not executed yet, and the facilitator's reference as its
+    /// transaction.
+    Accept,
+    /// Poll the settlement status up to `attempts` times, waiting `interval`, or the delay the
+    /// facilitator asked for, before each poll.
+    ///
+    /// Settlements still pending after the last poll fail with a `504 Gateway Timeout`, as
+    /// settlements not confirmed in time with [`PayWall::settlement_polling`].
+    #[cfg(feature = "tokio")]
+    Poll {
+        attempts: u32,
+        interval: std::time::Duration,
+    },
+}
+
+impl<F: Facilitator + std::fmt::Debug> std::fmt::Debug for PayWall<F> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mut d = f.debug_struct("PayWall");
+        d.field("facilitator", &self.facilitator)
+            .field("resource", &self.resource)
+            .field("resource_template", &self.resource_template)
+            .field("accepts", &self.accepts)
+            .field("accepts_source", &self.accepts_source)
+            .field("timeout_clamp", &self.timeout_clamp)
+            .field("fee_policy", &self.fee_policy)
+            .field("accepts_order", &self.accepts_order)
+            .field("pay_to_resolver", &self.pay_to_resolver)
+            .field("pricing_source", &self.pricing_source)
+            .field("amount_caps", &self.amount_caps)
+            .field("known_assets", &self.known_assets)
+            .field("balance_checker", &self.balance_checker)
+            .field("extensions", &self.extensions)
+            .field("order_id", &self.order_id)
+            .field("strict_extensions", &self.strict_extensions)
+            .field("required_extensions", &self.required_extensions)
+            .field("on_settled", &self.on_settled.is_some())
+            .field("strict", &self.strict)
+            .field("verify_payer_consistency", &self.verify_payer_consistency)
+            .field("pass_through_methods", &self.pass_through_methods)
+            .field("required_content_type", &self.required_content_type)
+            .field("max_body_hint", &self.max_body_hint)
+            .field("max_payment_header_size", &self.max_payment_header_size)
+            .field("payment_query_param", &self.payment_query_param)
+            .field("skip_supported_update", &self.skip_supported_update)
+            .field("on_supported_change", &self.on_supported_change.is_some())
+            .field("expected_signers", &self.expected_signers)
+            .field("ledger", &self.ledger)
+            .field("on_ledger_error", &self.on_ledger_error.is_some())
+            .field("journal", &self.journal)
+            .field("payer_policy", &self.payer_policy)
+            .field("facilitator_unreachable", &self.facilitator_unreachable)
+            .field("error_format", &self.error_format)
+            .field("messages", &self.messages)
+            .field("retry_after", &self.retry_after)
+            .field("payment_response_header", &self.payment_response_header)
+            .field("settlement_deadline", &self.settlement_deadline)
+            .field("challenge_etag", &self.challenge_etag)
+            .field("on_pending", &self.on_pending);
+        #[cfg(feature = "tokio")]
+        d.field("flow_timeout", &self.flow_timeout)
+            .field("settlement_polling", &self.settlement_polling)
+            .field("require_confirmation", &self.require_confirmation);
+        d.finish()
+    }
 }
 
 impl<F: Facilitator> PayWall<F> {
+    /// Replace the resource and the accepted payment requirements with the ones looked up by the
+    /// [`accepts_source`](PayWall::accepts_source), if set.
+    ///
+    /// Returns a server error if the lookup fails, or if the request does not expose its
+    /// [`request_parts`](HttpRequest::request_parts). Call this first, before
+    /// [`with_request_resource`](PayWall::with_request_resource) and
+    /// [`with_request_payee`](PayWall::with_request_payee), as they refine the looked up values.
+    pub async fn with_request_accepts<Req: HttpRequest>(
+        mut self,
+        request: &Req,
+    ) -> Result<Self, ErrorResponse> {
+        let parts = self.source_parts(request)?;
+        if let Some((resource, accepts)) = self.source_accepts(parts).await? {
+            self.resource = resource;
+            self.accepts = accepts;
+        }
+
+        Ok(self)
+    }
+
+    /// The parts of the request to look up the accepts for, if an accepts source is set.
+    ///
+    /// Taken before the lookup so that the request is not borrowed across it.
+    fn source_parts<Req: HttpRequest>(
+        &self,
+        request: &Req,
+    ) -> Result<Option<http::request::Parts>, ErrorResponse> {
+        if self.accepts_source.is_none() {
+            return Ok(None);
+        }
+
+        request.request_parts().map(Some).ok_or_else(|| {
+            self.server_error(PaymentError::Misconfigured(
+                "The request does not expose its parts to look up the accepts".to_string(),
+            ))
+        })
+    }
+
+    async fn source_accepts(
+        &self,
+        parts: Option<http::request::Parts>,
+    ) -> Result<Option<(Resource, Accepts)>, ErrorResponse> {
+        let (Some(source), Some(parts)) = (&self.accepts_source, parts) else {
+            return Ok(None);
+        };
+
+        match source.accepts_for(&parts).await {
+            Ok(sourced) => Ok(Some(sourced)),
+            Err(err) => {
+                #[cfg(feature = "tracing")]
+                tracing::error!("Failed to look up the accepted payment requirements: {err}");
+
+                Err(self.server_error(PaymentError::AcceptsUnavailable(err.to_string())))
+            }
+        }
+    }
+
+    /// Derive the resource from the request using the [`resource_template`](PayWall::resource_template), if set.
+    ///
+    /// Keeps the configured [`resource`](PayWall::resource) if the request carries no host.
+    /// Call this before [`process_request`](PayWall::process_request) so that error responses
+    /// advertise the derived resource.
+    pub fn with_request_resource<Req: HttpRequest>(mut self, request: &Req) -> Self {
+        if let Some(resource) = self
+            .resource_template
+            .as_ref()
+            .and_then(|template| template.resource_for(request))
+        {
+            self.resource = resource;
+        }
+
+        self
+    }
+
+    /// Rewrite the `pay_to` of the accepted payment requirements with the payee resolved by the
+    /// [`pay_to_resolver`](PayWall::pay_to_resolver), if set.
+    ///
+    /// Keeps the configured `pay_to` addresses if the resolver returns no payee for the request.
+    /// Returns a server error if the resolved payee has no address for the network family of an
+    /// accepted payment requirement, or if the request does not expose its
+    /// [`request_parts`](HttpRequest::request_parts). Call this before
+    /// [`process_request`](PayWall::process_request) so that the rewritten requirements are
+    /// both advertised and matched against the payload.
+    pub fn with_request_payee<Req: HttpRequest>(
+        mut self,
+        request: &Req,
+    ) -> Result<Self, ErrorResponse> {
+        let Some(resolver) = &self.pay_to_resolver else {
+            return Ok(self);
+        };
+        let parts = request.request_parts().ok_or_else(|| {
+            self.server_error(PaymentError::Misconfigured(
+                "The request does not expose its parts to resolve the pay_to address".to_string(),
+            ))
+        })?;
+        let Some(payee) = resolver.resolve(&parts) else {
+            return Ok(self);
+        };
+
+        let accepts = self
+            .accepts
+            .clone()
+            .into_iter()
+            .map(|pr| payee.apply(pr))
+            .collect::<Result<Accepts, _>>()
+            .map_err(|reason| self.server_error(PaymentError::Misconfigured(reason)))?;
+        self.accepts = accepts;
+
+        Ok(self)
+    }
+
+    /// Whether the request method is one of the [`pass_through_methods`](PayWall::pass_through_methods).
+    pub fn is_pass_through<Req: HttpRequest>(&self, request: &Req) -> bool {
+        request.method().is_some_and(|method| {
+            self.pass_through_methods
+                .iter()
+                .any(|pass_through| pass_through.as_str() == method)
+        })
+    }
+
+    /// Cheap checks of the request before any payment processing.
+    ///
+    /// Rejects requests not matching [`required_content_type`](PayWall::required_content_type) or
+    /// exceeding [`max_body_hint`](PayWall::max_body_hint), so that no facilitator round-trip is made
+    /// for requests the resource would reject anyway.
+    pub fn check_request<Req: HttpRequest>(&self, request: &Req) -> Result<(), ErrorResponse> {
+        if let Some(required) = &self.required_content_type {
+            let content_type = request
+                .get_header("Content-Type")
+                .and_then(|h| str::from_utf8(h).ok())
+                .unwrap_or_default();
+            let media_type = content_type.split(';').next().unwrap_or_default().trim();
+
+            if !media_type.eq_ignore_ascii_case(required) {
+                return Err(
+                    self.unsupported_media_type(PaymentError::RequestRejected(format!(
+                        "Content-Type must be {required}, got '{content_type}'"
+                    ))),
+                );
+            }
+        }
+
+        if let Some(max) = self.max_body_hint {
+            let length = request
+                .get_header("Content-Length")
+                .and_then(|h| str::from_utf8(h).ok())
+                .and_then(|s| s.trim().parse::<u64>().ok());
+
+            if let Some(length) = length.filter(|length| *length > max) {
+                return Err(
+                    self.payload_too_large(PaymentError::RequestRejected(format!(
+                        "Request body of {length} bytes exceeds the limit of {max} bytes"
+                    ))),
+                );
+            }
+        }
+
+        Ok(())
+    }
+
     /// Entrypoint of an X402 payment flow.
     ///
     /// Process an incoming request and extract payment information.
     ///
-    /// Returns a [`RequestProcessor`] on success for further processing.
+    /// Returns a [`RequestProcessor`] on success for further processing. If
+    /// [`amount_caps`](PayWall::amount_caps) are set, a selected amount above the cap of its asset
+    /// is rejected with a `500 Internal Server Error`, as the server's price is at fault. Payloads
+    /// of requirements naming a `requiredPayer` in their `extra` must be paid by that address,
+    /// otherwise they are rejected with a `400 Bad Request`.
+    ///
+    /// If [`payment_query_param`](PayWall::payment_query_param) is set, the payment is read from
+    /// that query parameter when the `PAYMENT-SIGNATURE` header is absent, and the parameter is
+    /// stripped from the request URI in any case.
     pub fn process_request<'pw, Req: HttpRequest>(
         &'pw self,
-        request: Req,
+        mut request: Req,
     ) -> Result<RequestProcessor<'pw, F, Req>, ErrorResponse> {
-        let payment_signature = request
-            .get_header("PAYMENT-SIGNATURE")
-            .ok_or_else(|| self.payment_required())
-            .and_then(|h| {
-                str::from_utf8(h).map_err(|err| {
-                    self.invalid_payment(format!(
-                        "Failed to decode PAYMENT-SIGNATURE header: {err}"
-                    ))
-                })
-            })
-            .map(|s| Base64EncodedHeader(s.to_string()))?;
-
-        let payload = PaymentPayload::try_from(payment_signature.clone()).map_err(|err| {
-            self.invalid_payment(format!("Failed to parse PAYMENT-SIGNATURE header: {err}"))
-        })?;
+        let (payload, selected) = match request.get_header("PAYMENT-SIGNATURE") {
+            Some(header) => self.extract_payment(
+                "PAYMENT-SIGNATURE header",
+                Some(header),
+                request.get_header("If-None-Match"),
+            )?,
+            None => {
+                let (source, payment) = self.query_payment(&request)?;
+                self.extract_payment(
+                    &source,
+                    payment.as_deref().map(str::as_bytes),
+                    request.get_header("If-None-Match"),
+                )?
+            }
+        };
+        self.strip_query_payment(&mut request);
 
         let initial_state = PaymentState {
             verified: None,
+            verified_at: None,
             settled: None,
-            required_extensions: self.extensions.to_owned(),
+            free_access: false,
+            unverified: None,
+            selected: selected.clone(),
+            settlement_key: SettlementKey::derive(&payload),
+            order_id: self.order_id.clone(),
+            resource: self.resource.url.clone(),
+            required_extensions: self.advertised_extensions(),
             payload_extensions: payload.extensions.clone(),
         };
 
-        let selected = self
-            .accepts
-            .clone()
-            .into_iter()
-            // Match a PaymentRequirements with PartialEq
-            .find(|a| a == &payload.accepted)
-            .ok_or_else(|| self.invalid_payment("PaymentRequirements in payload not accepted"))?;
-
         Ok(RequestProcessor {
             paywall: self,
             selected,
@@ -121,10 +831,228 @@ impl<F: Facilitator> PayWall<F> {
         })
     }
 
+    /// Extract the payment payload and the selected payment requirements from request headers.
+    ///
+    /// A lower-level alternative to [`process_request`](PayWall::process_request) for integrations
+    /// that cannot hand over the full request, e.g. because the body has already been consumed.
+    pub fn process_headers(
+        &self,
+        headers: &http::HeaderMap,
+    ) -> Result<(PaymentPayload, PaymentRequirements), ErrorResponse> {
+        self.extract_payment(
+            "PAYMENT-SIGNATURE header",
+            headers
+                .get("PAYMENT-SIGNATURE")
+                .map(|value| value.as_bytes()),
+            headers
+                .get(http::header::IF_NONE_MATCH)
+                .map(|value| value.as_bytes()),
+        )
+    }
+
+    /// The payment from the [`payment_query_param`](PayWall::payment_query_param), if set and
+    /// present, percent-decoded, with the name of its source for error messages.
+    ///
+    /// Oversized values are rejected before being decoded.
+    fn query_payment<Req: HttpRequest>(
+        &self,
+        request: &Req,
+    ) -> Result<(String, Option<String>), ErrorResponse> {
+        let Some(name) = &self.payment_query_param else {
+            return Ok(("PAYMENT-SIGNATURE header".to_string(), None));
+        };
+        let source = format!("'{name}' query parameter");
+        let Some(pair) = request
+            .query()
+            .into_iter()
+            .flat_map(|query| query.split('&'))
+            .find(|pair| query_key_is(pair, name))
+        else {
+            return Ok((source, None));
+        };
+
+        let size = pair.split_once('=').map_or(0, |(_, value)| value.len());
+        if size > self.max_payment_header_size {
+            return Err(self.invalid_payment(PaymentError::MalformedHeader(format!(
+                "{source} of {size} bytes exceeds the limit of {} bytes",
+                self.max_payment_header_size
+            ))));
+        }
+
+        // Unencoded `+` of the base64 alphabet are decoded as spaces, which base64 never contains
+        let value = url::form_urlencoded::parse(pair.as_bytes())
+            .next()
+            .map(|(_, value)| value.replace(' ', "+"));
+
+        Ok((source, value))
+    }
+
+    /// Remove the [`payment_query_param`](PayWall::payment_query_param) from the request URI, if set
+    /// and present, so that the payment does not leak into the handler or its logs.
+    fn strip_query_payment<Req: HttpRequest>(&self, request: &mut Req) {
+        let Some(name) = &self.payment_query_param else {
+            return;
+        };
+        let Some(query) = request.query() else {
+            return;
+        };
+        if !query.split('&').any(|pair| query_key_is(pair, name)) {
+            return;
+        }
+
+        let stripped = query
+            .split('&')
+            .filter(|pair| !query_key_is(pair, name))
+            .collect::<Vec<_>>()
+            .join("&");
+        let stripped = (!stripped.is_empty()).then_some(stripped);
+
+        if !request.set_query(stripped.as_deref()) {
+            #[cfg(feature = "tracing")]
+            tracing::warn!("Failed to strip the '{name}' query parameter from the request URI");
+        }
+    }
+
+    /// The protocol violations checked in [`strict`](PayWall::strict) mode, after the version.
+    fn check_conformance(&self, payload: &PaymentPayload) -> Result<(), String> {
+        if payload.resource.url != self.resource.url {
+            return Err(format!(
+                "Payload resource {} does not match the resource {}",
+                payload.resource.url, self.resource.url
+            ));
+        }
+
+        let advertised = self.advertised_extensions();
+        let mut unexpected: Vec<&str> = payload
+            .extensions
+            .keys()
+            .filter(|key| !advertised.contains_key(*key))
+            .map(String::as_str)
+            .collect();
+        if !unexpected.is_empty() {
+            unexpected.sort();
+            return Err(format!(
+                "Payload has extensions that were not advertised: {}",
+                unexpected.join(", ")
+            ));
+        }
+
+        Ok(())
+    }
+
+    fn extract_payment(
+        &self,
+        source: &str,
+        payment_signature: Option<&[u8]>,
+        if_none_match: Option<&[u8]>,
+    ) -> Result<(PaymentPayload, PaymentRequirements), ErrorResponse> {
+        let payment_signature = payment_signature
+            .ok_or_else(|| self.challenge(if_none_match))
+            .and_then(|h| {
+                if h.len() > self.max_payment_header_size {
+                    return Err(self.invalid_payment(PaymentError::MalformedHeader(format!(
+                        "{source} of {} bytes exceeds the limit of {} bytes",
+                        h.len(),
+                        self.max_payment_header_size
+                    ))));
+                }
+                Ok(h)
+            })
+            .and_then(|h| {
+                str::from_utf8(h).map_err(|err| {
+                    self.invalid_payment(PaymentError::MalformedHeader(format!(
+                        "Failed to decode {source}: {err}"
+                    )))
+                })
+            })?;
+
+        let payload = PaymentPayload::from_header(payment_signature).map_err(|err| {
+            let version = self
+                .strict
+                .then(|| payload_version(payment_signature))
+                .flatten()
+                .filter(|version| *version != 2);
+            match version {
+                Some(version) => self.invalid_payment(PaymentError::NonConformant(format!(
+                    "x402Version {version} does not match the accepted version 2"
+                ))),
+                None => self.invalid_payment(PaymentError::MalformedHeader(format!(
+                    "Failed to parse {source}: {err}"
+                ))),
+            }
+        })?;
+
+        let selected = self
+            .is_advertised(&payload.accepted)
+            .then(|| payload.accepted.clone())
+            .ok_or_else(|| {
+                // Usually signed against an earlier challenge, e.g. before a price change
+                self.invalid_payment(PaymentError::UnsupportedOption)
+                    .with_error_code(PaymentRequired::STALE_REQUIREMENTS)
+            })?;
+
+        if self.strict {
+            self.check_conformance(&payload)
+                .map_err(|reason| self.invalid_payment(PaymentError::NonConformant(reason)))?;
+        }
+
+        if self.strict_extensions {
+            let mut missing: Vec<String> = self
+                .advertised_extensions()
+                .into_keys()
+                .filter(|key| !payload.extensions.contains_key(key))
+                .collect();
+            if !missing.is_empty() {
+                missing.sort();
+                return Err(self.invalid_payment(PaymentError::MissingExtensions(missing)));
+            }
+        }
+
+        for required in &self.required_extensions {
+            let Some(extension) = payload.extensions.get(&required.id) else {
+                return Err(self
+                    .invalid_payment(PaymentError::MissingExtensions(vec![required.id.clone()])));
+            };
+            if let Some(validate) = required.validate {
+                validate(&extension.info).map_err(|reason| {
+                    self.invalid_payment(PaymentError::InvalidExtension(reason))
+                })?;
+            }
+        }
+
+        check_required_payer(&selected, &payload)
+            .map_err(|reason| self.invalid_payment(PaymentError::PayerDenied(reason)))?;
+
+        if let Some(caps) = &self.amount_caps {
+            caps.check(&selected)
+                .map_err(|reason| self.server_error(PaymentError::Misconfigured(reason)))?;
+        }
+
+        Ok((payload, selected))
+    }
+
     /// Standard payment handling flow.
     ///
-    /// This handler will **update** the accepted payment requirements from the facilitator,
+    /// This handler will **look up** the resource and the accepted payment requirements if an
+    /// [`accepts_source`](PayWall::accepts_source) is set, **update** them from the facilitator,
+    /// **check** the payer's balance if a [`balance_checker`](PayWall::balance_checker) is set,
     /// **verify** the payment, **run** the provided resource handler, and **settle** the payment on success.
+    /// Updating the accepted payment requirements is skipped if
+    /// [`skip_supported_update`](PayWall::skip_supported_update) is set.
+    ///
+    /// Requests with one of the [`pass_through_methods`](PayWall::pass_through_methods), `HEAD` and
+    /// `OPTIONS` by default, are handed to the handler directly, without payment.
+    ///
+    /// If [`flow_timeout`](PayWall::flow_timeout) is set, the steps before settlement must complete
+    /// within it, otherwise a `504 Gateway Timeout` error is returned without settling.
+    ///
+    /// With [`require_confirmation`](PayWall::require_confirmation), the payment is settled and
+    /// confirmed before running the handler instead.
+    ///
+    /// With the `tracing` feature, the flow runs in an `x402.payment` span recording the resource,
+    /// the selected scheme, network and amount, the payer, and the outcome, with the
+    /// [`code`](crate::errors::PaymentError::code) of the error of failed flows, and `x402.verify` and
+    /// `x402.settle` child spans recording the elapsed time of the facilitator calls.
     pub async fn handle_payment<Fun, Fut, Req, Res>(
         self,
         request: Req,
@@ -136,75 +1064,658 @@ impl<F: Facilitator> PayWall<F> {
         Req: HttpRequest,
         Res: HttpResponse,
     {
-        let response = self
-            .update_accepts()
-            .await?
-            .process_request(request)?
-            .verify()
-            .await?
-            .run_handler(handler)
-            .await?
-            .settle_on_success()
-            .await?
-            .response();
+        #[cfg(feature = "tracing")]
+        {
+            use tracing::{Instrument, field::Empty};
 
-        Ok(response)
-    }
+            let span = tracing::info_span!(
+                "x402.payment",
+                resource = Empty,
+                scheme = Empty,
+                network = Empty,
+                amount = Empty,
+                payer = Empty,
+                order_id = Empty,
+                outcome = Empty,
+                status = Empty,
+                error = Empty,
+            );
+            let result = self
+                .payment_flow(request, handler)
+                .instrument(span.clone())
+                .await;
 
-    /// Update the accepted payment requirements based on the facilitator's supported kinds.
-    pub async fn update_accepts(mut self) -> Result<Self, ErrorResponse> {
-        let supported = self.facilitator.supported().await.map_err(|err| {
-            self.server_error(format!("Failed to get supported payment kinds: {err}"))
-        })?;
-        let filtered = filter_supported_accepts(&supported, self.accepts.to_owned());
-        self.accepts = filtered;
+            if let Err(err) = &result {
+                span.record("outcome", "failed");
+                span.record("status", err.status.as_u16());
+                span.record("error", err.cause.code());
+            }
+            span.in_scope(|| tracing::debug!("Payment flow finished"));
 
-        Ok(self)
-    }
+            result
+        }
 
-    /// Payment needed to access resource
-    pub fn payment_required(&self) -> ErrorResponse {
-        ErrorResponse::payment_required(
-            self.resource.to_owned().into(),
-            self.accepts.to_owned(),
-            self.extensions.to_owned(),
-        )
+        #[cfg(not(feature = "tracing"))]
+        self.payment_flow(request, handler).await
     }
 
-    /// Malformed payment payload or requirements
-    pub fn invalid_payment(&self, reason: impl Display) -> ErrorResponse {
-        ErrorResponse::invalid_payment(
-            reason,
-            self.resource.to_owned().into(),
-            self.accepts.to_owned(),
-            self.extensions.to_owned(),
-        )
+    async fn payment_flow<Fun, Fut, Req, Res>(
+        self,
+        request: Req,
+        handler: Fun,
+    ) -> Result<Res, ErrorResponse>
+    where
+        Fun: FnOnce(Req) -> Fut,
+        Fut: Future<Output = Res>,
+        Req: HttpRequest,
+        Res: HttpResponse,
+    {
+        if self.is_pass_through(&request) {
+            #[cfg(feature = "tracing")]
+            tracing::Span::current().record("outcome", "pass_through");
+
+            return Ok(handler(request).await);
+        }
+
+        #[cfg(feature = "tokio")]
+        let deadline = self.flow_timeout.map(|timeout| Instant::now() + timeout);
+        #[cfg(not(feature = "tokio"))]
+        let deadline = None;
+
+        let mut paywall = self;
+        let parts = paywall.source_parts(&request)?;
+        let Some(sourced) = within(deadline, paywall.source_accepts(parts)).await else {
+            return Err(paywall.flow_timed_out());
+        };
+        if let Some((resource, accepts)) = sourced? {
+            paywall.resource = resource;
+            paywall.accepts = accepts;
+        }
+
+        let mut paywall = paywall
+            .with_request_resource(&request)
+            .with_request_payee(&request)?;
+        #[cfg(feature = "tracing")]
+        tracing::Span::current().record("resource", paywall.resource.url.as_str());
+
+        paywall.check_request(&request)?;
+
+        if !paywall.skip_supported_update {
+            let ctx = PricingContext {
+                parts: paywall
+                    .pricing_source
+                    .as_ref()
+                    .and_then(|_| request.request_parts()),
+            };
+            let Some(updated) = within(deadline, paywall.refresh_accepts(&ctx)).await else {
+                return Err(paywall.flow_timed_out());
+            };
+            updated?;
+        }
+
+        let Some(processor) = within(deadline, async {
+            let processor = paywall.process_request(request)?;
+            #[cfg(feature = "tracing")]
+            record_payment_state(&processor.payment_state, &paywall.known_assets);
+
+            let processor = processor.check_balance().await?.verify().await?;
+            #[cfg(feature = "tracing")]
+            record_payment_state(&processor.payment_state, &paywall.known_assets);
+
+            Ok(processor)
+        })
+        .await
+        else {
+            return Err(paywall.flow_timed_out());
+        };
+        let processor = processor?;
+
+        // Settlement is never cancelled by the flow timeout, see `PayWall::flow_timeout`.
+        let processor = if paywall.settles_before_handler() {
+            processor.settle().await?.run_handler(handler).await?
+        } else {
+            let Some(processor) = within(deadline, processor.run_handler(handler)).await else {
+                return Err(paywall.flow_timed_out());
+            };
+            processor?.settle_on_success().await?
+        };
+
+        #[cfg(feature = "tracing")]
+        {
+            let state = &processor.payment_state;
+            record_payment_state(state, &paywall.known_assets);
+            tracing::Span::current().record(
+                "outcome",
+                match (state.free_access, state.settled.is_some()) {
+                    _ if state.unverified.is_some() => "unverified",
+                    (true, _) => "free",
+                    (false, true) => "settled",
+                    (false, false) => "unsettled",
+                },
+            );
+        }
+
+        Ok(processor.response())
     }
 
-    /// Payment verification or settlement failed
-    pub fn payment_failed(&self, reason: impl Display) -> ErrorResponse {
-        ErrorResponse::payment_failed(
-            reason,
-            self.resource.to_owned().into(),
-            self.accepts.to_owned(),
-            self.extensions.to_owned(),
-        )
+    /// Whether the flow settles before running the handler, to deliver only confirmed payments,
+    /// see [`require_confirmation`](PayWall::require_confirmation).
+    fn settles_before_handler(&self) -> bool {
+        #[cfg(feature = "tokio")]
+        return self.require_confirmation.is_some();
+        #[cfg(not(feature = "tokio"))]
+        false
     }
 
-    /// Internal server error during payment processing
-    pub fn server_error(&self, reason: impl Display) -> ErrorResponse {
-        ErrorResponse::server_error(
-            reason,
-            self.resource.to_owned().into(),
-            self.accepts.to_owned(),
-            self.extensions.to_owned(),
-        )
+    fn flow_timed_out(&self) -> ErrorResponse {
+        #[cfg(feature = "tracing")]
+        tracing::warn!("Payment flow timed out before settlement");
+
+        self.timeout(PaymentError::Timeout)
     }
-}
 
-/// Filters the payment requirements based on the supported kinds from the facilitator.
+    /// Update the accepted payment requirements based on the facilitator's supported kinds.
+    ///
+    /// Returns a server error if none of the configured payment requirements are supported
+    /// by the facilitator, since the paywall would otherwise advertise an empty `accepts` list.
+    pub async fn update_accepts(mut self) -> Result<Self, ErrorResponse> {
+        self.refresh_accepts(&PricingContext::default()).await?;
+
+        Ok(self)
+    }
+
+    /// Filter the accepts by the facilitator's supported kinds, then price them.
+    async fn refresh_accepts(&mut self, ctx: &PricingContext) -> Result<(), ErrorResponse> {
+        self.filter_supported().await?;
+        self.price_accepts(ctx).await
+    }
+
+    /// Set the amount of every accepted payment requirement from the
+    /// [`pricing_source`](PayWall::pricing_source), if set.
+    async fn price_accepts(&mut self, ctx: &PricingContext) -> Result<(), ErrorResponse> {
+        let Some(source) = &self.pricing_source else {
+            return Ok(());
+        };
+
+        let mut priced = Vec::with_capacity(self.accepts.len());
+        for pr in self.accepts.iter() {
+            let amount = source.price(pr, ctx).await.map_err(|err| {
+                #[cfg(feature = "tracing")]
+                tracing::error!("Failed to price {}@{}: {err}", pr.scheme, pr.network);
+
+                self.server_error(PaymentError::AcceptsUnavailable(err.to_string()))
+            })?;
+            priced.push(PaymentRequirements {
+                amount,
+                ..pr.clone()
+            });
+        }
+        self.accepts = priced.into();
+
+        Ok(())
+    }
+
+    async fn filter_supported(&mut self) -> Result<(), ErrorResponse> {
+        let supported = match self.facilitator.supported().await {
+            Ok(supported) => {
+                if self.facilitator_unreachable.is_some() || self.on_supported_change.is_some() {
+                    let previous = self.supported_cache.replace(&supported);
+                    if let (Some(callback), Some(previous)) = (&self.on_supported_change, previous)
+                    {
+                        let diff = SupportedDiff::compute(&previous, &supported);
+                        if !diff.is_empty() {
+                            callback(&diff);
+                        }
+                    }
+                }
+                supported
+            }
+            Err(err) => {
+                let error = PaymentError::Facilitator(format!(
+                    "Failed to get supported payment kinds: {err}"
+                ));
+                let cached = match &self.facilitator_unreachable {
+                    Some(Degrade::UseCachedSupported | Degrade::AllowUnverified { .. }) => {
+                        self.supported_cache.get()
+                    }
+                    _ => None,
+                };
+                match (cached, &self.facilitator_unreachable) {
+                    (Some(cached), _) => {
+                        #[cfg(feature = "tracing")]
+                        tracing::warn!("{error}; using the last supported payment kinds");
+
+                        cached
+                    }
+                    // Nothing to filter the accepts with yet, so they are kept as configured
+                    (None, Some(Degrade::AllowUnverified { .. })) => {
+                        #[cfg(feature = "tracing")]
+                        tracing::warn!("{error}; keeping the configured payment requirements");
+
+                        return Ok(());
+                    }
+                    (None, _) => return Err(self.facilitator_error(error)),
+                }
+            }
+        };
+        #[cfg(feature = "tracing")]
+        self.warn_timeouts_above_facilitator_max(&supported);
+
+        let filtered = filter_supported_accepts(&supported, self.accepts.to_owned());
+
+        if filtered.is_empty() && !self.accepts.is_empty() {
+            let configured = self
+                .accepts
+                .as_ref()
+                .iter()
+                .map(|pr| format!("{}@{}", pr.scheme, pr.network))
+                .collect::<Vec<_>>()
+                .join(", ");
+            let kinds = supported
+                .kinds
+                .iter()
+                .map(|kind| format!("{}@{} (v{})", kind.scheme, kind.network, kind.x402_version))
+                .collect::<Vec<_>>()
+                .join(", ");
+            let reason = format!(
+                "No configured payment requirements are supported by the facilitator: configured [{configured}], facilitator supports [{kinds}]"
+            );
+
+            #[cfg(feature = "tracing")]
+            tracing::error!("{reason}");
+
+            // The cause names the kinds for the operator, while clients get a generic message
+            let mut err = self.server_error(PaymentError::Misconfigured(
+                "No payment requirements are available for this resource".to_string(),
+            ));
+            err.cause = PaymentError::Misconfigured(reason);
+            return Err(err);
+        }
+
+        self.accepts = filtered;
+        self.check_expected_signers(&supported)?;
+
+        Ok(())
+    }
+
+    /// Fail if the facilitator advertises none of the [`expected_signers`](PayWall::expected_signers)
+    /// for a network of the accepts.
+    fn check_expected_signers(&self, supported: &SupportedResponse) -> Result<(), ErrorResponse> {
+        if self.expected_signers.is_empty() {
+            return Ok(());
+        }
+
+        let mut unexpected = Vec::new();
+        for pr in self.accepts.iter() {
+            let matches = supported
+                .signers_for(&pr.network)
+                .into_iter()
+                .any(|signer| self.is_expected_signer(signer));
+            if !matches && !unexpected.contains(&pr.network) {
+                unexpected.push(pr.network.clone());
+            }
+        }
+
+        if unexpected.is_empty() {
+            return Ok(());
+        }
+        let networks = unexpected.join(", ");
+
+        #[cfg(feature = "tracing")]
+        tracing::error!("The facilitator advertises none of the expected signers for [{networks}]");
+
+        Err(self.server_error(PaymentError::Misconfigured(format!(
+            "The facilitator advertises none of the expected signers for networks [{networks}]"
+        ))))
+    }
+
+    /// Whether `signer` is one of the [`expected_signers`](PayWall::expected_signers).
+    pub(crate) fn is_expected_signer(&self, signer: &str) -> bool {
+        self.expected_signers.iter().any(|expected| {
+            if expected.starts_with("0x") {
+                expected.eq_ignore_ascii_case(signer)
+            } else {
+                expected == signer
+            }
+        })
+    }
+
+    /// Warn about accepted payment requirements whose timeout exceeds the `maxTimeoutSeconds` the
+    /// facilitator advertises in the `extra` of their kind, as their verification would likely fail.
+    #[cfg(feature = "tracing")]
+    fn warn_timeouts_above_facilitator_max(&self, supported: &SupportedResponse) {
+        for pr in self.accepts.iter() {
+            let max = supported
+                .kinds
+                .iter()
+                .find(|kind| kind.scheme == pr.scheme && kind.network == pr.network)
+                .and_then(|kind| kind.extra.as_ref()?.get("maxTimeoutSeconds")?.as_u64());
+            let timeout = self.clamped_timeout(pr.max_timeout_seconds);
+
+            if let Some(max) = max
+                && timeout > max
+            {
+                tracing::warn!(
+                    "Payment timeout exceeds the facilitator maximum: scheme='{}', network='{}', max_timeout_seconds={timeout}, facilitator_max={max}",
+                    pr.scheme,
+                    pr.network
+                );
+            }
+        }
+    }
+
+    /// The timeout bounded by the [`timeout_clamp`](PayWall::timeout_clamp), if set.
+    fn clamped_timeout(&self, max_timeout_seconds: u64) -> u64 {
+        match &self.timeout_clamp {
+            Some(clamp) => max_timeout_seconds.max(*clamp.start()).min(*clamp.end()),
+            None => max_timeout_seconds,
+        }
+    }
+
+    /// The payment requirements as offered to clients, with the timeout clamped and fees applied.
+    fn offered(&self, mut requirements: PaymentRequirements) -> PaymentRequirements {
+        requirements.max_timeout_seconds = self.clamped_timeout(requirements.max_timeout_seconds);
+
+        match &self.fee_policy {
+            Some(fees) => fees.apply(requirements),
+            None => requirements,
+        }
+    }
+
+    /// The extensions sent to clients: the configured [`extensions`](PayWall::extensions), plus
+    /// the [`ORDER_ID_EXTENSION`] if an [`order_id`](PayWall::order_id) is set.
+    pub fn advertised_extensions(&self) -> Record<Extension> {
+        let mut extensions = self.extensions.to_owned();
+        if let Some(order_id) = &self.order_id {
+            extensions.insert(
+                ORDER_ID_EXTENSION.to_string(),
+                Extension::new(
+                    serde_json::json!({ "orderId": order_id }),
+                    serde_json::json!({
+                        "type": "object",
+                        "properties": { "orderId": { "type": "string" } },
+                        "required": ["orderId"]
+                    }),
+                ),
+            );
+        }
+        extensions
+    }
+
+    /// The accepted payment requirements offered to clients, with the
+    /// [`fee_policy`](PayWall::fee_policy) applied to their amounts and their timeouts bounded by
+    /// the [`timeout_clamp`](PayWall::timeout_clamp), sorted by the
+    /// [`accepts_order`](PayWall::accepts_order).
+    ///
+    /// Payloads must match one of these exactly, at any position, so that a client paying the
+    /// advertised amount is always accepted.
+    pub fn advertised_accepts(&self) -> Accepts {
+        let accepts = match (&self.fee_policy, &self.timeout_clamp) {
+            (None, None) => self.accepts.clone(),
+            _ => self
+                .accepts
+                .clone()
+                .into_iter()
+                .map(|pr| self.offered(pr))
+                .collect(),
+        };
+
+        self.accepts_order.apply(accepts)
+    }
+
+    /// Whether the payment requirements are among the [`advertised_accepts`](PayWall::advertised_accepts),
+    /// without building them.
+    fn is_advertised(&self, requirements: &PaymentRequirements) -> bool {
+        self.accepts
+            .iter()
+            .any(|pr| match (&self.fee_policy, &self.timeout_clamp) {
+                (None, None) => pr == requirements,
+                // Only requirements charging the advertised amount are compared in full
+                (Some(fees), _)
+                    if fees.amount_for(pr.amount, &pr.network) != requirements.amount =>
+                {
+                    false
+                }
+                _ => &self.offered(pr.clone()) == requirements,
+            })
+    }
+
+    /// Payment needed to access resource
+    ///
+    /// Returns a server error instead if there are no accepted payment requirements,
+    /// as clients cannot act on a challenge with an empty `accepts` list.
+    ///
+    /// The encoded `PAYMENT-REQUIRED` header is cached, and shared by clones of the paywall:
+    /// it is only encoded again when the resource, the advertised accepts, or the extensions change.
+    pub fn payment_required(&self) -> ErrorResponse {
+        if self.accepts.is_empty() {
+            #[cfg(feature = "tracing")]
+            tracing::error!("PayWall has no accepted payment requirements to advertise");
+
+            return self.server_error(PaymentError::Misconfigured(
+                "No payment requirements are available for this resource".to_string(),
+            ));
+        }
+
+        let payment_required = PaymentRequired::builder()
+            .error(self.messages.payment_required.clone())
+            .resource(self.resource.to_owned())
+            .accepts(self.advertised_accepts())
+            .extensions(self.advertised_extensions())
+            .build();
+        let header = self.payment_required_cache.header_for(&payment_required);
+
+        let response = ErrorResponse::payment_required_encoded(payment_required, header);
+        self.rendered(match self.challenge_etag {
+            true => response.with_etag(),
+            false => response,
+        })
+    }
+
+    /// The `402` challenge to a request without a payment, or a `304 Not Modified` if
+    /// [`challenge_etag`](PayWall::challenge_etag) is set and the `If-None-Match` of the
+    /// request matches the `ETag` of the challenge.
+    fn challenge(&self, if_none_match: Option<&[u8]>) -> ErrorResponse {
+        let response = self.payment_required();
+        match (response.etag_header(), if_none_match) {
+            (Some(etag), Some(if_none_match)) if etag_matches(&etag, if_none_match) => {
+                response.not_modified()
+            }
+            _ => response,
+        }
+    }
+
+    /// Malformed payment payload or requirements
+    pub fn invalid_payment(&self, cause: PaymentError) -> ErrorResponse {
+        let message = self.messages.format(self.messages.invalid_payment, &cause);
+        self.rendered(
+            ErrorResponse::invalid_payment(
+                cause,
+                self.resource.to_owned().into(),
+                self.advertised_accepts(),
+                self.advertised_extensions(),
+            )
+            .with_message(message),
+        )
+    }
+
+    /// Payment verification or settlement failed
+    pub fn payment_failed(&self, cause: PaymentError) -> ErrorResponse {
+        let message = self.messages.format(self.messages.payment_failed, &cause);
+        self.rendered(
+            ErrorResponse::payment_failed(
+                cause,
+                self.resource.to_owned().into(),
+                self.advertised_accepts(),
+                self.advertised_extensions(),
+            )
+            .with_message(message),
+        )
+    }
+
+    /// Internal server error during payment processing
+    pub fn server_error(&self, cause: PaymentError) -> ErrorResponse {
+        let message = self.messages.format(self.messages.server_error, &cause);
+        self.rendered(
+            ErrorResponse::server_error(
+                cause,
+                self.resource.to_owned().into(),
+                self.advertised_accepts(),
+                self.advertised_extensions(),
+            )
+            .with_message(message),
+        )
+    }
+
+    /// The facilitator is unreachable
+    pub fn service_unavailable(&self, cause: PaymentError) -> ErrorResponse {
+        let message = self.messages.format(self.messages.server_error, &cause);
+        self.rendered(
+            ErrorResponse::service_unavailable(
+                cause,
+                self.resource.to_owned().into(),
+                self.advertised_accepts(),
+                self.advertised_extensions(),
+            )
+            .with_message(message),
+        )
+    }
+
+    /// A call to the facilitator failed: a server error, or a
+    /// [`service_unavailable`](PayWall::service_unavailable) one with [`Degrade::RejectWith503`].
+    pub fn facilitator_error(&self, cause: PaymentError) -> ErrorResponse {
+        match self.facilitator_unreachable {
+            Some(Degrade::RejectWith503) => self.service_unavailable(cause),
+            _ => self.server_error(cause),
+        }
+    }
+
+    /// The payment flow did not complete in time
+    pub fn timeout(&self, cause: PaymentError) -> ErrorResponse {
+        let message = self.messages.format(self.messages.server_error, &cause);
+        self.rendered(
+            ErrorResponse::timeout(
+                cause,
+                self.resource.to_owned().into(),
+                self.advertised_accepts(),
+                self.advertised_extensions(),
+            )
+            .with_message(message),
+        )
+    }
+
+    /// The request content type is not accepted by the resource
+    pub fn unsupported_media_type(&self, cause: PaymentError) -> ErrorResponse {
+        let message = self.messages.format(self.messages.request_rejected, &cause);
+        self.rendered(
+            ErrorResponse::unsupported_media_type(
+                cause,
+                self.resource.to_owned().into(),
+                self.advertised_accepts(),
+                self.advertised_extensions(),
+            )
+            .with_message(message),
+        )
+    }
+
+    /// The request body is larger than the resource accepts
+    pub fn payload_too_large(&self, cause: PaymentError) -> ErrorResponse {
+        let message = self.messages.format(self.messages.request_rejected, &cause);
+        self.rendered(
+            ErrorResponse::payload_too_large(
+                cause,
+                self.resource.to_owned().into(),
+                self.advertised_accepts(),
+                self.advertised_extensions(),
+            )
+            .with_message(message),
+        )
+    }
+
+    /// Apply the configured [`error_format`](PayWall::error_format) and [`retry_after`](PayWall::retry_after).
+    fn rendered(&self, response: ErrorResponse) -> ErrorResponse {
+        let response = response.with_format(self.error_format);
+        match self.retry_after {
+            Some(seconds) => response.with_retry_after(seconds),
+            None => response,
+        }
+    }
+}
+
+/// Reject payloads not signed by the `requiredPayer` in the `extra` of the selected requirements, if any.
+///
+/// Invoice-style requirements name the only address allowed to pay them. The payer is read from
+/// the `authorization.from` of the payload, as signed for the `exact` scheme on EVM networks.
+fn check_required_payer(
+    selected: &PaymentRequirements,
+    payload: &PaymentPayload,
+) -> Result<(), String> {
+    let Some(required) = selected
+        .extra
+        .as_ref()
+        .and_then(|extra| extra.get("requiredPayer"))
+        .and_then(|payer| payer.as_str())
+    else {
+        return Ok(());
+    };
+
+    match payload
+        .payload
+        .pointer("/authorization/from")
+        .and_then(|payer| payer.as_str())
+    {
+        Some(payer) if payer.eq_ignore_ascii_case(required) => Ok(()),
+        Some(payer) => Err(format!(
+            "Payment must be made by {required}, got a payment from {payer}"
+        )),
+        None => Err(format!(
+            "Payment must be made by {required}, but the payload names no payer"
+        )),
+    }
+}
+
+/// The `x402Version` of an encoded payload, if it decodes to a JSON object with a numeric version.
+fn payload_version(header: &str) -> Option<u64> {
+    let decoded = Base64EncodedHeader::decode_str(header).ok()?;
+    let payload: serde_json::Value = serde_json::from_slice(&decoded).ok()?;
+
+    payload.get("x402Version")?.as_u64()
+}
+
+/// Record the known payment details on the current `x402.payment` span.
+#[cfg(feature = "tracing")]
+fn record_payment_state(state: &PaymentState, known_assets: &KnownAssetRegistry) {
+    let span = tracing::Span::current();
+    span.record("scheme", state.selected.scheme.as_str());
+    span.record("network", state.network());
+    span.record(
+        "amount",
+        tracing::field::display(state.selected.display_amount(known_assets)),
+    );
+    if let Some(payer) = state.payer() {
+        span.record("payer", payer);
+    }
+    if let Some(order_id) = &state.order_id {
+        span.record("order_id", order_id.as_str());
+    }
+}
+
+/// Await a step of the payment flow, returning `None` if the deadline passes first.
+async fn within<T>(deadline: Option<Instant>, step: impl Future<Output = T>) -> Option<T> {
+    #[cfg(feature = "tokio")]
+    if let Some(deadline) = deadline {
+        return tokio::time::timeout_at(deadline, step).await.ok();
+    }
+    #[cfg(not(feature = "tokio"))]
+    let _ = deadline;
+
+    Some(step.await)
+}
+
+/// Filters the payment requirements based on the supported kinds from the facilitator.
 ///
-/// Returns only the payment requirements that are supported by the facilitator with updated extra fields.
+/// Returns only the payment requirements that are supported by the facilitator, with the `extra`
+/// of their kind deep-merged under their own, so configured keys such as `requiredPayer` win.
 pub fn filter_supported_accepts(supported: &SupportedResponse, accepts: Accepts) -> Accepts {
     accepts
         .into_iter()
@@ -218,10 +1729,7 @@ pub fn filter_supported_accepts(supported: &SupportedResponse, accepts: Accepts)
                         && kind.network == pr.network
                 })
                 .map(|s| {
-                    // Update extra field if present
-                    if s.extra.is_some() {
-                        pr.extra = s.extra.clone();
-                    }
+                    pr.extra = merge_extra(s.extra.clone(), pr.extra.take());
                     pr
                 })
         })
@@ -232,12 +1740,23 @@ pub fn filter_supported_accepts(supported: &SupportedResponse, accepts: Accepts)
 mod tests {
     use serde_json::json;
     use x402_core::{
-        facilitator::SupportedResponse,
-        transport::{Accepts, PaymentRequirements},
-        types::AmountValue,
+        facilitator::{
+            ConfirmationStatus, SettleFailed, SettlePending, SettleResult, SettleStatus,
+            SettleSuccess, SupportedDiff, SupportedResponse, VerifyInvalid, VerifyResult,
+        },
+        transport::{
+            Accepts, PaymentPayload, PaymentRequired, PaymentRequirements, SettlementResponse,
+        },
+        types::{
+            AmountValue, AnyJson, Base64EncodedHeader, Extension, ExtensionInfo, Record, SchemeName,
+        },
     };
 
-    use crate::paywall::filter_supported_accepts;
+    use crate::{
+        errors::{ErrorFormat, ErrorResponse, ErrorResponseHeader, PaymentError},
+        paywall::{OnPending, PayWall, filter_supported_accepts},
+        testing::{self, MockFacilitator},
+    };
 
     #[test]
     fn test_filter_supported_accepts() {
@@ -284,7 +1803,7 @@ mod tests {
 
         let accepts = Accepts::from(vec![
             PaymentRequirements {
-                scheme: "exact".to_string(),
+                scheme: SchemeName::EXACT,
                 network: "eip155:84532".to_string(),
                 amount: AmountValue(1000),
                 asset: "0xA0b86991c6218b36c1d19D4a2e9Eb0cE3606eB48".to_string(),
@@ -296,7 +1815,7 @@ mod tests {
                 })),
             },
             PaymentRequirements {
-                scheme: "exact".to_string(),
+                scheme: SchemeName::EXACT,
                 network: "solana:EtWTRABZaYq6iMfeYKouRu166VU2xqa1".to_string(),
                 amount: AmountValue(2000000),
                 asset: "EPjFWdd5AufqSSqeM2qN1xzybapC8G4wEGGkZwyTDt1v".to_string(),
@@ -305,7 +1824,7 @@ mod tests {
                 extra: None,
             },
             PaymentRequirements {
-                scheme: "exact".to_string(),
+                scheme: SchemeName::EXACT,
                 network: "solana:UnknownNetwork".to_string(),
                 amount: AmountValue(2000000),
                 asset: "EPjFWdd5AufqSSqeM2qN1xzybapC8G4wEGGkZwyTDt1v".to_string(),
@@ -340,4 +1859,1896 @@ mod tests {
             "Solana payment requirement should have updated extra from supported kinds"
         );
     }
+
+    fn no_overlap_facilitator() -> MockFacilitator {
+        MockFacilitator {
+            supported: serde_json::from_value(json!({
+                "kinds": [
+                    { "x402Version": 2, "scheme": "exact", "network": "eip155:1" },
+                    { "x402Version": 1, "scheme": "exact", "network": "base-sepolia" }
+                ],
+                "extensions": [],
+                "signers": {}
+            }))
+            .unwrap(),
+            ..Default::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn update_accepts_rejects_empty_overlap() {
+        let paywall = PayWall::builder()
+            .facilitator(no_overlap_facilitator())
+            .accepts(testing::requirements())
+            .resource(testing::resource())
+            .build();
+
+        let err = paywall.update_accepts().await.unwrap_err();
+
+        assert_eq!(err.status, http::StatusCode::INTERNAL_SERVER_ERROR);
+        assert!(matches!(&err.cause, PaymentError::Misconfigured(reason)
+                if reason.contains("configured [exact@eip155:84532]")
+                    && reason.contains("exact@eip155:1 (v2)")
+                    && reason.contains("exact@base-sepolia (v1)")));
+        assert_eq!(
+            err.body.error,
+            "No payment requirements are available for this resource"
+        );
+    }
+
+    #[cfg(feature = "tracing")]
+    #[tokio::test]
+    #[tracing_test::traced_test]
+    async fn update_accepts_logs_empty_overlap() {
+        let paywall = PayWall::builder()
+            .facilitator(no_overlap_facilitator())
+            .accepts(testing::requirements())
+            .resource(testing::resource())
+            .build();
+
+        paywall.update_accepts().await.unwrap_err();
+
+        assert!(logs_contain("configured [exact@eip155:84532]"));
+        assert!(logs_contain("exact@eip155:1 (v2)"));
+        assert!(logs_contain("exact@base-sepolia (v1)"));
+    }
+
+    #[tokio::test]
+    async fn handle_payment_fails_before_verify_on_empty_overlap() {
+        let facilitator = no_overlap_facilitator();
+        let calls = facilitator.calls.clone();
+        let paywall = PayWall::builder()
+            .facilitator(facilitator)
+            .accepts(testing::requirements())
+            .resource(testing::resource())
+            .build();
+
+        let err = paywall
+            .handle_payment(testing::paid_request(testing::requirements()), |_| async {
+                http::Response::new(())
+            })
+            .await
+            .unwrap_err();
+
+        assert_eq!(err.status, http::StatusCode::INTERNAL_SERVER_ERROR);
+        assert_eq!(calls.supported(), 1);
+        assert_eq!(calls.verify(), 0);
+        assert_eq!(calls.settle(), 0);
+    }
+
+    #[tokio::test]
+    async fn skip_supported_update_bypasses_supported() {
+        let facilitator = no_overlap_facilitator();
+        let calls = facilitator.calls.clone();
+        let paywall = PayWall::builder()
+            .facilitator(facilitator)
+            .accepts(testing::requirements())
+            .resource(testing::resource())
+            .skip_supported_update(true)
+            .build();
+
+        let response = paywall
+            .handle_payment(testing::paid_request(testing::requirements()), |_| async {
+                http::Response::new(())
+            })
+            .await
+            .unwrap();
+
+        assert!(response.headers().contains_key("payment-response"));
+        assert_eq!(calls.supported(), 0);
+        assert_eq!(calls.verify(), 1);
+        assert_eq!(calls.settle(), 1);
+    }
+
+    const RELAYER: &str = "0xD407000000000000000000000000000000000001";
+
+    fn signers_facilitator(signers: serde_json::Value) -> MockFacilitator {
+        MockFacilitator {
+            supported: serde_json::from_value(json!({
+                "kinds": [
+                    { "x402Version": 2, "scheme": "exact", "network": "eip155:84532" },
+                    { "x402Version": 2, "scheme": "exact", "network": "eip155:8453" }
+                ],
+                "extensions": [],
+                "signers": signers
+            }))
+            .unwrap(),
+            ..Default::default()
+        }
+    }
+
+    fn two_network_accepts() -> Accepts {
+        Accepts::from(vec![
+            testing::requirements(),
+            PaymentRequirements {
+                network: "eip155:8453".to_string(),
+                ..testing::requirements()
+            },
+        ])
+    }
+
+    #[tokio::test]
+    async fn expected_signers_match_advertised_signers() {
+        let paywall = PayWall::builder()
+            .facilitator(signers_facilitator(json!({
+                "eip155:*": [RELAYER.to_lowercase()]
+            })))
+            .accepts(two_network_accepts())
+            .resource(testing::resource())
+            .expected_signers([RELAYER])
+            .build();
+
+        let paywall = paywall.update_accepts().await.unwrap();
+
+        assert_eq!(paywall.accepts.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn unexpected_signers_are_rejected() {
+        let paywall = PayWall::builder()
+            .facilitator(signers_facilitator(json!({
+                "eip155:*": ["0x0000000000000000000000000000000000000bad"]
+            })))
+            .accepts(two_network_accepts())
+            .resource(testing::resource())
+            .expected_signers([RELAYER])
+            .build();
+
+        let err = paywall.update_accepts().await.unwrap_err();
+
+        assert_eq!(err.status, http::StatusCode::INTERNAL_SERVER_ERROR);
+        assert!(matches!(err.cause, PaymentError::Misconfigured(_)));
+        assert!(err.body.error.contains("eip155:84532, eip155:8453"));
+    }
+
+    #[tokio::test]
+    async fn expected_signers_are_checked_per_network() {
+        let paywall = PayWall::builder()
+            .facilitator(signers_facilitator(json!({
+                "eip155:84532": [RELAYER],
+                "eip155:*": ["0x0000000000000000000000000000000000000bad"]
+            })))
+            .accepts(two_network_accepts())
+            .resource(testing::resource())
+            .expected_signers([RELAYER])
+            .build();
+
+        let err = paywall.update_accepts().await.unwrap_err();
+
+        assert!(matches!(err.cause, PaymentError::Misconfigured(_)));
+        assert!(err.body.error.contains("[eip155:8453]"));
+    }
+
+    #[tokio::test]
+    async fn settlement_by_unexpected_signer_is_rejected() {
+        let mut facilitator = signers_facilitator(json!({ "eip155:*": [RELAYER] }));
+        facilitator.settle = SettleResult::success(SettleSuccess {
+            verified_signature: true,
+            signer: Some("0x0000000000000000000000000000000000000bad".to_string()),
+            ..facilitator.settle.as_success().unwrap().clone()
+        });
+        let paywall = PayWall::builder()
+            .facilitator(facilitator)
+            .accepts(testing::requirements())
+            .resource(testing::resource())
+            .expected_signers([RELAYER])
+            .build();
+
+        let err = paywall
+            .handle_payment(testing::paid_request(testing::requirements()), |_| async {
+                http::Response::new(())
+            })
+            .await
+            .unwrap_err();
+
+        assert_eq!(err.status, http::StatusCode::INTERNAL_SERVER_ERROR);
+        assert!(matches!(err.cause, PaymentError::Facilitator(_)));
+    }
+
+    #[test]
+    fn payment_required_never_advertises_empty_accepts() {
+        let paywall = PayWall::builder()
+            .facilitator(MockFacilitator::default())
+            .accepts(Accepts::new())
+            .resource(testing::resource())
+            .build();
+
+        let err = paywall.payment_required();
+
+        assert_eq!(err.status, http::StatusCode::INTERNAL_SERVER_ERROR);
+        assert!(matches!(err.cause, PaymentError::Misconfigured(_)));
+        assert!(err.body.accepts.is_empty());
+    }
+
+    fn payment_required_header(err: ErrorResponse) -> http::HeaderValue {
+        let (name, value) = err.header.header_value().unwrap();
+        assert_eq!(name, "payment-required");
+        value
+    }
+
+    #[test]
+    fn payment_required_header_is_cached() {
+        let paywall = PayWall::builder()
+            .facilitator(MockFacilitator::default())
+            .accepts(testing::requirements())
+            .resource(testing::resource())
+            .build();
+
+        let first = payment_required_header(paywall.payment_required());
+        // Clones made per request share the cached header buffer
+        let cached = payment_required_header(paywall.clone().payment_required());
+        assert_eq!(cached.as_bytes().as_ptr(), first.as_bytes().as_ptr());
+
+        let decoded =
+            PaymentRequired::try_from(Base64EncodedHeader(cached.to_str().unwrap().to_string()))
+                .unwrap();
+        assert_eq!(decoded, *paywall.payment_required().body);
+
+        let mut repriced = paywall.clone();
+        repriced.accepts = PaymentRequirements {
+            amount: AmountValue(2000),
+            ..testing::requirements()
+        }
+        .into();
+        let updated = payment_required_header(repriced.payment_required());
+        assert_ne!(updated, first);
+
+        let decoded =
+            PaymentRequired::try_from(Base64EncodedHeader(updated.to_str().unwrap().to_string()))
+                .unwrap();
+        assert_eq!(decoded.accepts.as_ref()[0].amount, AmountValue(2000));
+    }
+
+    fn etag_paywall(amount: u128) -> PayWall<MockFacilitator> {
+        PayWall::builder()
+            .facilitator(MockFacilitator::default())
+            .accepts(PaymentRequirements {
+                amount: AmountValue(amount),
+                ..testing::requirements()
+            })
+            .resource(testing::resource())
+            .challenge_etag(true)
+            .build()
+    }
+
+    fn revalidate(
+        paywall: &PayWall<MockFacilitator>,
+        if_none_match: &http::HeaderValue,
+    ) -> ErrorResponse {
+        let request = http::Request::builder()
+            .header(http::header::IF_NONE_MATCH, if_none_match)
+            .body(())
+            .unwrap();
+        paywall.process_request(request).err().unwrap()
+    }
+
+    #[test]
+    fn challenge_etag_not_modified() {
+        let paywall = etag_paywall(1000);
+
+        let challenge = paywall
+            .process_request(http::Request::new(()))
+            .err()
+            .unwrap();
+        assert_eq!(challenge.status, http::StatusCode::PAYMENT_REQUIRED);
+        let etag = challenge.etag_header().unwrap();
+
+        let response: http::Response<Vec<u8>> = revalidate(&paywall, &etag).into_http_response();
+        assert_eq!(response.status(), http::StatusCode::NOT_MODIFIED);
+        assert_eq!(response.headers()[http::header::ETAG], etag);
+        assert_eq!(
+            response.headers()["payment-required"],
+            payment_required_header(challenge)
+        );
+        assert!(!response.headers().contains_key(http::header::CONTENT_TYPE));
+        assert!(response.body().is_empty());
+
+        let weak = http::HeaderValue::try_from(format!("\"other\", W/{}", etag.to_str().unwrap()))
+            .unwrap();
+        assert_eq!(
+            revalidate(&paywall, &weak).status,
+            http::StatusCode::NOT_MODIFIED
+        );
+
+        let stale = http::HeaderValue::from_static("\"0000000000000000\"");
+        assert_eq!(
+            revalidate(&paywall, &stale).status,
+            http::StatusCode::PAYMENT_REQUIRED
+        );
+
+        // Without the flag the ETag is neither sent nor honored
+        let mut plain = etag_paywall(1000);
+        plain.challenge_etag = false;
+        let err = revalidate(&plain, &etag);
+        assert_eq!(err.status, http::StatusCode::PAYMENT_REQUIRED);
+        assert_eq!(err.etag_header(), None);
+    }
+
+    #[test]
+    fn challenge_etag_changes_with_price() {
+        let etag = etag_paywall(1000).payment_required().etag_header().unwrap();
+
+        assert_eq!(
+            etag_paywall(1000).payment_required().etag_header().unwrap(),
+            etag
+        );
+        assert_ne!(
+            etag_paywall(2000).payment_required().etag_header().unwrap(),
+            etag
+        );
+        assert_eq!(
+            revalidate(&etag_paywall(2000), &etag).status,
+            http::StatusCode::PAYMENT_REQUIRED
+        );
+    }
+
+    #[test]
+    fn stale_requirements_carry_error_code() {
+        // Signed against the challenge before the price was raised
+        let mut raised = testing::requirements();
+        raised.amount = AmountValue(2000);
+        let paywall = PayWall::builder()
+            .facilitator(MockFacilitator::default())
+            .accepts(raised.clone())
+            .resource(testing::resource())
+            .build();
+
+        let err = paywall
+            .process_request(testing::paid_request(testing::requirements()))
+            .err()
+            .unwrap();
+        assert_eq!(err.cause, PaymentError::UnsupportedOption);
+        assert_eq!(
+            err.body.error_code(),
+            Some(PaymentRequired::STALE_REQUIREMENTS)
+        );
+        assert_eq!(err.body.accepts.as_ref(), [raised]);
+
+        let ErrorResponseHeader::PaymentResponse(header) = &err.header else {
+            panic!("expected a payment header");
+        };
+        let encoded =
+            PaymentRequired::try_from(Base64EncodedHeader(header.to_str().unwrap().to_string()))
+                .unwrap();
+        assert_eq!(
+            encoded.error_code(),
+            Some(PaymentRequired::STALE_REQUIREMENTS)
+        );
+    }
+
+    #[cfg(feature = "tokio")]
+    #[tokio::test(start_paused = true)]
+    async fn flow_timeout_before_settlement() {
+        let facilitator = MockFacilitator {
+            delay: Some(std::time::Duration::from_millis(200)),
+            ..Default::default()
+        };
+        let calls = facilitator.calls.clone();
+        let paywall = PayWall::builder()
+            .facilitator(facilitator)
+            .accepts(testing::requirements())
+            .resource(testing::resource())
+            .flow_timeout(std::time::Duration::from_millis(50))
+            .build();
+
+        let err = paywall
+            .handle_payment(testing::paid_request(testing::requirements()), |_| async {
+                http::Response::new(())
+            })
+            .await
+            .unwrap_err();
+
+        assert_eq!(err.status, http::StatusCode::GATEWAY_TIMEOUT);
+        assert_eq!(err.cause, PaymentError::Timeout);
+        assert_eq!(calls.verify(), 1);
+        assert_eq!(calls.settle(), 0);
+    }
+
+    #[cfg(feature = "tokio")]
+    #[tokio::test(start_paused = true)]
+    async fn flow_timeout_does_not_cancel_settlement() {
+        let facilitator = MockFacilitator {
+            delay: Some(std::time::Duration::from_millis(60)),
+            ..Default::default()
+        };
+        let calls = facilitator.calls.clone();
+        let paywall = PayWall::builder()
+            .facilitator(facilitator)
+            .accepts(testing::requirements())
+            .resource(testing::resource())
+            .flow_timeout(std::time::Duration::from_millis(100))
+            .build();
+
+        let response = paywall
+            .handle_payment(testing::paid_request(testing::requirements()), |_| async {
+                http::Response::new(())
+            })
+            .await
+            .unwrap();
+
+        assert!(response.headers().contains_key("payment-response"));
+        assert_eq!(calls.settle(), 1);
+    }
+
+    #[cfg(feature = "tokio")]
+    fn polling_paywall(statuses: Vec<SettleStatus>) -> PayWall<MockFacilitator> {
+        use crate::paywall::SettlementPolling;
+
+        PayWall::builder()
+            .facilitator(MockFacilitator {
+                settle_statuses: statuses,
+                ..Default::default()
+            })
+            .accepts(testing::requirements())
+            .resource(testing::resource())
+            .settlement_polling(
+                SettlementPolling::builder()
+                    .initial_backoff(std::time::Duration::from_millis(10))
+                    .max_backoff(std::time::Duration::from_millis(40))
+                    .deadline(std::time::Duration::from_millis(200))
+                    .build(),
+            )
+            .build()
+    }
+
+    #[cfg(feature = "tokio")]
+    #[tokio::test(start_paused = true)]
+    async fn settlement_polling_waits_for_confirmation() {
+        let paywall = polling_paywall(vec![
+            SettleStatus::Pending,
+            SettleStatus::Pending,
+            SettleStatus::confirmed(),
+        ]);
+        let calls = paywall.facilitator.calls.clone();
+
+        let start = tokio::time::Instant::now();
+        let response = paywall
+            .handle_payment(testing::paid_request(testing::requirements()), |_| async {
+                http::Response::new(())
+            })
+            .await
+            .unwrap();
+
+        assert!(response.headers().contains_key("payment-response"));
+        assert_eq!(calls.settle(), 1);
+        assert_eq!(calls.settle_status(), 3);
+        assert!(start.elapsed() >= std::time::Duration::from_millis(30));
+    }
+
+    #[cfg(feature = "tokio")]
+    #[tokio::test]
+    async fn settlement_polling_times_out() {
+        let paywall = polling_paywall(vec![SettleStatus::Pending]);
+
+        let err = paywall
+            .handle_payment(testing::paid_request(testing::requirements()), |_| async {
+                http::Response::new(())
+            })
+            .await
+            .unwrap_err();
+
+        assert_eq!(err.status, http::StatusCode::GATEWAY_TIMEOUT);
+        assert_eq!(
+            err.cause,
+            PaymentError::SettlementUnconfirmed("0xdeadbeef".to_string())
+        );
+    }
+
+    #[cfg(feature = "tokio")]
+    #[tokio::test]
+    async fn settlement_polling_reports_failure() {
+        let paywall = polling_paywall(vec![
+            SettleStatus::Pending,
+            SettleStatus::Failed {
+                error_reason: "transaction_reverted".to_string(),
+            },
+        ]);
+
+        let err = paywall
+            .handle_payment(testing::paid_request(testing::requirements()), |_| async {
+                http::Response::new(())
+            })
+            .await
+            .unwrap_err();
+
+        assert_eq!(err.status, http::StatusCode::PAYMENT_REQUIRED);
+        assert_eq!(
+            err.cause,
+            PaymentError::SettleFailed("transaction_reverted".to_string())
+        );
+    }
+
+    fn pending_paywall(
+        on_pending: OnPending,
+        statuses: Vec<SettleStatus>,
+    ) -> PayWall<MockFacilitator> {
+        PayWall::builder()
+            .facilitator(MockFacilitator {
+                settle: SettleResult::pending(SettlePending {
+                    poll_after: None,
+                    reference: Some("settle-42".to_string()),
+                }),
+                settle_statuses: statuses,
+                ..Default::default()
+            })
+            .accepts(testing::requirements())
+            .resource(testing::resource())
+            .on_pending(on_pending)
+            .build()
+    }
+
+    #[tokio::test]
+    async fn pending_settlement_fails_by_default() {
+        let paywall = pending_paywall(OnPending::default(), vec![]);
+
+        let err = paywall
+            .handle_payment(testing::paid_request(testing::requirements()), |_| async {
+                http::Response::new(())
+            })
+            .await
+            .unwrap_err();
+
+        assert_eq!(err.status, http::StatusCode::PAYMENT_REQUIRED);
+        assert_eq!(
+            err.cause,
+            PaymentError::SettleFailed("Settlement pending".to_string())
+        );
+    }
+
+    #[tokio::test]
+    async fn accepted_pending_settlement_is_flagged_as_broadcast() {
+        let paywall = pending_paywall(OnPending::Accept, vec![]);
+        let calls = paywall.facilitator.calls.clone();
+
+        let response = paywall
+            .handle_payment(testing::paid_request(testing::requirements()), |_| async {
+                http::Response::new(())
+            })
+            .await
+            .unwrap();
+
+        let header = response.headers()["payment-response"].to_str().unwrap();
+        let settlement = SettlementResponse::try_from(header).unwrap();
+        assert_eq!(settlement.transaction, "settle-42");
+        assert_eq!(settlement.payer, testing::PAYER);
+        assert_eq!(settlement.confirmation, Some(ConfirmationStatus::Broadcast));
+        assert_eq!(calls.settle_status(), 0);
+    }
+
+    #[tokio::test]
+    async fn pending_settlement_without_reference_fails() {
+        let mut paywall = pending_paywall(OnPending::Accept, vec![]);
+        paywall.facilitator.settle = SettleResult::pending(SettlePending {
+            poll_after: None,
+            reference: None,
+        });
+
+        let err = paywall
+            .handle_payment(testing::paid_request(testing::requirements()), |_| async {
+                http::Response::new(())
+            })
+            .await
+            .unwrap_err();
+
+        assert_eq!(err.status, http::StatusCode::PAYMENT_REQUIRED);
+        assert_eq!(
+            err.cause,
+            PaymentError::SettleFailed("Settlement pending without a reference".to_string())
+        );
+    }
+
+    #[cfg(feature = "tokio")]
+    #[tokio::test(start_paused = true)]
+    async fn pending_settlement_is_polled_until_confirmed() {
+        let paywall = pending_paywall(
+            OnPending::Poll {
+                attempts: 3,
+                interval: std::time::Duration::from_millis(10),
+            },
+            vec![
+                SettleStatus::Pending,
+                SettleStatus::Confirmed { confirmations: 2 },
+            ],
+        );
+        let calls = paywall.facilitator.calls.clone();
+
+        let response = paywall
+            .handle_payment(testing::paid_request(testing::requirements()), |_| async {
+                http::Response::new(())
+            })
+            .await
+            .unwrap();
+
+        let header = response.headers()["payment-response"].to_str().unwrap();
+        let settlement = SettlementResponse::try_from(header).unwrap();
+        assert_eq!(settlement.transaction, "settle-42");
+        assert_eq!(
+            settlement.confirmation,
+            Some(ConfirmationStatus::Confirmed(2))
+        );
+        assert_eq!(calls.settle_status(), 2);
+    }
+
+    #[cfg(feature = "tokio")]
+    #[tokio::test(start_paused = true)]
+    async fn pending_settlement_polls_are_bounded() {
+        let paywall = pending_paywall(
+            OnPending::Poll {
+                attempts: 2,
+                interval: std::time::Duration::from_millis(10),
+            },
+            vec![SettleStatus::Pending],
+        );
+        let calls = paywall.facilitator.calls.clone();
+
+        let err = paywall
+            .handle_payment(testing::paid_request(testing::requirements()), |_| async {
+                http::Response::new(())
+            })
+            .await
+            .unwrap_err();
+
+        assert_eq!(err.status, http::StatusCode::GATEWAY_TIMEOUT);
+        assert_eq!(
+            err.cause,
+            PaymentError::SettlementUnconfirmed("settle-42".to_string())
+        );
+        assert_eq!(calls.settle_status(), 2);
+    }
+
+    #[cfg(feature = "tokio")]
+    fn confirming_paywall(facilitator: MockFacilitator) -> PayWall<MockFacilitator> {
+        use x402_core::facilitator::ConfirmationStatus;
+
+        use crate::paywall::SettlementPolling;
+
+        PayWall::builder()
+            .facilitator(facilitator)
+            .accepts(testing::requirements())
+            .resource(testing::resource())
+            .require_confirmation(ConfirmationStatus::Confirmed(2))
+            .settlement_polling(
+                SettlementPolling::builder()
+                    .initial_backoff(std::time::Duration::from_millis(10))
+                    .deadline(std::time::Duration::from_millis(100))
+                    .build(),
+            )
+            .build()
+    }
+
+    #[cfg(feature = "tokio")]
+    #[tokio::test]
+    async fn require_confirmation_settles_before_handler() {
+        use x402_core::{facilitator::ConfirmationStatus, transport::SettlementResponse};
+
+        use crate::processor::PaymentState;
+
+        let paywall = confirming_paywall(MockFacilitator {
+            settle_statuses: vec![
+                SettleStatus::Pending,
+                SettleStatus::confirmed(),
+                SettleStatus::Confirmed { confirmations: 2 },
+            ],
+            ..Default::default()
+        });
+        let calls = paywall.facilitator.calls.clone();
+
+        let response = paywall
+            .handle_payment(
+                testing::paid_request(testing::requirements()),
+                |request: http::Request<()>| async move {
+                    let state = request.extensions().get::<PaymentState>().unwrap();
+                    let confirmation = state.settled.as_ref().unwrap().confirmation;
+                    assert_eq!(confirmation, Some(ConfirmationStatus::Confirmed(2)));
+                    http::Response::new(())
+                },
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(calls.settle(), 1);
+        assert_eq!(calls.settle_status(), 3);
+        let header = response.headers()["payment-response"].to_str().unwrap();
+        assert_eq!(
+            SettlementResponse::try_from(header).unwrap().confirmation,
+            Some(ConfirmationStatus::Confirmed(2))
+        );
+
+        // A settlement reported as final is not polled
+        let mut facilitator = MockFacilitator::default();
+        if let SettleResult::Success(settled) = &mut facilitator.settle {
+            settled.confirmation = Some(ConfirmationStatus::Finalized);
+        }
+        let calls = facilitator.calls.clone();
+        confirming_paywall(facilitator)
+            .handle_payment(testing::paid_request(testing::requirements()), |_| async {
+                http::Response::new(())
+            })
+            .await
+            .unwrap();
+        assert_eq!(calls.settle_status(), 0);
+    }
+
+    #[cfg(feature = "tokio")]
+    #[tokio::test]
+    async fn require_confirmation_withholds_unconfirmed_resource() {
+        use std::sync::{
+            Arc,
+            atomic::{AtomicBool, Ordering},
+        };
+
+        let paywall = confirming_paywall(MockFacilitator {
+            settle_statuses: vec![SettleStatus::confirmed()],
+            ..Default::default()
+        });
+        let handled = Arc::new(AtomicBool::new(false));
+
+        let err = paywall
+            .handle_payment(testing::paid_request(testing::requirements()), {
+                let handled = handled.clone();
+                |_| async move {
+                    handled.store(true, Ordering::SeqCst);
+                    http::Response::new(())
+                }
+            })
+            .await
+            .unwrap_err();
+
+        assert_eq!(err.status, http::StatusCode::GATEWAY_TIMEOUT);
+        assert_eq!(
+            err.cause,
+            PaymentError::SettlementUnconfirmed("0xdeadbeef".to_string())
+        );
+        assert!(!handled.load(Ordering::SeqCst));
+    }
+
+    #[tokio::test]
+    async fn wrong_content_type_is_rejected_before_facilitator() {
+        let facilitator = MockFacilitator::default();
+        let calls = facilitator.calls.clone();
+        let paywall = PayWall::builder()
+            .facilitator(facilitator)
+            .accepts(testing::requirements())
+            .resource(testing::resource())
+            .require_content_type("application/json")
+            .build();
+
+        let mut request = testing::paid_request(testing::requirements());
+        request
+            .headers_mut()
+            .insert("Content-Type", http::HeaderValue::from_static("text/plain"));
+
+        let err = paywall
+            .handle_payment(request, |_| async { http::Response::new(()) })
+            .await
+            .unwrap_err();
+
+        assert_eq!(err.status, http::StatusCode::UNSUPPORTED_MEDIA_TYPE);
+        assert!(err.header.header_value().is_none());
+        assert_eq!(calls.supported(), 0);
+        assert_eq!(calls.verify(), 0);
+        assert_eq!(calls.settle(), 0);
+    }
+
+    #[tokio::test]
+    async fn oversized_body_is_rejected_before_facilitator() {
+        let facilitator = MockFacilitator::default();
+        let calls = facilitator.calls.clone();
+        let paywall = PayWall::builder()
+            .facilitator(facilitator)
+            .accepts(testing::requirements())
+            .resource(testing::resource())
+            .max_body_hint(1024)
+            .build();
+
+        let mut request = testing::paid_request(testing::requirements());
+        request
+            .headers_mut()
+            .insert("Content-Length", http::HeaderValue::from_static("4096"));
+
+        let err = paywall
+            .handle_payment(request, |_| async { http::Response::new(()) })
+            .await
+            .unwrap_err();
+
+        assert_eq!(err.status, http::StatusCode::PAYLOAD_TOO_LARGE);
+        assert_eq!(calls.supported(), 0);
+        assert_eq!(calls.verify(), 0);
+        assert_eq!(calls.settle(), 0);
+    }
+
+    #[tokio::test]
+    async fn request_passing_prechecks_is_settled() {
+        let facilitator = MockFacilitator::default();
+        let calls = facilitator.calls.clone();
+        let paywall = PayWall::builder()
+            .facilitator(facilitator)
+            .accepts(testing::requirements())
+            .resource(testing::resource())
+            .require_content_type("application/json")
+            .max_body_hint(1024)
+            .build();
+
+        let mut request = testing::paid_request(testing::requirements());
+        request.headers_mut().insert(
+            "Content-Type",
+            http::HeaderValue::from_static("Application/JSON; charset=utf-8"),
+        );
+        request
+            .headers_mut()
+            .insert("Content-Length", http::HeaderValue::from_static("512"));
+
+        paywall
+            .handle_payment(request, |_| async { http::Response::new(()) })
+            .await
+            .unwrap();
+
+        assert_eq!(calls.settle(), 1);
+    }
+
+    #[cfg(feature = "tracing")]
+    #[tokio::test]
+    #[tracing_test::traced_test]
+    async fn payment_span_records_settled_flow() {
+        let paywall = PayWall::builder()
+            .facilitator(MockFacilitator::default())
+            .accepts(testing::requirements())
+            .resource(testing::resource())
+            .build();
+
+        paywall
+            .handle_payment(testing::paid_request(testing::requirements()), |_| async {
+                http::Response::new(())
+            })
+            .await
+            .unwrap();
+
+        assert!(logs_contain("x402.payment{"));
+        assert!(logs_contain("resource=\"https://example.com/resource\""));
+        assert!(logs_contain("scheme=\"exact\""));
+        assert!(logs_contain("network=\"eip155:84532\""));
+        assert!(logs_contain("amount=1000"));
+        assert!(logs_contain(&format!("payer=\"{}\"", testing::PAYER)));
+        assert!(logs_contain("outcome=\"settled\""));
+        assert!(logs_contain("x402.verify{elapsed_ms="));
+        assert!(logs_contain("x402.settle{elapsed_ms="));
+    }
+
+    #[cfg(feature = "tracing")]
+    #[tokio::test]
+    #[tracing_test::traced_test]
+    async fn payment_span_displays_known_amount() {
+        use x402_core::types::KnownAssetRegistry;
+
+        let requirements = testing::requirements();
+        let paywall = PayWall::builder()
+            .facilitator(MockFacilitator::default())
+            .accepts(requirements.clone())
+            .resource(testing::resource())
+            .known_assets(KnownAssetRegistry::new().register_decimals(
+                requirements.network.clone(),
+                requirements.asset.clone(),
+                6,
+                "USDC",
+            ))
+            .build();
+
+        paywall
+            .handle_payment(testing::paid_request(requirements), |_| async {
+                http::Response::new(())
+            })
+            .await
+            .unwrap();
+
+        assert!(logs_contain("amount=0.001000 USDC"));
+    }
+
+    #[cfg(feature = "tracing")]
+    #[tokio::test]
+    #[tracing_test::traced_test]
+    async fn payment_span_records_failed_flow() {
+        let facilitator = MockFacilitator {
+            settle: SettleResult::failed(SettleFailed {
+                error_reason: "insufficient_funds".to_string(),
+                payer: None,
+            }),
+            ..Default::default()
+        };
+        let paywall = PayWall::builder()
+            .facilitator(facilitator)
+            .accepts(testing::requirements())
+            .resource(testing::resource())
+            .build();
+
+        paywall
+            .handle_payment(testing::paid_request(testing::requirements()), |_| async {
+                http::Response::new(())
+            })
+            .await
+            .unwrap_err();
+
+        assert!(logs_contain(&format!("payer=\"{}\"", testing::PAYER)));
+        assert!(logs_contain("outcome=\"failed\""));
+        assert!(logs_contain("status=402"));
+        assert!(logs_contain("error=\"settle_failed\""));
+    }
+
+    #[test]
+    fn process_headers_selects_requirements() {
+        let mut other = testing::requirements();
+        other.amount = AmountValue(5000);
+        let paywall = PayWall::builder()
+            .facilitator(MockFacilitator::default())
+            .accepts(vec![other, testing::requirements()])
+            .resource(testing::resource())
+            .build();
+
+        let mut headers = http::HeaderMap::new();
+        headers.insert(
+            "PAYMENT-SIGNATURE",
+            http::HeaderValue::from_str(&testing::payment_header(testing::requirements()).0)
+                .unwrap(),
+        );
+
+        let (payload, selected) = paywall.process_headers(&headers).unwrap();
+
+        assert_eq!(selected, testing::requirements());
+        assert_eq!(payload.accepted, selected);
+
+        let err = paywall
+            .process_headers(&http::HeaderMap::new())
+            .unwrap_err();
+        assert_eq!(err.status, http::StatusCode::PAYMENT_REQUIRED);
+        assert_eq!(err.cause, PaymentError::MissingSignature);
+    }
+
+    #[test]
+    fn oversized_payment_header_is_rejected() {
+        let paywall = PayWall::builder()
+            .facilitator(MockFacilitator::default())
+            .accepts(testing::requirements())
+            .resource(testing::resource())
+            .max_payment_header_size(64)
+            .build();
+
+        let header = testing::payment_header(testing::requirements());
+        let err = paywall
+            .process_request(testing::paid_request(testing::requirements()))
+            .err()
+            .unwrap();
+
+        assert_eq!(err.status, http::StatusCode::BAD_REQUEST);
+        assert_eq!(
+            err.cause,
+            PaymentError::MalformedHeader(format!(
+                "PAYMENT-SIGNATURE header of {} bytes exceeds the limit of 64 bytes",
+                header.0.len()
+            ))
+        );
+
+        let default = PayWall::builder()
+            .facilitator(MockFacilitator::default())
+            .accepts(testing::requirements())
+            .resource(testing::resource())
+            .build();
+        assert_eq!(default.max_payment_header_size, 16 * 1024);
+
+        let huge = "A".repeat(default.max_payment_header_size + 1);
+        let request = http::Request::builder()
+            .header("PAYMENT-SIGNATURE", huge)
+            .body(())
+            .unwrap();
+        let err = default.process_request(request).err().unwrap();
+        assert!(
+            matches!(err.cause, PaymentError::MalformedHeader(ref reason) if reason.contains("exceeds the limit"))
+        );
+    }
+
+    fn invoice_request(required: PaymentRequirements, from: &str) -> http::Request<()> {
+        let payload = x402_core::transport::PaymentPayload {
+            x402_version: x402_core::types::X402V2,
+            resource: testing::resource().into(),
+            accepted: required,
+            payload: json!({ "signature": "0x00", "authorization": { "from": from } }),
+            extensions: Default::default(),
+        };
+        testing::request_with_payment(
+            http::Request::builder(),
+            &Base64EncodedHeader::try_from(payload).unwrap(),
+        )
+        .body(())
+        .unwrap()
+    }
+
+    #[test]
+    fn required_payer_is_enforced() {
+        let invoice = PaymentRequirements {
+            extra: Some(json!({ "name": "USDC", "version": "2", "requiredPayer": testing::PAYER })),
+            ..testing::requirements()
+        };
+        let paywall = PayWall::builder()
+            .facilitator(MockFacilitator::default())
+            .accepts(invoice.clone())
+            .resource(testing::resource())
+            .build();
+
+        let processor = paywall
+            .process_request(invoice_request(
+                invoice.clone(),
+                &testing::PAYER.to_lowercase(),
+            ))
+            .unwrap();
+        assert_eq!(processor.selected, invoice);
+
+        let err = paywall
+            .process_request(invoice_request(
+                invoice,
+                "0x3CB9B3bBfde8501f411bB69Ad3DC07908ED0dE20",
+            ))
+            .err()
+            .unwrap();
+        assert_eq!(err.status, http::StatusCode::BAD_REQUEST);
+        assert!(
+            matches!(err.cause, PaymentError::PayerDenied(ref reason) if reason.contains(testing::PAYER))
+        );
+    }
+
+    #[tokio::test]
+    async fn configured_extra_survives_update_accepts() {
+        let network = "solana:EtWTRABZaYq6iMfeYKouRu166VU2xqa1";
+        let facilitator = MockFacilitator {
+            supported: serde_json::from_value(json!({
+                "kinds": [{
+                    "x402Version": 2,
+                    "scheme": "exact",
+                    "network": network,
+                    "extra": { "feePayer": "FacilitatorFeePayer1111111111111111111111111" }
+                }],
+                "extensions": [],
+                "signers": {}
+            }))
+            .unwrap(),
+            ..Default::default()
+        };
+        // As built by `ExactSvm` with a fee, a fee-payer opt-in, and an `extra_merge`
+        let configured = json!({
+            "destinationAta": "DSoccwgFrbdQmEcXH7FrVtj6sJ46FUZNfa7HH5UEDzmd",
+            "allowFacilitatorFeePayer": true,
+            "feeBreakdown": { "baseAmount": "1000000", "feeAmount": "10000" },
+            "memo": "order-42"
+        });
+        let paywall = PayWall::builder()
+            .facilitator(facilitator)
+            .accepts(PaymentRequirements {
+                network: network.to_string(),
+                extra: Some(configured),
+                ..testing::requirements()
+            })
+            .resource(testing::resource())
+            .build();
+
+        let advertised = paywall.update_accepts().await.unwrap().payment_required();
+
+        let extra = advertised.body.accepts.as_ref()[0].extra.clone().unwrap();
+        assert_eq!(
+            extra,
+            json!({
+                "destinationAta": "DSoccwgFrbdQmEcXH7FrVtj6sJ46FUZNfa7HH5UEDzmd",
+                "allowFacilitatorFeePayer": true,
+                "feeBreakdown": { "baseAmount": "1000000", "feeAmount": "10000" },
+                "memo": "order-42",
+                "feePayer": "FacilitatorFeePayer1111111111111111111111111"
+            })
+        );
+    }
+
+    #[tokio::test]
+    async fn required_payer_survives_supported_extra() {
+        let facilitator = MockFacilitator {
+            supported: serde_json::from_value(json!({
+                "kinds": [{
+                    "x402Version": 2,
+                    "scheme": "exact",
+                    "network": "eip155:84532",
+                    "extra": { "feePayer": "0xfee" }
+                }],
+                "extensions": [],
+                "signers": {}
+            }))
+            .unwrap(),
+            ..Default::default()
+        };
+        let calls = facilitator.calls.clone();
+        let invoice = PaymentRequirements {
+            extra: Some(json!({ "name": "USDC", "version": "2", "requiredPayer": testing::PAYER })),
+            ..testing::requirements()
+        };
+        let paywall = PayWall::builder()
+            .facilitator(facilitator)
+            .accepts(invoice.clone())
+            .resource(testing::resource())
+            .build();
+
+        let advertised = paywall
+            .clone()
+            .update_accepts()
+            .await
+            .unwrap()
+            .accepts
+            .as_ref()[0]
+            .clone();
+        assert_eq!(
+            advertised.extra,
+            Some(json!({
+                "name": "USDC",
+                "version": "2",
+                "requiredPayer": testing::PAYER,
+                "feePayer": "0xfee"
+            }))
+        );
+
+        let err = paywall
+            .handle_payment(
+                invoice_request(advertised, "0x3CB9B3bBfde8501f411bB69Ad3DC07908ED0dE20"),
+                |_| async { http::Response::new(()) },
+            )
+            .await
+            .unwrap_err();
+        assert_eq!(err.status, http::StatusCode::BAD_REQUEST);
+        assert!(matches!(err.cause, PaymentError::PayerDenied(_)));
+        assert_eq!(calls.settle(), 0);
+    }
+
+    async fn flow_error(facilitator: MockFacilitator, request: http::Request<()>) -> ErrorResponse {
+        PayWall::builder()
+            .facilitator(facilitator)
+            .accepts(testing::requirements())
+            .resource(testing::resource())
+            .require_content_type("application/json")
+            .build()
+            .handle_payment(request, |_| async { http::Response::new(()) })
+            .await
+            .unwrap_err()
+    }
+
+    fn json_request(header: Option<&Base64EncodedHeader>) -> http::Request<()> {
+        let builder = http::Request::builder().header("Content-Type", "application/json");
+        match header {
+            Some(header) => testing::request_with_payment(builder, header),
+            None => builder,
+        }
+        .body(())
+        .unwrap()
+    }
+
+    #[tokio::test]
+    async fn errors_carry_payment_error() {
+        let paid = || json_request(Some(&testing::payment_header(testing::requirements())));
+
+        let err = flow_error(MockFacilitator::default(), json_request(None)).await;
+        assert_eq!(err.status, http::StatusCode::PAYMENT_REQUIRED);
+        assert_eq!(err.cause, PaymentError::MissingSignature);
+
+        let malformed = Base64EncodedHeader("not base64".to_string());
+        let err = flow_error(MockFacilitator::default(), json_request(Some(&malformed))).await;
+        assert_eq!(err.status, http::StatusCode::BAD_REQUEST);
+        assert!(matches!(err.cause, PaymentError::MalformedHeader(_)));
+
+        let mut other = testing::requirements();
+        other.amount = AmountValue(5000);
+        let unsupported = testing::payment_header(other);
+        let err = flow_error(MockFacilitator::default(), json_request(Some(&unsupported))).await;
+        assert_eq!(err.status, http::StatusCode::BAD_REQUEST);
+        assert_eq!(err.cause, PaymentError::UnsupportedOption);
+
+        let facilitator = MockFacilitator {
+            verify: VerifyResult::invalid(VerifyInvalid {
+                invalid_reason: "invalid_signature".to_string(),
+                payer: None,
+            }),
+            ..Default::default()
+        };
+        let err = flow_error(facilitator, paid()).await;
+        assert_eq!(err.status, http::StatusCode::PAYMENT_REQUIRED);
+        assert_eq!(
+            err.cause,
+            PaymentError::VerifyFailed("invalid_signature".to_string())
+        );
+
+        let facilitator = MockFacilitator {
+            settle: SettleResult::failed(SettleFailed {
+                error_reason: "insufficient_funds".to_string(),
+                payer: None,
+            }),
+            ..Default::default()
+        };
+        let err = flow_error(facilitator, paid()).await;
+        assert_eq!(err.status, http::StatusCode::PAYMENT_REQUIRED);
+        assert_eq!(
+            err.cause,
+            PaymentError::SettleFailed("insufficient_funds".to_string())
+        );
+        assert_eq!(err.body.error, "insufficient_funds");
+
+        let facilitator = MockFacilitator {
+            unavailable: true,
+            ..Default::default()
+        };
+        let err = flow_error(facilitator, paid()).await;
+        assert_eq!(err.status, http::StatusCode::INTERNAL_SERVER_ERROR);
+        assert!(matches!(err.cause, PaymentError::Facilitator(_)));
+
+        let request = http::Request::builder()
+            .header("Content-Type", "text/plain")
+            .body(())
+            .unwrap();
+        let err = flow_error(MockFacilitator::default(), request).await;
+        assert_eq!(err.status, http::StatusCode::UNSUPPORTED_MEDIA_TYPE);
+        assert!(matches!(err.cause, PaymentError::RequestRejected(_)));
+    }
+
+    #[tokio::test]
+    async fn errors_use_configured_format_and_retry_after() {
+        let paywall = PayWall::builder()
+            .facilitator(MockFacilitator {
+                unavailable: true,
+                ..Default::default()
+            })
+            .accepts(testing::requirements())
+            .resource(testing::resource())
+            .error_format(ErrorFormat::Problem)
+            .retry_after(30)
+            .build();
+
+        let response: http::Response<Vec<u8>> = paywall
+            .clone()
+            .handle_payment(testing::paid_request(testing::requirements()), |_| async {
+                http::Response::new(())
+            })
+            .await
+            .unwrap_err()
+            .into_http_response();
+
+        assert_eq!(response.status(), http::StatusCode::INTERNAL_SERVER_ERROR);
+        assert_eq!(
+            response.headers()[http::header::CONTENT_TYPE],
+            "application/problem+json"
+        );
+        assert_eq!(response.headers()[http::header::RETRY_AFTER], "30");
+
+        let response: http::Response<Vec<u8>> = paywall.payment_required().into_http_response();
+        assert_eq!(response.status(), http::StatusCode::PAYMENT_REQUIRED);
+        assert!(!response.headers().contains_key(http::header::RETRY_AFTER));
+        let body: serde_json::Value = serde_json::from_slice(response.body()).unwrap();
+        assert_eq!(body["type"], "urn:x402:error:missing_signature");
+    }
+
+    #[test]
+    fn standalone_challenge_matches_paywall() {
+        let paywall = PayWall::builder()
+            .facilitator(MockFacilitator::default())
+            .accepts(testing::requirements())
+            .resource(testing::resource())
+            .order_id("order-42")
+            .build();
+        let response: http::Response<Vec<u8>> = paywall.payment_required().into_http_response();
+
+        let standalone = PaymentRequired::builder()
+            .error("PAYMENT-SIGNATURE header is required")
+            .resource(testing::resource())
+            .accepts(testing::requirements())
+            .extensions(paywall.advertised_extensions())
+            .build();
+
+        assert_eq!(
+            response.headers()["payment-required"].as_bytes(),
+            standalone.to_header().unwrap().0.as_bytes()
+        );
+        assert_eq!(*response.body(), standalone.to_json_body().unwrap());
+    }
+
+    fn kyc() -> Record<Extension> {
+        Record::from([(
+            "kyc".to_string(),
+            Extension::new(json!({ "level": "basic" }), json!({ "type": "object" })),
+        )])
+    }
+
+    #[tokio::test]
+    async fn strict_extensions_require_payload_extensions() {
+        let paywall = PayWall::builder()
+            .facilitator(MockFacilitator::default())
+            .accepts(testing::requirements())
+            .resource(testing::resource())
+            .extensions(kyc())
+            .strict_extensions(true)
+            .build();
+
+        let mut payload =
+            PaymentPayload::from_header(&testing::payment_header(testing::requirements()).0)
+                .unwrap();
+        payload.extensions = kyc();
+        let request = testing::request_with_payment(
+            http::Request::builder().uri("https://example.com/resource"),
+            &Base64EncodedHeader::try_from(payload).unwrap(),
+        )
+        .body(())
+        .unwrap();
+
+        let response = paywall
+            .clone()
+            .handle_payment(request, |_| async { http::Response::new(()) })
+            .await
+            .unwrap();
+        assert_eq!(response.status(), http::StatusCode::OK);
+
+        let err = paywall
+            .handle_payment(testing::paid_request(testing::requirements()), |_| async {
+                http::Response::new(())
+            })
+            .await
+            .unwrap_err();
+        assert_eq!(err.status, http::StatusCode::BAD_REQUEST);
+        assert_eq!(
+            err.cause,
+            PaymentError::MissingExtensions(vec!["kyc".to_string()])
+        );
+        assert_eq!(
+            err.body.error,
+            "Payment payload is missing required extensions: kyc"
+        );
+    }
+
+    #[tokio::test]
+    async fn missing_extensions_pass_without_strict_extensions() {
+        let paywall = PayWall::builder()
+            .facilitator(MockFacilitator::default())
+            .accepts(testing::requirements())
+            .resource(testing::resource())
+            .extensions(kyc())
+            .build();
+
+        let response = paywall
+            .handle_payment(testing::paid_request(testing::requirements()), |_| async {
+                http::Response::new(())
+            })
+            .await
+            .unwrap();
+        assert_eq!(response.status(), http::StatusCode::OK);
+    }
+
+    #[derive(Debug, Clone, serde::Deserialize)]
+    struct Kyc {
+        #[allow(dead_code)]
+        level: String,
+    }
+
+    impl ExtensionInfo for Kyc {
+        const ID: &'static str = "kyc";
+
+        fn schema() -> AnyJson {
+            json!({
+                "type": "object",
+                "properties": { "level": { "type": "string" } },
+                "required": ["level"]
+            })
+        }
+    }
+
+    fn request_with_extensions(extensions: Record<Extension>) -> http::Request<()> {
+        let mut payload =
+            PaymentPayload::from_header(&testing::payment_header(testing::requirements()).0)
+                .unwrap();
+        payload.extensions = extensions;
+        testing::request_with_payment(
+            http::Request::builder().uri("https://example.com/resource"),
+            &Base64EncodedHeader::try_from(payload).unwrap(),
+        )
+        .body(())
+        .unwrap()
+    }
+
+    #[tokio::test]
+    async fn required_extensions_are_enforced() {
+        let paywall = PayWall::builder()
+            .require_typed_extension_strict::<Kyc>()
+            .require_extension_strict("terms")
+            .facilitator(MockFacilitator::default())
+            .accepts(testing::requirements())
+            .resource(testing::resource())
+            .extensions(kyc())
+            .build();
+        let terms = || {
+            (
+                "terms".to_string(),
+                Extension::new(json!({ "accepted": true }), json!({})),
+            )
+        };
+
+        // Present and valid
+        let mut extensions = kyc();
+        extensions.extend([terms()]);
+        let response = paywall
+            .clone()
+            .handle_payment(request_with_extensions(extensions), |_| async {
+                http::Response::new(())
+            })
+            .await
+            .unwrap();
+        assert_eq!(response.status(), http::StatusCode::OK);
+
+        // Absent
+        let err = paywall
+            .clone()
+            .handle_payment(request_with_extensions(kyc()), |_| async {
+                http::Response::new(())
+            })
+            .await
+            .unwrap_err();
+        assert_eq!(err.status, http::StatusCode::BAD_REQUEST);
+        assert_eq!(
+            err.cause,
+            PaymentError::MissingExtensions(vec!["terms".to_string()])
+        );
+
+        // Present, but not matching the typed extension
+        let invalid = Record::from([
+            (
+                "kyc".to_string(),
+                Extension::new(json!({ "level": 3 }), Kyc::schema()),
+            ),
+            terms(),
+        ]);
+        let err = paywall
+            .handle_payment(request_with_extensions(invalid), |_| async {
+                http::Response::new(())
+            })
+            .await
+            .unwrap_err();
+        assert_eq!(err.status, http::StatusCode::BAD_REQUEST);
+        assert_eq!(err.cause.code(), "invalid_extension");
+        assert!(
+            err.body.error.starts_with("Extension kyc is invalid: "),
+            "{}",
+            err.body.error
+        );
+    }
+
+    fn strict_paywall() -> PayWall<MockFacilitator> {
+        PayWall::builder()
+            .facilitator(MockFacilitator::default())
+            .accepts(testing::requirements())
+            .resource(testing::resource())
+            .strict(true)
+            .build()
+    }
+
+    /// A request paying with the JSON of the default payload, edited.
+    fn request_with_edited_payload(edit: impl FnOnce(&mut AnyJson)) -> http::Request<()> {
+        use base64::{Engine, prelude::BASE64_STANDARD};
+
+        let header = testing::payment_header(testing::requirements());
+        let mut payload: AnyJson = serde_json::from_slice(&header.decode().unwrap()).unwrap();
+        edit(&mut payload);
+
+        testing::request_with_payment(
+            http::Request::builder().uri("https://example.com/resource"),
+            &Base64EncodedHeader(BASE64_STANDARD.encode(payload.to_string())),
+        )
+        .body(())
+        .unwrap()
+    }
+
+    #[tokio::test]
+    async fn strict_passes_conformant_payloads() {
+        let response = strict_paywall()
+            .handle_payment(testing::paid_request(testing::requirements()), |_| async {
+                http::Response::new(())
+            })
+            .await
+            .unwrap();
+        assert_eq!(response.status(), http::StatusCode::OK);
+    }
+
+    #[test]
+    fn strict_rejects_version_mismatch() {
+        let request = || request_with_edited_payload(|payload| payload["x402Version"] = json!(1));
+
+        let err = strict_paywall().process_request(request()).err().unwrap();
+        assert_eq!(err.status, http::StatusCode::BAD_REQUEST);
+        assert_eq!(
+            err.cause,
+            PaymentError::NonConformant(
+                "x402Version 1 does not match the accepted version 2".to_string()
+            )
+        );
+
+        // Rejected as malformed without strict mode
+        let err = PayWall {
+            strict: false,
+            ..strict_paywall()
+        }
+        .process_request(request())
+        .err()
+        .unwrap();
+        assert!(matches!(err.cause, PaymentError::MalformedHeader(_)));
+    }
+
+    #[test]
+    fn strict_rejects_other_resource() {
+        let request = || {
+            request_with_edited_payload(|payload| {
+                payload["resource"]["url"] = json!("https://example.com/other");
+            })
+        };
+
+        let err = strict_paywall().process_request(request()).err().unwrap();
+        assert_eq!(err.status, http::StatusCode::BAD_REQUEST);
+        assert_eq!(
+            err.cause,
+            PaymentError::NonConformant(
+                "Payload resource https://example.com/other does not match the resource https://example.com/resource"
+                    .to_string()
+            )
+        );
+        assert_eq!(err.cause.code(), "non_conformant_payload");
+
+        let lenient = PayWall {
+            strict: false,
+            ..strict_paywall()
+        };
+        assert!(lenient.process_request(request()).is_ok());
+    }
+
+    #[test]
+    fn strict_rejects_unadvertised_extensions() {
+        let request = || {
+            request_with_edited_payload(|payload| {
+                payload["extensions"] = serde_json::to_value(kyc()).unwrap();
+            })
+        };
+
+        let err = strict_paywall().process_request(request()).err().unwrap();
+        assert_eq!(err.status, http::StatusCode::BAD_REQUEST);
+        assert_eq!(
+            err.body.error,
+            "Payload has extensions that were not advertised: kyc"
+        );
+
+        let advertised = PayWall {
+            extensions: kyc(),
+            ..strict_paywall()
+        };
+        assert!(advertised.process_request(request()).is_ok());
+
+        let lenient = PayWall {
+            strict: false,
+            ..strict_paywall()
+        };
+        assert!(lenient.process_request(request()).is_ok());
+    }
+
+    fn requirements_with_timeout(max_timeout_seconds: u64) -> PaymentRequirements {
+        PaymentRequirements {
+            max_timeout_seconds,
+            ..testing::requirements()
+        }
+    }
+
+    #[tokio::test]
+    async fn timeout_is_clamped() {
+        let paywall = PayWall::builder()
+            .facilitator(MockFacilitator::default())
+            .accepts(vec![
+                requirements_with_timeout(60_000),
+                PaymentRequirements {
+                    network: "eip155:8453".to_string(),
+                    max_timeout_seconds: 5,
+                    ..testing::requirements()
+                },
+            ])
+            .resource(testing::resource())
+            .skip_supported_update(true)
+            .clamp_timeout(30..=600)
+            .build();
+
+        let advertised = paywall.advertised_accepts();
+        assert_eq!(advertised.as_ref()[0].max_timeout_seconds, 600);
+        assert_eq!(advertised.as_ref()[1].max_timeout_seconds, 30);
+
+        // Payments for the clamped requirements are accepted, not the configured ones
+        paywall
+            .clone()
+            .handle_payment(
+                testing::paid_request(requirements_with_timeout(600)),
+                |_| async { http::Response::new(()) },
+            )
+            .await
+            .unwrap();
+        let err = paywall
+            .handle_payment(
+                testing::paid_request(requirements_with_timeout(60_000)),
+                |_| async { http::Response::new(()) },
+            )
+            .await
+            .unwrap_err();
+        assert_eq!(err.status, http::StatusCode::BAD_REQUEST);
+    }
+
+    #[cfg(feature = "tracing")]
+    #[tokio::test]
+    #[tracing_test::traced_test]
+    async fn update_accepts_warns_about_timeout_above_facilitator_max() {
+        let facilitator = MockFacilitator {
+            supported: serde_json::from_value(json!({
+                "kinds": [{
+                    "x402Version": 2,
+                    "scheme": "exact",
+                    "network": "eip155:84532",
+                    "extra": { "maxTimeoutSeconds": 300 }
+                }],
+                "extensions": [],
+                "signers": {}
+            }))
+            .unwrap(),
+            ..Default::default()
+        };
+        let paywall = PayWall::builder()
+            .facilitator(facilitator)
+            .accepts(requirements_with_timeout(3600))
+            .resource(testing::resource())
+            .build();
+
+        paywall.clone().update_accepts().await.unwrap();
+        assert!(logs_contain(
+            "Payment timeout exceeds the facilitator maximum"
+        ));
+        assert!(logs_contain(
+            "max_timeout_seconds=3600, facilitator_max=300"
+        ));
+    }
+
+    fn query_paywall() -> PayWall<MockFacilitator> {
+        PayWall::builder()
+            .facilitator(MockFacilitator::default())
+            .accepts(testing::requirements())
+            .resource(testing::resource())
+            .skip_supported_update(true)
+            .accept_query_param("x402_payment")
+            .build()
+    }
+
+    /// A request for the resource with the given query string.
+    fn query_request(query: &str) -> http::Request<()> {
+        http::Request::get(format!("https://example.com/resource?{query}"))
+            .body(())
+            .unwrap()
+    }
+
+    async fn handled_uri(
+        paywall: PayWall<MockFacilitator>,
+        request: http::Request<()>,
+    ) -> Result<String, ErrorResponse> {
+        paywall
+            .handle_payment(request, |req| async move {
+                http::Response::new(req.uri().to_string())
+            })
+            .await
+            .map(http::Response::into_body)
+    }
+
+    #[tokio::test]
+    async fn query_param_payment_is_stripped() {
+        let header = testing::payment_header(testing::requirements()).0;
+        let encoded = url::form_urlencoded::byte_serialize(header.as_bytes()).collect::<String>();
+
+        for value in [encoded.as_str(), header.as_str()] {
+            let uri = handled_uri(
+                query_paywall(),
+                query_request(&format!("id=1&x402_payment={value}&lang=en")),
+            )
+            .await
+            .unwrap();
+            assert_eq!(uri, "https://example.com/resource?id=1&lang=en");
+        }
+
+        let uri = handled_uri(
+            query_paywall(),
+            query_request(&format!("x402_payment={encoded}")),
+        )
+        .await
+        .unwrap();
+        assert_eq!(uri, "https://example.com/resource");
+
+        // Without opting in, the query is not read
+        let paywall = PayWall {
+            payment_query_param: None,
+            ..query_paywall()
+        };
+        let err = handled_uri(paywall, query_request(&format!("x402_payment={encoded}")))
+            .await
+            .unwrap_err();
+        assert_eq!(err.status, http::StatusCode::PAYMENT_REQUIRED);
+    }
+
+    #[tokio::test]
+    async fn header_takes_precedence_over_query_param() {
+        let mut unadvertised = testing::requirements();
+        unadvertised.amount = AmountValue(1);
+        let query = url::form_urlencoded::byte_serialize(
+            testing::payment_header(unadvertised).0.as_bytes(),
+        )
+        .collect::<String>();
+
+        let request = testing::request_with_payment(
+            http::Request::get(format!("https://example.com/resource?x402_payment={query}")),
+            &testing::payment_header(testing::requirements()),
+        )
+        .body(())
+        .unwrap();
+
+        // The header is accepted, and the query parameter stripped nonetheless
+        let uri = handled_uri(query_paywall(), request).await.unwrap();
+        assert_eq!(uri, "https://example.com/resource");
+    }
+
+    #[tokio::test]
+    async fn oversized_query_param_is_rejected() {
+        let paywall = PayWall {
+            max_payment_header_size: 64,
+            ..query_paywall()
+        };
+        let value = "A".repeat(65);
+
+        let err = handled_uri(paywall, query_request(&format!("x402_payment={value}")))
+            .await
+            .unwrap_err();
+
+        assert_eq!(err.status, http::StatusCode::BAD_REQUEST);
+        assert_eq!(
+            err.cause,
+            PaymentError::MalformedHeader(
+                "'x402_payment' query parameter of 65 bytes exceeds the limit of 64 bytes"
+                    .to_string()
+            )
+        );
+    }
+
+    #[tokio::test]
+    async fn head_and_options_pass_through() {
+        let paywall = PayWall::builder()
+            .facilitator(MockFacilitator::default())
+            .accepts(testing::requirements())
+            .resource(testing::resource())
+            .build();
+        let calls = paywall.facilitator.calls.clone();
+
+        let head = http::Request::head("https://example.com/resource")
+            .body(())
+            .unwrap();
+        let response = paywall
+            .clone()
+            .handle_payment(head, |_| async {
+                http::Response::builder()
+                    .header(http::header::CONTENT_LENGTH, "42")
+                    .body(())
+                    .unwrap()
+            })
+            .await
+            .unwrap();
+        assert_eq!(response.headers()[http::header::CONTENT_LENGTH], "42");
+        assert!(!response.headers().contains_key("payment-response"));
+
+        let options = http::Request::options("https://example.com/resource")
+            .body(())
+            .unwrap();
+        let response = paywall
+            .clone()
+            .handle_payment(options, |_| async { http::Response::new(()) })
+            .await
+            .unwrap();
+        assert_eq!(response.status(), http::StatusCode::OK);
+
+        // Paid `HEAD` requests are not charged either
+        let mut paid_head = testing::paid_request(testing::requirements());
+        *paid_head.method_mut() = http::Method::HEAD;
+        paywall
+            .clone()
+            .handle_payment(paid_head, |_| async { http::Response::new(()) })
+            .await
+            .unwrap();
+
+        assert_eq!(calls.supported(), 0);
+        assert_eq!(calls.verify(), 0);
+        assert_eq!(calls.settle(), 0);
+
+        let err = paywall
+            .handle_payment(
+                http::Request::get("https://example.com/resource")
+                    .body(())
+                    .unwrap(),
+                |_| async { http::Response::new(()) },
+            )
+            .await
+            .unwrap_err();
+        assert_eq!(err.status, http::StatusCode::PAYMENT_REQUIRED);
+    }
+
+    #[tokio::test]
+    async fn pass_through_methods_are_configurable() {
+        let paywall = PayWall::builder()
+            .facilitator(MockFacilitator::default())
+            .accepts(testing::requirements())
+            .resource(testing::resource())
+            .pass_through_methods(vec![])
+            .build();
+
+        let err = paywall
+            .handle_payment(
+                http::Request::head("https://example.com/resource")
+                    .body(())
+                    .unwrap(),
+                |_| async { http::Response::new(()) },
+            )
+            .await
+            .unwrap_err();
+        assert_eq!(err.status, http::StatusCode::PAYMENT_REQUIRED);
+    }
+
+    #[tokio::test]
+    async fn supported_changes_are_reported() {
+        let diffs = std::sync::Arc::new(std::sync::Mutex::new(Vec::new()));
+        let reported = diffs.clone();
+        let mut paywall = PayWall::builder()
+            .facilitator(MockFacilitator::default())
+            .accepts(testing::requirements())
+            .resource(testing::resource())
+            .on_supported_change(move |diff: &SupportedDiff| {
+                reported.lock().unwrap().push(diff.clone())
+            })
+            .build();
+
+        // Neither the first response nor an unchanged one is a change
+        paywall.clone().update_accepts().await.unwrap();
+        paywall.clone().update_accepts().await.unwrap();
+        assert!(diffs.lock().unwrap().is_empty());
+
+        paywall.facilitator.supported = serde_json::from_value(json!({
+            "kinds": [
+                { "x402Version": 2, "scheme": "exact", "network": "eip155:84532" },
+                { "x402Version": 2, "scheme": "exact", "network": "eip155:8453" }
+            ],
+            "extensions": [],
+            "signers": { "eip155:*": ["0x3CB9B3bBfde8501f411bB69Ad3DC07908ED0dE20"] }
+        }))
+        .unwrap();
+        paywall.clone().update_accepts().await.unwrap();
+
+        let diffs = diffs.lock().unwrap();
+        assert_eq!(diffs.len(), 1);
+        assert_eq!(diffs[0].added.len(), 1);
+        assert_eq!(diffs[0].added[0].network, "eip155:8453");
+        assert!(diffs[0].removed.is_empty());
+        assert_eq!(diffs[0].changed_signers, ["eip155:*"]);
+    }
 }
@@ -0,0 +1,182 @@
+//! Payer policies for allowing, denying, or granting free access to specific payers.
+//!
+//! A [`PayerPolicy`] is consulted by [`RequestProcessor::verify`](crate::processor::RequestProcessor::verify)
+//! once the facilitator has reported the payer of a valid payment.
+
+use std::fmt::Debug;
+
+/// The decision of a [`PayerPolicy`] for a given payer.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PayerDecision {
+    /// Proceed with the payment as usual.
+    Allow,
+    /// Reject the payment with the given reason.
+    Deny(String),
+    /// Grant access without settling the payment.
+    Free,
+}
+
+/// A policy deciding how the paywall treats a verified payer.
+pub trait PayerPolicy: Debug + Send + Sync {
+    /// Evaluate the payer on the given network (CAIP-2 identifier).
+    fn evaluate(&self, payer: &str, network: &str) -> PayerDecision;
+}
+
+/// A static list of denied payers and payers granted free access.
+///
+/// EVM addresses (`0x`-prefixed) are compared case-insensitively, other addresses are compared exactly.
+/// A payer on both lists is denied.
+///
+/// # Example
+///
+/// ```
+/// use x402_paywall::policy::{PayerDecision, PayerList, PayerPolicy};
+///
+/// let policy = PayerList::new()
+///     .deny("0x857b06519E91e3A54538791bDbb0E22373e36b66")
+///     .free("0x3CB9B3bBfde8501f411bB69Ad3DC07908ED0dE20");
+///
+/// assert!(matches!(
+///     policy.evaluate("0x857b06519e91e3a54538791bdbb0e22373e36b66", "eip155:8453"),
+///     PayerDecision::Deny(_)
+/// ));
+/// assert_eq!(
+///     policy.evaluate("0x3cb9b3bbfde8501f411bb69ad3dc07908ed0de20", "eip155:8453"),
+///     PayerDecision::Free
+/// );
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct PayerList {
+    /// Payers whose payments are rejected.
+    pub denied: Vec<String>,
+    /// Payers granted access without settlement.
+    pub free: Vec<String>,
+}
+
+impl PayerList {
+    /// Create an empty list, under which every payer pays.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a payer to the deny list.
+    pub fn deny(mut self, payer: impl Into<String>) -> Self {
+        self.denied.push(payer.into());
+        self
+    }
+
+    /// Add a payer to the free access list.
+    pub fn free(mut self, payer: impl Into<String>) -> Self {
+        self.free.push(payer.into());
+        self
+    }
+}
+
+impl PayerPolicy for PayerList {
+    fn evaluate(&self, payer: &str, _network: &str) -> PayerDecision {
+        if self.denied.iter().any(|p| same_payer(p, payer)) {
+            PayerDecision::Deny(format!("Payer {payer} is not allowed"))
+        } else if self.free.iter().any(|p| same_payer(p, payer)) {
+            PayerDecision::Free
+        } else {
+            PayerDecision::Allow
+        }
+    }
+}
+
+fn same_payer(a: &str, b: &str) -> bool {
+    if a.starts_with("0x") && b.starts_with("0x") {
+        a.eq_ignore_ascii_case(b)
+    } else {
+        a == b
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use http::StatusCode;
+
+    use crate::{
+        HttpResponse,
+        errors::PaymentError,
+        paywall::PayWall,
+        processor::PaymentState,
+        testing::{self, MockFacilitator},
+    };
+
+    use super::*;
+
+    fn paywall(policy: PayerList) -> PayWall<MockFacilitator> {
+        PayWall::builder()
+            .facilitator(MockFacilitator::default())
+            .accepts(testing::requirements())
+            .resource(testing::resource())
+            .payer_policy(policy)
+            .build()
+    }
+
+    async fn handler(req: http::Request<()>) -> http::Response<Option<PaymentState>> {
+        http::Response::new(req.extensions().get::<PaymentState>().cloned())
+    }
+
+    #[test]
+    fn solana_addresses_compare_exactly() {
+        let policy = PayerList::new().deny("Ge3jkza5KRfXvaq3GELNLh6V1pjjdEKNpEdGXJgjjKUR");
+
+        assert_eq!(
+            policy.evaluate("ge3jkza5KRfXvaq3GELNLh6V1pjjdEKNpEdGXJgjjKUR", "solana:x"),
+            PayerDecision::Allow
+        );
+    }
+
+    #[tokio::test]
+    async fn allowed_payer_settles() {
+        let paywall = paywall(PayerList::new().deny("0x0000000000000000000000000000000000000001"));
+        let calls = paywall.facilitator.calls.clone();
+
+        let response = paywall
+            .handle_payment(testing::paid_request(testing::requirements()), handler)
+            .await
+            .unwrap();
+
+        assert!(!response.body().as_ref().unwrap().free_access);
+        assert!(response.headers().contains_key("payment-response"));
+        assert_eq!(calls.settle(), 1);
+    }
+
+    #[tokio::test]
+    async fn denied_payer_is_rejected() {
+        let paywall = paywall(PayerList::new().deny(testing::PAYER.to_lowercase()));
+        let calls = paywall.facilitator.calls.clone();
+
+        let err = paywall
+            .handle_payment(testing::paid_request(testing::requirements()), handler)
+            .await
+            .unwrap_err();
+
+        assert_eq!(err.status, StatusCode::PAYMENT_REQUIRED);
+        assert!(err.body.error.contains("is not allowed"));
+        assert!(matches!(err.cause, PaymentError::PayerDenied(_)));
+        assert_eq!(calls.settle(), 0);
+    }
+
+    #[tokio::test]
+    async fn free_payer_skips_settlement() {
+        let paywall =
+            paywall(PayerList::new().free(testing::PAYER.to_uppercase().replace("0X", "0x")));
+        let calls = paywall.facilitator.calls.clone();
+
+        let response = paywall
+            .handle_payment(testing::paid_request(testing::requirements()), handler)
+            .await
+            .unwrap();
+
+        let state = response.body().as_ref().unwrap();
+        assert!(state.free_access);
+        assert!(state.verified.is_some());
+        assert!(response.is_success());
+        assert!(!response.headers().contains_key("payment-response"));
+        assert_eq!(calls.verify(), 1);
+        assert_eq!(calls.settle(), 0);
+    }
+}
@@ -0,0 +1,177 @@
+//! Live prices from an external pricing service.
+//!
+//! A [`PricingSource`] on a [`PayWall`](crate::paywall::PayWall) sets the amount of every accepted
+//! payment requirement when the accepts are updated, e.g. from a dynamic pricing service queried
+//! with the request. The priced amount is the base price, on top of which the
+//! [`fee_policy`](crate::paywall::PayWall::fee_policy) applies. Failed lookups are answered with a
+//! `500 Internal Server Error`.
+
+use std::{fmt::Debug, pin::Pin};
+
+use x402_core::{transport::PaymentRequirements, types::AmountValue};
+
+use crate::source::SourceError;
+
+/// The future returned by [`PricingSource::price`].
+pub type PriceFuture<'a> =
+    Pin<Box<dyn Future<Output = Result<AmountValue, SourceError>> + Send + 'a>>;
+
+/// What a [`PricingSource`] knows about the request being priced.
+#[derive(Debug, Clone, Default)]
+#[non_exhaustive]
+pub struct PricingContext {
+    /// The parts of the request, `None` if the accepts are updated outside of a request, e.g. by
+    /// [`PayWall::update_accepts`](crate::paywall::PayWall::update_accepts).
+    pub parts: Option<http::request::Parts>,
+}
+
+impl PricingContext {
+    /// The context of pricing the given request.
+    pub fn for_request(parts: http::request::Parts) -> Self {
+        PricingContext { parts: Some(parts) }
+    }
+}
+
+/// A source of the live price of accepted payment requirements.
+pub trait PricingSource: Debug + Send + Sync {
+    /// Look up the amount of the given payment requirements, in the smallest units of its asset.
+    ///
+    /// `base` carries the configured amount, e.g. to apply a multiplier to it.
+    fn price<'a>(
+        &'a self,
+        base: &'a PaymentRequirements,
+        ctx: &'a PricingContext,
+    ) -> PriceFuture<'a>;
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use x402_core::transport::PaymentRequired;
+
+    use crate::{
+        errors::PaymentError,
+        paywall::PayWall,
+        testing::{self, MockFacilitator},
+    };
+
+    use super::*;
+
+    /// Doubles every amount, recording the paths of the priced requests.
+    #[derive(Debug, Default)]
+    struct Doubling {
+        paths: Arc<Mutex<Vec<Option<String>>>>,
+    }
+
+    impl PricingSource for Doubling {
+        fn price<'a>(
+            &'a self,
+            base: &'a PaymentRequirements,
+            ctx: &'a PricingContext,
+        ) -> PriceFuture<'a> {
+            self.paths
+                .lock()
+                .unwrap()
+                .push(ctx.parts.as_ref().map(|parts| parts.uri.path().to_string()));
+            let amount = AmountValue(base.amount.0 * 2);
+
+            Box::pin(async move { Ok(amount) })
+        }
+    }
+
+    #[derive(Debug)]
+    struct Failing;
+
+    impl PricingSource for Failing {
+        fn price<'a>(
+            &'a self,
+            _: &'a PaymentRequirements,
+            _: &'a PricingContext,
+        ) -> PriceFuture<'a> {
+            Box::pin(async { Err(SourceError::Timeout) })
+        }
+    }
+
+    #[tokio::test]
+    async fn challenge_reflects_live_price() {
+        let paywall = PayWall::builder()
+            .facilitator(MockFacilitator::default())
+            .accepts(testing::requirements())
+            .resource(testing::resource())
+            .pricing_source(Doubling::default())
+            .build();
+
+        let err = paywall
+            .clone()
+            .handle_payment(
+                http::Request::builder()
+                    .uri("https://example.com/resource")
+                    .body(())
+                    .unwrap(),
+                |_| async { http::Response::new(()) },
+            )
+            .await
+            .unwrap_err();
+
+        assert_eq!(err.status, http::StatusCode::PAYMENT_REQUIRED);
+        let body: PaymentRequired =
+            serde_json::from_slice(&err.body.to_json_body().unwrap()).unwrap();
+        assert_eq!(body.accepts.as_ref()[0].amount, AmountValue(2000));
+
+        // Payments at the live price are accepted
+        let mut priced = testing::requirements();
+        priced.amount = AmountValue(2000);
+        paywall
+            .handle_payment(testing::paid_request(priced), |_| async {
+                http::Response::new(())
+            })
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn pricing_context_carries_the_request() {
+        let source = Doubling::default();
+        let paths = source.paths.clone();
+        let paywall = PayWall::builder()
+            .facilitator(MockFacilitator::default())
+            .accepts(testing::requirements())
+            .resource(testing::resource())
+            .pricing_source(source)
+            .build();
+
+        paywall
+            .clone()
+            .handle_payment(testing::paid_request(testing::requirements()), |_| async {
+                http::Response::new(())
+            })
+            .await
+            .unwrap_err();
+        let updated = paywall.update_accepts().await.unwrap();
+
+        assert_eq!(updated.accepts.as_ref()[0].amount, AmountValue(2000));
+        assert_eq!(
+            *paths.lock().unwrap(),
+            [Some("/resource".to_string()), None]
+        );
+    }
+
+    #[tokio::test]
+    async fn failed_pricing_is_a_server_error() {
+        let paywall = PayWall::builder()
+            .facilitator(MockFacilitator::default())
+            .accepts(testing::requirements())
+            .resource(testing::resource())
+            .pricing_source(Failing)
+            .build();
+
+        let err = paywall.update_accepts().await.unwrap_err();
+
+        assert_eq!(err.status, http::StatusCode::INTERNAL_SERVER_ERROR);
+        assert_eq!(
+            err.cause,
+            PaymentError::AcceptsUnavailable(SourceError::Timeout.to_string())
+        );
+    }
+}
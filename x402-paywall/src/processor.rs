@@ -1,17 +1,57 @@
+#[cfg(not(feature = "tokio"))]
+use std::time::Instant;
+#[cfg(feature = "tokio")]
+use tokio::time::Instant;
+
 use x402_core::{
     facilitator::{
-        Facilitator, PaymentRequest, SettleResult, SettleSuccess, VerifyResult, VerifyValid,
+        ConfirmationStatus, Facilitator, PaymentReceipt, PaymentRequest, SettlePending,
+        SettleResult, SettleSuccess, SettlementKey, VerifyResult, VerifyValid,
     },
-    transport::{PaymentPayload, PaymentRequirements, SettlementResponse},
-    types::{Base64EncodedHeader, Extension, Record},
+    transport::{PaymentPayload, PaymentRequirements, SettlementResponse, v1},
+    types::{AmountValue, AnyJson, Base64EncodedHeader, Extension, Record},
+};
+
+use crate::{
+    HttpRequest, HttpResponse,
+    degrade::{Degrade, UnverifiedAccess},
+    errors::{ErrorResponse, PaymentError},
+    journal::PendingSettlement,
+    paywall::{OnPending, PayWall, SettlingGuard},
+    policy::PayerDecision,
 };
+use url::Url;
 
-use crate::{HttpRequest, HttpResponse, errors::ErrorResponse, paywall::PayWall};
+/// The header carrying the settlement response to the client, see [`PayWall::payment_response_header`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum PaymentResponseHeader {
+    /// `PAYMENT-RESPONSE`, as specified by x402 v2.
+    #[default]
+    PaymentResponse,
+    /// `X-PAYMENT-RESPONSE`, as used by x402 v1 sellers, carrying the v1
+    /// [`PaymentResponse`](v1::PaymentResponse).
+    XPaymentResponse,
+    /// Both headers, for clients migrating from x402 v1 to v2.
+    Both,
+}
+
+impl PaymentResponseHeader {
+    /// The names of the headers to write.
+    pub fn names(self) -> &'static [&'static str] {
+        match self {
+            PaymentResponseHeader::PaymentResponse => &["payment-response"],
+            PaymentResponseHeader::XPaymentResponse => &["x-payment-response"],
+            PaymentResponseHeader::Both => &["payment-response", "x-payment-response"],
+        }
+    }
+}
 
 /// The state of a payment processed by the paywall when accessing the resource handler.
 ///
 /// This state is attached to the request extensions before running the resource handler,
-/// and can be accessed within the handler to inspect the payment status.
+/// and can be accessed within the handler to inspect the payment status. Behind nested paywalls,
+/// it is the state of the innermost one, see [`PaymentStates`] for all of them.
 ///
 /// # Example
 ///
@@ -33,14 +73,148 @@ use crate::{HttpRequest, HttpResponse, errors::ErrorResponse, paywall::PayWall};
 pub struct PaymentState {
     /// Verification result, if verification was performed.
     pub verified: Option<VerifyValid>,
+    /// When verification succeeded, used to enforce the [`PayWall::settlement_deadline`].
+    ///
+    /// A `tokio` instant with the `tokio` feature, so that the deadline follows tokio's clock.
+    pub verified_at: Option<Instant>,
     /// Settlement result, if settlement was performed.
+    ///
+    /// Together with the [`amount`](Self::amount), identifies the payment to refund with
+    /// [`Refundable::refund`](x402_core::facilitator::Refundable::refund), e.g. when the work
+    /// paid for failed after settlement.
     pub settled: Option<SettleSuccess>,
+    /// Whether the paywall's payer policy granted free access, in which case settlement is skipped.
+    pub free_access: bool,
+    /// Set if the payment is served without verification while the facilitator is unreachable,
+    /// in which case settlement is skipped, see [`Degrade::AllowUnverified`].
+    pub unverified: Option<UnverifiedAccess>,
+    /// The payment requirements selected by the payer.
+    pub selected: PaymentRequirements,
+    /// The key of the payment's settlement, sent to the facilitator as the idempotency key.
+    pub settlement_key: SettlementKey,
+    /// The seller's order id for the payment, see [`PayWall::order_id`].
+    pub order_id: Option<String>,
+    /// The URL of the paid resource, recorded in the payment's [`PaymentReceipt`].
+    pub resource: Url,
     /// All extensions info provided by the paywall.
     pub required_extensions: Record<Extension>,
     /// All extensions info provided by the signer.
     pub payload_extensions: Record<Extension>,
 }
 
+/// The states of the payments processed by every paywall wrapping the resource handler, outermost
+/// first.
+///
+/// Attached to the request extensions alongside the [`PaymentState`] of the innermost paywall, so
+/// that handlers behind nested paywalls, e.g. a base price and a surcharge on the same route, can
+/// inspect every payment rather than only the last one attached.
+///
+/// # Example
+///
+/// ```rust
+/// use axum::extract::Extension;
+/// use x402_paywall::processor::PaymentStates;
+///
+/// async fn example_handler(Extension(states): Extension<PaymentStates>) -> String {
+///     states
+///         .iter()
+///         .filter_map(|state| state.payer())
+///         .collect::<Vec<_>>()
+///         .join(", ")
+/// }
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct PaymentStates(pub Vec<PaymentState>);
+
+impl PaymentStates {
+    /// The state of the innermost paywall, i.e. the one closest to the resource handler.
+    ///
+    /// The same state as the [`PaymentState`] extension.
+    pub fn innermost(&self) -> Option<&PaymentState> {
+        self.0.last()
+    }
+
+    /// The states, outermost first.
+    pub fn iter(&self) -> impl Iterator<Item = &PaymentState> {
+        self.0.iter()
+    }
+}
+
+impl PaymentState {
+    /// Whether the payment has been settled, logging a warning if so.
+    ///
+    /// Used to turn repeated `settle*` calls into no-ops instead of settling the payment twice.
+    fn is_settled(&self) -> bool {
+        let Some(_settled) = &self.settled else {
+            return false;
+        };
+
+        #[cfg(feature = "tracing")]
+        tracing::warn!(
+            "Payment already settled: transaction='{}'; skipping settlement",
+            _settled.transaction
+        );
+
+        true
+    }
+
+    /// The attestation returned by verification, to be echoed to the facilitator on settle.
+    fn attestation(&self) -> Option<AnyJson> {
+        self.verified.as_ref().and_then(|v| v.attestation.clone())
+    }
+
+    /// The payer of the payment, from verification or, if not verified, from settlement.
+    ///
+    /// Returns `None` if the payment has been neither verified nor settled.
+    pub fn payer(&self) -> Option<&str> {
+        self.verified
+            .as_ref()
+            .map(|v| v.payer.as_str())
+            .or_else(|| self.settled.as_ref().map(|s| s.payer.as_str()))
+    }
+
+    /// The network of the selected payment requirements (CAIP-2 identifier).
+    pub fn network(&self) -> &str {
+        &self.selected.network
+    }
+
+    /// The amount of the selected payment requirements.
+    pub fn amount(&self) -> AmountValue {
+        self.selected.amount
+    }
+
+    /// Whether the settlement was signed by one of the facilitator's advertised signers.
+    ///
+    /// Returns `false` if the payment has not been settled, or the facilitator did not sign it.
+    pub fn is_settlement_verified(&self) -> bool {
+        self.settled
+            .as_ref()
+            .is_some_and(|settled| settled.verified_signature)
+    }
+
+    /// Build a [`PaymentReceipt`] for the selected payment requirements.
+    ///
+    /// The same receipt the paywall records to its [`ledger`](PayWall::ledger). Named `receipt`
+    /// rather than `into_receipt`, as it borrows the state and reads the selected requirements
+    /// from it.
+    ///
+    /// Returns `None` if the payment has not been settled.
+    pub fn receipt(&self) -> Option<PaymentReceipt> {
+        self.settled
+            .as_ref()
+            .map(|settled| self.settled_receipt(settled))
+    }
+
+    /// Build the [`PaymentReceipt`] of the given settlement of the selected payment requirements.
+    fn settled_receipt(&self, settled: &SettleSuccess) -> PaymentReceipt {
+        PaymentReceipt {
+            resource: Some(self.resource.to_string()),
+            order_id: self.order_id.clone(),
+            ..PaymentReceipt::new(settled, &self.selected)
+        }
+    }
+}
+
 /// Payment processing state before running the resource handler.
 ///
 /// See [`PayWall`] for usage in the full payment processing flow.
@@ -53,77 +227,201 @@ pub struct RequestProcessor<'pw, F: Facilitator, Req: HttpRequest> {
 }
 
 impl<'pw, F: Facilitator, Req: HttpRequest> RequestProcessor<'pw, F, Req> {
+    /// Check that the payer can fund the payment with the paywall's
+    /// [`BalanceChecker`](crate::balance::BalanceChecker), if set.
+    ///
+    /// Payments the checker rejects fail with a `400 Bad Request`, without calling the facilitator.
+    pub async fn check_balance(self) -> Result<Self, ErrorResponse> {
+        let Some(checker) = &self.paywall.balance_checker else {
+            return Ok(self);
+        };
+
+        if let Err(reason) = checker.check(&self.payload, &self.selected).await {
+            #[cfg(feature = "tracing")]
+            tracing::debug!("Payment rejected by balance check: {reason}");
+
+            return Err(self
+                .paywall
+                .invalid_payment(PaymentError::InsufficientFunds(reason)));
+        }
+
+        Ok(self)
+    }
+
     /// Verify the payment with the facilitator.
     ///
     /// `self.payment_state.verified` will be populated on success.
+    ///
+    /// If the paywall has a [`PayerPolicy`](crate::policy::PayerPolicy), it is consulted with the
+    /// verified payer: denied payers are rejected, and payers granted free access are marked with
+    /// `self.payment_state.free_access` so that settlement is skipped.
+    ///
+    /// If the facilitator is unreachable and the paywall allows it with
+    /// [`Degrade::AllowUnverified`], the payment is marked with `self.payment_state.unverified`
+    /// instead, so that the handler runs and settlement is skipped.
     pub async fn verify(mut self) -> Result<Self, ErrorResponse> {
-        let response = self
-            .paywall
-            .facilitator
-            .verify(PaymentRequest {
-                payment_payload: self.payload.clone(),
-                payment_requirements: self.selected.clone(),
-            })
-            .await
-            .map_err(|err| {
-                self.paywall
-                    .server_error(format!("Failed to verify payment: {err}"))
-            })?;
+        let call = self.paywall.facilitator.verify(PaymentRequest {
+            payment_payload: self.payload.clone(),
+            payment_requirements: self.selected.clone(),
+            attestation: None,
+            idempotency_key: None,
+        });
+        #[cfg(feature = "tracing")]
+        let call = timed(
+            tracing::info_span!("x402.verify", elapsed_ms = tracing::field::Empty),
+            call,
+        );
+
+        let response = call.await;
+        #[cfg(feature = "metrics")]
+        record_verify(&self.selected, &response);
+
+        let response = match response {
+            Ok(response) => response,
+            Err(err) => {
+                let error = PaymentError::Facilitator(format!("Failed to verify payment: {err}"));
+                return match self.allow_unverified() {
+                    Some(access) => {
+                        record_liability(self.paywall, &self.payment_state, &self.payload).await;
+                        self.payment_state.unverified = Some(access);
+                        Ok(self)
+                    }
+                    None => Err(self.paywall.facilitator_error(error)),
+                };
+            }
+        };
 
         let valid = match response {
             VerifyResult::Valid(v) => v,
             VerifyResult::Invalid(iv) => {
-                return Err(self.paywall.payment_failed(iv.invalid_reason));
+                return Err(self
+                    .paywall
+                    .payment_failed(PaymentError::VerifyFailed(iv.invalid_reason)));
             }
         };
 
         #[cfg(feature = "tracing")]
         tracing::debug!("Payment verified: payer='{}'", valid.payer);
 
+        if self.paywall.verify_payer_consistency
+            && let Some(signer) = payload_signer(&self.payload)
+            && !signer.eq_ignore_ascii_case(&valid.payer)
+        {
+            #[cfg(feature = "tracing")]
+            tracing::warn!(
+                "Facilitator reported another payer than the signer: payer='{}', signer='{}'",
+                valid.payer,
+                signer
+            );
+
+            return Err(self
+                .paywall
+                .payment_failed(PaymentError::VerifyFailed(format!(
+                    "Facilitator reported payer {}, but the payment is signed by {signer}",
+                    valid.payer
+                ))));
+        }
+
+        if let Some(policy) = &self.paywall.payer_policy {
+            match policy.evaluate(&valid.payer, &self.selected.network) {
+                PayerDecision::Allow => {}
+                PayerDecision::Deny(reason) => {
+                    #[cfg(feature = "tracing")]
+                    tracing::debug!("Payer denied by policy: payer='{}'", valid.payer);
+
+                    return Err(self
+                        .paywall
+                        .payment_failed(PaymentError::PayerDenied(reason)));
+                }
+                PayerDecision::Free => {
+                    #[cfg(feature = "tracing")]
+                    tracing::debug!("Payer granted free access: payer='{}'", valid.payer);
+
+                    self.payment_state.free_access = true;
+                }
+            }
+        }
+
         self.payment_state.verified = Some(valid);
+        self.payment_state.verified_at = Some(Instant::now());
 
         Ok(self)
     }
 
-    /// Settle the payment with the facilitator.
-    ///
-    /// `self.payment_state.settled` will be populated on success.
-    pub async fn settle(mut self) -> Result<Self, ErrorResponse> {
-        let settlement = self
-            .paywall
-            .facilitator
-            .settle(PaymentRequest {
-                payment_payload: self.payload.clone(),
-                payment_requirements: self.selected.clone(),
-            })
-            .await
-            .map_err(|err| {
-                self.paywall
-                    .server_error(format!("Failed to settle payment: {err}"))
-            })?;
+    /// Take a slot to serve the payment unverified, if the paywall's [`Degrade::AllowUnverified`]
+    /// policy allows its amount and a slot is free.
+    fn allow_unverified(&self) -> Option<UnverifiedAccess> {
+        let Some(Degrade::AllowUnverified {
+            max_amount,
+            max_concurrent,
+            log: _log,
+        }) = &self.paywall.facilitator_unreachable
+        else {
+            return None;
+        };
 
-        let settled = match settlement {
-            SettleResult::Success(s) => s,
-            SettleResult::Failed(f) => {
-                return Err(self.paywall.payment_failed(f.error_reason));
-            }
+        if self.selected.amount.0 > max_amount.0 {
+            #[cfg(feature = "tracing")]
+            tracing::warn!(
+                "Facilitator unreachable; amount {} exceeds the unverified maximum of {}",
+                self.selected.amount,
+                max_amount
+            );
+
+            return None;
+        }
+        let Some(access) = self.paywall.unverified_slots.take(*max_concurrent) else {
+            #[cfg(feature = "tracing")]
+            tracing::warn!(
+                "Facilitator unreachable; all {max_concurrent} unverified payment slots are taken"
+            );
+
+            return None;
         };
 
         #[cfg(feature = "tracing")]
-        tracing::debug!(
-            "Payment settled: payer='{}', transaction='{}', network='{}'",
-            settled.payer,
-            settled.transaction,
-            settled.network
-        );
+        if *_log {
+            tracing::warn!(
+                "Facilitator unreachable; serving unverified payment: amount='{}', network='{}', settlement_key='{}'",
+                self.selected.amount,
+                self.selected.network,
+                self.payment_state.settlement_key
+            );
+        }
 
-        self.payment_state.settled = Some(settled);
+        Some(access)
+    }
+
+    /// Settle the payment with the facilitator.
+    ///
+    /// `self.payment_state.settled` will be populated on success.
+    /// Settlement is skipped if the payer was granted free access, the payment is unverified, or
+    /// the payment is already settled.
+    /// With [`PayWall::settlement_polling`], waits until the settlement is confirmed.
+    ///
+    /// The [`settlement_key`](PaymentState::settlement_key) is sent as the idempotency key, and
+    /// concurrent settlements of the same payment by the paywall or its clones are rejected.
+    pub async fn settle(mut self) -> Result<Self, ErrorResponse> {
+        settle_payment(
+            self.paywall,
+            &self.payload,
+            &self.selected,
+            &mut self.payment_state,
+        )
+        .await?;
 
         Ok(self)
     }
 
     /// Run the resource handler with the payment state attached to the request extensions.
     ///
+    /// The attached [`PaymentState`] reflects every step run before this call, i.e. verification
+    /// and settlement through [`RequestProcessor::settle`]. Settlement after the handler, e.g. with
+    /// [`ResponseProcessor::settle_on_success`], is not visible to the handler.
+    ///
+    /// The state replaces the [`PaymentState`] of an outer paywall processing the same request,
+    /// and is pushed onto its [`PaymentStates`].
+    ///
     /// After running the handler, returns a [`ResponseProcessor`] for further processing.
     pub async fn run_handler<Fun, Fut, Res>(
         mut self,
@@ -133,6 +431,12 @@ impl<'pw, F: Facilitator, Req: HttpRequest> RequestProcessor<'pw, F, Req> {
         Fun: FnOnce(Req) -> Fut,
         Fut: Future<Output = Res>,
     {
+        let mut states = self
+            .request
+            .insert_extension(PaymentStates::default())
+            .unwrap_or_default();
+        states.0.push(self.payment_state.clone());
+        self.request.insert_extension(states);
         self.request.insert_extension(self.payment_state.clone());
 
         let response = handler(self.request).await;
@@ -159,37 +463,17 @@ impl<'pw, F: Facilitator, Res: HttpResponse> ResponseProcessor<'pw, F, Res> {
     /// Settle the payment with the facilitator after running the resource handler.
     ///
     /// After settlement, `self.payment_state.settled` will be populated on success.
+    /// Settlement is skipped if the payer was granted free access, the payment is unverified, or
+    /// the payment is already settled, e.g. by [`RequestProcessor::settle`] before running the handler.
     pub async fn settle(mut self) -> Result<Self, ErrorResponse> {
-        // Settle payment with facilitator
-        let settlement = self
-            .paywall
-            .facilitator
-            .settle(PaymentRequest {
-                payment_payload: self.payload.clone(),
-                payment_requirements: self.selected.clone(),
-            })
-            .await
-            .map_err(|err| {
-                self.paywall
-                    .server_error(format!("Failed to settle payment: {err}"))
-            })?;
+        settle_payment(
+            self.paywall,
+            &self.payload,
+            &self.selected,
+            &mut self.payment_state,
+        )
+        .await?;
 
-        let settled = match settlement {
-            SettleResult::Success(s) => s,
-            SettleResult::Failed(f) => {
-                return Err(self.paywall.payment_failed(f.error_reason));
-            }
-        };
-
-        #[cfg(feature = "tracing")]
-        tracing::debug!(
-            "Payment settled: payer='{}', transaction='{}', network='{}'",
-            settled.payer,
-            settled.transaction,
-            settled.network
-        );
-
-        self.payment_state.settled = Some(settled);
         Ok(self)
     }
 
@@ -212,29 +496,43 @@ impl<'pw, F: Facilitator, Res: HttpResponse> ResponseProcessor<'pw, F, Res> {
     }
 
     /// Generate the final response, including the `PAYMENT-RESPONSE` header if settled.
+    ///
+    /// The header is written under the name(s) of the paywall's
+    /// [`payment_response_header`](PayWall::payment_response_header).
     pub fn response(self) -> Res {
         let mut response = self.response;
 
         if let Some(settled) = &self.payment_state.settled {
             let settlement_response = SettlementResponse {
-                success: true,
-                payer: settled.payer.clone(),
-                transaction: settled.transaction.clone(),
-                network: settled.network.clone(),
+                order_id: self.payment_state.order_id.clone(),
+                confirmation: settled.confirmation,
+                ..SettlementResponse::new(&settled.payer, &settled.transaction, &settled.network)
             };
 
-            let header = Base64EncodedHeader::try_from(settlement_response)
-                .inspect_err(|err| {
-                    #[cfg(feature = "tracing")]
-                    tracing::warn!("Failed to encode PAYMENT-RESPONSE header: {err}; skipping")
-                })
-                .ok();
-            if let Some(header) = header {
+            for &name in self.paywall.payment_response_header.names() {
+                // v1 clients expect the v1 shape under the legacy name
+                let header = if name == "x-payment-response" {
+                    Base64EncodedHeader::try_from(v1::PaymentResponse::from(
+                        settlement_response.clone(),
+                    ))
+                } else {
+                    Base64EncodedHeader::try_from(settlement_response.clone())
+                };
+                let Some(header) = header
+                    .inspect_err(|_err| {
+                        #[cfg(feature = "tracing")]
+                        tracing::warn!("Failed to encode {name} header: {_err}; skipping")
+                    })
+                    .ok()
+                else {
+                    continue;
+                };
+
                 response
-                    .insert_header("payment-response", header.0.as_bytes())
-                    .inspect_err(|err| {
+                    .insert_header(name, header.0.as_bytes())
+                    .inspect_err(|_err| {
                         #[cfg(feature = "tracing")]
-                        tracing::warn!("Failed to encode PAYMENT-RESPONSE header: {err}; skipping")
+                        tracing::warn!("Failed to encode {name} header: {_err}; skipping")
                     })
                     .ok();
             }
@@ -243,3 +541,1177 @@ impl<'pw, F: Facilitator, Res: HttpResponse> ResponseProcessor<'pw, F, Res> {
         response
     }
 }
+
+/// The settled payment of a settle result, resolving pending settlements with the paywall's
+/// [`on_pending`](PayWall::on_pending) policy.
+async fn settled_payment<F: Facilitator>(
+    paywall: &PayWall<F>,
+    state: &PaymentState,
+    settlement: SettleResult,
+) -> Result<SettleSuccess, ErrorResponse> {
+    match settlement {
+        SettleResult::Success(settled) => Ok(settled),
+        SettleResult::Failed(failed) => {
+            Err(paywall.payment_failed(PaymentError::SettleFailed(failed.error_reason)))
+        }
+        SettleResult::Pending(pending) => resolve_pending(paywall, state, pending).await,
+        _ => Err(paywall.payment_failed(PaymentError::SettleFailed(
+            "Unrecognized settlement result".to_string(),
+        ))),
+    }
+}
+
+/// Resolve a pending settlement according to the paywall's [`on_pending`](PayWall::on_pending)
+/// policy.
+///
+/// The settlement is identified by the facilitator's reference, the selected network and the
+/// verified payer, as the facilitator did not report a settled transaction. Pending settlements
+/// without a reference cannot be identified, so they fail like failed settlements.
+async fn resolve_pending<F: Facilitator>(
+    paywall: &PayWall<F>,
+    state: &PaymentState,
+    pending: SettlePending,
+) -> Result<SettleSuccess, ErrorResponse> {
+    #[cfg(feature = "tracing")]
+    tracing::debug!(
+        "Settlement pending: reference={:?}, policy={:?}",
+        pending.reference,
+        paywall.on_pending
+    );
+
+    if paywall.on_pending == OnPending::Fail {
+        return Err(
+            paywall.payment_failed(PaymentError::SettleFailed("Settlement pending".to_string()))
+        );
+    }
+    let Some(reference) = pending.reference.filter(|reference| !reference.is_empty()) else {
+        return Err(paywall.payment_failed(PaymentError::SettleFailed(
+            "Settlement pending without a reference".to_string(),
+        )));
+    };
+
+    #[cfg_attr(not(feature = "tokio"), allow(unused_mut))]
+    let mut settled = SettleSuccess {
+        payer: state.payer().unwrap_or_default().to_string(),
+        transaction: reference,
+        network: state.selected.network.clone(),
+        verified_signature: false,
+        signer: None,
+        confirmation: Some(ConfirmationStatus::Broadcast),
+    };
+
+    #[cfg(feature = "tokio")]
+    if let OnPending::Poll { attempts, interval } = &paywall.on_pending {
+        use crate::paywall::SettlementPolling;
+
+        // Poll `attempts` times, `interval` apart, starting after the first interval
+        let interval = pending.poll_after.unwrap_or(*interval);
+        let polling = SettlementPolling::builder()
+            .initial_backoff(interval)
+            .max_backoff(interval)
+            .deadline(interval * (*attempts).max(1))
+            .build();
+        poll_settlement(
+            paywall,
+            &mut settled,
+            ConfirmationStatus::Confirmed(1),
+            interval,
+            &polling,
+        )
+        .await?;
+    }
+
+    Ok(settled)
+}
+
+/// Poll the settlement status until the settlement reaches the minimum of
+/// [`PayWall::require_confirmation`], or is confirmed if only [`PayWall::settlement_polling`] is
+/// set.
+///
+/// Uses the backoff and deadline of [`PayWall::settlement_polling`] if set, or the defaults.
+#[cfg(feature = "tokio")]
+async fn confirm_settlement<F: Facilitator>(
+    paywall: &PayWall<F>,
+    settled: &mut SettleSuccess,
+) -> Result<(), ErrorResponse> {
+    use crate::paywall::SettlementPolling;
+
+    let required = match (paywall.require_confirmation, &paywall.settlement_polling) {
+        (Some(required), _) => required,
+        (None, Some(_)) => ConfirmationStatus::Confirmed(1),
+        (None, None) => return Ok(()),
+    };
+    let polling = paywall
+        .settlement_polling
+        .clone()
+        .unwrap_or_else(|| SettlementPolling::builder().build());
+
+    poll_settlement(
+        paywall,
+        settled,
+        required,
+        std::time::Duration::ZERO,
+        &polling,
+    )
+    .await
+}
+
+/// Poll the settlement status until the settlement reaches the `required` confirmation status,
+/// recording the last status in its `confirmation`.
+///
+/// The first poll is made after `first_delay`, then with the backoff of `polling` until its
+/// deadline, after which the settlement fails with a `504 Gateway Timeout`.
+#[cfg(feature = "tokio")]
+async fn poll_settlement<F: Facilitator>(
+    paywall: &PayWall<F>,
+    settled: &mut SettleSuccess,
+    required: ConfirmationStatus,
+    first_delay: std::time::Duration,
+    polling: &crate::paywall::SettlementPolling,
+) -> Result<(), ErrorResponse> {
+    use x402_core::facilitator::SettleStatus;
+
+    let deadline = Instant::now() + polling.deadline;
+    let mut delay = first_delay;
+    let mut backoff = polling.initial_backoff;
+
+    while settled
+        .confirmation
+        .is_none_or(|confirmation| confirmation < required)
+    {
+        if !delay.is_zero() {
+            tokio::time::sleep(delay).await;
+        }
+
+        let status = paywall
+            .facilitator
+            .settle_status(settled)
+            .await
+            .map_err(|err| {
+                paywall.facilitator_error(PaymentError::Facilitator(format!(
+                    "Failed to get settlement status: {err}"
+                )))
+            })?;
+        let status = match status {
+            SettleStatus::Failed { error_reason } => {
+                return Err(paywall.payment_failed(PaymentError::SettleFailed(error_reason)));
+            }
+            status => status.confirmation(),
+        };
+        settled.confirmation = status;
+        if status.is_some_and(|status| status >= required) {
+            break;
+        }
+
+        let now = Instant::now();
+        if now >= deadline {
+            #[cfg(feature = "tracing")]
+            tracing::warn!(
+                "Settlement did not reach {:?} in time: transaction='{}', status={:?}",
+                required,
+                settled.transaction,
+                status
+            );
+
+            return Err(paywall.timeout(PaymentError::SettlementUnconfirmed(
+                settled.transaction.clone(),
+            )));
+        }
+
+        #[cfg(feature = "tracing")]
+        tracing::debug!(
+            "Settlement at {:?}, waiting for {:?}: transaction='{}', retrying in {:?}",
+            status,
+            required,
+            settled.transaction,
+            backoff
+        );
+
+        delay = backoff.min(deadline - now);
+        backoff = (backoff * 2).min(polling.max_backoff);
+    }
+
+    Ok(())
+}
+
+#[cfg(not(feature = "tokio"))]
+async fn confirm_settlement<F: Facilitator>(
+    _paywall: &PayWall<F>,
+    _settled: &mut SettleSuccess,
+) -> Result<(), ErrorResponse> {
+    Ok(())
+}
+
+/// The signer of the payload, if it names one, see [`PayWall::verify_payer_consistency`].
+fn payload_signer(payload: &PaymentPayload) -> Option<&str> {
+    ["/authorization/from", "/permit/owner"]
+        .into_iter()
+        .find_map(|pointer| payload.payload.pointer(pointer)?.as_str())
+}
+
+/// Settle the payment with the facilitator, the shared body of [`RequestProcessor::settle`] and
+/// [`ResponseProcessor::settle`].
+///
+/// Skipped if the payer was granted free access, the payment is unverified, or the payment is
+/// already settled. Otherwise the settled payment is confirmed, recorded to the ledger, and
+/// stored in the `state`.
+async fn settle_payment<F: Facilitator>(
+    paywall: &PayWall<F>,
+    payload: &PaymentPayload,
+    selected: &PaymentRequirements,
+    state: &mut PaymentState,
+) -> Result<(), ErrorResponse> {
+    if state.free_access || state.unverified.is_some() || state.is_settled() {
+        return Ok(());
+    }
+    check_settlement_deadline(paywall, state)?;
+    let _settling = begin_settlement(paywall, state)?;
+
+    let request = PaymentRequest {
+        payment_payload: payload.clone(),
+        payment_requirements: selected.clone(),
+        attestation: state.attestation(),
+        idempotency_key: Some(state.settlement_key.clone()),
+    };
+    journal_pending(paywall, &request).await;
+    let call = paywall.facilitator.settle(request);
+    #[cfg(feature = "tracing")]
+    let call = timed(
+        tracing::info_span!("x402.settle", elapsed_ms = tracing::field::Empty),
+        call,
+    );
+
+    let settlement = call.await;
+    #[cfg(feature = "metrics")]
+    record_settle(selected, &settlement);
+    if settlement.is_ok() {
+        journal_settled(paywall, &state.settlement_key).await;
+    }
+
+    let settlement = settlement.map_err(|err| {
+        paywall.facilitator_error(PaymentError::Facilitator(format!(
+            "Failed to settle payment: {err}"
+        )))
+    })?;
+
+    let mut settled = settled_payment(paywall, state, settlement).await?;
+
+    check_settle_signer(paywall, &settled)?;
+    confirm_settlement(paywall, &mut settled).await?;
+
+    #[cfg(feature = "tracing")]
+    tracing::debug!(
+        "Payment settled: payer='{}', transaction='{}', network='{}'",
+        settled.payer,
+        settled.transaction,
+        settled.network
+    );
+
+    record_payment(paywall, state, &settled).await;
+    notify_settled(paywall, selected, &settled).await?;
+    state.settled = Some(settled);
+
+    Ok(())
+}
+
+/// Reject the settlement if the payment was verified longer ago than the
+/// [`settlement_deadline`](PayWall::settlement_deadline).
+fn check_settlement_deadline<F: Facilitator>(
+    paywall: &PayWall<F>,
+    state: &PaymentState,
+) -> Result<(), ErrorResponse> {
+    let (Some(deadline), Some(verified_at)) = (paywall.settlement_deadline, state.verified_at)
+    else {
+        return Ok(());
+    };
+    if verified_at.elapsed() <= deadline {
+        return Ok(());
+    }
+
+    #[cfg(feature = "tracing")]
+    tracing::warn!(
+        "Settlement deadline exceeded: settlement_key='{}', deadline={:?}",
+        state.settlement_key,
+        deadline
+    );
+
+    Err(paywall.payment_failed(PaymentError::SettlementDeadlineExceeded))
+}
+
+/// Mark the payment as being settled, rejecting it if another request is already settling it.
+fn begin_settlement<F: Facilitator>(
+    paywall: &PayWall<F>,
+    state: &PaymentState,
+) -> Result<SettlingGuard, ErrorResponse> {
+    paywall
+        .settling
+        .begin(&state.settlement_key)
+        .ok_or_else(|| {
+            #[cfg(feature = "tracing")]
+            tracing::warn!(
+                "Payment is already being settled: settlement_key='{}'",
+                state.settlement_key
+            );
+
+            paywall.payment_failed(PaymentError::SettleFailed(
+                "Payment is already being settled".to_string(),
+            ))
+        })
+}
+
+/// Record the settlement as pending to the paywall's [`journal`](PayWall::journal), if set.
+///
+/// Failures are logged, but do not fail the request since the settlement can proceed without
+/// being journaled.
+async fn journal_pending<F: Facilitator>(paywall: &PayWall<F>, request: &PaymentRequest) {
+    let Some(journal) = &paywall.journal else {
+        return;
+    };
+    let Some(pending) = PendingSettlement::new(request.clone()) else {
+        return;
+    };
+    let _settlement_key = pending.settlement_key.clone();
+
+    if let Err(_err) = journal.record_pending(pending).await {
+        #[cfg(feature = "tracing")]
+        tracing::warn!(
+            "Failed to journal pending settlement: settlement_key='{_settlement_key}': {_err}"
+        );
+    }
+}
+
+/// Mark the settlement as answered in the paywall's [`journal`](PayWall::journal), if set.
+///
+/// Failures are logged, leaving the settlement pending, so that it is retried idempotently.
+async fn journal_settled<F: Facilitator>(paywall: &PayWall<F>, settlement_key: &SettlementKey) {
+    let Some(journal) = &paywall.journal else {
+        return;
+    };
+
+    if let Err(_err) = journal.mark_settled(settlement_key).await {
+        #[cfg(feature = "tracing")]
+        tracing::warn!(
+            "Failed to mark settlement as answered in the journal: settlement_key='{settlement_key}': {_err}"
+        );
+    }
+}
+
+/// Reject settlements whose signature was verified by a signer not in the paywall's
+/// [`expected_signers`](PayWall::expected_signers).
+///
+/// Settlements without a verified signature cannot be attributed to a signer and are let through.
+fn check_settle_signer<F: Facilitator>(
+    paywall: &PayWall<F>,
+    settled: &SettleSuccess,
+) -> Result<(), ErrorResponse> {
+    match &settled.signer {
+        Some(signer)
+            if !paywall.expected_signers.is_empty() && !paywall.is_expected_signer(signer) =>
+        {
+            #[cfg(feature = "tracing")]
+            tracing::error!(
+                "Settlement signed by an unexpected signer: signer='{signer}', transaction='{}'",
+                settled.transaction
+            );
+
+            Err(paywall.server_error(PaymentError::Facilitator(format!(
+                "Settlement signed by an unexpected signer: {signer}"
+            ))))
+        }
+        _ => Ok(()),
+    }
+}
+
+/// Record the settled payment to the paywall's [`ledger`](PayWall::ledger), if set.
+///
+/// Failures are reported to the [`on_ledger_error`](PayWall::on_ledger_error) hook, logged and
+/// counted in `x402_ledger_errors_total`, but do not fail the request since the payment is settled.
+async fn record_payment<F: Facilitator>(
+    paywall: &PayWall<F>,
+    state: &PaymentState,
+    settled: &SettleSuccess,
+) {
+    let Some(ledger) = &paywall.ledger else {
+        return;
+    };
+    let receipt = state.settled_receipt(settled);
+
+    if let Err(err) = ledger.record(receipt.clone()).await {
+        #[cfg(feature = "tracing")]
+        tracing::warn!(
+            "Failed to record settled payment to the ledger: transaction='{}': {err}",
+            settled.transaction
+        );
+
+        if let Some(hook) = &paywall.on_ledger_error {
+            hook(err.as_ref(), &receipt);
+        }
+
+        #[cfg(feature = "metrics")]
+        metrics::counter!(
+            "x402_ledger_errors_total",
+            "scheme" => state.selected.scheme.to_string(),
+            "network" => state.selected.network.clone(),
+        )
+        .increment(1);
+    }
+}
+
+/// Run the paywall's [`on_settled`](PayWall::on_settled) callback, if set.
+///
+/// Failures are answered with a server error, as the callback's side effect, e.g. granting an
+/// entitlement, did not happen although the payment is settled.
+async fn notify_settled<F: Facilitator>(
+    paywall: &PayWall<F>,
+    selected: &PaymentRequirements,
+    settled: &SettleSuccess,
+) -> Result<(), ErrorResponse> {
+    let Some(on_settled) = &paywall.on_settled else {
+        return Ok(());
+    };
+
+    on_settled(settled, selected).await.map_err(|err| {
+        #[cfg(feature = "tracing")]
+        tracing::error!(
+            "Post-settlement callback failed: transaction='{}': {err}",
+            settled.transaction
+        );
+
+        paywall.server_error(PaymentError::SettledHookFailed(format!(
+            "Post-settlement callback failed: {err}"
+        )))
+    })
+}
+
+/// Record the unverified payment to the paywall's [`ledger`](PayWall::ledger) as a liability, if set.
+///
+/// Failures are reported like those of settled payments.
+async fn record_liability<F: Facilitator>(
+    paywall: &PayWall<F>,
+    state: &PaymentState,
+    payload: &PaymentPayload,
+) {
+    let Some(ledger) = &paywall.ledger else {
+        return;
+    };
+    let selected = &state.selected;
+    let receipt = PaymentReceipt {
+        resource: Some(state.resource.to_string()),
+        order_id: state.order_id.clone(),
+        ..PaymentReceipt::liability(payload, selected)
+    };
+
+    if let Err(err) = ledger.record(receipt.clone()).await {
+        #[cfg(feature = "tracing")]
+        tracing::warn!(
+            "Failed to record unverified payment to the ledger: settlement_key='{}': {err}",
+            state.settlement_key
+        );
+
+        if let Some(hook) = &paywall.on_ledger_error {
+            hook(err.as_ref(), &receipt);
+        }
+
+        #[cfg(feature = "metrics")]
+        metrics::counter!(
+            "x402_ledger_errors_total",
+            "scheme" => selected.scheme.to_string(),
+            "network" => selected.network.clone(),
+        )
+        .increment(1);
+    }
+}
+
+/// Count a verification in `x402_verify_total`, labelled with the scheme, network and outcome.
+#[cfg(feature = "metrics")]
+fn record_verify<E>(selected: &PaymentRequirements, response: &Result<VerifyResult, E>) {
+    let outcome = match response {
+        Ok(VerifyResult::Valid(_)) => "valid",
+        Ok(VerifyResult::Invalid(_)) => "invalid",
+        Err(_) => "error",
+    };
+
+    metrics::counter!(
+        "x402_verify_total",
+        "scheme" => selected.scheme.to_string(),
+        "network" => selected.network.clone(),
+        "outcome" => outcome,
+    )
+    .increment(1);
+}
+
+/// Count a settlement in `x402_settle_total`, labelled with the scheme, network and outcome,
+/// and record the amount of successful settlements in `x402_settle_amount`, in the asset's smallest units.
+#[cfg(feature = "metrics")]
+fn record_settle<E>(selected: &PaymentRequirements, settlement: &Result<SettleResult, E>) {
+    let outcome = match settlement {
+        Ok(SettleResult::Success(_)) => "success",
+        Ok(SettleResult::Failed(_)) => "failed",
+        Ok(SettleResult::Pending(_)) => "pending",
+        Ok(_) => "unknown",
+        Err(_) => "error",
+    };
+
+    metrics::counter!(
+        "x402_settle_total",
+        "scheme" => selected.scheme.to_string(),
+        "network" => selected.network.clone(),
+        "outcome" => outcome,
+    )
+    .increment(1);
+
+    if let Ok(SettleResult::Success(_)) = settlement {
+        metrics::histogram!(
+            "x402_settle_amount",
+            "scheme" => selected.scheme.to_string(),
+            "network" => selected.network.clone(),
+            "asset" => selected.asset.clone(),
+        )
+        .record(selected.amount.0 as f64);
+    }
+}
+
+/// Run a facilitator call within the given span, recording its elapsed time.
+#[cfg(feature = "tracing")]
+async fn timed<T>(span: tracing::Span, call: impl Future<Output = T>) -> T {
+    use tracing::Instrument;
+
+    let start = std::time::Instant::now();
+    let output = call.instrument(span.clone()).await;
+    span.record("elapsed_ms", start.elapsed().as_millis() as u64);
+    span.in_scope(|| tracing::debug!("Facilitator call finished"));
+
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use serde_json::json;
+    use x402_core::{
+        facilitator::{SettleResult, SettleSuccess, SettlementKey, VerifyResult, VerifyValid},
+        transport::{PaymentPayload, PaymentRequirements, SettlementResponse, v1},
+        types::{AmountValue, Base64EncodedHeader, Record},
+    };
+
+    use crate::{
+        errors::PaymentError,
+        ledger::VecLedger,
+        paywall::{ORDER_ID_EXTENSION, PayWall},
+        processor::{PaymentResponseHeader, PaymentState, PaymentStates},
+        testing::{self, MockFacilitator},
+    };
+
+    fn paywall(facilitator: MockFacilitator) -> PayWall<MockFacilitator> {
+        PayWall::builder()
+            .facilitator(facilitator)
+            .accepts(testing::requirements())
+            .resource(testing::resource())
+            .build()
+    }
+
+    #[test]
+    fn receipt_from_settled_state() {
+        let state = PaymentState {
+            verified: None,
+            verified_at: None,
+            settled: Some(SettleSuccess {
+                payer: testing::PAYER.to_string(),
+                transaction: "0xabc".to_string(),
+                network: "eip155:84532".to_string(),
+                verified_signature: false,
+                signer: None,
+                confirmation: None,
+            }),
+            free_access: false,
+            unverified: None,
+            selected: testing::requirements(),
+            settlement_key: SettlementKey("key".to_string()),
+            order_id: None,
+            resource: testing::resource().url,
+            required_extensions: Record::new(),
+            payload_extensions: Record::new(),
+        };
+
+        let receipt = state.receipt().unwrap();
+
+        assert_eq!(receipt.payer, testing::PAYER);
+        assert_eq!(receipt.transaction, "0xabc");
+        assert_eq!(receipt.network, "eip155:84532");
+        assert_eq!(receipt.scheme, "exact");
+        assert_eq!(receipt.amount, AmountValue(1000));
+        assert_eq!(
+            receipt.resource.as_deref(),
+            Some("https://example.com/resource")
+        );
+        assert!(receipt.timestamp > 0);
+
+        let json = serde_json::to_value(&receipt).unwrap();
+        assert_eq!(json["amount"], "1000");
+        assert_eq!(json["transaction"], "0xabc");
+    }
+
+    #[test]
+    fn no_receipt_without_settlement() {
+        let state = PaymentState {
+            verified: None,
+            verified_at: None,
+            settled: None,
+            free_access: false,
+            unverified: None,
+            selected: testing::requirements(),
+            settlement_key: SettlementKey("key".to_string()),
+            order_id: None,
+            resource: testing::resource().url,
+            required_extensions: Record::new(),
+            payload_extensions: Record::new(),
+        };
+
+        assert!(state.receipt().is_none());
+    }
+
+    #[tokio::test]
+    async fn settle_before_handler_is_not_repeated() {
+        let paywall = paywall(MockFacilitator::default());
+        let calls = paywall.facilitator.calls.clone();
+
+        let response = paywall
+            .process_request(testing::paid_request(testing::requirements()))
+            .unwrap()
+            .verify()
+            .await
+            .unwrap()
+            .settle()
+            .await
+            .unwrap()
+            .run_handler(|_| async { http::Response::new(()) })
+            .await
+            .unwrap()
+            .settle_on_success()
+            .await
+            .unwrap()
+            .response();
+
+        assert!(response.headers().contains_key("payment-response"));
+        assert_eq!(calls.settle(), 1);
+    }
+
+    #[cfg(feature = "tokio")]
+    #[tokio::test(start_paused = true)]
+    async fn settlement_deadline_skips_late_settlement() {
+        let paywall = PayWall::builder()
+            .facilitator(MockFacilitator::default())
+            .accepts(testing::requirements())
+            .resource(testing::resource())
+            .settlement_deadline(std::time::Duration::from_millis(10))
+            .build();
+        let calls = paywall.facilitator.calls.clone();
+
+        let err = paywall
+            .clone()
+            .handle_payment(testing::paid_request(testing::requirements()), |_| async {
+                tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+                http::Response::new(())
+            })
+            .await
+            .unwrap_err();
+        assert_eq!(err.status, http::StatusCode::PAYMENT_REQUIRED);
+        assert_eq!(err.cause, PaymentError::SettlementDeadlineExceeded);
+        assert_eq!(calls.verify(), 1);
+        assert_eq!(calls.settle(), 0);
+
+        // Settlement within the deadline proceeds
+        paywall
+            .handle_payment(testing::paid_request(testing::requirements()), |_| async {
+                http::Response::new(())
+            })
+            .await
+            .unwrap();
+        assert_eq!(calls.settle(), 1);
+    }
+
+    #[tokio::test]
+    async fn payer_consistency_rejects_mismatched_payer() {
+        let signed = |from: &str| {
+            let mut request = testing::paid_request(testing::requirements());
+            let payload = PaymentPayload {
+                payload: json!({ "signature": "0x00", "authorization": { "from": from } }),
+                ..PaymentPayload::from_header(&testing::payment_header(testing::requirements()).0)
+                    .unwrap()
+            };
+            request.headers_mut().insert(
+                "PAYMENT-SIGNATURE",
+                Base64EncodedHeader::try_from(payload)
+                    .unwrap()
+                    .0
+                    .parse()
+                    .unwrap(),
+            );
+            request
+        };
+        let paywall = PayWall::builder()
+            .facilitator(MockFacilitator::default())
+            .accepts(testing::requirements())
+            .resource(testing::resource())
+            .verify_payer_consistency(true)
+            .build();
+
+        let err = paywall
+            .process_request(signed("0x0000000000000000000000000000000000000001"))
+            .unwrap()
+            .verify()
+            .await
+            .err()
+            .unwrap();
+        assert_eq!(err.status, http::StatusCode::PAYMENT_REQUIRED);
+        assert!(matches!(err.cause, PaymentError::VerifyFailed(_)));
+
+        // Addresses are compared case-insensitively
+        paywall
+            .process_request(signed(&testing::PAYER.to_lowercase()))
+            .unwrap()
+            .verify()
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn on_settled_runs_once_per_settlement() {
+        let settled = Arc::new(Mutex::new(Vec::new()));
+        let recorded = settled.clone();
+        let paywall = PayWall::builder()
+            .on_settled(
+                move |settled: &SettleSuccess, selected: &PaymentRequirements| {
+                    recorded
+                        .lock()
+                        .unwrap()
+                        .push((settled.transaction.clone(), selected.amount));
+                    async { Ok::<_, String>(()) }
+                },
+            )
+            .facilitator(MockFacilitator::default())
+            .accepts(testing::requirements())
+            .resource(testing::resource())
+            .build();
+
+        paywall
+            .clone()
+            .handle_payment(testing::paid_request(testing::requirements()), |_| async {
+                http::Response::new(())
+            })
+            .await
+            .unwrap();
+        assert_eq!(settled.lock().unwrap().len(), 1);
+        assert_eq!(settled.lock().unwrap()[0].1, AmountValue(1000));
+
+        // Not settled, so not called
+        let response = paywall
+            .handle_payment(testing::paid_request(testing::requirements()), |_| async {
+                http::Response::builder()
+                    .status(http::StatusCode::INTERNAL_SERVER_ERROR)
+                    .body(())
+                    .unwrap()
+            })
+            .await
+            .unwrap();
+        assert_eq!(response.status(), http::StatusCode::INTERNAL_SERVER_ERROR);
+        assert_eq!(settled.lock().unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn failed_on_settled_is_a_server_error() {
+        let paywall = PayWall::builder()
+            .on_settled(|_: &SettleSuccess, _: &PaymentRequirements| async {
+                Err("entitlement service down")
+            })
+            .facilitator(MockFacilitator::default())
+            .accepts(testing::requirements())
+            .resource(testing::resource())
+            .build();
+
+        let err = paywall
+            .handle_payment(testing::paid_request(testing::requirements()), |_| async {
+                http::Response::new(())
+            })
+            .await
+            .unwrap_err();
+
+        assert_eq!(err.status, http::StatusCode::INTERNAL_SERVER_ERROR);
+        assert_eq!(err.cause.code(), "settled_hook_failed");
+    }
+
+    #[tokio::test]
+    async fn settlement_is_guarded_by_its_key() {
+        let paywall = paywall(MockFacilitator::default());
+        let calls = paywall.facilitator.calls.clone();
+        let key = SettlementKey::derive(
+            &PaymentPayload::from_header(&testing::payment_header(testing::requirements()).0)
+                .unwrap(),
+        );
+
+        // Another request is settling the same payment
+        let settling = paywall.settling.begin(&key).unwrap();
+        let err = paywall
+            .process_request(testing::paid_request(testing::requirements()))
+            .unwrap()
+            .settle()
+            .await
+            .err()
+            .unwrap();
+        assert_eq!(
+            err.cause,
+            PaymentError::SettleFailed("Payment is already being settled".to_string())
+        );
+        assert_eq!(calls.settle(), 0);
+        drop(settling);
+
+        paywall
+            .process_request(testing::paid_request(testing::requirements()))
+            .unwrap()
+            .settle()
+            .await
+            .unwrap();
+        assert_eq!(
+            calls.last_settle_request().unwrap().idempotency_key,
+            Some(key.clone())
+        );
+
+        // The key is released after settlement
+        assert!(paywall.settling.begin(&key).is_some());
+    }
+
+    #[tokio::test]
+    async fn order_id_survives_to_receipt() {
+        let ledger = Arc::new(VecLedger::new());
+        let paywall = PayWall::builder()
+            .facilitator(MockFacilitator::default())
+            .accepts(testing::requirements())
+            .resource(testing::resource())
+            .order_id("order-42")
+            .ledger(ledger.clone())
+            .build();
+
+        let challenge = paywall.payment_required();
+        assert_eq!(
+            challenge.body.extensions[ORDER_ID_EXTENSION].info,
+            json!({ "orderId": "order-42" })
+        );
+
+        let processed = paywall
+            .process_request(testing::paid_request(testing::requirements()))
+            .unwrap()
+            .settle()
+            .await
+            .unwrap()
+            .run_handler(|_| async { http::Response::new(()) })
+            .await
+            .unwrap();
+        assert_eq!(
+            processed.payment_state.order_id.as_deref(),
+            Some("order-42")
+        );
+
+        let receipt = processed.payment_state.receipt().unwrap();
+        assert_eq!(receipt.order_id.as_deref(), Some("order-42"));
+        assert_eq!(
+            serde_json::to_value(&receipt).unwrap()["orderId"],
+            "order-42"
+        );
+        assert_eq!(ledger.receipts()[0].order_id.as_deref(), Some("order-42"));
+        assert_eq!(ledger.receipts()[0].resource, receipt.resource);
+
+        let response = processed.response();
+        let header = response.headers()["payment-response"].to_str().unwrap();
+        let settlement =
+            SettlementResponse::try_from(Base64EncodedHeader(header.to_string())).unwrap();
+        assert_eq!(settlement.order_id.as_deref(), Some("order-42"));
+    }
+
+    async fn settled_response(header: PaymentResponseHeader) -> http::Response<()> {
+        PayWall::builder()
+            .facilitator(MockFacilitator::default())
+            .accepts(testing::requirements())
+            .resource(testing::resource())
+            .payment_response_header(header)
+            .build()
+            .process_request(testing::paid_request(testing::requirements()))
+            .unwrap()
+            .settle()
+            .await
+            .unwrap()
+            .run_handler(|_| async { http::Response::new(()) })
+            .await
+            .unwrap()
+            .response()
+    }
+
+    #[tokio::test]
+    async fn payment_response_header_names() {
+        let legacy = settled_response(PaymentResponseHeader::XPaymentResponse).await;
+        assert!(legacy.headers().contains_key("x-payment-response"));
+        assert!(!legacy.headers().contains_key("payment-response"));
+
+        let both = settled_response(PaymentResponseHeader::Both).await;
+        assert_eq!(
+            both.headers()["payment-response"],
+            both.headers()["x-payment-response"]
+        );
+        let settlement = SettlementResponse::try_from(Base64EncodedHeader(
+            both.headers()["x-payment-response"]
+                .to_str()
+                .unwrap()
+                .to_string(),
+        ))
+        .unwrap();
+        assert_eq!(settlement.transaction, "0xdeadbeef");
+    }
+
+    #[tokio::test]
+    async fn legacy_header_carries_v1_response() {
+        let response = PayWall::builder()
+            .facilitator(MockFacilitator::default())
+            .accepts(testing::requirements())
+            .resource(testing::resource())
+            .payment_response_header(PaymentResponseHeader::Both)
+            .order_id("order-42")
+            .build()
+            .handle_payment(testing::paid_request(testing::requirements()), |_| async {
+                http::Response::new(())
+            })
+            .await
+            .unwrap();
+
+        let v2 =
+            SettlementResponse::try_from(response.headers()["payment-response"].to_str().unwrap())
+                .unwrap();
+        assert_eq!(v2.order_id.as_deref(), Some("order-42"));
+
+        let legacy = response.headers()["x-payment-response"].to_str().unwrap();
+        let v1 = v1::PaymentResponse::try_from(legacy).unwrap();
+        assert_eq!(v1, v1::PaymentResponse::from(v2.clone()));
+        assert_eq!(
+            v1::reencode_payment_response(response.headers()["payment-response"].to_str().unwrap())
+                .unwrap()
+                .0,
+            legacy
+        );
+    }
+
+    #[tokio::test]
+    async fn repeated_settle_is_a_no_op() {
+        let paywall = paywall(MockFacilitator::default());
+        let calls = paywall.facilitator.calls.clone();
+
+        let processor = paywall
+            .process_request(testing::paid_request(testing::requirements()))
+            .unwrap()
+            .settle()
+            .await
+            .unwrap()
+            .settle()
+            .await
+            .unwrap();
+
+        assert_eq!(
+            processor.payment_state.settled.unwrap().transaction,
+            "0xdeadbeef"
+        );
+        assert_eq!(calls.settle(), 1);
+    }
+
+    async fn handler(req: http::Request<()>) -> http::Response<PaymentState> {
+        http::Response::new(req.extensions().get::<PaymentState>().cloned().unwrap())
+    }
+
+    #[tokio::test]
+    async fn handler_sees_verified_state() {
+        let paywall = paywall(MockFacilitator::default());
+
+        let processor = paywall
+            .process_request(testing::paid_request(testing::requirements()))
+            .unwrap()
+            .verify()
+            .await
+            .unwrap()
+            .run_handler(handler)
+            .await
+            .unwrap()
+            .settle_on_success()
+            .await
+            .unwrap();
+
+        let seen = processor.response.body();
+        assert_eq!(seen.payer(), Some(testing::PAYER));
+        assert_eq!(seen.network(), "eip155:84532");
+        assert_eq!(seen.amount(), AmountValue(1000));
+        assert!(seen.settled.is_none());
+        assert!(processor.payment_state.settled.is_some());
+    }
+
+    #[tokio::test]
+    async fn nested_paywalls_attach_both_states() {
+        let outer = PayWall::builder()
+            .facilitator(MockFacilitator::default())
+            .accepts(testing::requirements())
+            .resource(testing::resource())
+            .order_id("outer")
+            .build();
+        let inner = PayWall::builder()
+            .facilitator(MockFacilitator::default())
+            .accepts(testing::requirements())
+            .resource(testing::resource())
+            .order_id("inner")
+            .build();
+
+        let response = outer
+            .handle_payment(
+                testing::paid_request(testing::requirements()),
+                |request| async move {
+                    inner
+                        .handle_payment(request, |request: http::Request<()>| async move {
+                            let states = request.extensions().get::<PaymentStates>().unwrap();
+                            let state = request.extensions().get::<PaymentState>().unwrap();
+                            assert_eq!(states.innermost().unwrap().order_id, state.order_id);
+                            http::Response::new(
+                                states
+                                    .iter()
+                                    .map(|state| state.order_id.clone().unwrap())
+                                    .collect::<Vec<_>>(),
+                            )
+                        })
+                        .await
+                        .unwrap()
+                },
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(response.into_body(), ["outer", "inner"]);
+    }
+
+    #[tokio::test]
+    async fn settle_echoes_verify_attestation() {
+        let facilitator = MockFacilitator {
+            verify: VerifyResult::valid(VerifyValid {
+                payer: testing::PAYER.to_string(),
+                attestation: Some(json!({ "verificationId": "v-123" })),
+            }),
+            ..Default::default()
+        };
+        let calls = facilitator.calls.clone();
+
+        paywall(facilitator)
+            .handle_payment(testing::paid_request(testing::requirements()), handler)
+            .await
+            .unwrap();
+
+        let settle_request = calls.last_settle_request().unwrap();
+        assert_eq!(
+            settle_request.attestation,
+            Some(json!({ "verificationId": "v-123" }))
+        );
+    }
+
+    #[tokio::test]
+    async fn settle_without_verification_has_no_attestation() {
+        let facilitator = MockFacilitator::default();
+        let calls = facilitator.calls.clone();
+        let paywall = paywall(facilitator);
+
+        paywall
+            .process_request(testing::paid_request(testing::requirements()))
+            .unwrap()
+            .settle()
+            .await
+            .unwrap();
+
+        assert_eq!(calls.last_settle_request().unwrap().attestation, None);
+    }
+
+    #[tokio::test]
+    async fn handler_sees_settled_state_without_verification() {
+        let facilitator = MockFacilitator {
+            settle: SettleResult::success(SettleSuccess {
+                payer: "0x0000000000000000000000000000000000000002".to_string(),
+                transaction: "0xabc".to_string(),
+                network: "eip155:84532".to_string(),
+                verified_signature: false,
+                signer: None,
+                confirmation: None,
+            }),
+            ..Default::default()
+        };
+        let paywall = paywall(facilitator);
+
+        let processor = paywall
+            .process_request(testing::paid_request(testing::requirements()))
+            .unwrap()
+            .settle()
+            .await
+            .unwrap()
+            .run_handler(handler)
+            .await
+            .unwrap();
+
+        let seen = processor.response.body();
+        assert!(seen.verified.is_none());
+        assert_eq!(
+            seen.payer(),
+            Some("0x0000000000000000000000000000000000000002")
+        );
+        assert_eq!(seen.settled.as_ref().unwrap().transaction, "0xabc");
+    }
+
+    #[cfg(feature = "metrics")]
+    #[test]
+    fn flow_records_metrics() {
+        use metrics_util::{
+            MetricKind,
+            debugging::{DebugValue, DebuggingRecorder},
+        };
+
+        let recorder = DebuggingRecorder::new();
+        let snapshotter = recorder.snapshotter();
+
+        metrics::with_local_recorder(&recorder, || {
+            tokio::runtime::Builder::new_current_thread()
+                .enable_time()
+                .build()
+                .unwrap()
+                .block_on(
+                    paywall(MockFacilitator::default()).handle_payment(
+                        testing::paid_request(testing::requirements()),
+                        |_| async { http::Response::new(()) },
+                    ),
+                )
+                .unwrap();
+        });
+
+        let metrics = snapshotter.snapshot().into_vec();
+        let find = |kind: MetricKind, name: &str| {
+            metrics
+                .iter()
+                .find(|(key, ..)| key.kind() == kind && key.key().name() == name)
+                .map(|(key, _, _, value)| (key.key(), value))
+                .unwrap_or_else(|| panic!("metric {name} should be recorded"))
+        };
+        let has_label = |key: &metrics::Key, name: &str, value: &str| {
+            key.labels().any(|l| l.key() == name && l.value() == value)
+        };
+
+        let (key, value) = find(MetricKind::Counter, "x402_verify_total");
+        assert!(matches!(value, DebugValue::Counter(1)));
+        assert!(has_label(key, "outcome", "valid"));
+
+        let (key, value) = find(MetricKind::Counter, "x402_settle_total");
+        assert!(matches!(value, DebugValue::Counter(1)));
+        assert!(has_label(key, "outcome", "success"));
+        assert!(has_label(key, "network", "eip155:84532"));
+
+        let (_, value) = find(MetricKind::Histogram, "x402_settle_amount");
+        assert!(
+            matches!(value, DebugValue::Histogram(amounts) if amounts.len() == 1 && *amounts[0] == 1000.0)
+        );
+    }
+}
@@ -0,0 +1,241 @@
+//! Resources derived from the incoming request.
+//!
+//! A [`ResourceTemplate`] lets a [`PayWall`](crate::paywall::PayWall) served behind several hostnames
+//! advertise the URL the client actually called, instead of a single hard-coded one.
+
+use bon::Builder;
+use x402_core::{core::Resource, types::OutputSchema};
+
+use crate::HttpRequest;
+
+/// A template for a [`Resource`] whose URL is derived from the incoming request.
+///
+/// The URL is built from the request's scheme and host plus the request path, while the
+/// description, MIME type and output schema are static.
+///
+/// The host is taken from the `Host` header, or the URI authority of requests without one, e.g.
+/// the `:authority` of HTTP/2 requests. If [`trust_forwarded`](ResourceTemplate::trust_forwarded)
+/// is set, the `X-Forwarded-Proto` and `X-Forwarded-Host` headers take precedence, which is only safe
+/// behind a proxy that overwrites them.
+///
+/// # Example
+///
+/// ```
+/// use x402_paywall::resource::ResourceTemplate;
+///
+/// let template = ResourceTemplate::builder()
+///     .description("Premium content")
+///     .mime_type("application/json")
+///     .trust_forwarded(true)
+///     .build();
+///
+/// let request = http::Request::builder()
+///     .uri("/premium")
+///     .header("Host", "internal:8080")
+///     .header("X-Forwarded-Proto", "https")
+///     .header("X-Forwarded-Host", "api.example.com")
+///     .body(())
+///     .unwrap();
+///
+/// let resource = template.resource_for(&request).unwrap();
+/// assert_eq!(resource.url.as_str(), "https://api.example.com/premium");
+/// ```
+#[derive(Builder, Debug, Clone)]
+pub struct ResourceTemplate {
+    /// Description of the resource.
+    #[builder(into)]
+    pub description: String,
+    /// MIME type of the resource.
+    #[builder(into)]
+    pub mime_type: String,
+    /// Optional output schema for the payment payload.
+    pub output_schema: Option<OutputSchema>,
+    /// Scheme used when neither the request nor a trusted forwarded header provides one.
+    #[builder(into, default = "https")]
+    pub default_scheme: String,
+    /// Whether to honor the `X-Forwarded-Proto` and `X-Forwarded-Host` headers.
+    #[builder(default)]
+    pub trust_forwarded: bool,
+}
+
+impl ResourceTemplate {
+    /// Build the resource for the given request.
+    ///
+    /// Returns `None` if the request carries no host or the resulting URL is invalid.
+    pub fn resource_for<Req: HttpRequest>(&self, request: &Req) -> Option<Resource> {
+        let forwarded = |name| {
+            self.trust_forwarded
+                .then(|| header_value(request, name))
+                .flatten()
+        };
+
+        let scheme = forwarded("X-Forwarded-Proto")
+            .or(request.scheme())
+            .unwrap_or(&self.default_scheme);
+        let host = forwarded("X-Forwarded-Host")
+            .or_else(|| header_value(request, "Host"))
+            .or_else(|| {
+                // Without the userinfo, which is not part of the resource URL
+                let authority = request.authority()?;
+                authority.rsplit('@').next()
+            })?;
+        let path = request.path().unwrap_or("/");
+
+        let url = format!("{scheme}://{host}{path}").parse().ok()?;
+
+        Some(Resource {
+            url,
+            description: self.description.clone(),
+            mime_type: self.mime_type.clone(),
+            output_schema: self.output_schema.clone(),
+        })
+    }
+}
+
+/// The first value of a possibly comma-separated header.
+fn header_value<'r, Req: HttpRequest>(request: &'r Req, name: &str) -> Option<&'r str> {
+    let value = str::from_utf8(request.get_header(name)?).ok()?;
+    let first = value.split(',').next()?.trim();
+
+    (!first.is_empty()).then_some(first)
+}
+
+#[cfg(test)]
+mod tests {
+    use http::StatusCode;
+
+    use crate::{
+        paywall::PayWall,
+        testing::{self, MockFacilitator},
+    };
+
+    use super::*;
+
+    fn template(trust_forwarded: bool) -> ResourceTemplate {
+        ResourceTemplate::builder()
+            .description("Protected resource")
+            .mime_type("application/json")
+            .trust_forwarded(trust_forwarded)
+            .build()
+    }
+
+    fn forwarded_request() -> http::Request<()> {
+        http::Request::builder()
+            .uri("/premium/article")
+            .header("Host", "10.0.0.7:8080")
+            .header("X-Forwarded-Proto", "https")
+            .header("X-Forwarded-Host", "api.example.com, proxy.internal")
+            .body(())
+            .unwrap()
+    }
+
+    #[test]
+    fn trusted_forwarded_headers() {
+        let resource = template(true).resource_for(&forwarded_request()).unwrap();
+
+        assert_eq!(
+            resource.url.as_str(),
+            "https://api.example.com/premium/article"
+        );
+        assert_eq!(resource.description, "Protected resource");
+    }
+
+    #[test]
+    fn untrusted_forwarded_headers_are_ignored() {
+        let resource = template(false).resource_for(&forwarded_request()).unwrap();
+
+        assert_eq!(
+            resource.url.as_str(),
+            "https://10.0.0.7:8080/premium/article"
+        );
+    }
+
+    #[test]
+    fn direct_request() {
+        let request = http::Request::builder()
+            .uri("http://shop.example.com/item?id=1")
+            .header("Host", "shop.example.com")
+            .body(())
+            .unwrap();
+
+        let resource = template(true).resource_for(&request).unwrap();
+
+        assert_eq!(resource.url.as_str(), "http://shop.example.com/item");
+    }
+
+    #[test]
+    fn http2_authority_without_host() {
+        let request = http::Request::builder()
+            .version(http::Version::HTTP_2)
+            .uri("https://api.example.com:8443/premium/article?id=1")
+            .body(())
+            .unwrap();
+
+        let resource = template(false).resource_for(&request).unwrap();
+
+        assert_eq!(
+            resource.url.as_str(),
+            "https://api.example.com:8443/premium/article"
+        );
+    }
+
+    #[test]
+    fn missing_host() {
+        let request = http::Request::builder().uri("/item").body(()).unwrap();
+
+        assert!(template(true).resource_for(&request).is_none());
+    }
+
+    #[tokio::test]
+    async fn challenge_uses_derived_resource() {
+        let paywall = PayWall::builder()
+            .facilitator(MockFacilitator::default())
+            .accepts(testing::requirements())
+            .resource(testing::resource())
+            .resource_from_request(template(true))
+            .build();
+
+        let err = paywall
+            .handle_payment(
+                http::Request::builder()
+                    .uri("/premium/article")
+                    .header("Host", "api.example.com")
+                    .body(())
+                    .unwrap(),
+                |_| async { http::Response::new(()) },
+            )
+            .await
+            .unwrap_err();
+
+        assert_eq!(err.status, StatusCode::PAYMENT_REQUIRED);
+        assert_eq!(
+            err.body.resource.url.as_str(),
+            "https://api.example.com/premium/article"
+        );
+    }
+
+    #[test]
+    fn invalid_payment_uses_derived_resource() {
+        let mut request = forwarded_request();
+        request.headers_mut().insert(
+            "PAYMENT-SIGNATURE",
+            http::HeaderValue::from_static("not base64"),
+        );
+
+        let paywall = PayWall::builder()
+            .facilitator(MockFacilitator::default())
+            .accepts(testing::requirements())
+            .resource(testing::resource())
+            .resource_from_request(template(true))
+            .build()
+            .with_request_resource(&request);
+
+        let err = paywall.process_request(request).err().unwrap();
+
+        assert_eq!(err.status, StatusCode::BAD_REQUEST);
+        assert_eq!(
+            err.body.resource.url.as_str(),
+            "https://api.example.com/premium/article"
+        );
+    }
+}
@@ -0,0 +1,320 @@
+//! Per-request resources and prices looked up asynchronously, e.g. from a database.
+//!
+//! An [`AcceptsSource`] on a [`PayWall`](crate::paywall::PayWall) replaces the configured resource
+//! and accepted payment requirements with the ones it returns for the incoming request, e.g. for
+//! the product id in the path. Wrap it in a [`CachedAcceptsSource`] so that the backing store is
+//! not queried for every request. Failed lookups are answered with a `500 Internal Server Error`.
+
+use std::{
+    collections::HashMap,
+    fmt::{Debug, Display},
+    pin::Pin,
+    sync::{Mutex, PoisonError},
+    time::{Duration, Instant},
+};
+
+use x402_core::{core::Resource, transport::Accepts};
+
+/// The future returned by [`AcceptsSource::accepts_for`].
+pub type AcceptsFuture<'a> =
+    Pin<Box<dyn Future<Output = Result<(Resource, Accepts), SourceError>> + Send + 'a>>;
+
+/// The reason an [`AcceptsSource`] could not provide the accepted payment requirements.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum SourceError {
+    /// Nothing is sold at the requested resource.
+    NotFound(String),
+    /// The backing store did not answer in time.
+    Timeout,
+    /// The backing store failed, e.g. the database connection was lost.
+    Backend(String),
+}
+
+impl Display for SourceError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SourceError::NotFound(key) => write!(f, "No payment requirements found for {key}"),
+            SourceError::Timeout => f.write_str("Payment requirements lookup timed out"),
+            SourceError::Backend(reason) => {
+                write!(f, "Payment requirements lookup failed: {reason}")
+            }
+        }
+    }
+}
+
+impl std::error::Error for SourceError {}
+
+/// A source of the resource and the accepted payment requirements of a request.
+pub trait AcceptsSource: Debug + Send + Sync {
+    /// Look up the resource and the accepted payment requirements for the given request.
+    fn accepts_for<'a>(&'a self, parts: &'a http::request::Parts) -> AcceptsFuture<'a>;
+}
+
+/// An [`AcceptsSource`] serving fixed resources and accepts by request path.
+///
+/// # Example
+///
+/// ```
+/// use x402_core::{core::Resource, transport::Accepts};
+/// use x402_paywall::source::InMemoryAcceptsSource;
+///
+/// # fn products(resource: Resource, accepts: Accepts) {
+/// let source = InMemoryAcceptsSource::default().with_path("/products/42", resource, accepts);
+/// # }
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct InMemoryAcceptsSource {
+    /// The resource and accepts by request path.
+    pub entries: HashMap<String, (Resource, Accepts)>,
+}
+
+impl InMemoryAcceptsSource {
+    /// Serve the resource and accepts for requests to the given path, replacing any previous entry.
+    pub fn with_path(
+        mut self,
+        path: impl Into<String>,
+        resource: Resource,
+        accepts: impl Into<Accepts>,
+    ) -> Self {
+        self.entries.insert(path.into(), (resource, accepts.into()));
+        self
+    }
+}
+
+impl AcceptsSource for InMemoryAcceptsSource {
+    fn accepts_for<'a>(&'a self, parts: &'a http::request::Parts) -> AcceptsFuture<'a> {
+        let path = parts.uri.path();
+        let entry = self
+            .entries
+            .get(path)
+            .cloned()
+            .ok_or_else(|| SourceError::NotFound(path.to_string()));
+
+        Box::pin(async move { entry })
+    }
+}
+
+/// Caches the lookups of an [`AcceptsSource`] for a time-to-live, by a key extracted from the request.
+///
+/// Requests with the same key share the cached result, e.g. requests for the same product id.
+/// Failed lookups are not cached, and expired entries are pruned on every miss.
+///
+/// # Example
+///
+/// ```
+/// use std::time::Duration;
+///
+/// use x402_paywall::source::{CachedAcceptsSource, InMemoryAcceptsSource};
+///
+/// let source = CachedAcceptsSource::new(
+///     InMemoryAcceptsSource::default(),
+///     Duration::from_secs(60),
+///     |parts| parts.uri.path().to_string(),
+/// );
+/// ```
+#[derive(Debug)]
+pub struct CachedAcceptsSource<S> {
+    /// The source queried on cache misses.
+    pub source: S,
+    /// How long a lookup is reused.
+    pub ttl: Duration,
+    /// Extracts the cache key from the request.
+    pub key: fn(&http::request::Parts) -> String,
+    entries: Mutex<HashMap<String, (Instant, Resource, Accepts)>>,
+}
+
+impl<S: AcceptsSource> CachedAcceptsSource<S> {
+    pub fn new(source: S, ttl: Duration, key: fn(&http::request::Parts) -> String) -> Self {
+        CachedAcceptsSource {
+            source,
+            ttl,
+            key,
+            entries: Mutex::new(HashMap::new()),
+        }
+    }
+
+    fn cached(&self, key: &str) -> Option<(Resource, Accepts)> {
+        let entries = self.entries.lock().unwrap_or_else(PoisonError::into_inner);
+        entries
+            .get(key)
+            .filter(|(cached_at, _, _)| cached_at.elapsed() < self.ttl)
+            .map(|(_, resource, accepts)| (resource.clone(), accepts.clone()))
+    }
+
+    fn insert(&self, key: String, resource: Resource, accepts: Accepts) {
+        let mut entries = self.entries.lock().unwrap_or_else(PoisonError::into_inner);
+        entries.retain(|_, (cached_at, _, _)| cached_at.elapsed() < self.ttl);
+        entries.insert(key, (Instant::now(), resource, accepts));
+    }
+}
+
+impl<S: AcceptsSource> AcceptsSource for CachedAcceptsSource<S> {
+    fn accepts_for<'a>(&'a self, parts: &'a http::request::Parts) -> AcceptsFuture<'a> {
+        Box::pin(async move {
+            let key = (self.key)(parts);
+            if let Some(cached) = self.cached(&key) {
+                return Ok(cached);
+            }
+
+            let (resource, accepts) = self.source.accepts_for(parts).await?;
+            self.insert(key, resource.clone(), accepts.clone());
+            Ok((resource, accepts))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{
+        Arc,
+        atomic::{AtomicUsize, Ordering},
+    };
+
+    use http::StatusCode;
+    use x402_core::{transport::PaymentRequirements, types::AmountValue};
+
+    use crate::{
+        errors::PaymentError,
+        paywall::PayWall,
+        testing::{self, MockFacilitator},
+    };
+
+    use super::*;
+
+    /// Counts the lookups of the wrapped source.
+    #[derive(Debug, Clone)]
+    struct Counting<S> {
+        source: S,
+        lookups: Arc<AtomicUsize>,
+    }
+
+    impl<S: AcceptsSource> AcceptsSource for Counting<S> {
+        fn accepts_for<'a>(&'a self, parts: &'a http::request::Parts) -> AcceptsFuture<'a> {
+            self.lookups.fetch_add(1, Ordering::SeqCst);
+            self.source.accepts_for(parts)
+        }
+    }
+
+    /// Fails every lookup.
+    #[derive(Debug)]
+    struct Unavailable;
+
+    impl AcceptsSource for Unavailable {
+        fn accepts_for<'a>(&'a self, _parts: &'a http::request::Parts) -> AcceptsFuture<'a> {
+            Box::pin(async { Err(SourceError::Backend("connection refused".to_string())) })
+        }
+    }
+
+    fn priced(amount: u128) -> PaymentRequirements {
+        PaymentRequirements {
+            amount: AmountValue(amount),
+            ..testing::requirements()
+        }
+    }
+
+    fn products() -> Counting<InMemoryAcceptsSource> {
+        Counting {
+            source: InMemoryAcceptsSource::default()
+                .with_path("/products/1", testing::resource(), priced(1000))
+                .with_path("/products/2", testing::resource(), priced(2000)),
+            lookups: Arc::new(AtomicUsize::new(0)),
+        }
+    }
+
+    fn parts(path: &str) -> http::request::Parts {
+        http::Request::get(path).body(()).unwrap().into_parts().0
+    }
+
+    fn paywall(source: impl AcceptsSource + 'static) -> PayWall<MockFacilitator> {
+        PayWall::builder()
+            .facilitator(MockFacilitator::default())
+            .accepts(Vec::new())
+            .resource(testing::resource())
+            .accepts_source(source)
+            .build()
+    }
+
+    fn request(path: &str) -> http::Request<()> {
+        http::Request::get(path).body(()).unwrap()
+    }
+
+    #[tokio::test]
+    async fn in_memory_source_by_path() {
+        let paywall = paywall(products());
+
+        let sourced = paywall
+            .clone()
+            .with_request_accepts(&request("/products/2"))
+            .await
+            .unwrap();
+        let challenge = sourced.payment_required();
+        assert_eq!(challenge.status, StatusCode::PAYMENT_REQUIRED);
+        assert_eq!(challenge.body.accepts.as_ref()[0].amount, AmountValue(2000));
+
+        let err = paywall
+            .with_request_accepts(&request("/products/3"))
+            .await
+            .err()
+            .unwrap();
+        assert_eq!(err.status, StatusCode::INTERNAL_SERVER_ERROR);
+        assert_eq!(
+            err.cause,
+            PaymentError::AcceptsUnavailable(
+                "No payment requirements found for /products/3".to_string()
+            )
+        );
+    }
+
+    #[tokio::test]
+    async fn cache_hit_and_miss() {
+        let cached = CachedAcceptsSource::new(products(), Duration::from_secs(60), |parts| {
+            parts.uri.path().to_string()
+        });
+        let lookups = &cached.source.lookups;
+
+        let (_, first) = cached.accepts_for(&parts("/products/1")).await.unwrap();
+        let (_, hit) = cached.accepts_for(&parts("/products/1")).await.unwrap();
+        assert_eq!(first, hit);
+        assert_eq!(lookups.load(Ordering::SeqCst), 1);
+
+        let (_, other) = cached.accepts_for(&parts("/products/2")).await.unwrap();
+        assert_eq!(other.as_ref()[0].amount, AmountValue(2000));
+        assert_eq!(lookups.load(Ordering::SeqCst), 2);
+
+        // Expired entries are looked up again
+        let expired = CachedAcceptsSource::new(products(), Duration::ZERO, |parts| {
+            parts.uri.path().to_string()
+        });
+        expired.accepts_for(&parts("/products/1")).await.unwrap();
+        expired.accepts_for(&parts("/products/1")).await.unwrap();
+        assert_eq!(expired.source.lookups.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn source_failure_is_server_error_and_not_cached() {
+        let lookups = Arc::new(AtomicUsize::new(0));
+        let source = CachedAcceptsSource::new(
+            Counting {
+                source: Unavailable,
+                lookups: lookups.clone(),
+            },
+            Duration::from_secs(60),
+            |_| "all".to_string(),
+        );
+        let paywall = paywall(source);
+
+        for _ in 0..2 {
+            let err = paywall
+                .clone()
+                .handle_payment(request("/products/1"), |_| async {
+                    http::Response::new(())
+                })
+                .await
+                .unwrap_err();
+            assert_eq!(err.status, StatusCode::INTERNAL_SERVER_ERROR);
+            assert!(matches!(err.cause, PaymentError::AcceptsUnavailable(_)));
+        }
+        assert_eq!(lookups.load(Ordering::SeqCst), 2);
+    }
+}
@@ -0,0 +1,236 @@
+//! Test utilities for paywalled applications.
+//!
+//! [`MockFacilitator`] returns canned responses without any chain or facilitator, so that
+//! the `402` and `200` paths of a paywalled application can be tested together with a
+//! `PAYMENT-SIGNATURE` header attached by [`request_with_payment`].
+//!
+//! Available with the `test-utils` feature.
+
+use std::{
+    fmt::Display,
+    sync::{
+        Arc, Mutex,
+        atomic::{AtomicUsize, Ordering},
+    },
+};
+
+use serde_json::json;
+use x402_core::{
+    facilitator::{
+        Facilitator, FacilitatorCapabilities, PaymentRequest, SettleResult, SettleStatus,
+        SettleSuccess, SupportedResponse, VerifyResult, VerifyValid,
+    },
+    transport::{PaymentPayload, PaymentRequirements, PaymentResource},
+    types::{AmountValue, Base64EncodedHeader, Record, SchemeName, X402V2},
+};
+
+/// Error returned by [`MockFacilitator`] when configured to fail.
+#[derive(Debug)]
+pub struct MockError(pub String);
+
+impl Display for MockError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl std::error::Error for MockError {}
+
+/// Number of calls received by a [`MockFacilitator`] for each endpoint.
+#[derive(Debug, Default)]
+pub struct MockCalls {
+    pub supported: AtomicUsize,
+    pub verify: AtomicUsize,
+    pub settle: AtomicUsize,
+    pub settle_status: AtomicUsize,
+    /// The requests received by `settle`, in order.
+    pub settle_requests: Mutex<Vec<PaymentRequest>>,
+}
+
+impl MockCalls {
+    pub fn supported(&self) -> usize {
+        self.supported.load(Ordering::SeqCst)
+    }
+
+    pub fn verify(&self) -> usize {
+        self.verify.load(Ordering::SeqCst)
+    }
+
+    pub fn settle(&self) -> usize {
+        self.settle.load(Ordering::SeqCst)
+    }
+
+    pub fn settle_status(&self) -> usize {
+        self.settle_status.load(Ordering::SeqCst)
+    }
+
+    /// The last request received by `settle`, if any.
+    pub fn last_settle_request(&self) -> Option<PaymentRequest> {
+        self.settle_requests.lock().unwrap().last().cloned()
+    }
+}
+
+/// A facilitator returning canned responses and counting calls.
+///
+/// By default it supports the [`requirements`] fixture, and verifies and settles every payment
+/// successfully for [`PAYER`].
+#[derive(Debug, Clone)]
+pub struct MockFacilitator {
+    /// Response to `supported` calls.
+    pub supported: SupportedResponse,
+    /// Result of `verify` calls.
+    pub verify: VerifyResult,
+    /// Result of `settle` calls.
+    pub settle: SettleResult,
+    /// When set, every call fails with a [`MockError`].
+    pub unavailable: bool,
+    /// Delay applied to verify and settle calls.
+    pub delay: Option<std::time::Duration>,
+    /// Results of successive `settle_status` calls, repeating the last one.
+    ///
+    /// Empty means every settlement is confirmed.
+    pub settle_statuses: Vec<SettleStatus>,
+    pub calls: Arc<MockCalls>,
+}
+
+impl Default for MockFacilitator {
+    fn default() -> Self {
+        MockFacilitator {
+            supported: serde_json::from_value(json!({
+                "kinds": [
+                    { "x402Version": 2, "scheme": "exact", "network": "eip155:84532" }
+                ],
+                "extensions": [],
+                "signers": {}
+            }))
+            .unwrap(),
+            verify: VerifyResult::valid(VerifyValid {
+                payer: PAYER.to_string(),
+                attestation: None,
+            }),
+            settle: SettleResult::success(SettleSuccess {
+                payer: PAYER.to_string(),
+                transaction: "0xdeadbeef".to_string(),
+                network: "eip155:84532".to_string(),
+                verified_signature: false,
+                signer: None,
+                confirmation: None,
+            }),
+            unavailable: false,
+            delay: None,
+            settle_statuses: Vec::new(),
+            calls: Default::default(),
+        }
+    }
+}
+
+impl MockFacilitator {
+    async fn sleep(&self) {
+        if let Some(delay) = self.delay {
+            tokio::time::sleep(delay).await;
+        }
+    }
+
+    fn result<T>(&self, value: T) -> Result<T, MockError> {
+        if self.unavailable {
+            Err(MockError("facilitator unavailable".to_string()))
+        } else {
+            Ok(value)
+        }
+    }
+}
+
+impl Facilitator for MockFacilitator {
+    type Error = MockError;
+
+    async fn supported(&self) -> Result<SupportedResponse, Self::Error> {
+        self.calls.supported.fetch_add(1, Ordering::SeqCst);
+        self.result(self.supported.clone())
+    }
+
+    async fn verify(&self, _request: PaymentRequest) -> Result<VerifyResult, Self::Error> {
+        self.calls.verify.fetch_add(1, Ordering::SeqCst);
+        self.sleep().await;
+        self.result(self.verify.clone())
+    }
+
+    async fn settle(&self, request: PaymentRequest) -> Result<SettleResult, Self::Error> {
+        self.calls.settle.fetch_add(1, Ordering::SeqCst);
+        self.calls.settle_requests.lock().unwrap().push(request);
+        self.sleep().await;
+        self.result(self.settle.clone())
+    }
+
+    async fn settle_status(&self, _settled: &SettleSuccess) -> Result<SettleStatus, Self::Error> {
+        let call = self.calls.settle_status.fetch_add(1, Ordering::SeqCst);
+        let status = self
+            .settle_statuses
+            .get(call)
+            .or(self.settle_statuses.last())
+            .cloned()
+            .unwrap_or_else(SettleStatus::confirmed);
+
+        self.result(status)
+    }
+
+    fn capabilities(&self) -> FacilitatorCapabilities {
+        FacilitatorCapabilities::builder()
+            .settlement_status(true)
+            .build()
+    }
+}
+
+/// The payer reported by the default [`MockFacilitator`].
+pub const PAYER: &str = "0x857b06519E91e3A54538791bDbb0E22373e36b66";
+
+/// A payment requirement matching the default [`MockFacilitator`] kinds.
+pub fn requirements() -> PaymentRequirements {
+    PaymentRequirements {
+        scheme: SchemeName::EXACT,
+        network: "eip155:84532".to_string(),
+        amount: AmountValue(1000),
+        asset: "0x036CbD53842c5426634e7929541eC2318f3dCF7e".to_string(),
+        pay_to: "0x3CB9B3bBfde8501f411bB69Ad3DC07908ED0dE20".to_string(),
+        max_timeout_seconds: 60,
+        extra: Some(json!({ "name": "USDC", "version": "2" })),
+    }
+}
+
+/// A resource fixture.
+pub fn resource() -> x402_core::core::Resource {
+    x402_core::core::Resource::builder()
+        .url("https://example.com/resource".parse().unwrap())
+        .description("Protected resource")
+        .mime_type("application/json")
+        .build()
+}
+
+/// Encode a `PAYMENT-SIGNATURE` header accepting the given requirements, with a dummy payload.
+pub fn payment_header(accepted: PaymentRequirements) -> Base64EncodedHeader {
+    let payload = PaymentPayload {
+        x402_version: X402V2,
+        resource: PaymentResource::from(resource()),
+        accepted,
+        payload: json!({ "signature": "0x00" }),
+        extensions: Record::new(),
+    };
+    Base64EncodedHeader::try_from(payload).unwrap()
+}
+
+/// Attach the `PAYMENT-SIGNATURE` header to a request builder.
+pub fn request_with_payment(
+    builder: http::request::Builder,
+    header: &Base64EncodedHeader,
+) -> http::request::Builder {
+    builder.header("PAYMENT-SIGNATURE", header.0.as_str())
+}
+
+/// Build a request carrying a `PAYMENT-SIGNATURE` header for the given requirements, with a dummy payload.
+pub fn paid_request(accepted: PaymentRequirements) -> http::Request<()> {
+    request_with_payment(
+        http::Request::builder().uri("https://example.com/resource"),
+        &payment_header(accepted),
+    )
+    .body(())
+    .unwrap()
+}